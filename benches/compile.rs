@@ -0,0 +1,72 @@
+// Times `zog check` on a generated project with thousands of functions, which
+// mostly measures resolving the resources each call and variable refers to
+use std::{fmt::Write, fs, path::PathBuf, process::Command};
+
+use criterion::{criterion_group, criterion_main, Criterion};
+
+const MODULES: usize = 60;
+const FUNCTIONS: usize = 50;
+
+// Each function calls one in its own module, one in an imported module and
+// one in a nested module, and reads and writes a few variables
+fn large_project() -> String {
+  let mut source = String::from("namespace bench\n");
+  for module in 0..MODULES {
+    let other = (module + 1) % MODULES;
+    writeln!(source, "\nmodule m{module} {{\n  import bench:m{other}").unwrap();
+    writeln!(
+      source,
+      "  module inner {{\n    fn helper(a) {{\n      return a\n    }}\n  }}"
+    )
+    .unwrap();
+    for function in 0..FUNCTIONS {
+      let previous = function.saturating_sub(1);
+      write!(
+        source,
+        "
+  fn f{function}(a, b) {{
+    x = a + b
+    $y = x * 2
+    if $y > 10 {{
+      z = f{previous}(x, 1)
+    }}
+    ~/shared = x
+    w = m{other}/f{function}(1, 2)
+    v = inner/helper(w)
+    return v
+  }}
+"
+      )
+      .unwrap();
+    }
+    source.push_str("}\n");
+  }
+  source
+}
+
+fn compile(c: &mut Criterion) {
+  let directory = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("bench_large_project");
+  fs::create_dir_all(&directory).unwrap();
+  fs::write(directory.join("main.zog"), large_project()).unwrap();
+
+  let mut group = c.benchmark_group("compile");
+  group.sample_size(10);
+  group.bench_function("check_large_project", |b| {
+    b.iter(|| {
+      let output = Command::new(env!("CARGO_BIN_EXE_zoglin"))
+        .args(["check", "--no-style-warnings"])
+        .current_dir(&directory)
+        .output()
+        .unwrap();
+      assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+      );
+    })
+  });
+  group.finish();
+}
+
+criterion_group!(benches, compile);
+criterion_main!(benches);
@@ -1,51 +1,151 @@
 use std::collections::HashSet;
-use std::hash::Hash;
 use std::mem::take;
 use std::ops::{Deref, DerefMut};
 use std::{collections::HashMap, path::Path};
 
 use ecow::{eco_format, EcoString};
-use expression::{verify_types, ConditionKind, Expression, ExpressionKind, NbtValue};
-use file_tree::{ResourceLocation, ScoreboardLocation, StorageLocation};
-use scope::{CalledFunction, ComptimeFunction, FunctionDefinition, Imported};
+use expression::{
+  verify_types, ConditionKind, Expression, ExpressionKind, Interpolation, NbtType, NbtValue,
+};
+use file_tree::{DataLocation, ScoreboardLocation, StorageLocation};
+use scope::{CalledFunction, ComptimeFunction, FunctionDefinition, GenericFunction, Imported};
 use serde::Serialize;
 
 use crate::parser::ast::{
-  self, ArrayType, Command, ElseStatement, File, FunctionCall, IfStatement, Index, KeyValue,
-  Member, ParameterKind, RangeIndex, ReturnType, Statement, StaticExpr, WhileLoop, ZoglinResource,
+  self, ArrayType, Command, DataKind, ElseStatement, ExecuteBlock, File, FunctionCall, IfStatement,
+  Index, KeyValue, Member, ParameterKind, RangeIndex, ReturnType, Statement, StaticExpr,
+  TemplatePart, TextComponentKind, WhileLoop, ZoglinResource,
 };
+use crate::parser::json5_to_json;
+use crate::parser::name::{check_reserved, validate, validate_or_quote, NameKind};
 
-use crate::error::{raise_error, raise_floating_error, Location, Result};
+use crate::config::McMeta;
+use crate::error::{
+  raise_error, raise_warning, Error, ErrorKind, Location, MultiResult, Result, Warnings,
+};
 
 use self::{
-  file_tree::{FileResource, FileTree, Function, Item, Namespace, TextResource},
+  builtins::{data_component, score_component, storage_component, OutputAssertion},
+  file_tree::{FileResource, Namespace, TextResource},
   scope::Scope,
+  temps::TempPool,
 };
+mod assignment;
 mod binary_operation;
 mod builtins;
+mod call_stubs;
 mod expression;
 mod file_tree;
+mod for_loop;
+mod generic;
 mod internals;
 mod register;
+mod renamed;
 mod scope;
+mod signature;
+mod snippet;
+mod temps;
 mod utils;
+mod wrapped;
+
+pub use file_tree::{FileTree, Function, Item, LineEndings, ResourceLocation};
 
-use utils::ToEcoString;
+// `@test_fail` records its message here, for `zog test` to report
+pub const TEST_STORAGE: &str = "zoglin:test";
+
+use signature::check_argument_count;
+use utils::{edit_distance, ToEcoString};
 
 #[derive(Default)]
 pub struct Compiler {
-  tick_functions: Vec<EcoString>,
-  load_functions: Vec<EcoString>,
+  // The functions added to each function tag, besides `minecraft:load`
+  function_tags: HashMap<ResourceLocation, Vec<EcoString>>,
+  load_functions: Vec<(EcoString, EcoString)>,
   scopes: Vec<Scope>,
   comptime_scopes: Vec<HashMap<EcoString, Expression>>,
   current_scope: usize,
-  counters: HashMap<EcoString, usize>,
+  counters: HashMap<(EcoString, EcoString), usize>,
   namespaces: HashMap<EcoString, Namespace>,
-  // TODO: Refactor used scoreboards to be a HashMap
-  used_scoreboards: HashSet<UsedScoreboard>,
-  constant_scoreboard_values: HashSet<i32>,
+  used_scoreboards: HashMap<EcoString, UsedScoreboard>,
+  // The constants used by each namespace
+  constant_scoreboard_values: HashMap<EcoString, HashSet<i32>>,
   function_registry: HashMap<ResourceLocation, FunctionDefinition>,
   comptime_function_registry: HashMap<ResourceLocation, ComptimeFunction>,
+  generic_function_registry: HashMap<ResourceLocation, GenericFunction>,
+  dependent_files: HashSet<EcoString>,
+  storage_prefix: Option<EcoString>,
+  inline_load: bool,
+  // The number of iterations after which a `while true` loop stops
+  loop_guard: Option<u32>,
+  // The players told when a loop is stopped
+  loop_guard_target: EcoString,
+  // Whether `@dump` prints values
+  debug: bool,
+  // The players shown `@dump` output and deprecation notices
+  message_target: EcoString,
+  emit_call_stubs: bool,
+  output_assertions: Vec<OutputAssertion>,
+  // Functions referenced with `@fn`, which must exist
+  function_refs: Vec<(ResourceLocation, Location)>,
+  test_functions: Vec<ResourceLocation>,
+  // Tag resources by kind and path. Tags defined more than once are merged,
+  // along with the functions added to function tags
+  user_tags: HashMap<(EcoString, ResourceLocation), Vec<TextResource>>,
+  // Internal helper functions already added to the tree
+  internal_helpers: HashSet<&'static str>,
+  // Errors in items which were skipped so that compilation could continue
+  errors: Vec<Error>,
+  warnings: Warnings,
+  profile: Profile,
+  // Functions left out of a release build by `#[test_only]`, so that
+  // references to them can be reported
+  excluded_functions: HashSet<ResourceLocation>,
+  // Temporaries of each function being compiled, innermost last
+  temp_pools: Vec<TempPool>,
+  // The `execute` subcommands of each module declared with `wraps`
+  module_wrappers: HashMap<ResourceLocation, EcoString>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Profile {
+  #[default]
+  Dev,
+  // Leaves out `#[test_only]` functions and modules
+  Release,
+}
+
+impl Profile {
+  pub fn from_name(name: &str) -> Option<Profile> {
+    match name {
+      "dev" => Some(Profile::Dev),
+      "release" => Some(Profile::Release),
+      _ => None,
+    }
+  }
+}
+
+pub struct CompileOptions {
+  pub line_endings: LineEndings,
+  pub storage_prefix: Option<EcoString>,
+  pub style_warnings: bool,
+  pub auto_fix_names: bool,
+  pub inline_load: bool,
+  pub loop_guard: Option<u32>,
+  pub loop_guard_target: EcoString,
+  pub debug: bool,
+  // A selector for the players shown debug output and deprecation notices
+  pub message_target: EcoString,
+  pub emit_call_stubs: bool,
+  // The project config's pack.mcmeta
+  pub meta: Option<McMeta>,
+  // Whether output from other versions of Zoglin is fully rebuilt
+  pub version_check: bool,
+  pub profile: Profile,
+  // What `fn` without a return sigil means, set by the project config
+  pub default_return: ReturnType,
+  pub require_explicit_return_type: bool,
+  // Where warnings and notes are collected while compiling
+  pub warnings: Warnings,
 }
 
 enum RefOrOwned<'a, T> {
@@ -112,8 +212,26 @@ struct FunctionContext<'a> {
   is_nested: bool,
   has_nested_returns: RefOrOwned<'a, bool>,
   code: RefOrOwned<'a, Vec<EcoString>>,
+  variables: RefOrOwned<'a, VariableUsage>,
+  // Results of earlier calls to pure functions, keyed by the function and its arguments
+  pure_calls: HashMap<EcoString, Expression>,
+  // Storage and scoreboard variables known to hold a constant, keyed by their
+  // location. Anything that might change them, such as commands and function
+  // calls, forgets every value
+  constants: HashMap<EcoString, Expression>,
+}
+
+// Tracks which function-local storage variables are assigned and read, so
+// that reads of variables which are never assigned can be warned about
+#[derive(Default)]
+struct VariableUsage {
+  assigned: HashSet<EcoString>,
+  read: Vec<(EcoString, Location)>,
+  allow_unassigned: bool,
 }
 
+const ALLOW_UNASSIGNED_COMMENT: &str = "zoglin: allow-unassigned";
+
 impl<'a> FunctionContext<'a> {
   fn new(location: ResourceLocation, return_type: ReturnType) -> FunctionContext<'a> {
     FunctionContext {
@@ -122,6 +240,9 @@ impl<'a> FunctionContext<'a> {
       is_nested: false,
       has_nested_returns: RefOrOwned::Owned(false),
       code: RefOrOwned::Owned(Vec::new()),
+      variables: RefOrOwned::Owned(VariableUsage::default()),
+      pure_calls: HashMap::new(),
+      constants: HashMap::new(),
     }
   }
 
@@ -139,39 +260,67 @@ impl<'a> FunctionContext<'a> {
       } else {
         RefOrOwned::Owned(Vec::new())
       },
+      variables: self.variables.as_mut().into(),
+      pure_calls: HashMap::new(),
+      // Child blocks run straight after the code before them, so start with
+      // the same values
+      constants: self.constants.clone(),
     }
   }
 }
 
 #[derive(Serialize)]
-struct FunctionTag<'a> {
-  values: &'a [EcoString],
+struct Tag {
+  #[serde(skip_serializing_if = "std::ops::Not::not")]
+  replace: bool,
+  values: Vec<serde_json::Value>,
 }
 
-#[derive(Eq)]
-pub struct UsedScoreboard {
-  name: EcoString,
-  criteria: EcoString,
+// Tags are written like `minecraft:tick`, optionally starting with `#`
+fn function_tag(tag: &str, location: &Location) -> Result<ResourceLocation> {
+  let tag = tag.strip_prefix('#').unwrap_or(tag);
+  let Some((namespace, path)) = tag
+    .split_once(':')
+    .filter(|(namespace, path)| !namespace.is_empty() && !path.is_empty())
+  else {
+    return Err(raise_error(
+      location.clone(),
+      "Function tags must include a namespace, like `minecraft:tick`.",
+    ));
+  };
+  let modules: Vec<_> = path.split('/').collect();
+  Ok(ResourceLocation::new_function(namespace, &modules))
 }
 
-impl UsedScoreboard {
-  pub fn new_dummy(name: EcoString) -> UsedScoreboard {
-    UsedScoreboard {
-      name,
-      criteria: "dummy".into(),
-    }
-  }
+// Entries are either a function id, or an object with an `id` field
+fn tag_entry_id(entry: &serde_json::Value) -> Option<&str> {
+  entry.as_str().or_else(|| entry.get("id")?.as_str())
 }
 
-impl PartialEq for UsedScoreboard {
-  fn eq(&self, other: &Self) -> bool {
-    self.name == other.name
-  }
+pub struct UsedScoreboard {
+  // Each criteria other than `dummy` the objective is declared with, and where
+  criteria: Vec<(EcoString, Location)>,
+  // The namespace whose load function adds the objective
+  owner: EcoString,
 }
 
-impl Hash for UsedScoreboard {
-  fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-    self.name.hash(state)
+impl UsedScoreboard {
+  // Objectives are `dummy` unless declared with another criteria, which must
+  // be the same everywhere they are declared
+  fn criteria(&self, name: &str) -> Result<&str> {
+    let Some((criteria, first)) = self.criteria.first() else {
+      return Ok("dummy");
+    };
+    if let Some((other, location)) = self.criteria.iter().find(|(other, _)| other != criteria) {
+      return Err(raise_error(
+        location.clone(),
+        format!(
+          "The objective `{name}` is declared with criteria `{other}`, but was declared with `{criteria}` at {}:{}:{}.",
+          first.file, first.line, first.column
+        ),
+      ));
+    }
+    Ok(criteria)
   }
 }
 
@@ -201,31 +350,65 @@ impl Compiler {
     self.scopes[scope].comptime_functions.insert(name, location);
   }
 
-  fn use_scoreboard(&mut self, name: EcoString, criteria: EcoString) {
-    let exists = self.used_scoreboards.insert(UsedScoreboard {
-      name: name.clone(),
-      criteria: criteria.clone(),
-    });
-    if criteria != "dummy" && !exists {
-      let scoreboard = UsedScoreboard { name, criteria };
-      self.used_scoreboards.remove(&scoreboard);
-      self.used_scoreboards.insert(scoreboard);
+  // Objectives used without a criteria are `dummy`. Conflicting criteria are
+  // reported when the load function adds the objective.
+  fn use_scoreboard(
+    &mut self,
+    name: EcoString,
+    criteria: Option<(EcoString, Location)>,
+    owner: &str,
+  ) {
+    let scoreboard = self
+      .used_scoreboards
+      .entry(name)
+      .or_insert_with(|| UsedScoreboard {
+        criteria: Vec::new(),
+        owner: owner.into(),
+      });
+    if let Some(criteria) = criteria.filter(|(criteria, _)| criteria != "dummy") {
+      scoreboard.criteria.push(criteria);
     }
   }
 
-  fn use_scoreboard_dummy(&mut self, name: EcoString) {
-    self
-      .used_scoreboards
-      .insert(UsedScoreboard::new_dummy(name));
+  // Objectives are added by the namespace they belong to. If that namespace
+  // isn't part of this project, the namespace referencing it adds it instead.
+  fn scoreboard_owner<'b>(&self, namespace: &'b str, user: &'b str) -> &'b str {
+    if self.scopes[0].children.contains_key(namespace) {
+      namespace
+    } else {
+      user
+    }
   }
 
-  fn lookup_resource(&self, resource: &ZoglinResource, comptime: bool) -> Option<ResourceLocation> {
+  fn lookup_resource(
+    &mut self,
+    resource: &ZoglinResource,
+    comptime: bool,
+  ) -> Option<ResourceLocation> {
     if resource.namespace.is_some() {
       return None;
     }
 
     let first = resource.modules.first().unwrap_or(&resource.name);
     let valid_function = resource.modules.is_empty();
+    let key = (first.clone(), comptime, valid_function);
+    if let Some(resolved) = self.scopes[self.current_scope].resolved_resources.get(&key) {
+      return resolved.clone();
+    }
+
+    let resolved = self.walk_scopes(first, comptime, valid_function);
+    self.scopes[self.current_scope]
+      .resolved_resources
+      .insert(key, resolved.clone());
+    resolved
+  }
+
+  fn walk_scopes(
+    &self,
+    first: &str,
+    comptime: bool,
+    valid_function: bool,
+  ) -> Option<ResourceLocation> {
     let mut index = self.current_scope;
 
     while index != 0 {
@@ -241,7 +424,7 @@ impl Compiler {
       }
       if let Some(imported) = scope.imported_items.get(first) {
         match imported {
-          Imported::ModuleOrFunction(path) if (!comptime || !resource.modules.is_empty()) => {
+          Imported::ModuleOrFunction(path) if (!comptime || !valid_function) => {
             return Some(path.clone())
           }
           Imported::Comptime(path) if comptime => return Some(path.clone()),
@@ -262,12 +445,17 @@ impl Compiler {
       }
     }
 
+    self.lookup_item_comptime_variable(name).cloned()
+  }
+
+  // Finds a compile-time variable assigned at namespace or module level
+  fn lookup_item_comptime_variable(&self, name: &str) -> Option<&Expression> {
     let mut index = self.current_scope;
 
     while index != 0 {
       let scope = &self.scopes[index];
       if let Some(value) = scope.comptime_values.get(name) {
-        return Some(value.clone());
+        return Some(value);
       }
 
       index = scope.parent;
@@ -283,6 +471,7 @@ impl Compiler {
         Namespace {
           name: location.namespace.clone(),
           items: Vec::new(),
+          call_stubs: None,
         },
       );
     }
@@ -339,141 +528,338 @@ impl Compiler {
     Ok(())
   }
 
-  fn next_counter(&mut self, counter_name: &str) -> usize {
-    if let Some(counter) = self.counters.get_mut(counter_name) {
+  // Counters are kept per namespace, so the names generated for one namespace
+  // don't depend on the order namespaces are compiled in. Each function has its
+  // own pool of temporaries, which later statements reuse once a statement is
+  // compiled, but names are never shared between functions, as a value must
+  // survive calls to other functions. A loop's counter and condition are made
+  // by the loop statement itself, so they stay reserved while its body, which
+  // may be in generated functions, reuses only the temporaries made after them.
+  fn next_counter(&mut self, namespace: &str, counter_name: &str) -> usize {
+    let key = (namespace.to_eco_string(), counter_name.to_eco_string());
+    if let Some(counter) = self.counters.get_mut(&key) {
       *counter += 1;
       return *counter;
     };
 
-    self.counters.insert(counter_name.to_eco_string(), 0);
+    self.counters.insert(key, 0);
     0
   }
 
   fn next_scoreboard(&mut self, namespace: &str) -> ScoreboardLocation {
-    self.use_scoreboard_dummy(eco_format!("zoglin.internal.{namespace}.vars"));
-    ScoreboardLocation {
-      scoreboard: ResourceLocation::new_function("zoglin", &["internal", namespace, "vars"]),
-      name: eco_format!("$var_{}", self.next_counter("scoreboard")),
+    if let Some(scoreboard) = self.reuse_temp_scoreboard(namespace) {
+      return scoreboard;
     }
+    self.use_scoreboard(
+      eco_format!("zoglin.internal.{namespace}.vars"),
+      None,
+      namespace,
+    );
+    let scoreboard = ScoreboardLocation {
+      scoreboard: ResourceLocation::new_function("zoglin", &["internal", namespace, "vars"]),
+      name: eco_format!("$var_{}", self.next_counter(namespace, "scoreboard")),
+    };
+    self.add_temp_scoreboard(&scoreboard, namespace);
+    scoreboard
   }
 
-  fn constant_scoreboard(&mut self, value: i32) -> ScoreboardLocation {
-    self.use_scoreboard_dummy("zoglin.internal.constants".into());
-    self.constant_scoreboard_values.insert(value);
+  fn constant_scoreboard(&mut self, value: i32, namespace: &str) -> ScoreboardLocation {
+    self.use_scoreboard(
+      eco_format!("zoglin.internal.{namespace}.constants"),
+      None,
+      namespace,
+    );
+    self
+      .constant_scoreboard_values
+      .entry(namespace.into())
+      .or_default()
+      .insert(value);
     ScoreboardLocation {
-      scoreboard: ResourceLocation::new_function("zoglin", &["internal", "constants"]),
+      scoreboard: ResourceLocation::new_function("zoglin", &["internal", namespace, "constants"]),
       name: eco_format!("${value}"),
     }
   }
 
+  // The storage used for a function's variables, parameters and return value.
+  // Storage in namespaces from other packs is left where they expect it
+  fn function_storage(&self, location: &ResourceLocation) -> ResourceLocation {
+    let mut location = location.clone();
+    if let Some(prefix) = &self.storage_prefix {
+      let is_project_namespace = self
+        .scopes
+        .first()
+        .is_some_and(|root| root.children.contains_key(&location.namespace));
+      if is_project_namespace {
+        location.modules.insert(0, prefix.clone());
+      }
+    }
+    location
+  }
+
+  // Qualified variables are resolved first, so `example:foo/bar/baz` is kept
+  // in the same storage as `baz` inside `example:foo/bar`
+  fn variable_storage(
+    &self,
+    location: &ResourceLocation,
+    variable: &ZoglinResource,
+  ) -> StorageLocation {
+    let storage = StorageLocation::from_zoglin_resource(location, variable);
+    StorageLocation::new(self.function_storage(&storage.storage), storage.name)
+  }
+
+  fn return_slot(&mut self, location: Location, context: &FunctionContext) -> Result<Expression> {
+    let kind = match context.return_type {
+      ReturnType::Storage => ExpressionKind::Storage(StorageLocation::new(
+        self.function_storage(&context.location),
+        "return".to_eco_string(),
+      )),
+      ReturnType::Scoreboard => {
+        let scoreboard = ScoreboardLocation::new(context.location.clone(), "$return");
+        self.use_scoreboard(
+          scoreboard.scoreboard_string(),
+          None,
+          &context.location.namespace,
+        );
+        ExpressionKind::Scoreboard(scoreboard)
+      }
+      ReturnType::Direct => {
+        return Err(raise_error(
+          location,
+          "Functions that return with `%` have no return slot.",
+        ))
+      }
+    };
+    Ok(Expression::new(kind, location))
+  }
+
   fn next_storage(&mut self, namespace: &str) -> StorageLocation {
-    StorageLocation::new(
+    if let Some(storage) = self.reuse_temp_storage(namespace) {
+      return storage;
+    }
+    let storage = StorageLocation::new(
       ResourceLocation::new_function("zoglin", &["internal", namespace, "vars"]),
-      eco_format!("var_{}", self.next_counter("storage")),
-    )
-  }
-
-  fn next_function(&mut self, function_type: &str, namespace: &str) -> ResourceLocation {
-    ResourceLocation::new_function(
-      "zoglin",
-      &[
-        "generated",
-        namespace,
-        function_type,
-        &eco_format!(
-          "fn_{}",
-          self.next_counter(&eco_format!("function:{}", function_type))
-        ),
-      ],
-    )
+      eco_format!("var_{}", self.next_counter(namespace, "storage")),
+    );
+    self.add_temp_storage(&storage, namespace);
+    storage
+  }
+
+  // Generated functions are grouped under the function they belong to, such as
+  // `zoglin:generated/<namespace>/<module>/<function>/if_0`, and numbered
+  // separately for each one
+  fn next_function(&mut self, function_type: &str, owner: &ResourceLocation) -> ResourceLocation {
+    let counter = self.next_counter(&owner.to_eco_string(), "function");
+    let mut location = ResourceLocation::new_module("zoglin", &["generated", &owner.namespace]);
+    location.modules.extend(owner.modules.iter().cloned());
+    location.with_name(&eco_format!("{function_type}_{counter}"))
   }
 }
 
 impl Compiler {
-  pub fn compile(mut ast: File, output: &str) -> Result<()> {
-    let mut compiler = Compiler::default();
-
-    compiler.register(&mut ast);
-    let tree = compiler.compile_tree(ast)?;
-    tree.generate(output)?;
+  pub fn compile(
+    ast: File,
+    output: &str,
+    options: &CompileOptions,
+    dependent_files: &mut HashSet<EcoString>,
+  ) -> MultiResult<()> {
+    Compiler::compile_to_tree(ast, options, dependent_files)?.generate(output, options)?;
     Ok(())
   }
 
-  fn compile_tree(&mut self, ast: File) -> Result<FileTree> {
+  pub fn compile_to_tree(
+    mut ast: File,
+    options: &CompileOptions,
+    dependent_files: &mut HashSet<EcoString>,
+  ) -> MultiResult<FileTree> {
+    let mut compiler = Compiler {
+      storage_prefix: options.storage_prefix.clone(),
+      inline_load: options.inline_load,
+      loop_guard: options.loop_guard,
+      loop_guard_target: options.loop_guard_target.clone(),
+      debug: options.debug || ast.debug,
+      message_target: options.message_target.clone(),
+      emit_call_stubs: options.emit_call_stubs,
+      profile: options.profile,
+      warnings: options.warnings.clone(),
+      ..Default::default()
+    };
+
+    compiler.register(&mut ast)?;
+    let result = compiler.compile_tree(ast);
+    // Files read at compile-time are tracked even if compilation fails,
+    // so watch mode still rebuilds when they are fixed
+    dependent_files.extend(take(&mut compiler.dependent_files));
+    result
+  }
+
+  fn compile_tree(&mut self, ast: File) -> MultiResult<FileTree> {
+    let mut namespace_names: Vec<EcoString> = Vec::new();
+    for namespace in ast.items.iter() {
+      if !namespace_names.contains(&namespace.name) {
+        namespace_names.push(namespace.name.clone());
+      }
+    }
+
+    // Priorities must be looked up before compiling, which consumes the
+    // namespace scopes
+    let mut load_order = Vec::new();
+    for (index, namespace) in namespace_names.iter().enumerate() {
+      load_order.push((self.load_priority(namespace)?, index, namespace));
+    }
+    load_order.sort_by_key(|(priority, index, _)| (*priority, *index));
+
     for namespace in ast.items {
-      self.compile_namespace(namespace)?;
+      self.compile_namespace(namespace);
+    }
+    // Later steps could report errors caused by the items that failed
+    if !self.errors.is_empty() {
+      return Err(take(&mut self.errors));
     }
 
-    let load_json = FunctionTag {
-      values: &self.load_functions,
-    };
+    // Load functions are generated once every namespace has been compiled,
+    // since any namespace may use objectives owned by another
+    let mut generated_loads = HashSet::new();
+    for namespace in namespace_names.iter() {
+      if self.compile_load_function(namespace)? {
+        generated_loads.insert(namespace.clone());
+      }
+    }
 
-    let load_text = serde_json::to_string_pretty(&load_json).expect("Json is valid");
+    // Every generated load runs before any user load, so objectives are
+    // always set up before they are used
+    let mut load_functions: Vec<EcoString> = load_order
+      .iter()
+      .filter(|(_, _, namespace)| generated_loads.contains(*namespace))
+      .map(|(_, _, namespace)| eco_format!("zoglin:generated/{namespace}/load"))
+      .collect();
+    for (_, _, namespace) in load_order.iter() {
+      load_functions.extend(
+        self
+          .load_functions
+          .iter()
+          .filter(|(owner, _)| owner == *namespace)
+          .map(|(_, function)| function.clone()),
+      );
+    }
 
-    let load = Item::TextResource(TextResource {
-      name: "load".to_eco_string(),
-      kind: "tags/function".to_eco_string(),
-      is_asset: false,
-      text: load_text.into(),
-      location: Location::blank(),
-    });
+    let load_tag = ResourceLocation::new_function("minecraft", &["load"]);
+    load_functions.extend(self.function_tags.remove(&load_tag).unwrap_or_default());
+    // Like the tick tag, the load tag is only written when something is in it.
+    // A user-defined load tag is still written with the other user tags
+    if !load_functions.is_empty() {
+      self.add_tag("tags/function", &load_tag, &load_functions)?;
+    }
 
-    let location = ResourceLocation::new_module("minecraft", &[]);
-    self.add_item(location.clone(), load)?;
+    self.check_output_assertions()?;
+    self.check_function_refs()?;
 
-    if !self.tick_functions.is_empty() {
-      let tick_json = FunctionTag {
-        values: &self.tick_functions,
-      };
-      let tick_text = serde_json::to_string_pretty(&tick_json).expect("Json is valid");
-
-      let tick: Item = Item::TextResource(TextResource {
-        name: "tick".to_eco_string(),
-        kind: "tags/function".to_eco_string(),
-        is_asset: false,
-        text: tick_text.into(),
-        location: Location::blank(),
-      });
-      self.add_item(location, tick)?;
+    for (tag, functions) in take(&mut self.function_tags) {
+      self.add_tag("tags/function", &tag, &functions)?;
+    }
+    // The rest are only defined as resources
+    let user_tags: Vec<_> = self.user_tags.keys().cloned().collect();
+    for (kind, tag) in user_tags {
+      self.add_tag(&kind, &tag, &[])?;
     }
 
-    let namespaces = take(&mut self.namespaces);
+    let mut namespaces = take(&mut self.namespaces);
+    if self.emit_call_stubs {
+      for (name, namespace) in namespaces.iter_mut() {
+        namespace.call_stubs = self.call_stubs(name);
+      }
+    }
+    // Sorted, so compiling the same code always gives the same tree
+    let mut namespaces: Vec<_> = namespaces.into_values().collect();
+    namespaces.sort_by(|a, b| a.name.cmp(&b.name));
     Ok(FileTree {
-      namespaces: namespaces.into_values().collect(),
+      namespaces,
+      tests: take(&mut self.test_functions),
     })
   }
 
-  fn compile_namespace(&mut self, namespace: ast::Namespace) -> Result<()> {
-    self
-      .load_functions
-      .insert(0, eco_format!("zoglin:generated/{}/load", namespace.name));
-
+  fn compile_namespace(&mut self, namespace: ast::Namespace) {
     self.enter_scope(&namespace.name);
     self.comptime_scopes.push(HashMap::new());
 
     let resource = ResourceLocation::new_module(&namespace.name, &[]);
 
     for item in namespace.items {
-      self.compile_item(item, &resource)?;
+      self.compile_item_or_skip(item, &resource);
     }
 
     self.exit_scope();
     self.comptime_scopes.pop();
+  }
+
+  fn load_priority(&self, namespace: &str) -> Result<i32> {
+    let mut priority = 0;
+    for index in self.scopes[0].children.get(namespace).into_iter().flatten() {
+      if let Some(value) = self.scopes[*index].comptime_values.get("load_priority") {
+        priority = match value.kind {
+          ExpressionKind::Byte(_)
+          | ExpressionKind::Short(_)
+          | ExpressionKind::Integer(_)
+          | ExpressionKind::Long(_) => value.kind.numeric_value().expect("Value is numeric"),
+          _ => {
+            return Err(raise_error(
+              value.location.clone(),
+              "`load_priority` must be an integer.",
+            ))
+          }
+        };
+      }
+    }
+    Ok(priority)
+  }
 
-    let load_commands = self
+  // Returns whether a load function was generated, which is skipped when it
+  // would have no commands
+  fn compile_load_function(&mut self, namespace: &str) -> Result<bool> {
+    let mut scoreboards: Vec<_> = self
       .used_scoreboards
       .iter()
-      .map(|scoreboard| {
-        eco_format!(
-          "scoreboard objectives add {} {}",
-          scoreboard.name,
-          scoreboard.criteria
-        )
-      })
-      .chain(self.constant_scoreboard_values.iter().map(|value| {
-        eco_format!("scoreboard players set ${value} zoglin.internal.constants {value}")
-      }))
+      .filter(|(_, scoreboard)| scoreboard.owner == namespace)
       .collect();
+    scoreboards.sort_by_key(|(name, _)| *name);
+
+    let mut load_commands = Vec::new();
+    for (name, scoreboard) in scoreboards {
+      let criteria = scoreboard.criteria(name)?;
+      load_commands.push(eco_format!("scoreboard objectives add {name} {criteria}"));
+    }
+
+    // Deprecation notices are shown once each time the pack is loaded
+    let renamed_objective = eco_format!("zoglin.internal.{namespace}.renamed");
+    if self.used_scoreboards.contains_key(&renamed_objective) {
+      load_commands.push(eco_format!(
+        "scoreboard players reset * {renamed_objective}"
+      ));
+    }
+
+    if let Some(constants) = self.constant_scoreboard_values.get(namespace) {
+      let mut constants: Vec<_> = constants.iter().collect();
+      constants.sort();
+      load_commands.extend(constants.into_iter().map(|value| {
+        eco_format!("scoreboard players set ${value} zoglin.internal.{namespace}.constants {value}")
+      }));
+    }
+
+    if self.inlines_load(namespace) {
+      let items = self.get_location(ResourceLocation::new_module(namespace, &[]));
+      for item in items.iter_mut() {
+        match item {
+          Item::Function(function) if function.name == "load" => {
+            function.commands.splice(0..0, load_commands);
+            break;
+          }
+          _ => {}
+        }
+      }
+      return Ok(false);
+    }
+    if load_commands.is_empty() {
+      return Ok(false);
+    }
 
     let load_function = Item::Function(Function {
       name: "load".to_eco_string(),
@@ -481,25 +867,73 @@ impl Compiler {
       location: Location::blank(),
     });
     self.add_item(
-      ResourceLocation::new_module("zoglin", &["generated", &namespace.name]),
+      ResourceLocation::new_module("zoglin", &["generated", namespace]),
       load_function,
     )?;
+    Ok(true)
+  }
 
-    Ok(())
+  // With `inline_load`, the setup for a namespace is added to the start of
+  // its own load function rather than a generated one
+  fn inlines_load(&self, namespace: &str) -> bool {
+    self.inline_load
+      && self
+        .load_functions
+        .iter()
+        .any(|(owner, _)| owner == namespace)
+  }
+
+  // When an item has an error, it is recorded and compilation carries on with
+  // the next item, so that every error can be reported at once
+  fn compile_item_or_skip(&mut self, item: ast::Item, location: &ResourceLocation) {
+    let scope = self.current_scope;
+    let comptime_depth = self.comptime_scopes.len();
+    if let Err(e) = self.compile_item(item, location) {
+      self.errors.push(e);
+      self.current_scope = scope;
+      self.comptime_scopes.truncate(comptime_depth);
+    }
   }
 
   fn compile_item(&mut self, item: ast::Item, location: &ResourceLocation) -> Result<()> {
     match item {
+      ast::Item::Module(module) if module.is_test_only && self.profile == Profile::Release => {
+        Ok(())
+      }
       ast::Item::Module(module) => self.compile_module(module, location.clone()),
-      ast::Item::Import(_) => Ok(()),
+      ast::Item::Import(import) => {
+        let modules: Vec<_> = import.path.path.iter().map(EcoString::as_str).collect();
+        let path = ResourceLocation::new_function(&import.path.namespace, &modules);
+        self.check_not_excluded(&path, &import.location)
+      }
+      ast::Item::Function(function)
+        if self
+          .excluded_functions
+          .contains(&location.clone().with_name(&function.name)) =>
+      {
+        Ok(())
+      }
       ast::Item::Function(function) => self.compile_ast_function(function, location),
       ast::Item::Resource(resource) => self.compile_resource(resource, location),
-      ast::Item::ComptimeAssignment(_, _) => Ok(()),
+      ast::Item::ComptimeAssignment(..) => Ok(()),
+      ast::Item::BuiltinFunction(name, arguments, builtin_location) => {
+        self.compile_item_builtin(&name, arguments, builtin_location, location)
+      }
       ast::Item::ComptimeFunction(_) => todo!(),
       ast::Item::None => Ok(()),
     }
   }
 
+  fn check_not_excluded(&self, function: &ResourceLocation, location: &Location) -> Result<()> {
+    if self.excluded_functions.contains(function) {
+      return Err(raise_error(
+        location.clone(),
+        format!("Function `{function}` is test-only and excluded in release builds."),
+      ));
+    }
+    Ok(())
+  }
+
   fn compile_module(&mut self, module: ast::Module, mut location: ResourceLocation) -> Result<()> {
     self.enter_scope(&module.name);
     self.comptime_scopes.push(HashMap::new());
@@ -507,7 +941,7 @@ impl Compiler {
     location.modules.push(module.name);
 
     for item in module.items {
-      self.compile_item(item, &location)?;
+      self.compile_item_or_skip(item, &location);
     }
 
     self.exit_scope();
@@ -515,6 +949,121 @@ impl Compiler {
     Ok(())
   }
 
+  fn add_tag(&mut self, kind: &str, tag: &ResourceLocation, generated: &[EcoString]) -> Result<()> {
+    let mut replace = false;
+    let mut replacing = Vec::new();
+    let mut values: Vec<serde_json::Value> = generated
+      .iter()
+      .map(|function| function.as_str().into())
+      .collect();
+
+    let resources = self
+      .user_tags
+      .remove(&(kind.into(), tag.clone()))
+      .unwrap_or_default();
+    for resource in resources {
+      let json: serde_json::Value = serde_json::from_str(&resource.text)
+        .map_err(|e| raise_error(resource.location.clone(), e.to_string()))?;
+      let Some(user_values) = json.get("values").and_then(|values| values.as_array()) else {
+        return Err(raise_error(
+          resource.location,
+          "Tags must have a `values` list.",
+        ));
+      };
+      // Without `replace`, user entries are added after the generated ones
+      if json.get("replace") == Some(&true.into()) {
+        replace = true;
+        replacing.extend(user_values.iter().cloned());
+      } else {
+        values.extend(user_values.iter().cloned());
+      }
+    }
+    replacing.extend(values);
+    let mut values = replacing;
+
+    let mut seen = HashSet::new();
+    values.retain(|entry| tag_entry_id(entry).is_none_or(|id| seen.insert(id.to_string())));
+
+    let text = serde_json::to_string_pretty(&Tag { replace, values }).expect("Json is valid");
+    let name = tag.modules.last().expect("Tags have a name").clone();
+    let resource = Item::TextResource(TextResource {
+      name,
+      kind: kind.into(),
+      is_asset: false,
+      text: text.into(),
+      location: Location::blank(),
+    });
+    self.add_item(tag.clone().module(), resource)
+  }
+
+  fn add_text_resource(
+    &mut self,
+    location: &ResourceLocation,
+    resource: TextResource,
+  ) -> Result<()> {
+    if resource.is_asset || !resource.kind.starts_with("tags/") {
+      return self.add_item(location.clone(), Item::TextResource(resource));
+    }
+
+    let tag = location.clone().with_name(&resource.name);
+    self
+      .user_tags
+      .entry((resource.kind.clone(), tag))
+      .or_default()
+      .push(resource);
+    Ok(())
+  }
+
+  // Adds the advancement of an `on advancement` function, with the same path
+  // and rewarding the function
+  fn compile_function_advancement(
+    &mut self,
+    advancement: ast::Expression,
+    fn_location: &ResourceLocation,
+    location: Location,
+  ) -> Result<()> {
+    let mut context = FunctionContext::new(fn_location.clone(), ReturnType::Direct);
+    let advancement = self.compile_expression(advancement, &mut context, false)?;
+    let Some(serde_json::Value::Object(mut json)) = advancement.kind.to_json() else {
+      return Err(raise_error(
+        advancement.location,
+        "Advancements must be compile-time compounds.",
+      ));
+    };
+
+    let rewards = json
+      .entry("rewards")
+      .or_insert_with(|| serde_json::Value::Object(serde_json::Map::new()));
+    let Some(rewards) = rewards.as_object_mut() else {
+      return Err(raise_error(
+        advancement.location,
+        "The advancement's `rewards` must be a compound.",
+      ));
+    };
+    if rewards.contains_key("function") {
+      return Err(raise_error(
+        advancement.location,
+        "The advancement's reward function is set to this function automatically.",
+      ));
+    }
+    rewards.insert("function".into(), fn_location.to_string().into());
+
+    let (module, name) = fn_location
+      .clone()
+      .try_split()
+      .expect("Is a function location");
+    let resource = TextResource {
+      kind: "advancement".into(),
+      name,
+      is_asset: false,
+      text: serde_json::to_string_pretty(&json)
+        .expect("Json is valid")
+        .into(),
+      location,
+    };
+    self.add_text_resource(&module, resource)
+  }
+
   fn compile_resource(
     &mut self,
     resource: ast::Resource,
@@ -529,7 +1078,78 @@ impl Compiler {
           location: resource.location,
           text,
         };
-        self.add_item(location.clone(), Item::TextResource(resource))
+        self.add_text_resource(location, resource)
+      }
+      ast::ResourceContent::Template(name, body) => {
+        let mut context = FunctionContext::new(location.clone(), ReturnType::Direct);
+        let name = self.compile_expression(name, &mut context, false)?;
+        let ExpressionKind::String(name_text) = &name.kind else {
+          return Err(raise_error(
+            name.location,
+            "Resource names must be compile-time strings.",
+          ));
+        };
+        validate(name_text, &name.location, NameKind::Resource)?;
+
+        let mut text = EcoString::new();
+        for part in body {
+          match part {
+            TemplatePart::Literal(literal) => text.push_str(&literal),
+            TemplatePart::Expression(expression, string_char) => {
+              let value = self.compile_expression(expression, &mut context, false)?;
+              let spliced = match (string_char, &value.kind) {
+                (Some(quote), ExpressionKind::String(string)) => {
+                  Some(escape_json_string(string, quote))
+                }
+                (Some(quote), kind) => kind
+                  .to_comptime_string(false)
+                  .map(|string| escape_json_string(&string, quote)),
+                (None, _) => value.kind.to_json().map(|json| json.to_eco_string()),
+              };
+              let Some(spliced) = spliced else {
+                return Err(raise_error(
+                  value.location,
+                  "Only compile-time values can be used in resource templates.",
+                ));
+              };
+              text.push_str(&spliced);
+            }
+            TemplatePart::TextComponent(kind, expression) => {
+              let commands = context.code.len();
+              let value = self.compile_expression(expression, &mut context, false)?;
+              let component = match (kind, &value.kind) {
+                (TextComponentKind::Score, ExpressionKind::Scoreboard(scoreboard)) => {
+                  Some(score_component(scoreboard))
+                }
+                (TextComponentKind::Nbt, ExpressionKind::Storage(storage)) => {
+                  Some(storage_component(storage))
+                }
+                (TextComponentKind::Nbt, ExpressionKind::Data(data)) => Some(data_component(data)),
+                _ => None,
+              };
+              // Values that need commands to calculate can't be shown in a resource
+              let Some(component) = component.filter(|_| context.code.len() == commands) else {
+                return Err(raise_error(
+                  value.location,
+                  match kind {
+                    TextComponentKind::Score => "Only scoreboard variables can be used in `score` components.",
+                    TextComponentKind::Nbt => "Only storage variables and entity or block data can be used in `nbt` components.",
+                  },
+                ));
+              };
+              text.push_str(&component.to_eco_string());
+            }
+          }
+        }
+
+        let resource = TextResource {
+          kind: resource.kind,
+          name: name_text.clone(),
+          is_asset: resource.is_asset,
+          text: json5_to_json(&text, resource.location.clone())?,
+          location: resource.location,
+        };
+        self.add_text_resource(location, resource)
       }
       ast::ResourceContent::File(path, file) => {
         let file_path = Path::new(file.as_str())
@@ -559,15 +1179,29 @@ impl Compiler {
       Statement::Command(command) => {
         let result = self.compile_command(command, context)?;
         context.code.push(result);
+        context.pure_calls.clear();
+        context.constants.clear();
       }
       Statement::Comment(comment) => {
+        if comment.trim_start_matches('#').trim() == ALLOW_UNASSIGNED_COMMENT {
+          context.variables.allow_unassigned = true;
+        }
         context.code.push(comment);
       }
       Statement::Expression(expression) => {
+        if let ast::Expression::FunctionCall(function_call) = &expression {
+          self.warn_unused_result(function_call, context)?;
+        }
+        self.compile_expression(expression, context, true)?;
+      }
+      Statement::Discard(expression) => {
         self.compile_expression(expression, context, true)?;
       }
       Statement::If(if_statement) => {
+        // Branches known to be taken run in the same function, so can return directly
+        let is_nested = context.is_nested;
         let mut sub_context = context.child(true);
+        sub_context.is_nested = is_nested;
         sub_context.has_nested_returns = RefOrOwned::Owned(false);
 
         self.comptime_scopes.push(HashMap::new());
@@ -577,8 +1211,12 @@ impl Compiler {
           self.generate_nested_return(context);
         }
         self.comptime_scopes.pop();
+        context.pure_calls.clear();
+        context.constants.clear();
       }
       Statement::WhileLoop(while_loop) => {
+        // The condition is checked again after the body runs
+        context.constants.clear();
         let mut sub_context = context.child(true);
         sub_context.has_nested_returns = RefOrOwned::Owned(false);
 
@@ -589,13 +1227,68 @@ impl Compiler {
           self.generate_nested_return(context);
         }
         self.comptime_scopes.pop();
+        context.pure_calls.clear();
+        context.constants.clear();
+      }
+      Statement::Execute(execute) => {
+        // The block can run any number of times, as other entities
+        context.constants.clear();
+        let mut sub_context = context.child(true);
+        sub_context.has_nested_returns = RefOrOwned::Owned(false);
+
+        self.comptime_scopes.push(HashMap::new());
+        self.compile_execute_block(execute, &mut sub_context)?;
+        if *sub_context.has_nested_returns {
+          *context.has_nested_returns = true;
+          self.generate_nested_return(context);
+        }
+        self.comptime_scopes.pop();
+        context.pure_calls.clear();
+        context.constants.clear();
+      }
+      Statement::ForLoop(for_loop) => {
+        // Unrolled loops run in the same function, so can return directly
+        let is_nested = context.is_nested;
+        let mut sub_context = context.child(true);
+        sub_context.is_nested = is_nested;
+        sub_context.has_nested_returns = RefOrOwned::Owned(false);
+
+        self.comptime_scopes.push(HashMap::new());
+        self.compile_for_loop(*for_loop, &mut sub_context)?;
+        if *sub_context.has_nested_returns {
+          *context.has_nested_returns = true;
+          self.generate_nested_return(context);
+        }
+        self.comptime_scopes.pop();
+        context.pure_calls.clear();
+        context.constants.clear();
       }
       Statement::Return(value) => self.compile_return(value, context)?,
+      Statement::LocalComptime(name, value) => {
+        let value = self.compile_expression(value, context, false)?;
+        if !value.kind.compile_time_known() {
+          self.pin_temps();
+        }
+        self
+          .comptime_scopes
+          .last_mut()
+          .expect("There must be at least one scope")
+          .insert(name, value);
+      }
     }
     Ok(())
   }
 
   fn generate_nested_return(&mut self, context: &mut FunctionContext) {
+    // Generated functions leave the flag set, so each function it was returned
+    // through also returns
+    if context.is_nested {
+      context.code.push(eco_format!(
+        "execute if score $should_return zoglin.internal.{namespace}.vars matches -2147483648..2147483647 run return 0",
+        namespace = context.location.namespace
+      ));
+      return;
+    }
     let return_command = match context.return_type {
       ReturnType::Storage | ReturnType::Scoreboard => &eco_format!(
         "return run scoreboard players reset $should_return zoglin.internal.{namespace}.vars",
@@ -611,20 +1304,102 @@ impl Compiler {
 
   fn compile_ast_function(
     &mut self,
-    function: ast::Function,
+    mut function: ast::Function,
     location: &ResourceLocation,
   ) -> Result<()> {
     let fn_location = location.clone().with_name(&function.name);
-    let mut context = FunctionContext::new(fn_location, function.return_type);
+    let wrapper = self.module_wrapper(&fn_location);
+    if function.is_test {
+      if !function.parameters.is_empty() {
+        return Err(raise_error(
+          function.location,
+          "Test functions cannot take parameters.",
+        ));
+      }
+      // Tests have no entity to run as, so run without the wrapper
+      self.test_functions.push(match wrapper {
+        Some(_) => Compiler::wrapped_implementation(&fn_location),
+        None => fn_location.clone(),
+      });
+    }
+    let mut context = FunctionContext::new(fn_location.clone(), function.return_type);
+    if let Some(advancement) = function.advancement.take() {
+      if let Some((module, _)) = wrapper {
+        return Err(raise_error(
+          function.location,
+          eco_format!(
+            "Advancement functions run as the player, so can't be in `{module}`, which wraps its functions."
+          ),
+        ));
+      }
+      if !function.parameters.is_empty() {
+        return Err(raise_error(
+          function.location,
+          "Advancement functions cannot take parameters.",
+        ));
+      }
+      self.compile_function_advancement(advancement, &fn_location, function.location.clone())?;
+      // Revoking it first lets the advancement be granted again
+      context
+        .code
+        .push(eco_format!("advancement revoke @s only {fn_location}"));
+    }
+    // Functions with compile-time parameters are only compiled when called
+    if self.generic_function_registry.contains_key(&fn_location) {
+      if let Some((_, location)) = function.renamed_from.first() {
+        return Err(raise_error(
+          location.clone(),
+          "Functions with compile-time parameters cannot be renamed.",
+        ));
+      }
+      if let Some((_, location)) = function.tags.first() {
+        return Err(raise_error(
+          location.clone(),
+          "Functions with compile-time parameters cannot be added to tags.",
+        ));
+      }
+      return Ok(());
+    }
+    for (tag, location) in function.tags.iter() {
+      let tag = function_tag(tag, location)?;
+      self
+        .function_tags
+        .entry(tag)
+        .or_default()
+        .push(fn_location.to_eco_string());
+    }
+    for (old_path, location) in function.renamed_from.iter() {
+      self.compile_renamed_shim(old_path, location.clone(), &fn_location, &function)?;
+    }
+    context.variables.assigned.extend(
+      function
+        .parameters
+        .iter()
+        .filter(|parameter| parameter.kind == ParameterKind::Storage)
+        .map(|parameter| parameter.name.clone()),
+    );
     self.comptime_scopes.push(HashMap::new());
 
-    self.compile_block(&mut context, function.items)?;
+    if function.is_pure {
+      warn_impure_commands(&function.items, &self.warnings);
+    }
+    self.push_temp_pool(&fn_location.namespace);
+    let result = self.compile_block(&mut context, function.items);
+    self.pop_temp_pool();
+    result?;
     self.comptime_scopes.pop();
-    self.add_function_item(
-      function.location,
-      context.location.moved(),
-      context.code.moved(),
-    )
+    warn_unassigned_variables(&context.variables, &self.warnings);
+    let mut fn_location = context.location.moved();
+    if let Some((_, fragment)) = wrapper {
+      self.compile_wrapper(
+        &fn_location,
+        &fragment,
+        &function.parameters,
+        function.return_type,
+      )?;
+      fn_location = Compiler::wrapped_implementation(&fn_location);
+    }
+    self.add_function_item(function.location, fn_location, context.code.moved())
   }
 
   fn add_function_item(
@@ -645,7 +1420,9 @@ impl Compiler {
 
   fn compile_block(&mut self, context: &mut FunctionContext, block: Vec<Statement>) -> Result<()> {
     for item in block {
+      let mark = self.temp_mark();
       self.compile_statement(item, context)?;
+      self.release_temps(mark);
     }
     Ok(())
   }
@@ -655,34 +1432,115 @@ impl Compiler {
     command: Command,
     context: &mut FunctionContext,
   ) -> Result<EcoString> {
+    let has_macro_prefix = matches!(
+      command.parts.first(),
+      Some(ast::CommandPart::Literal(lit)) if lit.starts_with('$')
+    );
+    let (mut result, is_macro) = self.compile_command_parts(command.parts, context)?;
+    self.check_raw_function_calls(&result, &command.location);
+
+    if is_macro && !has_macro_prefix {
+      result = eco_format!("${result}")
+    }
+
+    Ok(result)
+  }
+
+  // Functions with macro parameters read them from their storage, so raw
+  // commands calling them must pass it with `with storage`
+  fn check_raw_function_calls(&self, command: &str, location: &Location) {
+    let words: Vec<_> = command.split_whitespace().collect();
+    for (index, word) in words.iter().enumerate() {
+      if word.trim_start_matches('$') != "function" {
+        continue;
+      }
+      let Some((namespace, path)) = words.get(index + 1).and_then(|path| path.split_once(':'))
+      else {
+        continue;
+      };
+      let modules: Vec<_> = path.split('/').collect();
+      let function = ResourceLocation::new_function(namespace, &modules);
+      let has_macro_parameters = self
+        .function_registry
+        .get(&function)
+        .is_some_and(|definition| {
+          definition
+            .arguments
+            .iter()
+            .any(|parameter| parameter.kind == ParameterKind::Macro)
+        });
+      if !has_macro_parameters {
+        continue;
+      }
+
+      let storage = self.function_storage(&function).to_eco_string();
+      let source = &words[index + 2..];
+      match source {
+        ["with", "storage", given] if *given == storage => {}
+        // Macro storage can't be checked, and entity or block data could be anything
+        ["with", "storage", given, ..] if given.contains("$(") => {}
+        ["with", "entity" | "block", ..] => {}
+        ["with", ..] => self.warnings.push(raise_warning(
+          location.clone(),
+          format!(
+            "The macro parameters of `{function}` are read from `storage {storage}`, not `{}`.",
+            source[1..].join(" ")
+          ),
+        )),
+        _ => self.warnings.push(raise_warning(
+          location.clone(),
+          format!("`{function}` has macro parameters, so must be called `with storage {storage}`."),
+        )),
+      }
+    }
+  }
+
+  fn compile_command_parts(
+    &mut self,
+    parts: Vec<ast::CommandPart>,
+    context: &mut FunctionContext,
+  ) -> Result<(EcoString, bool)> {
     let mut result = EcoString::new();
     let mut is_macro = false;
-    let mut has_macro_prefix = false;
 
-    for (i, part) in command.parts.into_iter().enumerate() {
+    for part in parts {
       match part {
-        ast::CommandPart::Literal(lit) => {
-          if i == 0 && lit.starts_with('$') {
-            has_macro_prefix = true;
-          }
-
-          result.push_str(&lit)
-        }
+        ast::CommandPart::Literal(lit) => result.push_str(&lit),
         ast::CommandPart::Expression(expr) => {
-          let (code, needs_macro) = self.compile_static_expr(expr, context)?;
+          let (code, needs_macro) =
+            self.compile_static_expr(expr, Interpolation::Plain, context)?;
           is_macro = is_macro || needs_macro;
           result.push_str(&code)
         }
       }
     }
 
-    result = result.trim().into();
+    Ok((result.trim().into(), is_macro))
+  }
 
-    if is_macro && !has_macro_prefix {
-      result = eco_format!("${result}")
+  fn compile_data_target(
+    &mut self,
+    kind: DataKind,
+    parts: Vec<ast::CommandPart>,
+    location: Location,
+    context: &mut FunctionContext,
+  ) -> Result<Expression> {
+    let (target, needs_macro) = self.compile_command_parts(parts, context)?;
+    if target.is_empty() {
+      return Err(raise_error(
+        location,
+        match kind {
+          DataKind::Entity => "Expected an entity selector.",
+          DataKind::Block => "Expected a block position.",
+        },
+      ));
     }
 
-    Ok(result)
+    Ok(Expression::with_macro(
+      ExpressionKind::Data(DataLocation::new(kind, target)),
+      location,
+      needs_macro,
+    ))
   }
 
   fn compile_expression(
@@ -697,15 +1555,34 @@ impl Compiler {
       }
       ast::Expression::FunctionCall(function_call) => {
         let location = function_call.path.location.clone();
+        let code_start = context.code.len();
         let (command, called) = self.compile_function_call(function_call, context)?;
-        match called.return_type {
+        if let Some(cached) = called
+          .pure_key
+          .as_ref()
+          .and_then(|key| context.pure_calls.get(key))
+        {
+          // The arguments are all constants, so the only code generated sets the parameters
+          context.code.truncate(code_start);
+          return Ok(Expression::new(cached.kind.clone(), location));
+        }
+
+        // Unused results skip resetting the return slot, so nothing may read it afterwards
+        if ignored {
+          context.code.push(command);
+          return Ok(Expression::new(ExpressionKind::Void, location));
+        }
+
+        let result = match called.return_type {
           ReturnType::Storage => {
-            let storage = StorageLocation::new(called.location, "return".to_eco_string());
-            if !ignored {
-              context
-                .code
-                .push(eco_format!("data modify storage {storage} set value false",))
-            }
+            let storage = StorageLocation::new(
+              self.function_storage(&called.location),
+              "return".to_eco_string(),
+            );
+            context.code.push(eco_format!(
+              "data modify storage {storage} set value {}",
+              called.return_shape.empty_value()
+            ));
             context.code.push(command);
             Expression {
               location,
@@ -715,11 +1592,9 @@ impl Compiler {
           }
           ReturnType::Scoreboard => {
             let scoreboard = ScoreboardLocation::new(called.location, "$return");
-            if !ignored {
-              context
-                .code
-                .push(eco_format!("scoreboard players set {scoreboard} 0",))
-            }
+            context
+              .code
+              .push(eco_format!("scoreboard players set {scoreboard} 0",));
             context.code.push(command);
             Expression {
               location,
@@ -738,6 +1613,11 @@ impl Compiler {
               needs_macro: false,
             }
           }
+        };
+
+        match called.pure_key {
+          Some(key) => self.cache_pure_call(key, result, called.return_type, context)?,
+          None => result,
         }
       }
       ast::Expression::Byte(b, location) => Expression::new(ExpressionKind::Byte(b), location),
@@ -756,23 +1636,33 @@ impl Compiler {
       ast::Expression::Compound(key_values, location) => {
         self.compile_compound(key_values, location, context)?
       }
-      ast::Expression::Variable(variable) => Expression::new(
-        ExpressionKind::Storage(StorageLocation::from_zoglin_resource(
-          &context.location,
-          &variable,
-        )),
-        variable.location,
-      ),
-      ast::Expression::ScoreboardVariable(variable) => Expression::new(
-        ExpressionKind::Scoreboard(ScoreboardLocation::from_zoglin_resource(
-          &context.location,
-          &variable,
-        )),
-        variable.location,
-      ),
+      ast::Expression::Variable(variable) => {
+        if variable.namespace.is_none() && variable.modules.is_empty() {
+          context
+            .variables
+            .read
+            .push((variable.name.clone(), variable.location.clone()));
+        }
+        let storage = self.variable_storage(&context.location, &variable);
+        match context.constants.get(&storage.to_eco_string()) {
+          Some(value) => Expression::new(value.kind.clone(), variable.location),
+          None => Expression::new(ExpressionKind::Storage(storage), variable.location),
+        }
+      }
+      ast::Expression::ScoreboardVariable(variable) => {
+        let scoreboard = ScoreboardLocation::from_zoglin_resource(&context.location, &variable);
+        match context.constants.get(&scoreboard.to_eco_string()) {
+          Some(value) => Expression::new(value.kind.clone(), variable.location),
+          None => Expression::new(ExpressionKind::Scoreboard(scoreboard), variable.location),
+        }
+      }
+      ast::Expression::ReturnSlot(location) => self.return_slot(location, context)?,
+      ast::Expression::DataTarget(kind, parts, location) => {
+        self.compile_data_target(kind, parts, location, context)?
+      }
       ast::Expression::MacroVariable(name, location) => Expression::with_macro(
         ExpressionKind::Macro(StorageLocation::new(
-          context.location.clone(),
+          self.function_storage(&context.location),
           eco_format!("__{name}"),
         )),
         location,
@@ -782,10 +1672,13 @@ impl Compiler {
         if let Some(value) = self.lookup_comptime_variable(&name) {
           return Ok(value.clone());
         } else {
-          return Err(raise_error(
-            location,
-            eco_format!("The compile-time variable {name} is not in scope."),
-          ));
+          return Err(
+            raise_error(
+              location,
+              eco_format!("The compile-time variable {name} is not in scope."),
+            )
+            .with_kind(ErrorKind::Missing(eco_format!("&{name}"))),
+          );
         }
       }
       ast::Expression::BinaryOperation(binary_operation) => {
@@ -797,10 +1690,15 @@ impl Compiler {
       ast::Expression::Index(index) => self.compile_index(index, context)?,
       ast::Expression::RangeIndex(index) => self.compile_range_index(index, context)?,
       ast::Expression::Member(member) => self.compile_member(member, context)?,
-      ast::Expression::BuiltinVariable(_name, _location) => todo!("Builtin variables"),
+      ast::Expression::BuiltinVariable(name, location) => {
+        self.compile_builtin_variable(&name, location, context)?
+      }
       ast::Expression::BuiltinFunction(name, arguments, location) => {
         self.compile_builtin_function(&name, arguments, location, context)?
       }
+      ast::Expression::Dump(arguments, location) => {
+        self.compile_dump(arguments, location, context)?
+      }
     })
   }
 
@@ -813,8 +1711,10 @@ impl Compiler {
   ) -> Result<Expression> {
     let mut types = Vec::new();
 
-    for expr in expressions {
-      types.push(self.compile_expression(expr, context, false)?);
+    let mut expressions = expressions.into_iter();
+    while let Some(expr) = expressions.next() {
+      let value = self.compile_expression(expr, context, false)?;
+      types.push(self.preserve_operand(value, expressions.as_slice().iter(), context)?);
     }
 
     let err_msg = match typ {
@@ -846,16 +1746,20 @@ impl Compiler {
   ) -> Result<Expression> {
     let mut types = HashMap::new();
 
-    for KeyValue {
+    let mut key_values = key_values.into_iter();
+    while let Some(KeyValue {
       key,
       value,
       location,
-    } in key_values
+    }) = key_values.next()
     {
-      if types
-        .insert(key, self.compile_expression(value, context, false)?)
-        .is_some()
-      {
+      let value = self.compile_expression(value, context, false)?;
+      let later = key_values
+        .as_slice()
+        .iter()
+        .map(|key_value| &key_value.value);
+      let value = self.preserve_operand(value, later, context)?;
+      if types.insert(key, value).is_some() {
         return Err(raise_error(location, "Duplicate keys not allowed"));
       }
     }
@@ -867,19 +1771,19 @@ impl Compiler {
   fn compile_static_expr(
     &mut self,
     expr: StaticExpr,
+    interpolation: Interpolation,
     context: &mut FunctionContext,
   ) -> Result<(EcoString, bool)> {
     match expr {
+      StaticExpr::Nbt(expr) => self.compile_static_expr(*expr, Interpolation::Nbt, context),
       StaticExpr::FunctionCall(call) => {
         if call.comptime {
+          let location = call.path.location.clone();
           let value = self
             .compile_comptime_call(call, context)?
             .kind
-            .to_comptime_string(true)
-            .ok_or(raise_floating_error(
-              // TODO: Add location
-              "This value cannot be statically resolved.",
-            ))?;
+            .to_command_string(interpolation)
+            .ok_or_else(|| raise_error(location, "This value cannot be statically resolved."))?;
           Ok((value, false))
         } else {
           Ok((self.compile_function_call(call, context)?.0, false))
@@ -896,24 +1800,33 @@ impl Compiler {
         false,
       )),
       StaticExpr::MacroVariable(name) => Ok((eco_format!("$(__{name})"), true)),
-      StaticExpr::ComptimeVariable(name) => {
+      StaticExpr::ComptimeVariable(name, location) => {
         if let Some(value) = self.lookup_comptime_variable(&name) {
           value
             .kind
-            .to_comptime_string(true)
-            .ok_or(raise_floating_error(
-              // TODO: Add location
-              "This value cannot be statically resolved.",
-            ))
+            .to_command_string(interpolation)
+            .ok_or_else(|| raise_error(location, "This value cannot be statically resolved."))
             .map(|value| (value, false))
         } else {
-          Err(raise_floating_error(
-            // TODO: Add a location here
-            eco_format!("The compile-time variable {name} is not in scope."),
-          ))
+          Err(
+            raise_error(
+              location,
+              eco_format!("The compile-time variable {name} is not in scope."),
+            )
+            .with_kind(ErrorKind::Missing(eco_format!("&{name}"))),
+          )
         }
       }
 
+      StaticExpr::Expression(expression) => {
+        let value = self.compile_expression(expression, context, false)?;
+        (value.kind.compile_time_known())
+          .then(|| value.kind.to_command_string(interpolation))
+          .flatten()
+          .ok_or_else(|| raise_error(value.location, "This value cannot be statically resolved."))
+          .map(|value| (value, false))
+      }
+
       StaticExpr::ResourceRef { resource } => Ok((
         ResourceLocation::from_zoglin_resource(&context.location.clone().module(), &resource)
           .to_eco_string(),
@@ -922,6 +1835,32 @@ impl Compiler {
     }
   }
 
+  fn warn_unused_result(
+    &mut self,
+    function_call: &FunctionCall,
+    context: &FunctionContext,
+  ) -> Result<()> {
+    if function_call.comptime {
+      return Ok(());
+    }
+    let path = self.resolve_zoglin_resource(
+      function_call.path.clone(),
+      &context.location.clone().module(),
+      false,
+    )?;
+    if self
+      .function_registry
+      .get(&path)
+      .is_some_and(|function| function.must_use)
+    {
+      self.warnings.push(raise_warning(
+        function_call.path.location.clone(),
+        format!("The result of `{path}` should be used. Assign it to `_` to ignore it."),
+      ));
+    }
+    Ok(())
+  }
+
   fn compile_function_call(
     &mut self,
     function_call: FunctionCall,
@@ -934,46 +1873,168 @@ impl Compiler {
       &context.location.clone().module(),
       false,
     )?;
-    let function_definition = if let Some(function_definition) = self.function_registry.get(&path) {
-      function_definition.clone()
-    } else {
-      FunctionDefinition {
-        location: path.clone(),
-        arguments: Vec::new(),
-        return_type: ReturnType::Direct,
-      }
-    };
+    self.check_not_excluded(&path, &src_location)?;
+    let mut function_definition =
+      if let Some(function_definition) = self.function_registry.get(&path) {
+        function_definition.clone()
+      } else {
+        FunctionDefinition {
+          location: path.clone(),
+          arguments: Vec::new(),
+          return_type: ReturnType::Direct,
+          return_shape: NbtType::Unknown,
+          is_pure: false,
+          must_use: false,
+        }
+      };
 
     let has_macro_args = function_definition
       .arguments
       .iter()
       .any(|param| param.kind == ParameterKind::Macro);
-    let parameter_storage = function_definition.location.clone();
-
-    let mut arguments = function_call.arguments.into_iter();
 
     let mut default_context =
       FunctionContext::new(function_definition.location.clone(), ReturnType::Direct);
 
-    for parameter in function_definition.arguments {
-      let argument = match (arguments.next(), parameter.default) {
-        (Some(arg), _) => self.compile_expression(arg, context, false)?,
-        (None, Some(parameter)) => {
-          let expr = self.compile_expression(parameter, &mut default_context, false)?;
+    // Calls to pure functions can only be reused if the arguments are known at compile time
+    let mut pure_key = function_definition
+      .is_pure
+      .then(|| function_definition.location.to_eco_string());
+
+    // Functions from other packs aren't known, so any arguments are passed
+    let argument_count = function_call.arguments.len();
+    if self.function_registry.contains_key(&path) {
+      check_argument_count(
+        &function_definition.arguments,
+        argument_count,
+        &function_definition.signature(),
+        &src_location,
+      )?;
+    }
+
+    // Arguments are evaluated from left to right, then the parameters are set,
+    // so an argument calling the same function can't overwrite earlier ones
+    let mut argument_sources: Vec<_> = function_definition
+      .arguments
+      .iter()
+      .map(|parameter| parameter.default.clone())
+      .collect();
+    for (index, argument) in function_call.arguments.into_iter().enumerate() {
+      if let Some(source) = argument_sources.get_mut(index) {
+        *source = Some(argument);
+      }
+    }
+
+    let mut arguments = Vec::new();
+    for index in 0..argument_sources.len() {
+      let argument = match argument_sources[index].take() {
+        Some(argument) if index < argument_count => {
+          self.compile_expression(argument, context, false)?
+        }
+        Some(default) => {
+          let expr = self.compile_expression(default, &mut default_context, false)?;
           context.code.extend(take(default_context.code.as_mut()));
           expr
         }
-        (None, None) => return Err(raise_error(src_location, "Expected more arguments")),
+        None => unreachable!("The number of arguments is checked"),
+      };
+      let argument = self.preserve_operand(
+        argument,
+        argument_sources[index + 1..].iter().flatten(),
+        context,
+      )?;
+
+      if let Some(key) = &mut pure_key {
+        match argument.kind.to_comptime_string(false) {
+          Some(value) if argument.kind.compile_time_known() && !argument.needs_macro => {
+            key.push(' ');
+            key.push_str(&value);
+          }
+          _ => pure_key = None,
+        }
+      }
+
+      arguments.push(argument);
+    }
+
+    if self.generic_function_registry.contains_key(&path) {
+      self.select_variant(&mut function_definition, &mut arguments)?;
+    }
+    let parameter_storage = self.function_storage(&function_definition.location);
+
+    // Two or more constant arguments are written with a single `data merge`.
+    // Compounds are left out, since merging keeps the old value's other keys
+    let constants: Vec<_> = function_definition
+      .arguments
+      .iter()
+      .zip(&arguments)
+      .map(|(parameter, argument)| merged_argument(parameter, argument))
+      .collect();
+    let merge_constants = constants.iter().flatten().count() >= 2;
+
+    // Parameters are set one at a time, so an argument read from a parameter
+    // that is set before it, such as in a recursive call swapping them, is
+    // copied first
+    let keys: Vec<_> = function_definition
+      .arguments
+      .iter()
+      .map(parameter_key)
+      .collect();
+    for (index, argument) in arguments.iter_mut().enumerate() {
+      let Some(read) =
+        read_parameter_key(argument, &parameter_storage, &function_definition.location)
+      else {
+        continue;
+      };
+      let overwritten = keys.iter().enumerate().any(|(other, key)| {
+        other != index
+          && key.as_deref() == Some(read)
+          && (other < index || (merge_constants && constants[other].is_some()))
+      });
+      if !overwritten {
+        continue;
+      }
+
+      let namespace = context.location.namespace.clone();
+      let kind = match argument.kind {
+        ExpressionKind::Scoreboard(_) => ExpressionKind::Scoreboard(self.copy_to_scoreboard(
+          &mut context.code,
+          argument,
+          &namespace,
+        )?),
+        _ => {
+          ExpressionKind::Storage(self.copy_to_storage(&mut context.code, argument, &namespace)?)
+        }
       };
+      *argument = Expression::new(kind, argument.location.clone());
+    }
+    if merge_constants {
+      let entries: Vec<_> = constants.iter().flatten().cloned().collect();
+      context.code.push(eco_format!(
+        "data merge storage {parameter_storage} {{{}}}",
+        entries.join(", ")
+      ));
+    }
 
+    for ((parameter, argument), constant) in function_definition
+      .arguments
+      .into_iter()
+      .zip(arguments)
+      .zip(constants)
+    {
+      if merge_constants && constant.is_some() {
+        continue;
+      }
       match parameter.kind {
         ParameterKind::Storage => {
           let storage = StorageLocation::new(parameter_storage.clone(), parameter.name);
           self.set_storage(&mut context.code, &storage, &argument)?;
         }
         ParameterKind::Scoreboard => {
+          // Scoreboards are never prefixed, as the function reads them from
+          // its own objective
           let scoreboard = ScoreboardLocation::new(
-            parameter_storage.clone(),
+            function_definition.location.clone(),
             &eco_format!("${}", &parameter.name),
           );
           self.set_scoreboard(&mut context.code, &scoreboard, &argument)?;
@@ -985,27 +2046,60 @@ impl Compiler {
           );
           self.set_storage(&mut context.code, &storage, &argument)?;
         }
-        ParameterKind::CompileTime => todo!(),
+        ParameterKind::CompileTime => unreachable!("Removed when selecting the variant"),
       }
     }
 
+    if !function_definition.is_pure {
+      context.pure_calls.clear();
+    }
+    // Even pure functions overwrite their parameters
+    context.constants.clear();
+
+    let target = self.call_target(&function_definition.location, &context.location);
     let command = if has_macro_args {
-      eco_format!(
-        "function {} with storage {parameter_storage}",
-        function_definition.location
-      )
+      eco_format!("function {target} with storage {parameter_storage}")
     } else {
-      eco_format!("function {}", function_definition.location)
+      eco_format!("function {target}")
     };
     Ok((
       command,
       CalledFunction {
         location: function_definition.location,
         return_type: function_definition.return_type,
+        return_shape: function_definition.return_shape,
+        pure_key,
       },
     ))
   }
 
+  // The return value of a function is overwritten by its next call, so the
+  // result is copied somewhere that only this call uses
+  fn cache_pure_call(
+    &mut self,
+    key: EcoString,
+    result: Expression,
+    return_type: ReturnType,
+    context: &mut FunctionContext,
+  ) -> Result<Expression> {
+    let namespace = context.location.namespace.clone();
+    let code = &mut context.code;
+    let kind = match return_type {
+      ReturnType::Storage => {
+        ExpressionKind::Storage(self.copy_to_storage(code, &result, &namespace)?)
+      }
+      ReturnType::Scoreboard => {
+        ExpressionKind::Scoreboard(self.copy_to_scoreboard(code, &result, &namespace)?)
+      }
+      ReturnType::Direct => result.kind,
+    };
+    let result = Expression::new(kind, result.location);
+    // Cached results are read by later statements
+    self.pin_temps();
+    context.pure_calls.insert(key, result.clone());
+    Ok(result)
+  }
+
   fn compile_comptime_call(
     &mut self,
     function_call: FunctionCall,
@@ -1013,16 +2107,26 @@ impl Compiler {
   ) -> Result<Expression> {
     let source_location = function_call.path.location.clone();
 
-    let resource = self.resolve_zoglin_resource(function_call.path, &context.location, true)?;
+    let resource =
+      self.resolve_zoglin_resource(function_call.path, &context.location.clone().module(), true)?;
     let comptime_function = self
       .comptime_function_registry
       .get(&resource)
-      .ok_or(raise_error(
-        source_location.clone(),
-        eco_format!("Compile-time function &{resource} does not exist"),
-      ))?
+      .ok_or_else(|| {
+        raise_error(
+          source_location.clone(),
+          eco_format!("Compile-time function &{resource} does not exist"),
+        )
+        .with_kind(ErrorKind::Missing(eco_format!("&{resource}")))
+      })?
       .clone();
 
+    check_argument_count(
+      &comptime_function.parameters,
+      function_call.arguments.len(),
+      &comptime_function.signature(),
+      &source_location,
+    )?;
     let mut arguments = function_call.arguments.into_iter();
 
     self.comptime_scopes.push(HashMap::new());
@@ -1038,34 +2142,88 @@ impl Compiler {
           context.code.extend(take(default_context.code.as_mut()));
           expr
         }
-        (None, None) => return Err(raise_error(source_location, "Expected more arguments")),
+        (None, None) => unreachable!("The number of arguments is checked"),
       };
       self
         .comptime_scopes
         .last_mut()
-        .expect("The must be at least one scope")
+        .expect("There must be at least one scope")
         .insert(parameter.name.clone(), argument);
     }
 
-    let mut return_value = None;
+    let return_value = self
+      .run_comptime_block(comptime_function.body, context)?
+      .flatten();
+
+    self.comptime_scopes.pop();
+    Ok(return_value.unwrap_or(Expression::new(ExpressionKind::Void, source_location)))
+  }
 
-    for statement in comptime_function.body {
+  // Runs the statements of a compile-time function, returning `Some` with the
+  // returned value once a return statement is reached
+  fn run_comptime_block(
+    &mut self,
+    block: Vec<Statement>,
+    context: &mut FunctionContext,
+  ) -> Result<Option<Option<Expression>>> {
+    for statement in block {
       match statement {
-        // TODO: Handle returns in comptime-if
         Statement::Return(value) => {
-          return_value = match value {
+          return Ok(Some(match value {
             Some(value) => Some(self.compile_expression(value, context, false)?),
             None => None,
-          };
-          break;
+          }));
+        }
+        Statement::If(if_statement) => {
+          if let Some(value) = self.run_comptime_if(if_statement, context)? {
+            return Ok(Some(value));
+          }
         }
         // TODO: Prevent returns in nested blocks
         _ => self.compile_statement(statement, context)?,
       }
     }
+    Ok(None)
+  }
+
+  // If statements in compile-time functions choose a branch while compiling,
+  // so a return inside one returns from the function
+  fn run_comptime_if(
+    &mut self,
+    if_statement: IfStatement,
+    context: &mut FunctionContext,
+  ) -> Result<Option<Option<Expression>>> {
+    let mut if_statement = if_statement;
+    let block = loop {
+      if let Some((_, location)) = if_statement.binding {
+        return Err(raise_error(
+          location,
+          "`if let` can't be used in compile-time functions.",
+        ));
+      }
+      let condition = self.compile_expression(if_statement.condition, context, false)?;
+      let location = condition.location.clone();
+      match condition.to_condition(self, &mut context.code, &context.location.namespace, false)? {
+        ConditionKind::Known(true) => break if_statement.block,
+        ConditionKind::Known(false) => {}
+        ConditionKind::Check(_) => {
+          return Err(raise_error(
+            location,
+            "Conditions in compile-time functions must be known at compile time.",
+          ))
+        }
+      }
+      match if_statement.child {
+        Some(ElseStatement::IfStatement(child)) => if_statement = *child,
+        Some(ElseStatement::Block(block)) => break block,
+        None => return Ok(None),
+      }
+    };
 
+    self.comptime_scopes.push(HashMap::new());
+    let result = self.run_comptime_block(block, context);
     self.comptime_scopes.pop();
-    Ok(return_value.unwrap_or(Expression::new(ExpressionKind::Void, source_location)))
+    result
   }
 
   fn resolve_zoglin_resource(
@@ -1112,26 +2270,39 @@ impl Compiler {
     context: &mut FunctionContext,
   ) -> Result<()> {
     if if_statement.child.is_some() {
-      let if_function = self.next_function("if", &context.location.namespace);
-
-      context.code.push(eco_format!("function {if_function}"));
       let mut sub_context = context.child(false);
+      // Where the code of the last branch that compiled to anything starts,
+      // and whether it ends in the command checking its condition
+      let mut last_branch = None;
 
       let mut if_statement = if_statement;
       loop {
-        self.compile_if_statement_without_child(
+        let start = sub_context.code.len();
+        let always_taken = self.compile_if_statement_without_child(
           if_statement.condition,
+          if_statement.binding,
           if_statement.block,
           &mut sub_context,
           true,
         )?;
+        if sub_context.code.len() > start {
+          last_branch = Some((start, !always_taken));
+        }
+        // Later branches can never run
+        if always_taken {
+          break;
+        }
         match if_statement.child {
           Some(ElseStatement::IfStatement(if_stmt)) => {
             if_statement = *if_stmt;
           }
 
           Some(ElseStatement::Block(block)) => {
+            let start = sub_context.code.len();
             self.compile_block(&mut sub_context, block)?;
+            if sub_context.code.len() > start {
+              last_branch = Some((start, false));
+            }
 
             break;
           }
@@ -1140,13 +2311,37 @@ impl Compiler {
         }
       }
 
+      let mut commands = sub_context.code.moved();
+      // When only one branch can run, the chain doesn't need its own function.
+      // Nothing runs after it, so the branch doesn't need to return
+      match last_branch {
+        None => return Ok(()),
+        Some((0, true)) => {
+          let check = commands.pop().expect("The branch checks its condition");
+          context.code.extend(commands);
+          if check.contains(" run return run ") {
+            context
+              .code
+              .push(check.replacen(" run return run ", " run ", 1));
+          }
+          return Ok(());
+        }
+        Some((0, false)) if commands.len() == 1 => {
+          context.code.extend(commands);
+          return Ok(());
+        }
+        _ => {}
+      }
+
+      let if_function = self.next_function("if", &context.location);
+      context.code.push(eco_format!("function {if_function}"));
       let (module, name) = if_function.try_split().expect("Is a function");
 
       self.add_item(
         module,
         Item::Function(Function {
           name,
-          commands: sub_context.code.moved(),
+          commands,
           location: Location::blank(),
         }),
       )?;
@@ -1155,39 +2350,76 @@ impl Compiler {
     }
     self.compile_if_statement_without_child(
       if_statement.condition,
+      if_statement.binding,
       if_statement.block,
       context,
       false,
-    )
+    )?;
+    Ok(())
   }
 
+  // Returns whether the branch is always taken. In an else-if chain, a taken
+  // branch returns from the chain's function, so later branches don't run
   fn compile_if_statement_without_child(
     &mut self,
     condition: ast::Expression,
+    binding: Option<(EcoString, Location)>,
     body: Vec<Statement>,
     context: &mut FunctionContext,
     is_child: bool,
-  ) -> Result<()> {
-    let condition = self.compile_expression(condition, context, false)?;
+  ) -> Result<bool> {
+    let condition = if binding.is_some() {
+      // Known values are ignored, so a missing key is checked while running
+      // rather than being an error
+      let constants = take(&mut context.constants);
+      let condition = self.compile_expression(condition, context, false);
+      context.constants = constants;
+      condition?
+    } else {
+      self.compile_expression(condition, context, false)?
+    };
 
-    let check_code =
+    let mut bound_value = None;
+    let check_code = if let Some((name, location)) = binding {
+      let storage = self.if_let_storage(condition, context)?;
+      let check_code = eco_format!("if data storage {storage}");
+      let variable = ast::Expression::Variable(ast::ZoglinResource {
+        location: location.clone(),
+        namespace: None,
+        modules: Vec::new(),
+        name,
+      });
+      let value = Expression::new(ExpressionKind::Storage(storage), location);
+      bound_value = Some((variable, value));
+      check_code
+    } else {
       match condition.to_condition(self, &mut context.code, &context.location.namespace, false)? {
-        ConditionKind::Known(false) => return Ok(()),
+        ConditionKind::Known(false) => return Ok(false),
         ConditionKind::Known(true) => {
           self.compile_block(context, body)?;
-          return Ok(());
+          return Ok(true);
         }
         ConditionKind::Check(check_code) => check_code,
-      };
+      }
+    };
 
     let mut sub_context = context.child(false);
+    if let Some((variable, value)) = bound_value {
+      self.assign_variable(&variable, &value, &mut sub_context)?;
+    }
     self.compile_block(&mut sub_context, body)?;
 
     let command = match sub_context.code.len() {
-      0 => return Ok(()),
+      0 if is_child => {
+        context
+          .code
+          .push(eco_format!("execute {check_code} run return 0"));
+        return Ok(false);
+      }
+      0 => return Ok(false),
       1 => &sub_context.code[0],
       _ => {
-        let function = self.next_function("if", &sub_context.location.namespace);
+        let function = self.next_function("if", &sub_context.location);
         let fn_str = function.to_eco_string();
         self.add_function_item(Location::blank(), function, sub_context.code.moved())?;
         &eco_format!("function {fn_str}")
@@ -1200,7 +2432,47 @@ impl Compiler {
       run_str = if is_child { "run return run" } else { "run" },
     );
     context.code.push(execute_command);
-    Ok(())
+    Ok(false)
+  }
+
+  // The storage path checked by `if let`, which only exists if the value does
+  fn if_let_storage(
+    &mut self,
+    value: Expression,
+    context: &mut FunctionContext,
+  ) -> Result<StorageLocation> {
+    match &value.kind {
+      ExpressionKind::Storage(storage) | ExpressionKind::Macro(storage) if !value.needs_macro => {
+        return Ok(storage.clone());
+      }
+      ExpressionKind::Storage(_) | ExpressionKind::Data(_) => {}
+      ExpressionKind::Scoreboard(_) | ExpressionKind::Condition(_) => {
+        return Err(raise_error(
+          value.location,
+          "A scoreboard always has a value, so `if let` can't check it. Use `if` instead.",
+        ))
+      }
+      kind if kind.compile_time_known() => {
+        return Err(raise_error(
+          value.location,
+          "This value is known at compile time, so always exists. Use `if` or assign it instead.",
+        ))
+      }
+      _ => {
+        return Err(raise_error(
+          value.location,
+          "`if let` can only check storage variables and entity or block data.",
+        ))
+      }
+    }
+
+    // The copy is left unset if the value is missing, so it is cleared first
+    let storage = self.next_storage(&context.location.namespace);
+    context
+      .code
+      .push(eco_format!("data remove storage {storage}"));
+    self.set_storage(&mut context.code, &storage, &value)?;
+    Ok(storage)
   }
 
   fn compile_return(
@@ -1218,13 +2490,19 @@ impl Compiler {
 
       match context.return_type {
         ReturnType::Storage => {
-          let return_storage =
-            StorageLocation::new(context.location.clone(), "return".to_eco_string());
+          let return_storage = StorageLocation::new(
+            self.function_storage(&context.location),
+            "return".to_eco_string(),
+          );
           self.set_storage(&mut context.code, &return_storage, &expression)?;
         }
         ReturnType::Scoreboard => {
           let scoreboard = ScoreboardLocation::new(context.location.clone(), "$return");
-          self.use_scoreboard_dummy(scoreboard.scoreboard_string());
+          self.use_scoreboard(
+            scoreboard.scoreboard_string(),
+            None,
+            &context.location.namespace,
+          );
           self.set_scoreboard(&mut context.code, &scoreboard, &expression)?;
         }
         ReturnType::Direct => {
@@ -1235,7 +2513,8 @@ impl Compiler {
               &expression,
             )?;
           } else {
-            context.code.push(expression.to_return_command()?)
+            let command = expression.to_return_command(&mut context.code)?;
+            context.code.push(command)
           }
         }
       }
@@ -1276,7 +2555,25 @@ impl Compiler {
     )? {
       ConditionKind::Known(false) => {}
       ConditionKind::Known(true) => {
-        let fn_location = self.next_function("while", &sub_context.location.namespace);
+        let fn_location = self.next_function("while", &sub_context.location);
+
+        let mut reset_guard = None;
+        if let Some(limit) = self.loop_guard {
+          let counter = self.next_scoreboard(&sub_context.location.namespace);
+          let message = eco_format!(
+            "Loop in {} stopped after {limit} iterations.",
+            sub_context.location.as_ref()
+          );
+          sub_context.code.push(eco_format!(
+            "execute if score {counter} matches {limit}.. run return run tellraw {} {}",
+            self.loop_guard_target,
+            serde_json::json!({ "text": message.as_str(), "color": "red" })
+          ));
+          sub_context
+            .code
+            .push(eco_format!("scoreboard players add {counter} 1"));
+          reset_guard = Some(eco_format!("scoreboard players set {counter} 0"));
+        }
 
         self.compile_block(&mut sub_context, while_loop.block)?;
 
@@ -1284,11 +2581,13 @@ impl Compiler {
         let function_call = eco_format!("function {fn_location}");
         self.add_function_item(Location::blank(), fn_location, sub_context.code.moved())?;
 
+        // Each time the loop starts, it gets a fresh budget
+        context.code.extend(reset_guard);
         context.code.push(function_call);
       }
 
       ConditionKind::Check(check_code) => {
-        let fn_location = self.next_function("while", &sub_context.location.namespace);
+        let fn_location = self.next_function("while", &sub_context.location);
         sub_context
           .code
           .push(eco_format!("execute {check_code} run return 0"));
@@ -1306,10 +2605,59 @@ impl Compiler {
     Ok(())
   }
 
+  fn compile_execute_block(
+    &mut self,
+    execute: ExecuteBlock,
+    context: &mut FunctionContext,
+  ) -> Result<()> {
+    let mut sub_context = context.child(false);
+    self.compile_block(&mut sub_context, execute.block)?;
+    // The block runs once for each entity or position, so once it returns,
+    // the remaining runs are skipped
+    if *sub_context.has_nested_returns {
+      let check = eco_format!(
+        "execute if score $should_return zoglin.internal.{namespace}.vars matches -2147483648..2147483647 run return 0",
+        namespace = sub_context.location.namespace
+      );
+      sub_context.code.insert(0, check);
+    }
+
+    let command = match sub_context.code.len() {
+      0 => return Ok(()),
+      1 => sub_context.code[0].clone(),
+      _ => {
+        let function = self.next_function("execute", &sub_context.location);
+        let command = eco_format!("function {function}");
+        self.add_function_item(execute.location, function, sub_context.code.moved())?;
+        command
+      }
+    };
+    // A macro command's `$` must start the line
+    context.code.push(match command.strip_prefix('$') {
+      Some(command) => eco_format!("$execute {} run {command}", execute.subcommands),
+      None => eco_format!("execute {} run {command}", execute.subcommands),
+    });
+    Ok(())
+  }
+
   fn compile_index(&mut self, index: Index, context: &mut FunctionContext) -> Result<Expression> {
     let location = index.left.location();
+    let constant_storage = self.constant_storage(&index.left, context);
     let left = self.compile_expression(*index.left, context, false)?;
     let index = self.compile_expression(*index.index, context, false)?;
+    // NBT paths index lists by number, so a key would be read as a list index
+    if let ExpressionKind::String(key) = &index.kind {
+      return Err(raise_error(
+        index.location,
+        format!("Strings can't be used as indexes. Use `.\"{key}\"` to access a member."),
+      ));
+    }
+    let left = match constant_storage {
+      Some(storage) if index.kind.numeric_value().is_none() => {
+        Expression::new(ExpressionKind::Storage(storage), left.location)
+      }
+      _ => left,
+    };
 
     match left.kind {
       ExpressionKind::Void
@@ -1335,7 +2683,7 @@ impl Compiler {
         if index.kind.numeric_value().is_some() =>
       {
         let numeric = index.kind.numeric_value().expect("Numeric value exists");
-        let numeric = if numeric > 0 {
+        let numeric = if numeric >= 0 {
           numeric as usize
         } else if -numeric as usize > values.len() {
           return Err(raise_error(location, "Index out of bounds."));
@@ -1357,15 +2705,43 @@ impl Compiler {
         Ok(Expression::new(ExpressionKind::Storage(storage), location))
       }
 
+      ExpressionKind::Data(data) if index.kind.numeric_value().is_some() => {
+        let index = index.kind.numeric_value().expect("Numeric value exists");
+        Ok(Expression::with_macro(
+          ExpressionKind::Data(data.index(index)),
+          location,
+          left.needs_macro,
+        ))
+      }
+
       ExpressionKind::ByteArray(_)
       | ExpressionKind::IntArray(_)
       | ExpressionKind::LongArray(_)
       | ExpressionKind::Array { .. }
       | ExpressionKind::Storage(_)
-      | ExpressionKind::Macro(_) => self.compile_dynamic_index(left, index, location, context),
+      | ExpressionKind::Macro(_)
+      | ExpressionKind::Data(_) => self.compile_dynamic_index(left, index, location, context),
     }
   }
 
+  // The storage of a variable known to hold a constant. When the index or
+  // member isn't known at compile-time, the variable is read from storage
+  // instead, which is also correct if the index changes the variable
+  fn constant_storage(
+    &self,
+    expression: &ast::Expression,
+    context: &FunctionContext,
+  ) -> Option<StorageLocation> {
+    let ast::Expression::Variable(variable) = expression else {
+      return None;
+    };
+    let storage = self.variable_storage(&context.location, variable);
+    context
+      .constants
+      .contains_key(&storage.to_eco_string())
+      .then_some(storage)
+  }
+
   fn compile_dynamic_index(
     &mut self,
     left: Expression,
@@ -1424,9 +2800,9 @@ impl Compiler {
     };
 
     let range_is_const = start.kind.numeric_value().is_some()
-      && !end
+      && end
         .as_ref()
-        .is_some_and(|e| e.kind.numeric_value().is_none());
+        .is_none_or(|e| e.kind.numeric_value().is_some());
 
     match left.kind {
       ExpressionKind::Void
@@ -1538,7 +2914,8 @@ impl Compiler {
       ExpressionKind::String(_)
       | ExpressionKind::Storage(_)
       | ExpressionKind::Macro(_)
-      | ExpressionKind::SubString(_, _, _) => {
+      | ExpressionKind::SubString(_, _, _)
+      | ExpressionKind::Data(_) => {
         self.compile_dynamic_range_index(left, start, end, location, context)
       }
     }
@@ -1593,7 +2970,10 @@ impl Compiler {
     context: &mut FunctionContext,
   ) -> Result<Expression> {
     let location = member.left.location();
+    let constant_storage = self.constant_storage(&member.left, context);
     let left = self.compile_expression(*member.left, context, false)?;
+    // Literal members are already quoted by the parser
+    let is_literal = matches!(*member.member, ast::MemberKind::Literal(_));
     let member = match *member.member {
       ast::MemberKind::Literal(lit) => {
         Expression::new(ExpressionKind::String(lit), location.clone())
@@ -1602,11 +2982,28 @@ impl Compiler {
     };
     let member_value = match member.kind.compile_time_value() {
       Some(value) => match value {
-        NbtValue::String(s) => Some(s),
+        NbtValue::String(s) => {
+          check_reserved(&s, &member.location, NameKind::NBTPathComponent)?;
+          if is_literal {
+            Some(s)
+          } else {
+            Some(validate_or_quote(
+              s,
+              &member.location,
+              NameKind::NBTPathComponent,
+            ))
+          }
+        }
         _ => return Err(raise_error(location, "Can only use strings as members")),
       },
       None => None,
     };
+    let left = match constant_storage {
+      Some(storage) if member_value.is_none() => {
+        Expression::new(ExpressionKind::Storage(storage), left.location)
+      }
+      _ => left,
+    };
 
     match left.kind {
       ExpressionKind::Void
@@ -1647,9 +3044,16 @@ impl Compiler {
         Ok(Expression::new(ExpressionKind::Storage(storage), location))
       }
 
-      ExpressionKind::Compound(_) | ExpressionKind::Storage(_) | ExpressionKind::Macro(_) => {
-        self.compile_dynamic_member(left, member, location, context)
-      }
+      ExpressionKind::Data(data) if member_value.is_some() => Ok(Expression::with_macro(
+        ExpressionKind::Data(data.member(&member_value.expect("Value is some"))),
+        location,
+        left.needs_macro,
+      )),
+
+      ExpressionKind::Compound(_)
+      | ExpressionKind::Storage(_)
+      | ExpressionKind::Macro(_)
+      | ExpressionKind::Data(_) => self.compile_dynamic_member(left, member, location, context),
     }
   }
 
@@ -1692,3 +3096,257 @@ impl Compiler {
     ))
   }
 }
+
+fn warn_unassigned_variables(variables: &VariableUsage, warnings: &Warnings) {
+  if variables.allow_unassigned {
+    return;
+  }
+
+  let mut warned = HashSet::new();
+  for (name, location) in variables.read.iter() {
+    if variables.assigned.contains(name) || !warned.insert(name) {
+      continue;
+    }
+
+    let suggestion = variables
+      .assigned
+      .iter()
+      .map(|assigned| (edit_distance(name, assigned), assigned))
+      .filter(|(distance, _)| *distance <= 2)
+      .min();
+    let message = match suggestion {
+      Some((_, assigned)) => eco_format!(
+        "Variable `{name}` is never assigned in this function. Did you mean `{assigned}`?"
+      ),
+      None => eco_format!("Variable `{name}` is never assigned in this function."),
+    };
+    warnings.push(raise_warning(location.clone(), message));
+  }
+}
+
+// Escapes a value spliced into a JSON string opened with `quote`
+fn escape_json_string(value: &str, quote: char) -> EcoString {
+  let mut escaped = EcoString::new();
+  for char in value.chars() {
+    if char == '\\' || char == quote {
+      escaped.push('\\');
+    }
+    escaped.push(char);
+  }
+  escaped
+}
+
+// Pure functions are reused rather than called again, so commands which
+// obviously change something are probably a mistake
+fn warn_impure_commands(statements: &[Statement], warnings: &Warnings) {
+  for statement in statements {
+    match statement {
+      Statement::Command(command) => {
+        let Some(ast::CommandPart::Literal(text)) = command.parts.first() else {
+          continue;
+        };
+        let words: Vec<_> = text.trim_start_matches('$').split_whitespace().collect();
+        let mutates = match words.as_slice() {
+          ["scoreboard", "players", "get" | "list", ..] | ["data", "get", ..] => false,
+          [command, ..] => MUTATING_COMMANDS.contains(command),
+          [] => false,
+        };
+        if mutates {
+          warnings.push(raise_warning(
+            command.location.clone(),
+            "This command has side effects, but is inside a `#[pure]` function.",
+          ));
+        }
+      }
+      Statement::If(if_statement) => warn_impure_if(if_statement, warnings),
+      Statement::WhileLoop(while_loop) => warn_impure_commands(&while_loop.block, warnings),
+      Statement::ForLoop(for_loop) => warn_impure_commands(&for_loop.block, warnings),
+      Statement::Execute(execute) => warn_impure_commands(&execute.block, warnings),
+      Statement::Comment(_)
+      | Statement::Expression(_)
+      | Statement::Discard(_)
+      | Statement::Return(_)
+      | Statement::LocalComptime(..) => {}
+    }
+  }
+}
+
+fn warn_impure_if(if_statement: &IfStatement, warnings: &Warnings) {
+  warn_impure_commands(&if_statement.block, warnings);
+  match &if_statement.child {
+    Some(ElseStatement::IfStatement(child)) => warn_impure_if(child, warnings),
+    Some(ElseStatement::Block(block)) => warn_impure_commands(block, warnings),
+    None => {}
+  }
+}
+
+const MUTATING_COMMANDS: &[&str] = &[
+  "scoreboard",
+  "data",
+  "kill",
+  "summon",
+  "setblock",
+  "fill",
+  "clone",
+  "give",
+  "clear",
+  "tp",
+  "teleport",
+  "effect",
+  "tag",
+];
+
+// The storage key, or scoreboard player for scoreboard parameters, that a
+// parameter is passed in
+fn parameter_key(parameter: &ast::Parameter) -> Option<EcoString> {
+  match parameter.kind {
+    ParameterKind::Storage => Some(parameter.name.clone()),
+    ParameterKind::Macro => Some(eco_format!("__{}", parameter.name)),
+    ParameterKind::Scoreboard => Some(eco_format!("${}", parameter.name)),
+    ParameterKind::CompileTime => None,
+  }
+}
+
+// The parameter key an argument is read from, if it is read from the
+// parameters of the function being called. Scoreboard parameters use the
+// unprefixed function location
+fn read_parameter_key<'a>(
+  argument: &'a Expression,
+  parameter_storage: &ResourceLocation,
+  function: &ResourceLocation,
+) -> Option<&'a str> {
+  let (location, key) = match &argument.kind {
+    ExpressionKind::Storage(storage) | ExpressionKind::SubString(storage, ..) => (
+      &storage.storage,
+      storage
+        .name
+        .split(['.', '['])
+        .next()
+        .expect("Split always yields at least one item"),
+    ),
+    ExpressionKind::Scoreboard(scoreboard) => {
+      return (&scoreboard.scoreboard == function).then_some(scoreboard.name.as_str());
+    }
+    _ => return None,
+  };
+  (location == parameter_storage).then_some(key)
+}
+
+// The `key: value` entry for an argument that can be merged into the
+// parameter storage
+fn merged_argument(parameter: &ast::Parameter, argument: &Expression) -> Option<EcoString> {
+  if parameter.kind == ParameterKind::Scoreboard {
+    return None;
+  }
+  let key = parameter_key(parameter)?;
+  if argument.needs_macro
+    || !argument.kind.compile_time_known()
+    || matches!(argument.kind, ExpressionKind::Compound(_))
+  {
+    return None;
+  }
+  let value = argument.kind.to_comptime_string(false)?;
+  Some(eco_format!("{key}: {value}"))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{lexer::Lexer, parser::Parser};
+
+  // Several namespaces, with generated functions and a warning in each, so
+  // any dependence on hash map order shows up as a difference
+  const SOURCE: &str = "namespace alpha
+fn a(x) {
+  if x > 1 {
+    say a
+  } else if x < 0 {
+    say b
+  } else {
+    say c
+  }
+  while $y < 3 {
+    $y += 1
+  }
+  z = missing
+}
+
+namespace beta
+fn b() {
+  if $y > 1 {
+    say a
+  } else {
+    say b
+  }
+  alpha:a(2)
+  z = missing
+}
+
+namespace gamma
+fn c() {
+  while $y < 10 {
+    $y += 2
+  }
+  z = missing
+}
+";
+
+  fn options() -> CompileOptions {
+    CompileOptions {
+      line_endings: LineEndings::Lf,
+      storage_prefix: None,
+      style_warnings: true,
+      auto_fix_names: false,
+      inline_load: false,
+      loop_guard: None,
+      loop_guard_target: "@a[tag=zoglin.ops]".into(),
+      debug: false,
+      message_target: "@a".into(),
+      emit_call_stubs: false,
+      meta: None,
+      version_check: true,
+      profile: Profile::Dev,
+      default_return: ReturnType::Storage,
+      require_explicit_return_type: false,
+      warnings: Warnings::default(),
+    }
+  }
+
+  // The tree and warnings of one compile, as text so they can be compared
+  fn compile(options: &CompileOptions) -> (String, Vec<String>) {
+    let tokens = Lexer::from_source("main.zog", SOURCE.to_string(), &options.warnings)
+      .tokenise()
+      .expect("Source is valid");
+    let ast = Parser::new(tokens, options)
+      .parse()
+      .expect("Source is valid");
+    let tree =
+      Compiler::compile_to_tree(ast, options, &mut HashSet::new()).expect("Source compiles");
+    let warnings = options
+      .warnings
+      .take()
+      .iter()
+      .map(|warning| format!("{warning:?}"))
+      .collect();
+    (format!("{tree:?}"), warnings)
+  }
+
+  #[test]
+  fn compiling_twice_gives_the_same_tree() {
+    let options = options();
+    let (first_tree, first_warnings) = compile(&options);
+    let (second_tree, second_warnings) = compile(&options);
+    assert_eq!(first_tree, second_tree);
+    assert_eq!(first_warnings.len(), 3);
+    assert_eq!(first_warnings, second_warnings);
+  }
+
+  #[test]
+  fn warnings_go_to_the_compile_sink() {
+    let (first, second) = (options(), options());
+    let (_, warnings) = compile(&first);
+    assert_eq!(warnings.len(), 3);
+    assert!(first.warnings.take().is_empty());
+    assert!(second.warnings.take().is_empty());
+  }
+}
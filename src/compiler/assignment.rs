@@ -0,0 +1,138 @@
+use ecow::{eco_format, EcoString};
+
+use crate::error::{raise_error, Location, Result};
+use crate::parser::ast::{self, MemberKind};
+use crate::parser::name::{check_reserved, validate_or_quote, NameKind};
+
+use super::{
+  expression::{Expression, ExpressionKind, NbtValue},
+  file_tree::StorageLocation,
+  utils::ToEcoString,
+  Compiler, FunctionContext,
+};
+
+impl Compiler {
+  // Assigns to a member or index of a storage variable. When a member or
+  // index isn't known at compile time, the assignment is done by a generated
+  // macro function, which is given them as arguments
+  pub(super) fn compile_storage_assignment(
+    &mut self,
+    left: ast::Expression,
+    right: ast::Expression,
+    context: &mut FunctionContext,
+  ) -> Result<Expression> {
+    let right = self.compile_expression(right, context, false)?;
+    let mut arguments = Vec::new();
+    let target = self.storage_target(left, context, &mut arguments)?;
+    if arguments.is_empty() {
+      self.set_storage(&mut context.code, &target, &right)?;
+      return Ok(right);
+    }
+
+    let namespace = context.location.namespace.clone();
+    let source = match right.kind.to_comptime_string(false) {
+      Some(constant) if !right.needs_macro => eco_format!("value {constant}"),
+      // Macro values must be read before calling, as the function can't see them
+      _ if right.needs_macro => {
+        let storage = self.copy_to_storage(&mut context.code, &right, &namespace)?;
+        eco_format!("from storage {storage}")
+      }
+      _ => {
+        let storage = self.move_to_storage(&mut context.code, right.clone(), &namespace)?;
+        eco_format!("from storage {storage}")
+      }
+    };
+
+    let function = self.next_function("assign", &context.location);
+    let storage = self.function_storage(&function);
+    for (name, value) in arguments {
+      self.set_storage(
+        &mut context.code,
+        &StorageLocation::new(storage.clone(), name),
+        &value,
+      )?;
+    }
+    self.add_function_item(
+      Location::blank(),
+      function.clone(),
+      vec![eco_format!("$data modify storage {target} set {source}")],
+    )?;
+    context
+      .code
+      .push(eco_format!("function {function} with storage {storage}"));
+    Ok(right)
+  }
+
+  // The storage a member or index of a variable refers to. Members and
+  // indexes not known at compile time are written as macro variables, which
+  // are added to `arguments` with their values
+  fn storage_target(
+    &mut self,
+    target: ast::Expression,
+    context: &mut FunctionContext,
+    arguments: &mut Vec<(EcoString, Expression)>,
+  ) -> Result<StorageLocation> {
+    match target {
+      ast::Expression::Variable(variable) => {
+        if variable.namespace.is_none() && variable.modules.is_empty() {
+          context.variables.assigned.insert(variable.name.clone());
+        }
+        let storage = self.variable_storage(&context.location, &variable);
+        // Part of the value changes, so it is no longer the constant it was
+        context.constants.remove(&storage.to_eco_string());
+        Ok(storage)
+      }
+      ast::Expression::Member(member) => {
+        let location = member.left.location();
+        let mut storage = self.storage_target(*member.left, context, arguments)?;
+        let (member, is_literal) = match *member.member {
+          MemberKind::Literal(literal) => (
+            Expression::new(ExpressionKind::String(literal), location.clone()),
+            true,
+          ),
+          MemberKind::Dynamic(expression) => {
+            (self.compile_expression(expression, context, false)?, false)
+          }
+        };
+        match member.kind.compile_time_value() {
+          Some(NbtValue::String(name)) if !member.needs_macro => {
+            check_reserved(&name, &member.location, NameKind::NBTPathComponent)?;
+            // Literal members are already quoted by the parser
+            let name = if is_literal {
+              name
+            } else {
+              validate_or_quote(name, &member.location, NameKind::NBTPathComponent)
+            };
+            storage.name = eco_format!("{}.{name}", storage.name);
+          }
+          Some(_) => return Err(raise_error(location, "Can only use strings as members")),
+          None => {
+            let name = eco_format!("__member_{}", arguments.len());
+            storage.name = eco_format!("{}.\"$({name})\"", storage.name);
+            arguments.push((name, member));
+          }
+        }
+        Ok(storage)
+      }
+      ast::Expression::Index(index) => {
+        let mut storage = self.storage_target(*index.left, context, arguments)?;
+        let index = self.compile_expression(*index.index, context, false)?;
+        match index.kind.numeric_value() {
+          Some(value) if !index.needs_macro => {
+            storage.name = eco_format!("{}[{value}]", storage.name);
+          }
+          _ => {
+            let name = eco_format!("__index_{}", arguments.len());
+            storage.name = eco_format!("{}[$({name})]", storage.name);
+            arguments.push((name, index));
+          }
+        }
+        Ok(storage)
+      }
+      _ => Err(raise_error(
+        target.location(),
+        "Can only assign to members and indexes of variables, and of entity and block data.",
+      )),
+    }
+  }
+}
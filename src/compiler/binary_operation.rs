@@ -1,15 +1,18 @@
 use ecow::{eco_format, EcoString};
 
-use crate::parser::ast::{self, BinaryOperation, Operator, UnaryExpression, UnaryOperator};
+use crate::parser::ast::{
+  self, BinaryOperation, Operator, ReturnType, UnaryExpression, UnaryOperator,
+};
 
-use crate::error::{raise_error, Result};
+use crate::error::{raise_error, raise_warning, Location, Result};
 
-use super::expression::NbtType;
+use super::expression::{Interpolation, NbtType};
 use super::utils::ToEcoString;
 use super::FunctionContext;
 use super::{
+  escape_json_string,
   expression::{Condition, ConditionKind, Expression, ExpressionKind, ScoreKind},
-  file_tree::{ScoreboardLocation, StorageLocation},
+  file_tree::{ResourceLocation, ScoreboardLocation, StorageLocation},
   Compiler,
 };
 
@@ -21,38 +24,42 @@ struct Operation {
 }
 
 impl Operation {
+  fn divides(&self) -> bool {
+    matches!(self.operator, "/" | "%")
+  }
+
   const ADD: Operation = Operation {
     operator: "+",
     native_operation: Some("add"),
-    constant_operation: |a, b| a + b,
+    constant_operation: i32::wrapping_add,
     commutative: true,
   };
 
   const SUB: Operation = Operation {
     operator: "-",
     native_operation: Some("remove"),
-    constant_operation: |a, b| a - b,
+    constant_operation: i32::wrapping_sub,
     commutative: false,
   };
 
   const MUL: Operation = Operation {
     operator: "*",
     native_operation: None,
-    constant_operation: |a, b| a * b,
+    constant_operation: i32::wrapping_mul,
     commutative: true,
   };
 
   const DIV: Operation = Operation {
     operator: "/",
     native_operation: None,
-    constant_operation: |a, b| a / b,
+    constant_operation: i32::wrapping_div,
     commutative: false,
   };
 
   const MOD: Operation = Operation {
     operator: "%",
     native_operation: None,
-    constant_operation: |a, b| a % b,
+    constant_operation: i32::wrapping_rem,
     commutative: false,
   };
 
@@ -75,16 +82,16 @@ impl Compiler {
     context: &mut FunctionContext,
   ) -> Result<Expression> {
     match binary_operation.operator {
-      Operator::Plus => self.compile_numeric_operation(binary_operation, Operation::ADD, context),
+      Operator::Plus => self.compile_addition(binary_operation, context),
       Operator::Minus => self.compile_numeric_operation(binary_operation, Operation::SUB, context),
       Operator::Divide => self.compile_numeric_operation(binary_operation, Operation::DIV, context),
       Operator::Multiply => {
         self.compile_numeric_operation(binary_operation, Operation::MUL, context)
       }
       Operator::Modulo => self.compile_numeric_operation(binary_operation, Operation::MOD, context),
-      Operator::Power => todo!(),
-      Operator::LeftShift => todo!(),
-      Operator::RightShift => todo!(),
+      Operator::Power => self.compile_power(binary_operation, context),
+      Operator::LeftShift => self.compile_shift(binary_operation, true, context),
+      Operator::RightShift => self.compile_shift(binary_operation, false, context),
       Operator::LessThan => self.compile_less_than(binary_operation, context),
       Operator::GreaterThan => self.compile_greater_than(binary_operation, context),
       Operator::LessThanEquals => self.compile_less_than_equals(binary_operation, context),
@@ -123,10 +130,33 @@ impl Compiler {
     binary_operation.operator = operator;
 
     match binary_operation.left.as_ref() {
-      ast::Expression::ScoreboardVariable(variable) => {
+      // Scoreboards holding a constant are recalculated at compile time instead
+      ast::Expression::ScoreboardVariable(variable)
+        if !is_constant_scoreboard(variable, context) =>
+      {
         let right = self.compile_expression(*binary_operation.right, context, false)?;
         let scoreboard = ScoreboardLocation::from_zoglin_resource(&context.location, variable);
-        self.use_scoreboard_dummy(scoreboard.scoreboard_string());
+        let owner = self.scoreboard_owner(
+          &scoreboard.scoreboard.namespace,
+          &context.location.namespace,
+        );
+        self.use_scoreboard(scoreboard.scoreboard_string(), None, owner);
+        self.scoreboard_operation(
+          &scoreboard,
+          right.clone(),
+          Operation::from_operator(operator).expect("Operator must be numeric"),
+          context,
+        )?;
+
+        Ok(right)
+      }
+      ast::Expression::ReturnSlot(location) if context.return_type == ReturnType::Scoreboard => {
+        let right = self.compile_expression(*binary_operation.right, context, false)?;
+        let ExpressionKind::Scoreboard(scoreboard) =
+          self.return_slot(location.clone(), context)?.kind
+        else {
+          unreachable!("Scoreboard functions return to a scoreboard")
+        };
         self.scoreboard_operation(
           &scoreboard,
           right.clone(),
@@ -136,6 +166,38 @@ impl Compiler {
 
         Ok(right)
       }
+      // Adding a compound to a variable merges it in place, as does adding
+      // anything to a variable holding a compound. When both compounds are
+      // known, they are merged while compiling instead
+      ast::Expression::Variable(variable)
+        if matches!(operator, Operator::Plus) && {
+          let left = context.constants.get(
+            &self
+              .variable_storage(&context.location, variable)
+              .to_eco_string(),
+          );
+          match left {
+            Some(left) => {
+              matches!(left.kind, ExpressionKind::Compound(_))
+                && !matches!(*binary_operation.right, ast::Expression::Compound(..))
+            }
+            None => self.is_compound(&binary_operation.right, context),
+          }
+        } =>
+      {
+        let storage = self.variable_storage(&context.location, variable);
+        if variable.namespace.is_none() && variable.modules.is_empty() {
+          context.variables.assigned.insert(variable.name.clone());
+        }
+        let right = self.compile_expression(*binary_operation.right, context, false)?;
+        context.constants.remove(&storage.to_eco_string());
+        self.merge_into_storage(&mut context.code, &storage, &right)?;
+
+        Ok(Expression::new(
+          ExpressionKind::Storage(storage),
+          binary_operation.location,
+        ))
+      }
       left => self.compile_assignment(
         left.clone(),
         ast::Expression::BinaryOperation(binary_operation),
@@ -151,30 +213,99 @@ impl Compiler {
     context: &mut FunctionContext,
   ) -> Result<Expression> {
     match left {
-      ast::Expression::Variable(variable) => {
+      // `$x = $x + 1` changes the scoreboard in place, like `$x += 1`
+      ast::Expression::ScoreboardVariable(variable)
+        if updates_in_place(&variable, &right, context) =>
+      {
+        let ast::Expression::BinaryOperation(binary_operation) = right else {
+          unreachable!("Only binary operations update in place")
+        };
+        let location = binary_operation.location.clone();
+        let operator = binary_operation.operator;
+        self.compile_operator_assignment(binary_operation, operator, context)?;
+        let scoreboard = ScoreboardLocation::from_zoglin_resource(&context.location, &variable);
+        Ok(Expression::new(
+          ExpressionKind::Scoreboard(scoreboard),
+          location,
+        ))
+      }
+      ast::Expression::Variable(_) | ast::Expression::ScoreboardVariable(_) => {
         let right = self.compile_expression(right, context, false)?;
-        let storage = StorageLocation::from_zoglin_resource(&context.location, &variable);
-        self.set_storage(&mut context.code, &storage, &right)?;
+        self.assign_variable(&left, &right, context)?;
 
         Ok(right)
       }
-      ast::Expression::ScoreboardVariable(variable) => {
+      ast::Expression::ReturnSlot(location) => {
+        let slot = self.return_slot(location, context)?;
         let right = self.compile_expression(right, context, false)?;
-        let scoreboard = ScoreboardLocation::from_zoglin_resource(&context.location, &variable);
-        self.set_scoreboard(&mut context.code, &scoreboard, &right)?;
-        self.use_scoreboard_dummy(scoreboard.scoreboard_string());
+        match &slot.kind {
+          ExpressionKind::Storage(storage) => {
+            self.set_storage(&mut context.code, storage, &right)?
+          }
+          ExpressionKind::Scoreboard(scoreboard) => {
+            self.set_scoreboard(&mut context.code, scoreboard, &right)?
+          }
+          _ => unreachable!("Return slots are either storages or scoreboards"),
+        }
+
+        Ok(right)
+      }
+      ast::Expression::DataTarget(..) | ast::Expression::Member(_) | ast::Expression::Index(_)
+        if is_data_target(&left) =>
+      {
+        let right = self.compile_expression(right, context, false)?;
+        let left = self.compile_expression(left, context, false)?;
+        if !matches!(left.kind, ExpressionKind::Data(_)) {
+          return Err(raise_error(
+            left.location,
+            "Can only assign to entity and block data with a constant path.",
+          ));
+        }
+        self.set_data(
+          &mut context.code,
+          &left,
+          &right,
+          &context.location.namespace,
+        )?;
 
         Ok(right)
       }
-      ast::Expression::ComptimeVariable(name, _) => {
+      ast::Expression::ComptimeVariable(name, location) => {
+        // Values assigned at namespace or module level can't be changed, only
+        // shadowed with `local`
+        let shadowed = self
+          .comptime_scopes
+          .iter()
+          .any(|scope| scope.contains_key(&name));
+        if !shadowed && self.lookup_item_comptime_variable(&name).is_some() {
+          return Err(raise_error(
+            location,
+            format!(
+              "Cannot reassign `&{name}`, as it is defined outside this function. Use `local &{name} = ...` to shadow it instead."
+            ),
+          ));
+        }
         let right = self.compile_expression(right, context, false)?;
+        // The value may be a temporary, which later statements read through the variable
+        if !right.kind.compile_time_known() {
+          self.pin_temps();
+        }
         self
           .comptime_scopes
           .last_mut()
-          .expect("The must be at least one scope")
+          .expect("There must be at least one scope")
           .insert(name, right.clone());
         Ok(right)
       }
+      ast::Expression::Member(_) | ast::Expression::Index(_) if is_comptime_target(&left) => {
+        Err(raise_error(
+          left.location(),
+          "Cannot assign to part of a compile-time value.",
+        ))
+      }
+      ast::Expression::Member(_) | ast::Expression::Index(_) => {
+        self.compile_storage_assignment(left, right, context)
+      }
       _ => Err(raise_error(
         left.location(),
         "Can only assign to variables.",
@@ -188,17 +319,111 @@ impl Compiler {
     operation: Operation,
     context: &mut FunctionContext,
   ) -> Result<Expression> {
-    let left = self.compile_expression(*binary_operation.left, context, false)?;
-    let right = self.compile_expression(*binary_operation.right, context, false)?;
+    let (left, right) =
+      self.compile_operands(*binary_operation.left, *binary_operation.right, context)?;
+    self.numeric_operation(left, right, operation, binary_operation.location, context)
+  }
+
+  // A chain of additions is compiled from left to right. Once a string is
+  // reached, it is joined with the rest of the chain all at once, so that text
+  // only known in-game is inserted into a macro command just once
+  fn compile_addition(
+    &mut self,
+    binary_operation: BinaryOperation,
+    context: &mut FunctionContext,
+  ) -> Result<Expression> {
+    let mut operands = Vec::new();
+    let mut first = ast::Expression::BinaryOperation(binary_operation);
+    loop {
+      match first {
+        ast::Expression::BinaryOperation(operation)
+          if matches!(operation.operator, Operator::Plus) =>
+        {
+          operands.push((*operation.right, operation.location));
+          first = *operation.left;
+        }
+        _ => break,
+      }
+    }
+    operands.reverse();
+
+    let is_string = |value: &Expression| {
+      matches!(
+        value.kind,
+        ExpressionKind::String(_) | ExpressionKind::SubString(..)
+      )
+    };
+    let mut result = self.compile_expression(first, context, false)?;
+    let mut parts: Option<Vec<Expression>> = None;
+    let mut operands = operands.into_iter();
+    while let Some((right, location)) = operands.next() {
+      let rest = operands.as_slice();
+      let later = || rest.iter().map(|(operand, _)| operand);
+      if parts.is_none() {
+        let later = rest.iter().map(|(operand, _)| operand);
+        let later = std::iter::once(&right).chain(later);
+        result = self.preserve_operand(result, later, context)?;
+      }
+      let right = self.compile_expression(right, context, false)?;
+
+      match &mut parts {
+        Some(parts) => parts.push(self.preserve_operand(right, later(), context)?),
+        None if is_string(&result) || is_string(&right) => {
+          let right = self.preserve_operand(right, later(), context)?;
+          parts = Some(vec![result.clone(), right]);
+        }
+        None => {
+          let location = location.clone();
+          result = self.numeric_operation(result, right, Operation::ADD, location, context)?
+        }
+      }
+      result.location = location;
+    }
+
+    match parts {
+      Some(parts) => self.join(parts, result.location, context),
+      None => Ok(result),
+    }
+  }
+
+  fn numeric_operation(
+    &mut self,
+    left: Expression,
+    right: Expression,
+    operation: Operation,
+    location: Location,
+    context: &mut FunctionContext,
+  ) -> Result<Expression> {
     let needs_macro = left.needs_macro || right.needs_macro;
 
     match (&left.kind, &right.kind) {
       (left, right) if left.numeric_value().is_some() && right.numeric_value().is_some() => {
+        let divisor = right.numeric_value().expect("Numeric value exists");
+        if divisor == 0 && operation.divides() {
+          return Err(raise_error(location, "Cannot divide by zero."));
+        }
         Ok(ExpressionKind::Integer((operation.constant_operation)(
           left.numeric_value().expect("Numeric value exists"),
-          right.numeric_value().expect("Numeric value exists"),
+          divisor,
         )))
       }
+      // Adding compounds merges them, with the keys on the right replacing those on the left
+      (ExpressionKind::Compound(left), ExpressionKind::Compound(right))
+        if operation.operator == "+" =>
+      {
+        let mut merged = left.clone();
+        merged.extend(right.clone());
+        Ok(ExpressionKind::Compound(merged))
+      }
+      (ExpressionKind::Storage(_) | ExpressionKind::Data(_), ExpressionKind::Compound(_))
+      | (ExpressionKind::Compound(_), ExpressionKind::Storage(_) | ExpressionKind::Data(_))
+        if operation.operator == "+" =>
+      {
+        let storage =
+          self.copy_to_storage(&mut context.code, &left, &context.location.namespace)?;
+        self.merge_into_storage(&mut context.code, &storage, &right)?;
+        Ok(ExpressionKind::Storage(storage))
+      }
       // It's more efficient to have a constant value on the right-hand-side,
       // So if the left side is constant, we rearrange it. However, that only works
       // if the operator is commutative.
@@ -215,7 +440,233 @@ impl Compiler {
         Ok(ExpressionKind::Scoreboard(scoreboard))
       }
     }
-    .map(|kind| Expression::with_macro(kind, binary_operation.location, needs_macro))
+    .map(|kind| Expression::with_macro(kind, location, needs_macro))
+  }
+
+  // Strings only known in-game are joined by a generated function, which
+  // inserts them into a string with macros. Text known at compile time is
+  // written into the function, escaped, so it can contain any character
+  fn join(
+    &mut self,
+    parts: Vec<Expression>,
+    location: Location,
+    context: &mut FunctionContext,
+  ) -> Result<Expression> {
+    let needs_macro = parts.iter().any(|part| part.needs_macro);
+    let mut folded: Vec<Expression> = Vec::new();
+    for part in parts {
+      let part = string_operand(&part)?;
+      match (folded.last_mut(), &part.kind) {
+        (
+          Some(Expression {
+            kind: ExpressionKind::String(text),
+            ..
+          }),
+          ExpressionKind::String(next),
+        ) => text.push_str(next),
+        _ => folded.push(part),
+      }
+    }
+    if let [text @ Expression {
+      kind: ExpressionKind::String(_),
+      ..
+    }] = folded.as_slice()
+    {
+      return Ok(Expression::with_macro(
+        text.kind.clone(),
+        location,
+        needs_macro,
+      ));
+    }
+
+    let function = self.next_function("join", &context.location);
+    let mut template = EcoString::new();
+    let mut arguments = 0;
+    for part in folded {
+      if let ExpressionKind::String(text) = &part.kind {
+        template.push_str(&escape_json_string(text, '"'));
+        continue;
+      }
+      let name = eco_format!("__{arguments}");
+      template.push_str(&eco_format!("$({name})"));
+      self.set_storage(
+        &mut context.code,
+        &StorageLocation::new(function.clone(), name),
+        &part,
+      )?;
+      arguments += 1;
+    }
+    self.add_function_item(
+      location.clone(),
+      function.clone(),
+      vec![eco_format!(
+        "$data modify storage {function} return set value \"{template}\""
+      )],
+    )?;
+    context
+      .code
+      .push(eco_format!("function {function} with storage {function}"));
+
+    // The whole string, so that adding to it again also joins them
+    Ok(Expression::with_macro(
+      ExpressionKind::SubString(
+        StorageLocation::new(function, "return".to_eco_string()),
+        0,
+        None,
+      ),
+      location,
+      needs_macro,
+    ))
+  }
+
+  // Powers known at compile time are calculated directly, otherwise the
+  // operands are passed to a helper which multiplies in a loop
+  fn compile_power(
+    &mut self,
+    binary_operation: BinaryOperation,
+    context: &mut FunctionContext,
+  ) -> Result<Expression> {
+    let (left, right) =
+      self.compile_operands(*binary_operation.left, *binary_operation.right, context)?;
+    let needs_macro = left.needs_macro || right.needs_macro;
+    let location = binary_operation.location;
+
+    if let (Some(base), Some(exponent)) = (left.kind.numeric_value(), right.kind.numeric_value()) {
+      let Ok(exponent) = u32::try_from(exponent) else {
+        return Err(raise_error(
+          right.location,
+          "Cannot raise to a negative power, as the result isn't an integer.",
+        ));
+      };
+      let Some(result) = base.checked_pow(exponent) else {
+        return Err(raise_error(
+          location,
+          "The result of this power is too large for an integer.",
+        ));
+      };
+      return Ok(Expression::with_macro(
+        ExpressionKind::Integer(result),
+        location,
+        needs_macro,
+      ));
+    }
+
+    let namespace = context.location.namespace.clone();
+    let power = |name: &str| ScoreboardLocation {
+      scoreboard: ResourceLocation::new_function("zoglin", &["internal", "power"]),
+      name: name.into(),
+    };
+    let (base, exponent, result) = (power("$base"), power("$exponent"), power("$result"));
+    self.use_scoreboard(base.scoreboard_string(), None, &namespace);
+    self.set_scoreboard(&mut context.code, &base, &left)?;
+    self.set_scoreboard(&mut context.code, &exponent, &right)?;
+    let helper = self.power();
+    context.code.push(eco_format!("function {helper}"));
+
+    let result = Expression::new(ExpressionKind::Scoreboard(result), location.clone());
+    let scoreboard = self.copy_to_scoreboard(&mut context.code, &result, &namespace)?;
+    Ok(Expression::with_macro(
+      ExpressionKind::Scoreboard(scoreboard),
+      location,
+      needs_macro,
+    ))
+  }
+
+  // Shifts by a constant amount are a multiplication or division by a power of
+  // two, otherwise the operands are passed to a helper which shifts in a loop.
+  // Scoreboard division rounds down, so shifting right keeps the sign
+  fn compile_shift(
+    &mut self,
+    binary_operation: BinaryOperation,
+    is_left: bool,
+    context: &mut FunctionContext,
+  ) -> Result<Expression> {
+    let (left, right) =
+      self.compile_operands(*binary_operation.left, *binary_operation.right, context)?;
+    let needs_macro = left.needs_macro || right.needs_macro;
+    let location = binary_operation.location;
+    let namespace = context.location.namespace.clone();
+
+    if let Some(amount) = right.kind.numeric_value() {
+      let Ok(amount) = u32::try_from(amount) else {
+        return Err(raise_error(
+          right.location,
+          "Cannot shift by a negative amount.",
+        ));
+      };
+
+      if let Some(value) = left.kind.numeric_value() {
+        let result = match (is_left, amount) {
+          (true, 32..) => 0,
+          (true, _) => value.wrapping_shl(amount),
+          (false, _) => value >> amount.min(31),
+        };
+        return Ok(Expression::with_macro(
+          ExpressionKind::Integer(result),
+          location,
+          needs_macro,
+        ));
+      }
+
+      let scoreboard = self.copy_to_scoreboard(&mut context.code, &left, &namespace)?;
+      match (is_left, amount) {
+        (_, 0) => {}
+        (true, 32..) => context
+          .code
+          .push(eco_format!("scoreboard players set {scoreboard} 0")),
+        (true, _) => {
+          let factor = self.constant_scoreboard(1i32.wrapping_shl(amount), &namespace);
+          context.code.push(eco_format!(
+            "scoreboard players operation {scoreboard} *= {factor}"
+          ));
+        }
+        // 2^31 doesn't fit in an integer, so larger shifts divide twice
+        (false, 31..) => {
+          let first = self.constant_scoreboard(1 << 30, &namespace);
+          let second = self.constant_scoreboard(2, &namespace);
+          context.code.push(eco_format!(
+            "scoreboard players operation {scoreboard} /= {first}"
+          ));
+          context.code.push(eco_format!(
+            "scoreboard players operation {scoreboard} /= {second}"
+          ));
+        }
+        (false, _) => {
+          let divisor = self.constant_scoreboard(1 << amount, &namespace);
+          context.code.push(eco_format!(
+            "scoreboard players operation {scoreboard} /= {divisor}"
+          ));
+        }
+      }
+      return Ok(Expression::with_macro(
+        ExpressionKind::Scoreboard(scoreboard),
+        location,
+        needs_macro,
+      ));
+    }
+
+    let shift = |name: &str| ScoreboardLocation {
+      scoreboard: ResourceLocation::new_function("zoglin", &["internal", "shift"]),
+      name: name.into(),
+    };
+    let (value, amount) = (shift("$value"), shift("$amount"));
+    self.use_scoreboard(value.scoreboard_string(), None, &namespace);
+    self.set_scoreboard(&mut context.code, &value, &left)?;
+    self.set_scoreboard(&mut context.code, &amount, &right)?;
+    let helper = if is_left {
+      self.shift_left()
+    } else {
+      self.shift_right()
+    };
+    context.code.push(eco_format!("function {helper}"));
+
+    let result = Expression::new(ExpressionKind::Scoreboard(value), location.clone());
+    let scoreboard = self.copy_to_scoreboard(&mut context.code, &result, &namespace)?;
+    Ok(Expression::with_macro(
+      ExpressionKind::Scoreboard(scoreboard),
+      location,
+      needs_macro,
+    ))
   }
 
   fn scoreboard_operation(
@@ -241,12 +692,18 @@ impl Compiler {
       }
       num if num.numeric_value().is_some() => {
         let number = num.numeric_value().expect("Numeric value exists");
+        if number == 0 && operation.divides() {
+          self.warnings.push(raise_warning(
+            value.location.clone(),
+            "Dividing a scoreboard by zero leaves it unchanged.",
+          ));
+        }
         if let Some(native_operation) = operation.native_operation {
           context.code.push(eco_format!(
             "scoreboard players {native_operation} {scoreboard} {number}",
           ));
         } else {
-          let constant_scoreboard = self.constant_scoreboard(number);
+          let constant_scoreboard = self.constant_scoreboard(number, &context.location.namespace);
           context.code.push(eco_format!(
             "scoreboard players operation {scoreboard} {}= {constant_scoreboard}",
             operation.operator
@@ -270,8 +727,8 @@ impl Compiler {
     binary_operation: BinaryOperation,
     context: &mut FunctionContext,
   ) -> Result<Expression> {
-    let left = self.compile_expression(*binary_operation.left, context, false)?;
-    let right = self.compile_expression(*binary_operation.right, context, false)?;
+    let (left, right) =
+      self.compile_operands(*binary_operation.left, *binary_operation.right, context)?;
     let needs_macro = left.needs_macro || right.needs_macro;
 
     match (&left.kind, &right.kind) {
@@ -290,24 +747,32 @@ impl Compiler {
             < right.numeric_value().expect("Numeric value exists"),
         ))
       }
-      (num, _) if num.numeric_value().is_some() => self.compile_match_comparison(
-        &mut context.code,
-        right,
-        eco_format!(
-          "{}..",
-          num.numeric_value().expect("Numeric value exists") + 1
-        ),
-        &context.location.namespace,
-      ),
-      (_, num) if num.numeric_value().is_some() => self.compile_match_comparison(
-        &mut context.code,
-        left,
-        eco_format!(
-          "..{}",
-          num.numeric_value().expect("Numeric value exists") - 1
-        ),
-        &context.location.namespace,
-      ),
+      (num, _) if num.numeric_value().is_some() => {
+        let number = num.numeric_value().expect("Numeric value exists");
+        match number.checked_add(1) {
+          Some(bound) => self.compile_match_comparison(
+            &mut context.code,
+            right,
+            eco_format!("{bound}.."),
+            &context.location.namespace,
+          ),
+          // No score is outside the range of an int
+          None => Ok(ExpressionKind::Boolean(false)),
+        }
+      }
+      (_, num) if num.numeric_value().is_some() => {
+        let number = num.numeric_value().expect("Numeric value exists");
+        match number.checked_sub(1) {
+          Some(bound) => self.compile_match_comparison(
+            &mut context.code,
+            left,
+            eco_format!("..{bound}"),
+            &context.location.namespace,
+          ),
+          // No score is outside the range of an int
+          None => Ok(ExpressionKind::Boolean(false)),
+        }
+      }
       _ => self.compile_comparison_operator(
         &mut context.code,
         left,
@@ -324,8 +789,8 @@ impl Compiler {
     binary_operation: BinaryOperation,
     context: &mut FunctionContext,
   ) -> Result<Expression> {
-    let left = self.compile_expression(*binary_operation.left, context, false)?;
-    let right = self.compile_expression(*binary_operation.right, context, false)?;
+    let (left, right) =
+      self.compile_operands(*binary_operation.left, *binary_operation.right, context)?;
     let needs_macro = left.needs_macro || right.needs_macro;
 
     match (&left.kind, &right.kind) {
@@ -344,24 +809,32 @@ impl Compiler {
             > right.numeric_value().expect("Numeric value exists"),
         ))
       }
-      (num, _) if num.numeric_value().is_some() => self.compile_match_comparison(
-        &mut context.code,
-        right,
-        eco_format!(
-          "..{}",
-          num.numeric_value().expect("Numeric value exists") - 1
-        ),
-        &context.location.namespace,
-      ),
-      (_, num) if num.numeric_value().is_some() => self.compile_match_comparison(
-        &mut context.code,
-        left,
-        eco_format!(
-          "{}..",
-          num.numeric_value().expect("Numeric value exists") + 1
-        ),
-        &context.location.namespace,
-      ),
+      (num, _) if num.numeric_value().is_some() => {
+        let number = num.numeric_value().expect("Numeric value exists");
+        match number.checked_sub(1) {
+          Some(bound) => self.compile_match_comparison(
+            &mut context.code,
+            right,
+            eco_format!("..{bound}"),
+            &context.location.namespace,
+          ),
+          // No score is outside the range of an int
+          None => Ok(ExpressionKind::Boolean(false)),
+        }
+      }
+      (_, num) if num.numeric_value().is_some() => {
+        let number = num.numeric_value().expect("Numeric value exists");
+        match number.checked_add(1) {
+          Some(bound) => self.compile_match_comparison(
+            &mut context.code,
+            left,
+            eco_format!("{bound}.."),
+            &context.location.namespace,
+          ),
+          // No score is outside the range of an int
+          None => Ok(ExpressionKind::Boolean(false)),
+        }
+      }
       _ => self.compile_comparison_operator(
         &mut context.code,
         left,
@@ -378,8 +851,8 @@ impl Compiler {
     binary_operation: BinaryOperation,
     context: &mut FunctionContext,
   ) -> Result<Expression> {
-    let left = self.compile_expression(*binary_operation.left, context, false)?;
-    let right = self.compile_expression(*binary_operation.right, context, false)?;
+    let (left, right) =
+      self.compile_operands(*binary_operation.left, *binary_operation.right, context)?;
     let needs_macro = left.needs_macro || right.needs_macro;
 
     match (&left.kind, &right.kind) {
@@ -426,8 +899,8 @@ impl Compiler {
     binary_operation: BinaryOperation,
     context: &mut FunctionContext,
   ) -> Result<Expression> {
-    let left = self.compile_expression(*binary_operation.left, context, false)?;
-    let right = self.compile_expression(*binary_operation.right, context, false)?;
+    let (left, right) =
+      self.compile_operands(*binary_operation.left, *binary_operation.right, context)?;
     let needs_macro = left.needs_macro || right.needs_macro;
 
     match (&left.kind, &right.kind) {
@@ -474,8 +947,8 @@ impl Compiler {
     binary_operation: BinaryOperation,
     context: &mut FunctionContext,
   ) -> Result<Expression> {
-    let left = self.compile_expression(*binary_operation.left, context, false)?;
-    let right = self.compile_expression(*binary_operation.right, context, false)?;
+    let (left, right) =
+      self.compile_operands(*binary_operation.left, *binary_operation.right, context)?;
     let needs_macro = left.needs_macro || right.needs_macro;
 
     if let Some(equal) = left.equal(&right) {
@@ -523,8 +996,8 @@ impl Compiler {
     binary_operation: BinaryOperation,
     context: &mut FunctionContext,
   ) -> Result<Expression> {
-    let left = self.compile_expression(*binary_operation.left, context, false)?;
-    let right = self.compile_expression(*binary_operation.right, context, false)?;
+    let (left, right) =
+      self.compile_operands(*binary_operation.left, *binary_operation.right, context)?;
     let needs_macro = left.needs_macro || right.needs_macro;
 
     if let Some(equal) = left.equal(&right) {
@@ -572,8 +1045,8 @@ impl Compiler {
     binary_operation: BinaryOperation,
     context: &mut FunctionContext,
   ) -> Result<Expression> {
-    let left = self.compile_expression(*binary_operation.left, context, false)?;
-    let right = self.compile_expression(*binary_operation.right, context, false)?;
+    let (left, right) =
+      self.compile_operands(*binary_operation.left, *binary_operation.right, context)?;
     let needs_macro = left.needs_macro || right.needs_macro;
 
     let left_condition =
@@ -602,8 +1075,8 @@ impl Compiler {
     binary_operation: BinaryOperation,
     context: &mut FunctionContext,
   ) -> Result<Expression> {
-    let left = self.compile_expression(*binary_operation.left, context, false)?;
-    let right = self.compile_expression(*binary_operation.right, context, false)?;
+    let (left, right) =
+      self.compile_operands(*binary_operation.left, *binary_operation.right, context)?;
     let needs_macro = left.needs_macro || right.needs_macro;
 
     let left_condition =
@@ -768,6 +1241,15 @@ impl Compiler {
         ExpressionKind::Storage(temp_storage)
       }
 
+      ExpressionKind::Data(data) => {
+        let temp_storage = self.next_storage(&context.location.namespace);
+        context.code.push(eco_format!(
+          "{}execute store result storage {temp_storage} int -1 run data get {data}",
+          if needs_macro { "$" } else { "" }
+        ));
+        ExpressionKind::Storage(temp_storage)
+      }
+
       ExpressionKind::Scoreboard(scoreboard) => {
         let temp_storage = self.next_storage(&context.location.namespace);
         context.code.push(eco_format!(
@@ -790,6 +1272,93 @@ impl Compiler {
     Ok(Expression::new(kind, unary_expression.location))
   }
 
+  // Copies a value that later operands could change, such as a variable they
+  // assign to, so that operands are evaluated from left to right
+  pub(super) fn preserve_operand<'a>(
+    &mut self,
+    value: Expression,
+    mut later: impl Iterator<Item = &'a ast::Expression>,
+    context: &mut FunctionContext,
+  ) -> Result<Expression> {
+    if value.kind.compile_time_known()
+      || matches!(value.kind, ExpressionKind::Void)
+      || !later.any(ast::Expression::has_side_effects)
+    {
+      return Ok(value);
+    }
+
+    let namespace = context.location.namespace.clone();
+    let kind = match value.kind {
+      ExpressionKind::Scoreboard(_) | ExpressionKind::Condition(_) => ExpressionKind::Scoreboard(
+        self.copy_to_scoreboard(&mut context.code, &value, &namespace)?,
+      ),
+      _ => ExpressionKind::Storage(self.copy_to_storage(&mut context.code, &value, &namespace)?),
+    };
+    Ok(Expression::new(kind, value.location))
+  }
+
+  fn compile_operands(
+    &mut self,
+    left: ast::Expression,
+    right: ast::Expression,
+    context: &mut FunctionContext,
+  ) -> Result<(Expression, Expression)> {
+    let left = self.compile_expression(left, context, false)?;
+    let left = self.preserve_operand(left, std::iter::once(&right), context)?;
+    let right = self.compile_expression(right, context, false)?;
+    Ok((left, right))
+  }
+
+  // Sets a storage or scoreboard variable to an already compiled value
+  pub(super) fn assign_variable(
+    &mut self,
+    variable: &ast::Expression,
+    value: &Expression,
+    context: &mut FunctionContext,
+  ) -> Result<()> {
+    match variable {
+      ast::Expression::Variable(variable) => {
+        if variable.namespace.is_none() && variable.modules.is_empty() {
+          context.variables.assigned.insert(variable.name.clone());
+        }
+        let storage = self.variable_storage(&context.location, variable);
+        self.set_storage(&mut context.code, &storage, value)?;
+        if value.kind.compile_time_known() && !value.needs_macro {
+          context
+            .constants
+            .insert(storage.to_eco_string(), value.clone());
+        } else {
+          context.constants.remove(&storage.to_eco_string());
+        }
+      }
+      ast::Expression::ScoreboardVariable(variable) => {
+        let scoreboard = ScoreboardLocation::from_zoglin_resource(&context.location, variable);
+        self.set_scoreboard(&mut context.code, &scoreboard, value)?;
+        let owner = self.scoreboard_owner(
+          &scoreboard.scoreboard.namespace,
+          &context.location.namespace,
+        );
+        self.use_scoreboard(scoreboard.scoreboard_string(), None, owner);
+        // Scoreboards only hold integers, so other constants are stored as one.
+        // Selectors like `$[@r]` may not pick the same entity when read
+        if let Some(number) = value
+          .kind
+          .numeric_value()
+          .filter(|_| !value.needs_macro && scoreboard.name.starts_with('$'))
+        {
+          context.constants.insert(
+            scoreboard.to_eco_string(),
+            Expression::new(ExpressionKind::Integer(number), value.location.clone()),
+          );
+        } else {
+          context.constants.remove(&scoreboard.to_eco_string());
+        }
+      }
+      _ => unreachable!("Only called with storage and scoreboard variables"),
+    }
+    Ok(())
+  }
+
   pub(super) fn copy_to_scoreboard(
     &mut self,
     code: &mut Vec<EcoString>,
@@ -871,4 +1440,182 @@ impl Compiler {
   ) -> Result<()> {
     value.to_storage(self, code, storage, "set", NbtType::Unknown)
   }
+
+  // Whether a value is known to be a compound before it is compiled, so that
+  // adding it merges rather than adds numbers
+  fn is_compound(&self, expression: &ast::Expression, context: &FunctionContext) -> bool {
+    let known = match expression {
+      ast::Expression::Compound(..) => return true,
+      // Entities and blocks as a whole are always compounds
+      ast::Expression::DataTarget(..) => return true,
+      ast::Expression::Variable(variable) => context
+        .constants
+        .get(
+          &self
+            .variable_storage(&context.location, variable)
+            .to_eco_string(),
+        )
+        .cloned(),
+      ast::Expression::ComptimeVariable(name, _) => self.lookup_comptime_variable(name),
+      _ => None,
+    };
+    known.is_some_and(|value| matches!(value.kind, ExpressionKind::Compound(_)))
+  }
+
+  // Compounds which aren't constant are built in a temporary storage first,
+  // as they can't be merged in piece by piece
+  fn merge_into_storage(
+    &mut self,
+    code: &mut Vec<EcoString>,
+    storage: &StorageLocation,
+    value: &Expression,
+  ) -> Result<()> {
+    if !matches!(
+      value.kind,
+      ExpressionKind::Compound(_) | ExpressionKind::Storage(_) | ExpressionKind::Data(_)
+    ) {
+      return Err(raise_error(
+        value.location.clone(),
+        "Only compounds can be merged into a compound.",
+      ));
+    }
+    if let Some(constant) = value.kind.to_comptime_string(false) {
+      code.push(eco_format!(
+        "data modify storage {storage} merge value {constant}"
+      ));
+    } else {
+      let source = match &value.kind {
+        ExpressionKind::Storage(source) if !value.needs_macro => source.clone(),
+        _ => self.copy_to_storage(code, value, &storage.storage.namespace)?,
+      };
+      code.push(eco_format!(
+        "data modify storage {storage} merge from storage {source}"
+      ));
+    }
+    Ok(())
+  }
+
+  // Values which aren't constant or already in data are copied through a
+  // temporary storage
+  pub(super) fn set_data(
+    &mut self,
+    code: &mut Vec<EcoString>,
+    target: &Expression,
+    value: &Expression,
+    namespace: &str,
+  ) -> Result<()> {
+    let ExpressionKind::Data(data) = &target.kind else {
+      unreachable!("Only entity and block data can be set");
+    };
+    if data.path.is_empty() {
+      return Err(raise_error(
+        target.location.clone(),
+        "Cannot assign to a whole entity or block, a path is needed.",
+      ));
+    }
+
+    let (source, needs_macro) = if let Some(constant) = value.kind.to_comptime_string(false) {
+      (eco_format!("value {constant}"), false)
+    } else {
+      match &value.kind {
+        ExpressionKind::Storage(storage) => {
+          (eco_format!("from storage {storage}"), value.needs_macro)
+        }
+        ExpressionKind::Data(data) => (eco_format!("from {data}"), value.needs_macro),
+        _ => {
+          let storage = self.copy_to_storage(code, value, namespace)?;
+          (eco_format!("from storage {storage}"), false)
+        }
+      }
+    };
+
+    let macro_prefix = if needs_macro || target.needs_macro {
+      "$"
+    } else {
+      ""
+    };
+    code.push(eco_format!("{macro_prefix}data modify {data} set {source}"));
+    Ok(())
+  }
+}
+
+// Whether `variable = right` adds a number to, or takes one from, the
+// variable itself. Only literals are allowed, as anything else could change
+// the variable before the operation is done
+fn updates_in_place(
+  variable: &ast::ZoglinResource,
+  right: &ast::Expression,
+  context: &FunctionContext,
+) -> bool {
+  let ast::Expression::BinaryOperation(BinaryOperation {
+    left,
+    right,
+    operator: Operator::Plus | Operator::Minus,
+    ..
+  }) = right
+  else {
+    return false;
+  };
+  let (ast::Expression::ScoreboardVariable(operand), ast::Expression::Integer(..)) =
+    (left.as_ref(), right.as_ref())
+  else {
+    return false;
+  };
+  let location = &context.location;
+  ScoreboardLocation::from_zoglin_resource(location, operand).to_string()
+    == ScoreboardLocation::from_zoglin_resource(location, variable).to_string()
+    && !is_constant_scoreboard(variable, context)
+}
+
+fn is_constant_scoreboard(variable: &ast::ZoglinResource, context: &FunctionContext) -> bool {
+  let scoreboard = ScoreboardLocation::from_zoglin_resource(&context.location, variable);
+  context.constants.contains_key(&scoreboard.to_eco_string())
+}
+
+// Numbers known at compile time are joined to strings as text, but other
+// numbers can't be converted in-game
+fn string_operand(expression: &Expression) -> Result<Expression> {
+  match &expression.kind {
+    ExpressionKind::String(_)
+    | ExpressionKind::SubString(..)
+    | ExpressionKind::Storage(_)
+    | ExpressionKind::Macro(_)
+    | ExpressionKind::Data(_) => Ok(expression.clone()),
+    ExpressionKind::Byte(_)
+    | ExpressionKind::Short(_)
+    | ExpressionKind::Integer(_)
+    | ExpressionKind::Long(_)
+    | ExpressionKind::Float(_)
+    | ExpressionKind::Double(_) => Ok(Expression::new(
+      ExpressionKind::String(
+        expression
+          .kind
+          .to_command_string(Interpolation::Plain)
+          .expect("Numbers can be written as text"),
+      ),
+      expression.location.clone(),
+    )),
+    _ => Err(raise_error(
+      expression.location.clone(),
+      "Can only add strings to other strings, or to numbers known at compile time.",
+    )),
+  }
+}
+
+fn is_comptime_target(expression: &ast::Expression) -> bool {
+  match expression {
+    ast::Expression::ComptimeVariable(..) => true,
+    ast::Expression::Member(member) => is_comptime_target(&member.left),
+    ast::Expression::Index(index) => is_comptime_target(&index.left),
+    _ => false,
+  }
+}
+
+fn is_data_target(expression: &ast::Expression) -> bool {
+  match expression {
+    ast::Expression::DataTarget(..) => true,
+    ast::Expression::Member(member) => is_data_target(&member.left),
+    ast::Expression::Index(index) => is_data_target(&index.left),
+    _ => false,
+  }
 }
@@ -1,14 +1,37 @@
+use std::{collections::HashMap, fs, path::Path};
+
 use crate::{
-  error::{raise_error, Location, Result},
-  parser::ast,
+  error::{raise_error, ErrorKind, Location, Result},
+  lexer::file_key,
+  parser::{
+    ast::{self, ArrayType, BinaryOperation, Operator, ReturnType, ZoglinResource},
+    name::{validate_or_quote, NameKind},
+  },
 };
-use ecow::EcoString;
+use ecow::{eco_format, EcoString};
+use serde_json::json;
 
 use super::{
-  expression::{Expression, ExpressionKind},
-  Compiler, FunctionContext,
+  expression::{verify_types, Expression, ExpressionKind},
+  file_tree::{DataLocation, ResourceLocation, ScoreboardLocation, StorageLocation},
+  utils::ToEcoString,
+  Compiler, FunctionContext, TEST_STORAGE,
 };
 
+#[derive(Clone, Copy)]
+enum Rounding {
+  Floor,
+  Ceil,
+  Round,
+}
+
+pub(super) struct OutputAssertion {
+  function: ResourceLocation,
+  max_commands: Option<usize>,
+  forbid: Vec<EcoString>,
+  location: Location,
+}
+
 impl Compiler {
   pub(super) fn compile_builtin_function(
     &mut self,
@@ -17,6 +40,20 @@ impl Compiler {
     location: Location,
     context: &mut FunctionContext,
   ) -> Result<Expression> {
+    // The argument to `@fn` is a function path, not a value
+    if name == "fn" {
+      return self.function_ref(raw_arguments, location, context);
+    }
+    if name == "schedule" {
+      return self.schedule(raw_arguments, location, context);
+    }
+
+    // The first argument to `@set` is where to write, so must not be replaced
+    // by its current value
+    if name == "set" {
+      context.constants.clear();
+    }
+
     let mut arguments = Vec::new();
     for argument in raw_arguments {
       arguments.push(self.compile_expression(argument, context, false)?);
@@ -27,13 +64,404 @@ impl Compiler {
       "temp_storage" => self.temp_storage(arguments, location, context),
       "scoreboard" => self.def_scoreboard(arguments, location, context),
       "set" => self.set(arguments, location, context),
+      "read_file" => self.read_file(arguments, location),
+      "len" => self.len(arguments, location, context),
+      "floor" => self.round(Rounding::Floor, arguments, location, context),
+      "ceil" => self.round(Rounding::Ceil, arguments, location, context),
+      "round" => self.round(Rounding::Round, arguments, location, context),
+      "parse_json" => parse_json(arguments, location),
+      "split" => split(arguments, location),
+      "test_fail" => test_fail(arguments, location, context),
+      "file" | "line" | "function_path" => source_location(name, arguments, location, context),
+      _ => Err(
+        raise_error(
+          location,
+          format!("Builtin function '@{name}' does not exist."),
+        )
+        .with_kind(ErrorKind::Missing(eco_format!("@{name}"))),
+      ),
+    }
+  }
+
+  pub(super) fn compile_builtin_variable(
+    &mut self,
+    name: &str,
+    location: Location,
+    context: &mut FunctionContext,
+  ) -> Result<Expression> {
+    let kind = match name {
+      "namespace" => ExpressionKind::String(context.location.namespace.clone()),
+      "function" => ExpressionKind::String(context.location.to_eco_string()),
+      "module" => ExpressionKind::String(context.location.clone().module().to_eco_string()),
+      "random" => {
+        let scoreboard = self.next_scoreboard(&context.location.namespace);
+        // The range can contain at most 2147483646 values
+        context.code.push(eco_format!(
+          "execute store result score {scoreboard} run random value 0..2147483645"
+        ));
+        ExpressionKind::Scoreboard(scoreboard)
+      }
+      _ => {
+        return Err(
+          raise_error(
+            location,
+            format!("Builtin variable '@{name}' does not exist."),
+          )
+          .with_kind(ErrorKind::Missing(eco_format!("@{name}"))),
+        )
+      }
+    };
+    Ok(Expression::new(kind, location))
+  }
+
+  // Prints each value in chat, along with where it is stored. Outside of
+  // debug builds, nothing is generated
+  pub(super) fn compile_dump(
+    &mut self,
+    arguments: Vec<(EcoString, ast::Expression)>,
+    location: Location,
+    context: &mut FunctionContext,
+  ) -> Result<Expression> {
+    if !self.debug {
+      return Ok(Expression::new(ExpressionKind::Void, location));
+    }
+
+    let mut components = vec![json!("")];
+    let mut needs_macro = false;
+    for (index, (source, argument)) in arguments.into_iter().enumerate() {
+      let value = self.compile_expression(argument, context, false)?;
+      let (kind, component) = match &value.kind {
+        ExpressionKind::Void => return Err(raise_error(value.location, "Cannot dump void.")),
+        kind if kind.compile_time_known() => {
+          let text = kind
+            .to_comptime_string(true)
+            .expect("Value is known at compile-time");
+          ("constant".to_eco_string(), json!({ "text": text.as_str() }))
+        }
+        ExpressionKind::Scoreboard(scoreboard) => {
+          needs_macro |= value.needs_macro;
+          (
+            eco_format!("scoreboard {scoreboard}"),
+            score_component(scoreboard),
+          )
+        }
+        ExpressionKind::Storage(storage) => {
+          needs_macro |= value.needs_macro;
+          (eco_format!("storage {storage}"), storage_component(storage))
+        }
+        // Macro arguments are also in storage, so don't need substituting
+        ExpressionKind::Macro(storage) => {
+          (eco_format!("storage {storage}"), storage_component(storage))
+        }
+        _ => {
+          let namespace = context.location.namespace.clone();
+          let storage = self.copy_to_storage(&mut context.code, &value, &namespace)?;
+          ("value".to_eco_string(), storage_component(&storage))
+        }
+      };
+
+      if index > 0 {
+        components.push(json!(" "));
+      }
+      components.push(json!({ "text": format!("{source} ({kind}) = "), "color": "gray" }));
+      components.push(component);
+    }
+
+    let prefix = if needs_macro { "$" } else { "" };
+    context.code.push(eco_format!(
+      "{prefix}tellraw {} {}",
+      self.message_target,
+      serde_json::Value::Array(components)
+    ));
+    Ok(Expression::new(ExpressionKind::Void, location))
+  }
+
+  pub(super) fn compile_item_builtin(
+    &mut self,
+    name: &str,
+    arguments: Vec<ast::Expression>,
+    location: Location,
+    module: &ResourceLocation,
+  ) -> Result<()> {
+    match name {
+      "assert_output" => self.assert_output(arguments, location, module),
       _ => Err(raise_error(
         location,
-        format!("Builtin function '@{name}' does not exist."),
+        format!("Builtin function '@{name}' cannot be used outside of a function."),
       )),
     }
   }
 
+  fn assert_output(
+    &mut self,
+    arguments: Vec<ast::Expression>,
+    location: Location,
+    module: &ResourceLocation,
+  ) -> Result<()> {
+    let mut context = FunctionContext::new(module.clone(), ReturnType::Direct);
+    let mut arguments = arguments.into_iter();
+
+    let Some(path) = arguments.next() else {
+      return Err(raise_error(
+        location,
+        "Incorrect number of arguments. Expected at least 1, got 0",
+      ));
+    };
+    let path = self.compile_expression(path, &mut context, false)?;
+    let path = comptime_string(&path.location, &path)?;
+    let resource = function_path(&path, location.clone());
+    let function = self.resolve_zoglin_resource(resource, module, false)?;
+
+    let mut assertion = OutputAssertion {
+      function,
+      max_commands: None,
+      forbid: Vec::new(),
+      location,
+    };
+
+    for argument in arguments {
+      let argument_location = argument.location();
+      let ast::Expression::BinaryOperation(BinaryOperation {
+        operator: Operator::Assign,
+        left,
+        right,
+        ..
+      }) = argument
+      else {
+        return Err(raise_error(
+          argument_location,
+          "Expected a named argument, such as `max_commands = 3`.",
+        ));
+      };
+      let name = match *left {
+        ast::Expression::Variable(ZoglinResource {
+          namespace: None,
+          modules,
+          name,
+          ..
+        }) if modules.is_empty() => name,
+        _ => {
+          return Err(raise_error(
+            argument_location,
+            "Expected a named argument, such as `max_commands = 3`.",
+          ))
+        }
+      };
+      let value = self.compile_expression(*right, &mut context, false)?;
+
+      match name.as_str() {
+        "max_commands" => match value.kind {
+          ExpressionKind::Integer(max) if max >= 0 => assertion.max_commands = Some(max as usize),
+          _ => {
+            return Err(raise_error(
+              value.location,
+              "Invalid argument. Expected a positive integer.",
+            ))
+          }
+        },
+        "forbid" => match value.kind {
+          ExpressionKind::String(text) => assertion.forbid.push(text),
+          ExpressionKind::Array { values, .. } => {
+            for value in values {
+              let text = comptime_string(&value.location, &value)?;
+              assertion.forbid.push(text);
+            }
+          }
+          _ => {
+            return Err(raise_error(
+              value.location,
+              "Invalid argument. Expected a string or an array of strings.",
+            ))
+          }
+        },
+        _ => {
+          return Err(raise_error(
+            argument_location,
+            format!("Unknown argument `{name}`. Expected `max_commands` or `forbid`."),
+          ))
+        }
+      }
+    }
+
+    self.output_assertions.push(assertion);
+    Ok(())
+  }
+
+  pub(super) fn check_output_assertions(&self) -> Result<()> {
+    for assertion in self.output_assertions.iter() {
+      let function = self
+        .namespaces
+        .get(&assertion.function.namespace)
+        .and_then(|namespace| namespace.find_function(&assertion.function.modules))
+        .ok_or_else(|| {
+          raise_error(
+            assertion.location.clone(),
+            format!("Function `{}` does not exist.", assertion.function),
+          )
+          .with_kind(ErrorKind::Missing(assertion.function.to_eco_string()))
+        })?;
+
+      let commands: Vec<&EcoString> = function
+        .commands
+        .iter()
+        .filter(|command| !command.trim().is_empty() && !command.starts_with('#'))
+        .collect();
+
+      if let Some(max_commands) = assertion.max_commands {
+        if commands.len() > max_commands {
+          return Err(raise_error(
+            assertion.location.clone(),
+            format!(
+              "Function `{}` compiled to {} commands, but at most {max_commands} are allowed:\n{}",
+              assertion.function,
+              commands.len(),
+              commands
+                .iter()
+                .map(|command| command.as_str())
+                .collect::<Vec<_>>()
+                .join("\n")
+            ),
+          ));
+        }
+      }
+
+      for text in assertion.forbid.iter() {
+        let offending: Vec<&str> = commands
+          .iter()
+          .filter(|command| command.contains(text.as_str()))
+          .map(|command| command.as_str())
+          .collect();
+        if !offending.is_empty() {
+          return Err(raise_error(
+            assertion.location.clone(),
+            format!(
+              "Function `{}` contains `{text}`, which is forbidden:\n{}",
+              assertion.function,
+              offending.join("\n")
+            ),
+          ));
+        }
+      }
+    }
+    Ok(())
+  }
+
+  fn function_ref(
+    &mut self,
+    arguments: Vec<ast::Expression>,
+    location: Location,
+    context: &mut FunctionContext,
+  ) -> Result<Expression> {
+    check_args(&location, 1, arguments.len())?;
+    let path = expect_function_path(arguments.into_iter().next().expect("One argument"))?;
+
+    let function = self.resolve_zoglin_resource(path, &context.location.clone().module(), false)?;
+    let value = ExpressionKind::String(function.to_eco_string());
+
+    // Compile-time assignments are evaluated before every function has been
+    // registered, so the function is only checked once compilation finishes
+    self.function_refs.push((function, location.clone()));
+    Ok(Expression::new(value, location))
+  }
+
+  // Schedules a function to run after a delay, which is a compile-time
+  // string like "5s", a number of ticks, or a macro variable
+  fn schedule(
+    &mut self,
+    arguments: Vec<ast::Expression>,
+    location: Location,
+    context: &mut FunctionContext,
+  ) -> Result<Expression> {
+    if !(2..=3).contains(&arguments.len()) {
+      return Err(raise_error(
+        location,
+        format!(
+          "Incorrect number of arguments. Expected 2 or 3, got {}",
+          arguments.len()
+        ),
+      ));
+    }
+    let mut arguments = arguments.into_iter();
+    let path = expect_function_path(arguments.next().expect("At least two arguments"))?;
+    let function = self.resolve_zoglin_resource(path, &context.location.clone().module(), false)?;
+    if let Some(definition) = self.function_registry.get(&function) {
+      if !definition.arguments.is_empty() {
+        return Err(raise_error(
+          location,
+          format!("Cannot schedule `{function}`, as scheduled functions can't take arguments."),
+        ));
+      }
+    }
+    self
+      .function_refs
+      .push((function.clone(), location.clone()));
+
+    let delay = arguments.next().expect("At least two arguments");
+    let delay = self.compile_expression(delay, context, false)?;
+    let (delay, needs_macro) = match &delay.kind {
+      ExpressionKind::Integer(ticks) if *ticks > 0 => (eco_format!("{ticks}t"), false),
+      ExpressionKind::String(delay) if is_valid_delay(delay) => (delay.clone(), false),
+      ExpressionKind::Macro(storage) => (eco_format!("$({})", storage.name), true),
+      ExpressionKind::Integer(_) | ExpressionKind::String(_) => {
+        return Err(raise_error(
+          delay.location,
+          "Invalid delay. Expected a positive number of ticks, or a number followed by `t`, `s` or `d`.",
+        ))
+      }
+      _ => {
+        return Err(raise_error(
+          delay.location,
+          "The delay must be a compile-time string or integer, or a macro variable.",
+        ))
+      }
+    };
+
+    let mode = match arguments.next() {
+      Some(mode) => {
+        let mode = self.compile_expression(mode, context, false)?;
+        match &mode.kind {
+          ExpressionKind::String(name) if name == "append" || name == "replace" => {
+            eco_format!(" {name}")
+          }
+          _ => {
+            return Err(raise_error(
+              mode.location,
+              "Invalid mode. Expected \"append\" or \"replace\".",
+            ))
+          }
+        }
+      }
+      None => EcoString::new(),
+    };
+
+    let prefix = if needs_macro { "$" } else { "" };
+    context.code.push(eco_format!(
+      "{prefix}schedule function {function} {delay}{mode}"
+    ));
+    Ok(Expression::new(ExpressionKind::Void, location))
+  }
+
+  pub(super) fn check_function_refs(&self) -> Result<()> {
+    for (function, location) in self.function_refs.iter() {
+      self.check_not_excluded(function, location)?;
+      // Functions in namespaces that aren't defined by this project can't be checked
+      let is_local = self
+        .function_registry
+        .keys()
+        .any(|registered| registered.namespace == function.namespace);
+      if is_local && !self.function_registry.contains_key(function) {
+        return Err(
+          raise_error(
+            location.clone(),
+            format!("Function `{function}` does not exist."),
+          )
+          .with_kind(ErrorKind::Missing(function.to_eco_string())),
+        );
+      }
+    }
+    Ok(())
+  }
+
   fn temp_score(
     &mut self,
     arguments: Vec<Expression>,
@@ -51,6 +479,8 @@ impl Compiler {
     }
 
     let scoreboard = self.next_scoreboard(&context.location.namespace);
+    // The temporary can be used by any later statement, so is never reused
+    self.pin_temps();
 
     match arguments.first() {
       None => {}
@@ -80,6 +510,7 @@ impl Compiler {
     }
 
     let storage = self.next_storage(&context.location.namespace);
+    self.pin_temps();
 
     match arguments.first() {
       None => {}
@@ -93,7 +524,7 @@ impl Compiler {
     &mut self,
     arguments: Vec<Expression>,
     location: Location,
-    _context: &mut FunctionContext,
+    context: &mut FunctionContext,
   ) -> Result<Expression> {
     if arguments.len() > 2 || arguments.is_empty() {
       return Err(raise_error(
@@ -105,7 +536,7 @@ impl Compiler {
       ));
     };
 
-    let name: EcoString = match &arguments
+    let (name, namespace): (EcoString, EcoString) = match &arguments
       .first()
       .expect("There must be at least one argument")
       .kind
@@ -116,7 +547,7 @@ impl Compiler {
         path.extend(storage.modules.clone());
         path.push(storage_location.name.clone());
 
-        path.join(".").into()
+        (path.join(".").into(), storage.namespace.clone())
       }
       _ => {
         return Err(raise_error(
@@ -126,15 +557,21 @@ impl Compiler {
       }
     };
 
+    let owner = self.scoreboard_owner(&namespace, &context.location.namespace);
+
     match arguments.get(1) {
       Some(expression) => match &expression.kind {
         ExpressionKind::String(critera) => {
-          self.use_scoreboard(name, critera.clone());
+          self.use_scoreboard(
+            name,
+            Some((critera.clone(), expression.location.clone())),
+            owner,
+          );
         }
         _ => return Err(raise_error(location, "Invalid argument. Expected string.")),
       },
       None => {
-        self.use_scoreboard_dummy(name);
+        self.use_scoreboard(name, None, owner);
       }
     };
 
@@ -173,19 +610,392 @@ impl Compiler {
       | ExpressionKind::Compound(_) => {
         return Err(raise_error(
           location,
-          "`@set` can only be used on scoreboards and storages.",
+          "`@set` can only be used on scoreboards, storages and entity or block data.",
         ))
       }
       ExpressionKind::Storage(storage_location) => {
+        if storage_location.storage == self.function_storage(&context.location) {
+          let root = storage_location
+            .name
+            .split(['.', '['])
+            .next()
+            .expect("Split always yields at least one item");
+          context.variables.assigned.insert(root.into());
+        }
         self.set_storage(&mut context.code, &storage_location, &src)?
       }
       ExpressionKind::Scoreboard(scoreboard_location) => {
         self.set_scoreboard(&mut context.code, &scoreboard_location, &src)?
       }
+      ExpressionKind::Data(_) => {
+        self.set_data(&mut context.code, &dst, &src, &context.location.namespace)?
+      }
     }
 
     Ok(Expression::new(ExpressionKind::Void, location))
   }
+
+  // The length of a string or array, or the number of entries in a compound
+  fn len(
+    &mut self,
+    arguments: Vec<Expression>,
+    location: Location,
+    context: &mut FunctionContext,
+  ) -> Result<Expression> {
+    check_args(&location, 1, arguments.len())?;
+    let value = &arguments[0];
+    let length = match &value.kind {
+      // Minecraft counts the length of strings in UTF-16 code units
+      ExpressionKind::String(string) => string.encode_utf16().count(),
+      ExpressionKind::Array { values, .. }
+      | ExpressionKind::ByteArray(values)
+      | ExpressionKind::IntArray(values)
+      | ExpressionKind::LongArray(values) => values.len(),
+      ExpressionKind::Compound(values) => values.len(),
+      // `data get` gives the length of lists and strings, and the size of compounds
+      ExpressionKind::Storage(_) | ExpressionKind::Macro(_) | ExpressionKind::Data(_) => {
+        let namespace = context.location.namespace.clone();
+        let scoreboard = self.copy_to_scoreboard(&mut context.code, value, &namespace)?;
+        return Ok(Expression::new(
+          ExpressionKind::Scoreboard(scoreboard),
+          location,
+        ));
+      }
+      ExpressionKind::Byte(_)
+      | ExpressionKind::Short(_)
+      | ExpressionKind::Integer(_)
+      | ExpressionKind::Long(_)
+      | ExpressionKind::Float(_)
+      | ExpressionKind::Double(_)
+      | ExpressionKind::Scoreboard(_) => {
+        return Err(raise_error(
+          value.location.clone(),
+          "Cannot take the length of a number.",
+        ))
+      }
+      _ => {
+        return Err(raise_error(
+          value.location.clone(),
+          "Can only get the length of strings, arrays and compounds.",
+        ))
+      }
+    };
+    Ok(Expression::new(
+      ExpressionKind::Integer(length as i32),
+      location,
+    ))
+  }
+
+  // Converts a decimal to an integer. Values only known in-game are read with
+  // `data get`, which rounds down, so they are scaled to round other ways
+  fn round(
+    &mut self,
+    rounding: Rounding,
+    arguments: Vec<Expression>,
+    location: Location,
+    context: &mut FunctionContext,
+  ) -> Result<Expression> {
+    check_args(&location, 1, arguments.len())?;
+    let value = &arguments[0];
+    let decimal = match value.kind {
+      ExpressionKind::Float(value) => Some(value as f64),
+      ExpressionKind::Double(value) => Some(value),
+      _ => None,
+    };
+    if let Some(decimal) = decimal {
+      let result = match rounding {
+        Rounding::Floor => decimal.floor(),
+        Rounding::Ceil => decimal.ceil(),
+        // Halves round up, to match rounding in-game
+        Rounding::Round => (decimal + 0.5).floor(),
+      };
+      return Ok(Expression::new(
+        ExpressionKind::Integer(result as i32),
+        location,
+      ));
+    }
+
+    let source = match &value.kind {
+      ExpressionKind::Storage(storage) | ExpressionKind::Macro(storage) => {
+        eco_format!("storage {storage}")
+      }
+      ExpressionKind::Data(data) => data.to_eco_string(),
+      // Integers are already whole
+      ExpressionKind::Byte(_)
+      | ExpressionKind::Short(_)
+      | ExpressionKind::Integer(_)
+      | ExpressionKind::Long(_)
+      | ExpressionKind::Scoreboard(_) => return Ok(value.clone()),
+      _ => {
+        return Err(raise_error(
+          value.location.clone(),
+          "Can only round numbers.",
+        ))
+      }
+    };
+
+    let namespace = context.location.namespace.clone();
+    let scoreboard = self.next_scoreboard(&namespace);
+    let prefix = if value.needs_macro { "$" } else { "" };
+    let scale = match rounding {
+      Rounding::Floor => "",
+      Rounding::Ceil => " -1",
+      Rounding::Round => " 2",
+    };
+    context.code.push(eco_format!(
+      "{prefix}execute store result score {scoreboard} run data get {source}{scale}"
+    ));
+    match rounding {
+      Rounding::Floor => {}
+      // The ceiling is the negated floor of the negated value
+      Rounding::Ceil => {
+        let negative = self.constant_scoreboard(-1, &namespace);
+        context.code.push(eco_format!(
+          "scoreboard players operation {scoreboard} *= {negative}"
+        ));
+      }
+      // Rounding `x` is the floor of `(floor(2x) + 1) / 2`
+      Rounding::Round => {
+        let two = self.constant_scoreboard(2, &namespace);
+        context
+          .code
+          .push(eco_format!("scoreboard players add {scoreboard} 1"));
+        context.code.push(eco_format!(
+          "scoreboard players operation {scoreboard} /= {two}"
+        ));
+      }
+    }
+    Ok(Expression::new(
+      ExpressionKind::Scoreboard(scoreboard),
+      location,
+    ))
+  }
+
+  fn read_file(&mut self, arguments: Vec<Expression>, location: Location) -> Result<Expression> {
+    check_args(&location, 1, arguments.len())?;
+    let path = comptime_string(&location, &arguments[0])?;
+
+    let root_file = Path::new(&*location.root)
+      .canonicalize()
+      .map_err(|e| raise_error(location.clone(), e))?;
+    let project_root = root_file.parent().expect("Path should be valid");
+
+    let relative_path = if let Some(stripped) = path.strip_prefix('/') {
+      project_root.join(stripped)
+    } else {
+      Path::new(&*location.file)
+        .parent()
+        .expect("Path should be valid")
+        .join(path.as_str())
+    };
+    let full_path = relative_path
+      .canonicalize()
+      .map_err(|e| raise_error(location.clone(), format!("Could not read `{path}`: {e}")))?;
+
+    if !full_path.starts_with(project_root) {
+      return Err(raise_error(
+        location,
+        format!("Cannot read `{path}`, as it is outside of the project directory."),
+      ));
+    }
+
+    let contents = fs::read_to_string(&full_path)
+      .map_err(|e| raise_error(location.clone(), format!("Could not read `{path}`: {e}")))?;
+    self.dependent_files.insert(file_key(&full_path));
+
+    Ok(Expression::new(
+      ExpressionKind::String(contents.into()),
+      location,
+    ))
+  }
+}
+
+// Parses a function path written as a string, in the same form as a
+// resource location in source code
+pub(super) fn function_path(path: &str, location: Location) -> ZoglinResource {
+  let (namespace, path) = if let Some(rest) = path.strip_prefix("~/") {
+    (Some("~".into()), rest)
+  } else if let Some((namespace, rest)) = path.split_once(':') {
+    (Some(namespace.into()), rest)
+  } else {
+    (None, path)
+  };
+  let mut modules: Vec<EcoString> = path.split('/').map(EcoString::from).collect();
+  let name = modules
+    .pop()
+    .expect("Split always yields at least one item");
+
+  ZoglinResource {
+    namespace,
+    location,
+    modules,
+    name,
+  }
+}
+
+fn expect_function_path(argument: ast::Expression) -> Result<ZoglinResource> {
+  match argument {
+    ast::Expression::Variable(path) => Ok(path),
+    argument => Err(raise_error(
+      argument.location(),
+      "Invalid argument. Expected a function path.",
+    )),
+  }
+}
+
+// Checks a delay is a number with an optional unit, like `10`, `1.5s` or
+// `2d`, which Minecraft rounds to at least one tick
+fn is_valid_delay(delay: &str) -> bool {
+  let (number, ticks_per_unit) = match delay.char_indices().last() {
+    Some((index, 't')) => (&delay[..index], 1.0),
+    Some((index, 's')) => (&delay[..index], 20.0),
+    Some((index, 'd')) => (&delay[..index], 24000.0),
+    _ => (delay, 1.0),
+  };
+  let is_digits = |text: &str| !text.is_empty() && text.bytes().all(|byte| byte.is_ascii_digit());
+  let is_number = match number.split_once('.') {
+    Some((whole, fraction)) => is_digits(whole) && is_digits(fraction),
+    None => is_digits(number),
+  };
+  is_number
+    && number
+      .parse::<f32>()
+      .is_ok_and(|number| (number * ticks_per_unit).round() >= 1.0)
+}
+
+fn test_fail(
+  arguments: Vec<Expression>,
+  location: Location,
+  context: &mut FunctionContext,
+) -> Result<Expression> {
+  check_args(&location, 1, arguments.len())?;
+  let message = ExpressionKind::String(comptime_string(&location, &arguments[0])?)
+    .to_comptime_string(false)
+    .expect("Strings can always be converted");
+  context.code.push(eco_format!(
+    "data modify storage {TEST_STORAGE} failures append value {message}"
+  ));
+  Ok(Expression::new(ExpressionKind::Void, location))
+}
+
+// Where the builtin is written, so code inlined from a compile-time function
+// or an included file gives the location in that function or file
+fn source_location(
+  name: &str,
+  arguments: Vec<Expression>,
+  location: Location,
+  context: &FunctionContext,
+) -> Result<Expression> {
+  check_args(&location, 0, arguments.len())?;
+  let kind = match name {
+    "file" => ExpressionKind::String(location.file.clone()),
+    "line" => ExpressionKind::Integer(location.line as i32),
+    _ => ExpressionKind::String(context.location.to_eco_string()),
+  };
+  Ok(Expression::new(kind, location))
+}
+
+fn comptime_string(location: &Location, argument: &Expression) -> Result<EcoString> {
+  match &argument.kind {
+    ExpressionKind::String(value) => Ok(value.clone()),
+    _ => Err(raise_error(
+      location.clone(),
+      "Invalid argument. Expected compile-time string.",
+    )),
+  }
+}
+
+fn parse_json(arguments: Vec<Expression>, location: Location) -> Result<Expression> {
+  check_args(&location, 1, arguments.len())?;
+  let text = comptime_string(&location, &arguments[0])?;
+
+  let value: serde_json::Value =
+    json5::from_str(&text).map_err(|e| raise_error(location.clone(), e))?;
+  json_to_expression(value, &location)
+}
+
+// Splits a compile-time string into an array of strings. Without a
+// separator, it is split into lines, such as the rows of a file read with
+// `@read_file`
+fn split(arguments: Vec<Expression>, location: Location) -> Result<Expression> {
+  if arguments.len() > 2 || arguments.is_empty() {
+    return Err(raise_error(
+      location,
+      format!(
+        "Incorrect number of arguments. Expected 1 or 2, got {}",
+        arguments.len()
+      ),
+    ));
+  };
+  let text = comptime_string(&location, &arguments[0])?;
+  let parts: Vec<&str> = match arguments.get(1) {
+    Some(separator) => {
+      let separator = comptime_string(&location, separator)?;
+      if separator.is_empty() {
+        return Err(raise_error(location, "Can't split on an empty string."));
+      }
+      text.split(separator.as_str()).collect()
+    }
+    None => text.lines().collect(),
+  };
+
+  let values: Vec<_> = parts
+    .into_iter()
+    .map(|part| Expression::new(ExpressionKind::String(part.into()), location.clone()))
+    .collect();
+  let data_type = verify_types(
+    &values,
+    ArrayType::Any,
+    "Arrays can only contain values of the same type",
+  )?;
+  Ok(Expression::new(
+    ExpressionKind::Array { values, data_type },
+    location,
+  ))
+}
+
+fn json_to_expression(value: serde_json::Value, location: &Location) -> Result<Expression> {
+  use serde_json::Value;
+
+  let kind = match value {
+    Value::Null => {
+      return Err(raise_error(
+        location.clone(),
+        "JSON null has no equivalent Zoglin value.",
+      ))
+    }
+    Value::Bool(b) => ExpressionKind::Boolean(b),
+    Value::Number(number) => match number.as_i64() {
+      Some(n) => match i32::try_from(n) {
+        Ok(n) => ExpressionKind::Integer(n),
+        Err(_) => ExpressionKind::Long(n),
+      },
+      None => ExpressionKind::Double(number.as_f64().expect("Number is representable as f64")),
+    },
+    Value::String(s) => ExpressionKind::String(s.into()),
+    Value::Array(values) => {
+      let values = values
+        .into_iter()
+        .map(|value| json_to_expression(value, location))
+        .collect::<Result<Vec<_>>>()?;
+      let data_type = verify_types(
+        &values,
+        ArrayType::Any,
+        "Arrays can only contain values of the same type",
+      )?;
+      ExpressionKind::Array { values, data_type }
+    }
+    Value::Object(map) => {
+      let mut values = HashMap::new();
+      for (key, value) in map {
+        let key = validate_or_quote(key.into(), location, NameKind::NBTPathComponent);
+        values.insert(key, json_to_expression(value, location)?);
+      }
+      ExpressionKind::Compound(values)
+    }
+  };
+
+  Ok(Expression::new(kind, location.clone()))
 }
 
 fn check_args(location: &Location, expected: usize, got: usize) -> Result<()> {
@@ -198,3 +1008,22 @@ fn check_args(location: &Location, expected: usize, got: usize) -> Result<()> {
     ))
   }
 }
+
+pub(super) fn score_component(scoreboard: &ScoreboardLocation) -> serde_json::Value {
+  json!({ "score": {
+    "name": scoreboard.name.as_str(),
+    "objective": scoreboard.scoreboard_string().as_str(),
+  }})
+}
+
+pub(super) fn storage_component(storage: &StorageLocation) -> serde_json::Value {
+  json!({ "nbt": storage.name.as_str(), "storage": storage.storage.to_string() })
+}
+
+pub(super) fn data_component(data: &DataLocation) -> serde_json::Value {
+  let kind = match data.kind {
+    ast::DataKind::Entity => "entity",
+    ast::DataKind::Block => "block",
+  };
+  json!({ "nbt": data.path.as_str(), kind: data.target.as_str() })
+}
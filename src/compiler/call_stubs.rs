@@ -0,0 +1,112 @@
+use std::fmt::Write;
+
+use ecow::{eco_format, EcoString};
+
+use crate::parser::ast::{self, ParameterKind};
+
+use super::{
+  expression::ExpressionKind,
+  file_tree::{ScoreboardLocation, StorageLocation},
+  Compiler,
+};
+
+impl Compiler {
+  // Shows how to call each function with storage or macro parameters from a
+  // command, such as in a command block or another pack
+  pub(super) fn call_stubs(&self, namespace: &str) -> Option<EcoString> {
+    let mut functions: Vec<_> = self
+      .function_registry
+      .values()
+      .filter(|definition| {
+        definition.location.namespace == namespace
+          && definition.arguments.iter().any(|parameter| {
+            matches!(
+              parameter.kind,
+              ParameterKind::Storage | ParameterKind::Macro
+            )
+          })
+          && definition
+            .arguments
+            .iter()
+            .all(|parameter| parameter.kind != ParameterKind::CompileTime)
+      })
+      .collect();
+    if functions.is_empty() {
+      return None;
+    }
+    functions.sort_by_key(|definition| definition.location.to_string());
+
+    let mut text = format!("# Calling functions in `{namespace}`\n");
+    for definition in functions {
+      let storage = self.function_storage(&definition.location);
+      let mut commands = Vec::new();
+      for parameter in definition.arguments.iter() {
+        let default = parameter.default.as_ref().and_then(literal_default);
+        let placeholder = eco_format!("<{}>", parameter.name);
+        let value = default
+          .as_ref()
+          .and_then(|default| default.to_comptime_string(false))
+          .unwrap_or_else(|| placeholder.clone());
+        commands.push(match parameter.kind {
+          ParameterKind::Storage => eco_format!(
+            "data modify storage {} set value {value}",
+            StorageLocation::new(storage.clone(), parameter.name.clone())
+          ),
+          ParameterKind::Macro => eco_format!(
+            "data modify storage {} set value {value}",
+            StorageLocation::new(storage.clone(), eco_format!("__{}", parameter.name))
+          ),
+          ParameterKind::Scoreboard => {
+            let value = default
+              .and_then(|default| default.numeric_value())
+              .map_or(placeholder, |value| value.to_string().into());
+            eco_format!(
+              "scoreboard players set {} {value}",
+              ScoreboardLocation::new(
+                definition.location.clone(),
+                &eco_format!("${}", parameter.name)
+              )
+            )
+          }
+          ParameterKind::CompileTime => {
+            unreachable!("Functions with compile-time parameters are skipped")
+          }
+        });
+      }
+      let has_macro_parameters = definition
+        .arguments
+        .iter()
+        .any(|parameter| parameter.kind == ParameterKind::Macro);
+      commands.push(if has_macro_parameters {
+        eco_format!("function {} with storage {storage}", definition.location)
+      } else {
+        eco_format!("function {}", definition.location)
+      });
+
+      write!(
+        text,
+        "\n## `{}`\n\n```mcfunction\n{}\n```\n",
+        definition.location,
+        commands.join("\n")
+      )
+      .expect("Writing to a string succeeds");
+    }
+    Some(text.into())
+  }
+}
+
+// The value of a default written as a literal. Anything else can't be shown
+// without the source, so is shown as a placeholder
+pub(super) fn literal_default(default: &ast::Expression) -> Option<ExpressionKind> {
+  Some(match default {
+    ast::Expression::Boolean(value, _) => ExpressionKind::Boolean(*value),
+    ast::Expression::Byte(value, _) => ExpressionKind::Byte(*value),
+    ast::Expression::Short(value, _) => ExpressionKind::Short(*value),
+    ast::Expression::Integer(value, _) => ExpressionKind::Integer(*value),
+    ast::Expression::Long(value, _) => ExpressionKind::Long(*value),
+    ast::Expression::Float(value, _) => ExpressionKind::Float(*value),
+    ast::Expression::Double(value, _) => ExpressionKind::Double(*value),
+    ast::Expression::String(value, _) => ExpressionKind::String(value.clone()),
+    _ => return None,
+  })
+}
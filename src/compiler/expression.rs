@@ -8,7 +8,7 @@ use crate::{
 };
 
 use super::{
-  file_tree::{ScoreboardLocation, StorageLocation},
+  file_tree::{DataLocation, ScoreboardLocation, StorageLocation},
   utils::ToEcoString,
   Compiler,
 };
@@ -44,6 +44,7 @@ pub enum ExpressionKind {
   SubString(StorageLocation, i32, Option<i32>),
   Scoreboard(ScoreboardLocation),
   Macro(StorageLocation),
+  Data(DataLocation),
   Condition(Condition),
 }
 
@@ -151,6 +152,13 @@ pub enum ConditionKind {
   Known(bool),
 }
 
+// How a value interpolated into a command is written
+#[derive(Clone, Copy)]
+pub enum Interpolation {
+  Plain,
+  Nbt,
+}
+
 impl Expression {
   pub fn new(kind: ExpressionKind, location: Location) -> Expression {
     Expression {
@@ -219,6 +227,7 @@ impl Expression {
       ExpressionKind::Storage(storage) => {
         (eco_format!("from storage {storage}"), StorageKind::Modify)
       }
+      ExpressionKind::Data(data) => (eco_format!("from {data}"), StorageKind::Modify),
       ExpressionKind::SubString(storage, start, end) => (
         eco_format!(
           "string storage {storage} {start}{}",
@@ -249,7 +258,14 @@ impl Expression {
       _ => kind,
     };
 
-    let store_type = data_type.to_store_string().unwrap_or("int".into());
+    let (store, store_type) = match &self.kind {
+      // Conditions are stored as booleans, whatever the type of the storage
+      ExpressionKind::Condition(_) => ("success", "byte".into()),
+      _ => (
+        "result",
+        data_type.to_store_string().unwrap_or("int".into()),
+      ),
+    };
 
     match kind {
       StorageKind::Modify => code.push(eco_format!(
@@ -261,12 +277,12 @@ impl Expression {
       StorageKind::Store => {
         if operation == "set" {
           code.push(eco_format!(
-            "execute store result storage {storage} {store_type} 1 run {conversion_code}",
+            "execute store {store} storage {storage} {store_type} 1 run {conversion_code}",
           ))
         } else {
           let temp_storage = state.next_storage(&storage.storage.namespace);
           code.push(eco_format!(
-            "execute store result storage {temp_storage} {store_type} 1 run {conversion_code}",
+            "execute store {store} storage {temp_storage} {store_type} 1 run {conversion_code}",
           ));
           code.push(eco_format!(
             "data modify storage {storage} {operation} from storage {temp_storage}",
@@ -276,12 +292,12 @@ impl Expression {
       StorageKind::MacroStore => {
         if operation == "set" {
           code.push(eco_format!(
-            "$execute store result storage {storage} {store_type} 1 run {conversion_code}",
+            "$execute store {store} storage {storage} {store_type} 1 run {conversion_code}",
           ))
         } else {
           let temp_storage = state.next_storage(&storage.storage.namespace);
           code.push(eco_format!(
-            "$execute store result storage {temp_storage} {store_type} 1 run {conversion_code}",
+            "$execute store {store} storage {temp_storage} {store_type} 1 run {conversion_code}",
           ));
           code.push(eco_format!(
             "data modify storage {storage} {operation} from storage {temp_storage}",
@@ -342,6 +358,7 @@ impl Expression {
         eco_format!("data get storage {storage}"),
         ScoreKind::Indirect,
       ),
+      ExpressionKind::Data(data) => (eco_format!("data get {data}"), ScoreKind::Indirect),
       ExpressionKind::Scoreboard(scoreboard) => (
         eco_format!("= {scoreboard}"),
         ScoreKind::Direct("operation".into()),
@@ -407,7 +424,7 @@ impl Expression {
         "{} score {scoreboard} matches 0",
         if inverted { "if" } else { "unless" },
       )),
-      ExpressionKind::Storage(_) => {
+      ExpressionKind::Storage(_) | ExpressionKind::Data(_) => {
         let scoreboard = compiler.copy_to_scoreboard(code, self, namespace)?;
         ConditionKind::Check(eco_format!(
           "{} score {scoreboard} matches 0",
@@ -424,7 +441,7 @@ impl Expression {
     })
   }
 
-  pub fn to_return_command(&self) -> Result<EcoString> {
+  pub fn to_return_command(&self, code: &mut Vec<EcoString>) -> Result<EcoString> {
     Ok(match &self.kind {
       ExpressionKind::Void => return Err(raise_error(self.location.clone(), "Cannot return void")),
       ExpressionKind::Byte(value) => eco_format!("return {value}"),
@@ -455,12 +472,19 @@ impl Expression {
       ExpressionKind::Storage(storage) => {
         eco_format!("return run data get storage {storage}")
       }
+      ExpressionKind::Data(data) => eco_format!("return run data get {data}"),
       ExpressionKind::Scoreboard(scoreboard) => {
         eco_format!("return run scoreboard players get {scoreboard}")
       }
       ExpressionKind::Macro(name) => eco_format!("$return $({name})"),
+      // `return run execute` fails instead of returning 0 when the condition
+      // is false, so return each case explicitly
       ExpressionKind::Condition(condition) => {
-        eco_format!("return run execute {}", condition)
+        code.push(eco_format!(
+          "{}execute {condition} run return 1",
+          if self.needs_macro { "$" } else { "" }
+        ));
+        "return 0".into()
       }
     })
   }
@@ -567,6 +591,7 @@ impl ExpressionKind {
       ExpressionKind::Float(_) => NbtType::Float,
       ExpressionKind::Double(_) => NbtType::Double,
       ExpressionKind::Storage(_) => NbtType::Unknown,
+      ExpressionKind::Data(_) => NbtType::Unknown,
       ExpressionKind::Scoreboard(_) => NbtType::Numeric,
       ExpressionKind::Boolean(_) => NbtType::Byte,
       ExpressionKind::String(_) => NbtType::String,
@@ -601,10 +626,26 @@ impl ExpressionKind {
       | ExpressionKind::SubString(_, _, _)
       | ExpressionKind::Scoreboard(_)
       | ExpressionKind::Condition(_)
-      | ExpressionKind::Macro(_) => false,
+      | ExpressionKind::Macro(_)
+      | ExpressionKind::Data(_) => false,
     }
   }
 
+  // Numbers are written without a suffix and booleans as `true` or `false`,
+  // unless written as SNBT
+  pub fn to_command_string(&self, interpolation: Interpolation) -> Option<EcoString> {
+    Some(match (self, interpolation) {
+      (ExpressionKind::Byte(b), Interpolation::Plain) => b.to_eco_string(),
+      (ExpressionKind::Short(s), Interpolation::Plain) => s.to_eco_string(),
+      (ExpressionKind::Long(l), Interpolation::Plain) => l.to_eco_string(),
+      (ExpressionKind::Float(f), Interpolation::Plain) => f.to_eco_string(),
+      (ExpressionKind::Double(d), Interpolation::Plain) => d.to_eco_string(),
+      (ExpressionKind::Boolean(b), Interpolation::Nbt) => if *b { "1b" } else { "0b" }.into(),
+      (_, Interpolation::Plain) => return self.to_comptime_string(true),
+      (_, Interpolation::Nbt) => return self.to_comptime_string(false),
+    })
+  }
+
   pub fn to_comptime_string(&self, top_level: bool) -> Option<EcoString> {
     Some(match self {
       ExpressionKind::Void => return None,
@@ -615,14 +656,20 @@ impl ExpressionKind {
       ExpressionKind::Float(f) => eco_format!("{f}f"),
       ExpressionKind::Double(d) => eco_format!("{d}d"),
       ExpressionKind::Boolean(b) => b.to_eco_string(),
-      ExpressionKind::String(s) => s.clone(),
+      ExpressionKind::String(s) => {
+        if top_level {
+          s.clone()
+        } else {
+          eco_format!("\"{}\"", s.replace("\\", "\\\\").replace("\"", "\\\""))
+        }
+      }
       ExpressionKind::Array { values, .. } => return array_to_string(values, ""),
       ExpressionKind::ByteArray(values) => return array_to_string(values, "B; "),
       ExpressionKind::IntArray(values) => return array_to_string(values, "I; "),
       ExpressionKind::LongArray(values) => return array_to_string(values, "L; "),
       ExpressionKind::Compound(values) => {
-        let value_strings: Vec<_> = values
-          .iter()
+        let value_strings: Vec<_> = sorted_entries(values)
+          .into_iter()
           .filter_map(|(key, value)| {
             value
               .kind
@@ -643,6 +690,13 @@ impl ExpressionKind {
           return None;
         }
       }
+      ExpressionKind::Data(data) => {
+        if top_level {
+          data.to_eco_string()
+        } else {
+          return None;
+        }
+      }
       ExpressionKind::SubString(_, _, _) => return None,
       ExpressionKind::Scoreboard(scoreboard) => {
         if top_level {
@@ -661,6 +715,37 @@ impl ExpressionKind {
       }
     })
   }
+
+  pub fn to_json(&self) -> Option<serde_json::Value> {
+    use serde_json::Value;
+
+    Some(match self {
+      ExpressionKind::Byte(b) => Value::from(*b),
+      ExpressionKind::Short(s) => Value::from(*s),
+      ExpressionKind::Integer(i) => Value::from(*i),
+      ExpressionKind::Long(l) => Value::from(*l),
+      ExpressionKind::Float(f) => Value::from(*f),
+      ExpressionKind::Double(d) => Value::from(*d),
+      ExpressionKind::Boolean(b) => Value::from(*b),
+      ExpressionKind::String(s) => Value::from(s.as_str()),
+      ExpressionKind::Array { values, .. }
+      | ExpressionKind::ByteArray(values)
+      | ExpressionKind::IntArray(values)
+      | ExpressionKind::LongArray(values) => Value::Array(
+        values
+          .iter()
+          .map(|value| value.kind.to_json())
+          .collect::<Option<_>>()?,
+      ),
+      ExpressionKind::Compound(values) => Value::Object(
+        values
+          .iter()
+          .map(|(key, value)| Some((key.to_string(), value.kind.to_json()?)))
+          .collect::<Option<_>>()?,
+      ),
+      _ => return None,
+    })
+  }
 }
 
 fn array_to_string(values: &[Expression], prefix: &str) -> Option<EcoString> {
@@ -686,6 +771,9 @@ impl Expression {
       (ExpressionKind::Float(l0), ExpressionKind::Float(r0)) => l0 == r0,
       (ExpressionKind::Double(l0), ExpressionKind::Double(r0)) => l0 == r0,
       (ExpressionKind::Boolean(l0), ExpressionKind::Boolean(r0)) => l0 == r0,
+      // Booleans are stored as bytes
+      (ExpressionKind::Boolean(boolean), ExpressionKind::Byte(byte))
+      | (ExpressionKind::Byte(byte), ExpressionKind::Boolean(boolean)) => *byte == *boolean as i8,
       (ExpressionKind::String(l0), ExpressionKind::String(r0)) => l0 == r0,
       (
         ExpressionKind::Array {
@@ -728,13 +816,16 @@ impl Expression {
           return equal;
         }
       }
+      // Storage and macros have an unknown type, so could still hold a number
       (ExpressionKind::Scoreboard(_), other) | (other, ExpressionKind::Scoreboard(_))
-        if !other.to_type().is_numeric() =>
+        if other.to_type() != NbtType::Unknown && !other.to_type().is_numeric() =>
       {
         false
       }
       (ExpressionKind::Storage(_), _)
       | (_, ExpressionKind::Storage(_))
+      | (ExpressionKind::Data(_), _)
+      | (_, ExpressionKind::Data(_))
       | (ExpressionKind::Scoreboard(_), _)
       | (_, ExpressionKind::Scoreboard(_))
       | (ExpressionKind::Condition(_), _)
@@ -780,6 +871,23 @@ fn array_to_storage(
       continue;
     }
 
+    // Copying from another storage keeps the source's type, so elements of
+    // typed arrays are converted to the array's element type first
+    if let (false, ExpressionKind::Storage(source)) = (prefix.is_empty(), &element.kind) {
+      let store_type = data_type
+        .to_store_string()
+        .expect("Typed arrays have numeric elements");
+      let macro_prefix = if element.needs_macro { "$" } else { "" };
+      let temp_storage = state.next_storage(&storage.storage.namespace);
+      computed_elements_code.push(eco_format!(
+        "{macro_prefix}execute store result storage {temp_storage} {store_type} 1 run data get storage {source}",
+      ));
+      computed_elements_code.push(eco_format!(
+        "data modify storage {storage} insert {i} from storage {temp_storage}",
+      ));
+      continue;
+    }
+
     element.to_storage(
       state,
       &mut computed_elements_code,
@@ -797,6 +905,14 @@ fn array_to_storage(
   Ok(())
 }
 
+// Compounds are emitted in key order, so the output doesn't depend on the
+// order the map is iterated in
+fn sorted_entries(elements: &HashMap<EcoString, Expression>) -> Vec<(&EcoString, &Expression)> {
+  let mut entries: Vec<_> = elements.iter().collect();
+  entries.sort_by_key(|(key, _)| *key);
+  entries
+}
+
 fn compound_to_storage(
   elements: &HashMap<EcoString, Expression>,
   state: &mut Compiler,
@@ -806,7 +922,7 @@ fn compound_to_storage(
   let mut constant_elements = Vec::new();
   let mut computed_elements_code = Vec::new();
 
-  for (key, value) in elements {
+  for (key, value) in sorted_entries(elements) {
     if let Some(value) = value.kind.to_comptime_string(false) {
       constant_elements.push(eco_format!("{key}: {value}"));
       continue;
@@ -875,6 +991,25 @@ impl NbtType {
     )
   }
 
+  // The value a return slot of this type is reset to before a call. Booleans
+  // and unknown types use `false`, as they always have
+  pub fn empty_value(self) -> &'static str {
+    match self {
+      NbtType::Numeric | NbtType::Int => "0",
+      NbtType::Short => "0s",
+      NbtType::Long => "0L",
+      NbtType::Float => "0f",
+      NbtType::Double => "0d",
+      NbtType::ByteArray => "[B;]",
+      NbtType::IntArray => "[I;]",
+      NbtType::LongArray => "[L;]",
+      NbtType::String => "\"\"",
+      NbtType::List => "[]",
+      NbtType::Compound => "{}",
+      NbtType::Unknown | NbtType::Byte => "false",
+    }
+  }
+
   pub fn is_numeric(self) -> bool {
     match self {
       NbtType::Numeric
@@ -921,6 +1056,7 @@ pub fn verify_types(types: &[Expression], typ: ArrayType, message: &str) -> Resu
       (ExpressionKind::Float(_), NbtType::Float) => {}
       (ExpressionKind::Double(_), NbtType::Double) => {}
       (ExpressionKind::Storage(_), _) => {}
+      (ExpressionKind::Data(_), _) => {}
       (ExpressionKind::Scoreboard(_), t) if t.is_numeric() => {}
       (ExpressionKind::Boolean(_), NbtType::Byte) => {}
       (ExpressionKind::String(_), NbtType::String) => {}
@@ -1,16 +1,25 @@
 use ecow::{eco_format, EcoString};
 use glob::glob;
-use serde::Serialize;
-use std::{fmt::Display, fs, path::Path};
+use serde::{Deserialize, Serialize};
+use std::{
+  collections::{HashMap, HashSet},
+  fmt::{Display, Write},
+  fs,
+  path::{Component, Path, PathBuf},
+};
 
 use crate::{
-  error::{raise_error, raise_floating_error, Location, Result},
+  compiler::CompileOptions,
+  config::Version,
+  error::{raise_error, raise_floating_error, raise_note, Location, Result, Warnings},
   parser::ast::{self, ZoglinResource},
 };
 
 #[derive(Debug)]
 pub struct FileTree {
   pub namespaces: Vec<Namespace>,
+  // Functions marked with `#[test]`, in the order they were defined
+  pub tests: Vec<ResourceLocation>,
 }
 
 #[derive(Serialize)]
@@ -24,6 +33,35 @@ struct Pack {
   description: &'static str,
 }
 
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum LineEndings {
+  #[default]
+  Lf,
+  Crlf,
+}
+
+impl LineEndings {
+  pub fn from_name(name: &str) -> Option<LineEndings> {
+    match name {
+      "lf" => Some(LineEndings::Lf),
+      "crlf" => Some(LineEndings::Crlf),
+      _ => None,
+    }
+  }
+}
+
+// All generated text files go through here, so they consistently end with
+// exactly one newline and use the same line endings on every platform
+fn write_text(path: &Path, text: &str, line_endings: LineEndings) -> Result<()> {
+  let mut text = text.replace("\r\n", "\n");
+  text.truncate(text.trim_end_matches('\n').len());
+  text.push('\n');
+  if line_endings == LineEndings::Crlf {
+    text = text.replace('\n', "\r\n");
+  }
+  fs::write(path, text).map_err(raise_floating_error)
+}
+
 const DEFAULT_MCMETA: PackMcmeta = PackMcmeta {
   pack: Pack {
     pack_format: 48,
@@ -31,19 +69,169 @@ const DEFAULT_MCMETA: PackMcmeta = PackMcmeta {
   },
 };
 
+// Lists the files written by the last build, relative to the output directory
+const MANIFEST: &str = ".zoglin-manifest.json";
+
+#[derive(Serialize, Deserialize)]
+struct Manifest {
+  // The version of Zoglin that wrote the files
+  zoglin: Version,
+  files: Vec<String>,
+}
+
+// Manifests written before the version was recorded only list the files
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PreviousManifest {
+  Versioned(Manifest),
+  Unversioned(Vec<String>),
+}
+
+// For files that don't come from anywhere in the source
+static BLANK_LOCATION: Location = Location::blank();
+
+// A file written by the file tree, and the item it comes from
+struct Output<'a> {
+  path: PathBuf,
+  source: &'static str,
+  location: &'a Location,
+}
+
 impl FileTree {
-  pub fn generate(&self, root_path: &str) -> Result<()> {
-    let _ = fs::remove_dir_all(root_path);
+  // Only files written by the previous build are removed, so other files in
+  // the output directory are left alone
+  pub fn generate(&self, root_path: &str, options: &CompileOptions) -> Result<()> {
+    let line_endings = options.line_endings;
+    let outputs = self.outputs()?;
+    check_conflicts(&outputs)?;
+    let mut files: Vec<_> = outputs
+      .iter()
+      .map(|output| manifest_path(&output.path))
+      .collect();
+    files.push("pack.mcmeta".into());
+    files.sort();
+    let manifest = Manifest {
+      zoglin: Version::current(),
+      files,
+    };
+    remove_previous_files(
+      Path::new(root_path),
+      &manifest,
+      options.version_check,
+      &options.warnings,
+    );
+
     let working_path = Path::new(root_path).join("data");
     fs::create_dir_all(working_path).map_err(raise_floating_error)?;
 
-    let text = serde_json::to_string_pretty(&DEFAULT_MCMETA).expect("Json is valid");
-    fs::write(Path::new(root_path).join("pack.mcmeta"), text).map_err(raise_floating_error)?;
+    let text = match &options.meta {
+      Some(meta) => serde_json::to_string_pretty(meta),
+      None => serde_json::to_string_pretty(&DEFAULT_MCMETA),
+    }
+    .expect("Json is valid");
+    write_text(
+      &Path::new(root_path).join("pack.mcmeta"),
+      &text,
+      line_endings,
+    )?;
 
     for namespace in self.namespaces.iter() {
-      namespace.generate(root_path)?;
+      namespace.generate(root_path, line_endings)?;
+    }
+
+    let manifest = serde_json::to_string_pretty(&manifest).expect("Json is valid");
+    write_text(
+      &Path::new(root_path).join(MANIFEST),
+      &manifest,
+      line_endings,
+    )
+  }
+
+  fn outputs(&self) -> Result<Vec<Output<'_>>> {
+    let mut outputs = Vec::new();
+    for namespace in self.namespaces.iter() {
+      let local_path = ResourceLocation::new_module(&namespace.name, &[]);
+      for item in namespace.items.iter() {
+        item.outputs(&local_path, &mut outputs)?;
+      }
+      if namespace.call_stubs.is_some() {
+        outputs.push(Output {
+          path: namespace.call_stubs_path(Path::new("")),
+          source: "call stubs",
+          location: &BLANK_LOCATION,
+        });
+      }
+    }
+    Ok(outputs)
+  }
+
+  // Lists the commands of each function whose path contains `filter`, for
+  // `--debug compiled`. Namespaces, modules and functions are sorted by name,
+  // so dumps of different builds can be compared
+  pub fn dump(&self, filter: &str) -> String {
+    let mut output = String::new();
+    let mut namespaces: Vec<_> = self.namespaces.iter().collect();
+    namespaces.sort_by_key(|namespace| &namespace.name);
+    for namespace in namespaces {
+      let mut lines = String::new();
+      dump_items(
+        &namespace.items,
+        &eco_format!("{}:", namespace.name),
+        1,
+        filter,
+        &mut lines,
+      );
+      if !lines.is_empty() {
+        writeln!(output, "namespace {}", namespace.name).expect("Writing to a string succeeds");
+        output.push_str(&lines);
+      }
+    }
+    output
+  }
+}
+
+fn dump_items(items: &[Item], prefix: &str, depth: usize, filter: &str, output: &mut String) {
+  let indent = "  ".repeat(depth);
+  let mut items: Vec<_> = items.iter().collect();
+  items.sort_by_key(|item| match item {
+    Item::Module(module) => Some(&module.name),
+    Item::Function(function) => Some(&function.name),
+    Item::TextResource(_) | Item::FileResource(_) => None,
+  });
+  for item in items {
+    match item {
+      Item::Module(module) => {
+        let mut lines = String::new();
+        dump_items(
+          &module.items,
+          &eco_format!("{prefix}{}/", module.name),
+          depth + 1,
+          filter,
+          &mut lines,
+        );
+        if !lines.is_empty() {
+          writeln!(output, "{indent}module {}", module.name).expect("Writing to a string succeeds");
+          output.push_str(&lines);
+        }
+      }
+      Item::Function(function) => {
+        if !eco_format!("{prefix}{}", function.name).contains(filter) {
+          continue;
+        }
+        // Functions the compiler generates have no source location
+        let marker = if function.location.file.is_empty() {
+          " (generated)"
+        } else {
+          ""
+        };
+        writeln!(output, "{indent}fn {}{marker}", function.name)
+          .expect("Writing to a string succeeds");
+        for command in function.commands.iter() {
+          writeln!(output, "{indent}  {command}").expect("Writing to a string succeeds");
+        }
+      }
+      Item::TextResource(_) | Item::FileResource(_) => {}
     }
-    Ok(())
   }
 }
 
@@ -51,16 +239,51 @@ impl FileTree {
 pub struct Namespace {
   pub name: EcoString,
   pub items: Vec<Item>,
+  // Written to `CALLING.md` with `--emit-call-stubs`
+  pub call_stubs: Option<EcoString>,
 }
 
 impl Namespace {
-  fn generate(&self, path: &str) -> Result<()> {
+  fn generate(&self, path: &str, line_endings: LineEndings) -> Result<()> {
     for item in self.items.iter() {
-      item.generate(path, &ResourceLocation::new_module(&self.name, &[]))?;
+      item.generate(
+        path,
+        &ResourceLocation::new_module(&self.name, &[]),
+        line_endings,
+      )?;
+    }
+    if let Some(call_stubs) = &self.call_stubs {
+      write_text(
+        &self.call_stubs_path(Path::new(path)),
+        call_stubs,
+        line_endings,
+      )?;
     }
     Ok(())
   }
 
+  fn call_stubs_path(&self, root_path: &Path) -> PathBuf {
+    root_path
+      .join("data")
+      .join(self.name.as_str())
+      .join("CALLING.md")
+  }
+
+  pub fn find_function(&self, path: &[EcoString]) -> Option<&Function> {
+    let (name, modules) = path.split_last()?;
+    let mut items = &self.items;
+    for module in modules {
+      items = items.iter().find_map(|item| match item {
+        Item::Module(Module { name, items }) if name == module => Some(items),
+        _ => None,
+      })?;
+    }
+    items.iter().find_map(|item| match item {
+      Item::Function(function) if &function.name == name => Some(function),
+      _ => None,
+    })
+  }
+
   pub fn get_module(&mut self, mut path: Vec<EcoString>) -> &mut Vec<Item> {
     if path.is_empty() {
       return &mut self.items;
@@ -100,11 +323,52 @@ pub enum Item {
 }
 
 impl Item {
-  fn generate(&self, root_path: &str, local_path: &ResourceLocation) -> Result<()> {
+  fn outputs<'a>(
+    &'a self,
+    local_path: &ResourceLocation,
+    outputs: &mut Vec<Output<'a>>,
+  ) -> Result<()> {
+    match self {
+      Item::Module(module) => {
+        let mut local_path = local_path.clone();
+        local_path.modules.push(module.name.clone());
+        for item in module.items.iter() {
+          item.outputs(&local_path, outputs)?;
+        }
+      }
+      Item::Function(function) => outputs.push(Output {
+        path: function.file_path(Path::new(""), local_path),
+        source: "function",
+        location: &function.location,
+      }),
+      Item::TextResource(resource) => outputs.push(Output {
+        path: resource.file_path(Path::new(""), local_path),
+        source: "resource",
+        location: &resource.location,
+      }),
+      Item::FileResource(resource) => {
+        for (_, path) in resource.files(Path::new(""), local_path)? {
+          outputs.push(Output {
+            path,
+            source: "file resource",
+            location: &resource.location,
+          });
+        }
+      }
+    }
+    Ok(())
+  }
+
+  fn generate(
+    &self,
+    root_path: &str,
+    local_path: &ResourceLocation,
+    line_endings: LineEndings,
+  ) -> Result<()> {
     match self {
-      Item::Module(module) => module.generate(root_path, local_path),
-      Item::Function(function) => function.generate(root_path, local_path),
-      Item::TextResource(resource) => resource.generate(root_path, local_path),
+      Item::Module(module) => module.generate(root_path, local_path, line_endings),
+      Item::Function(function) => function.generate(root_path, local_path, line_endings),
+      Item::TextResource(resource) => resource.generate(root_path, local_path, line_endings),
       Item::FileResource(resource) => resource.generate(root_path, local_path),
     }
   }
@@ -117,11 +381,16 @@ pub struct Module {
 }
 
 impl Module {
-  fn generate(&self, root_path: &str, local_path: &ResourceLocation) -> Result<()> {
+  fn generate(
+    &self,
+    root_path: &str,
+    local_path: &ResourceLocation,
+    line_endings: LineEndings,
+  ) -> Result<()> {
     let mut local_path = local_path.clone();
     local_path.modules.push(self.name.clone());
     for item in self.items.iter() {
-      item.generate(root_path, &local_path)?;
+      item.generate(root_path, &local_path, line_endings)?;
     }
     Ok(())
   }
@@ -164,16 +433,24 @@ pub struct Function {
 }
 
 impl Function {
-  fn generate(&self, root_path: &str, local_path: &ResourceLocation) -> Result<()> {
-    let dir_path = Path::new(root_path)
+  fn generate(
+    &self,
+    root_path: &str,
+    local_path: &ResourceLocation,
+    line_endings: LineEndings,
+  ) -> Result<()> {
+    let file_path = self.file_path(Path::new(root_path), local_path);
+    create_parent(&file_path)?;
+    write_text(&file_path, &self.commands.join("\n"), line_endings)
+  }
+
+  fn file_path(&self, root_path: &Path, local_path: &ResourceLocation) -> PathBuf {
+    root_path
       .join("data")
       .join(local_path.namespace.as_str())
       .join("function")
-      .join(local_path.modules.join("/"));
-
-    fs::create_dir_all(&dir_path).map_err(raise_floating_error)?;
-    let file_path = dir_path.join((self.name.clone() + ".mcfunction").as_str());
-    fs::write(file_path, self.commands.join("\n")).map_err(raise_floating_error)
+      .join(local_path.modules.join("/"))
+      .join((self.name.clone() + ".mcfunction").as_str())
   }
 }
 
@@ -193,21 +470,20 @@ impl PartialEq for TextResource {
 }
 
 impl TextResource {
-  fn generate(&self, root_path: &str, local_path: &ResourceLocation) -> Result<()> {
-    let resource_dir = if self.is_asset { "assets" } else { "data" };
-    let mut dir_path = Path::new(root_path)
-      .join(resource_dir)
-      .join(local_path.namespace.as_str());
-
-    if self.kind.as_str() != "." {
-      dir_path.push(self.kind.as_str());
-    }
-
-    dir_path.push(local_path.modules.join("/"));
+  fn generate(
+    &self,
+    root_path: &str,
+    local_path: &ResourceLocation,
+    line_endings: LineEndings,
+  ) -> Result<()> {
+    let file_path = self.file_path(Path::new(root_path), local_path);
+    create_parent(&file_path)?;
+    write_text(&file_path, &self.text, line_endings)
+  }
 
-    fs::create_dir_all(&dir_path).map_err(raise_floating_error)?;
-    let file_path = dir_path.join((self.name.clone() + ".json").as_str());
-    fs::write(file_path, self.text.as_str()).map_err(raise_floating_error)
+  fn file_path(&self, root_path: &Path, local_path: &ResourceLocation) -> PathBuf {
+    resource_dir(root_path, local_path, &self.kind, self.is_asset)
+      .join((self.name.clone() + ".json").as_str())
   }
 }
 
@@ -221,34 +497,208 @@ pub struct FileResource {
 
 impl FileResource {
   fn generate(&self, root_path: &str, local_path: &ResourceLocation) -> Result<()> {
-    let resource_dir = if self.is_asset { "assets" } else { "data" };
-    let mut dir_path = Path::new(&root_path)
-      .join(resource_dir)
-      .join(local_path.namespace.as_str());
-
-    if self.kind.as_str() != "." {
-      dir_path.push(self.kind.as_str());
+    let dir_path = resource_dir(Path::new(root_path), local_path, &self.kind, self.is_asset);
+    fs::create_dir_all(&dir_path).map_err(raise_floating_error)?;
+    for (source, destination) in self.files(Path::new(root_path), local_path)? {
+      fs::copy(&source, destination).map_err(raise_floating_error)?;
     }
 
-    dir_path.push(local_path.modules.join("/"));
+    Ok(())
+  }
 
-    fs::create_dir_all(&dir_path).map_err(raise_floating_error)?;
+  // Each file matched by the glob, and where it is copied to
+  fn files(
+    &self,
+    root_path: &Path,
+    local_path: &ResourceLocation,
+  ) -> Result<Vec<(PathBuf, PathBuf)>> {
+    let dir_path = resource_dir(root_path, local_path, &self.kind, self.is_asset);
+    let mut files = Vec::new();
     for entry in glob(&self.path).map_err(|e| raise_error(self.location.clone(), e.msg))? {
       match entry {
         Ok(path) => {
-          let filename = path.file_name().expect("Path should be valid");
-          if Path::new(&path).is_file() {
-            fs::copy(&path, dir_path.join(filename)).map_err(raise_floating_error)?;
+          if path.is_file() {
+            let filename = path.file_name().expect("Path should be valid");
+            let destination = dir_path.join(filename);
+            files.push((path, destination));
           }
         }
         Err(e) => return Err(raise_floating_error(e)),
       };
     }
+    Ok(files)
+  }
+}
 
-    Ok(())
+// Items are written one after another, so two items with the same output
+// path would otherwise silently overwrite each other
+fn check_conflicts(outputs: &[Output]) -> Result<()> {
+  let mut sources: HashMap<&Path, Vec<&Output>> = HashMap::new();
+  for output in outputs.iter() {
+    sources.entry(&output.path).or_default().push(output);
+  }
+  for output in outputs.iter() {
+    let conflicting = &sources[output.path.as_path()];
+    if conflicting.len() < 2 {
+      continue;
+    }
+    let mut message = format!("Multiple items are written to `{}`:", output.path.display());
+    for source in conflicting {
+      message.push_str(&format!("\n  {}", source.source));
+      if !source.location.file.is_empty() {
+        let location = source.location;
+        message.push_str(&format!(
+          " at {}:{}:{}",
+          location.file, location.line, location.column
+        ));
+      }
+    }
+    return Err(raise_error(
+      conflicting[conflicting.len() - 1].location.clone(),
+      message,
+    ));
+  }
+  Ok(())
+}
+
+// Paths in the manifest use `/`, whatever the platform
+fn manifest_path(path: &Path) -> String {
+  path
+    .components()
+    .map(|component| component.as_os_str().to_string_lossy())
+    .collect::<Vec<_>>()
+    .join("/")
+}
+
+fn manifest_location(manifest_path: &Path) -> Location {
+  Location {
+    file: manifest_path.to_string_lossy().into(),
+    line: 1,
+    column: 1,
+    root: EcoString::new(),
+  }
+}
+
+// Deletes the files the previous build wrote that this one doesn't, along with
+// any directories left empty. A missing or unreadable manifest is treated as
+// output from another version
+fn remove_previous_files(
+  root_path: &Path,
+  manifest: &Manifest,
+  version_check: bool,
+  warnings: &Warnings,
+) {
+  let manifest_path = root_path.join(MANIFEST);
+  let previous = fs::read_to_string(&manifest_path)
+    .ok()
+    .and_then(|text| serde_json::from_str::<PreviousManifest>(&text).ok());
+  let (version, previous_files) = match previous {
+    Some(PreviousManifest::Versioned(previous)) => (Some(previous.zoglin), previous.files),
+    Some(PreviousManifest::Unversioned(files)) => (None, files),
+    None => {
+      if version_check {
+        remove_generated_directories(root_path, manifest, warnings);
+      }
+      return;
+    }
+  };
+
+  // Other versions may name or lay out generated files differently, so
+  // everything they wrote is removed rather than only the stale files
+  let is_other_version = version_check && version.as_ref() != Some(&manifest.zoglin);
+  if is_other_version {
+    let version = match version {
+      Some(version) => format!("Zoglin {version}"),
+      None => "an older version of Zoglin".to_string(),
+    };
+    warnings.push(raise_note(
+      manifest_location(&manifest_path),
+      format!("The output was built by {version}, so all of its files are being rebuilt."),
+    ));
+  }
+
+  let files: HashSet<_> = manifest.files.iter().collect();
+  for file in previous_files
+    .iter()
+    .filter(|file| is_other_version || !files.contains(file))
+  {
+    let path = Path::new(file);
+    // The manifest could have been edited to point outside the output
+    if !path
+      .components()
+      .all(|component| matches!(component, Component::Normal(_)))
+    {
+      continue;
+    }
+    let _ = fs::remove_file(root_path.join(path));
+    for directory in path.ancestors().skip(1) {
+      if directory.as_os_str().is_empty() || fs::remove_dir(root_path.join(directory)).is_err() {
+        break;
+      }
+    }
   }
 }
 
+// Without a manifest, there is no telling which files an earlier build wrote,
+// so the directories of the namespaces this build writes to are cleared, along
+// with `zoglin`. `minecraft` is left alone, since other packs' files may be
+// kept there, so only the files written this time are replaced
+fn remove_generated_directories(root_path: &Path, manifest: &Manifest, warnings: &Warnings) {
+  let mut directories: Vec<_> = manifest
+    .files
+    .iter()
+    .filter_map(|file| {
+      let mut parts = file.split('/');
+      let (root, namespace) = (parts.next()?, parts.next()?);
+      parts.next()?;
+      Some(format!("{root}/{namespace}"))
+    })
+    .chain(["data/zoglin".to_string()])
+    .filter(|directory| !directory.ends_with("/minecraft"))
+    .collect();
+  directories.sort();
+  directories.dedup();
+
+  let removed: Vec<_> = directories
+    .into_iter()
+    .filter(|directory| fs::remove_dir_all(root_path.join(directory)).is_ok())
+    .collect();
+  if !removed.is_empty() {
+    warnings.push(raise_note(
+      manifest_location(&root_path.join(MANIFEST)),
+      format!(
+        "The output has no readable manifest, so `{}` {} cleared before building.",
+        removed.join("`, `"),
+        if removed.len() == 1 { "was" } else { "were" }
+      ),
+    ));
+  }
+}
+
+fn resource_dir(
+  root_path: &Path,
+  local_path: &ResourceLocation,
+  kind: &str,
+  is_asset: bool,
+) -> PathBuf {
+  let resource_dir = if is_asset { "assets" } else { "data" };
+  let mut dir_path = root_path
+    .join(resource_dir)
+    .join(local_path.namespace.as_str());
+
+  if kind != "." {
+    dir_path.push(kind);
+  }
+
+  dir_path.push(local_path.modules.join("/"));
+  dir_path
+}
+
+fn create_parent(file_path: &Path) -> Result<()> {
+  let dir_path = file_path.parent().expect("Files are always in a directory");
+  fs::create_dir_all(dir_path).map_err(raise_floating_error)
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct ResourceLocation {
   pub namespace: EcoString,
@@ -413,6 +863,53 @@ impl StorageLocation {
   }
 }
 
+// A path into an entity's or a block's data, rather than a storage
+#[derive(Clone, Debug)]
+pub struct DataLocation {
+  pub kind: ast::DataKind,
+  pub target: EcoString,
+  pub path: EcoString,
+}
+
+impl Display for DataLocation {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let kind = match self.kind {
+      ast::DataKind::Entity => "entity",
+      ast::DataKind::Block => "block",
+    };
+    if self.path.is_empty() {
+      write!(f, "{kind} {}", self.target)
+    } else {
+      write!(f, "{kind} {} {}", self.target, self.path)
+    }
+  }
+}
+
+impl DataLocation {
+  pub fn new(kind: ast::DataKind, target: EcoString) -> DataLocation {
+    DataLocation {
+      kind,
+      target,
+      path: EcoString::new(),
+    }
+  }
+
+  pub fn member(&self, member: &str) -> DataLocation {
+    let mut location = self.clone();
+    if !location.path.is_empty() {
+      location.path.push('.');
+    }
+    location.path.push_str(member);
+    location
+  }
+
+  pub fn index(&self, index: i32) -> DataLocation {
+    let mut location = self.clone();
+    location.path = eco_format!("{}[{index}]", location.path);
+    location
+  }
+}
+
 #[derive(Clone, Debug)]
 pub struct ScoreboardLocation {
   pub scoreboard: ResourceLocation,
@@ -0,0 +1,172 @@
+use ecow::eco_format;
+
+use crate::error::{raise_error, Location, Result};
+use crate::parser::ast::{self, ForLoop, Statement};
+
+use super::{
+  expression::{Expression, ExpressionKind},
+  file_tree::StorageLocation,
+  Compiler, FunctionContext,
+};
+
+// Ranges known at compile time with at most this many iterations are
+// unrolled, instead of generating a recursive function
+const MAX_UNROLLED_ITERATIONS: i32 = 16;
+
+impl Compiler {
+  pub(super) fn compile_for_loop(
+    &mut self,
+    for_loop: ForLoop,
+    context: &mut FunctionContext,
+  ) -> Result<()> {
+    let location = for_loop.end.location();
+    let start = match for_loop.start {
+      Some(start) => {
+        let start = self.compile_expression(start, context, false)?;
+        Some(self.preserve_operand(start, std::iter::once(&for_loop.end), context)?)
+      }
+      None => None,
+    };
+    let end = self.compile_expression(for_loop.end, context, false)?;
+
+    let start = match start {
+      Some(start) => start,
+      None => match end.kind {
+        ExpressionKind::Array { values, .. }
+        | ExpressionKind::ByteArray(values)
+        | ExpressionKind::IntArray(values)
+        | ExpressionKind::LongArray(values) => {
+          return self.unroll_loop(&for_loop.variable, values, for_loop.block, context);
+        }
+        // Values only known in-game are assumed to be arrays
+        ExpressionKind::Storage(_) | ExpressionKind::Macro(_) | ExpressionKind::Data(_) => {
+          return self.compile_array_loop(for_loop.variable, end, for_loop.block, context);
+        }
+        ExpressionKind::String(_) | ExpressionKind::SubString(..) | ExpressionKind::Compound(_) => {
+          return Err(raise_error(
+            location,
+            "Can only loop over ranges, numbers and arrays.",
+          ));
+        }
+        _ => Expression::new(ExpressionKind::Integer(0), location.clone()),
+      },
+    };
+
+    if let (Some(first), Some(last)) = (start.kind.numeric_value(), end.kind.numeric_value()) {
+      let is_comptime = matches!(for_loop.variable, ast::Expression::ComptimeVariable(..));
+      if is_comptime || last - first <= MAX_UNROLLED_ITERATIONS {
+        let values = (first..last)
+          .map(|value| Expression::new(ExpressionKind::Integer(value), location.clone()))
+          .collect();
+        return self.unroll_loop(&for_loop.variable, values, for_loop.block, context);
+      }
+    }
+    self.compile_range_loop(for_loop.variable, start, end, for_loop.block, context)
+  }
+
+  fn unroll_loop(
+    &mut self,
+    variable: &ast::Expression,
+    values: Vec<Expression>,
+    block: Vec<Statement>,
+    context: &mut FunctionContext,
+  ) -> Result<()> {
+    for value in values {
+      if let ast::Expression::ComptimeVariable(name, _) = variable {
+        self
+          .comptime_scopes
+          .last_mut()
+          .expect("There must be at least one scope")
+          .insert(name.clone(), value);
+      } else {
+        self.assign_variable(variable, &value, context)?;
+      }
+      self.compile_block(context, block.clone())?;
+    }
+    Ok(())
+  }
+
+  // Counts up from `start` in a scoreboard, setting the loop variable to the
+  // counter at the start of each iteration
+  fn compile_range_loop(
+    &mut self,
+    variable: ast::Expression,
+    start: Expression,
+    end: Expression,
+    block: Vec<Statement>,
+    context: &mut FunctionContext,
+  ) -> Result<()> {
+    check_runtime_variable(&variable)?;
+    let namespace = context.location.namespace.clone();
+    let counter = self.copy_to_scoreboard(&mut context.code, &start, &namespace)?;
+    let check = match end.kind.numeric_value() {
+      Some(end) => eco_format!("execute if score {counter} matches {end}.. run return 0"),
+      None => {
+        let bound = self.copy_to_scoreboard(&mut context.code, &end, &namespace)?;
+        eco_format!("execute if score {counter} >= {bound} run return 0")
+      }
+    };
+
+    let fn_location = self.next_function("for", &context.location);
+    let mut sub_context = context.child(false);
+    // The body changes variables between iterations
+    sub_context.constants.clear();
+    sub_context.code.push(check);
+    let value = Expression::new(ExpressionKind::Scoreboard(counter.clone()), end.location);
+    self.assign_variable(&variable, &value, &mut sub_context)?;
+    self.compile_block(&mut sub_context, block)?;
+    sub_context
+      .code
+      .push(eco_format!("scoreboard players add {counter} 1"));
+    sub_context.code.push(eco_format!("function {fn_location}"));
+
+    let function_call = eco_format!("function {fn_location}");
+    self.add_function_item(Location::blank(), fn_location, sub_context.code.moved())?;
+    context.code.push(function_call);
+    Ok(())
+  }
+
+  // Loops over a copy of the array, removing each item once the loop variable
+  // is set to it
+  fn compile_array_loop(
+    &mut self,
+    variable: ast::Expression,
+    array: Expression,
+    block: Vec<Statement>,
+    context: &mut FunctionContext,
+  ) -> Result<()> {
+    check_runtime_variable(&variable)?;
+    let namespace = context.location.namespace.clone();
+    let items = self.copy_to_storage(&mut context.code, &array, &namespace)?;
+    let first = StorageLocation::new(items.storage, eco_format!("{}[0]", items.name));
+
+    let fn_location = self.next_function("for", &context.location);
+    let mut sub_context = context.child(false);
+    sub_context.constants.clear();
+    sub_context.code.push(eco_format!(
+      "execute unless data storage {first} run return 0"
+    ));
+    let value = Expression::new(ExpressionKind::Storage(first.clone()), array.location);
+    self.assign_variable(&variable, &value, &mut sub_context)?;
+    sub_context
+      .code
+      .push(eco_format!("data remove storage {first}"));
+    self.compile_block(&mut sub_context, block)?;
+    sub_context.code.push(eco_format!("function {fn_location}"));
+
+    let function_call = eco_format!("function {fn_location}");
+    self.add_function_item(Location::blank(), fn_location, sub_context.code.moved())?;
+    context.code.push(function_call);
+    Ok(())
+  }
+}
+
+fn check_runtime_variable(variable: &ast::Expression) -> Result<()> {
+  if let ast::Expression::ComptimeVariable(_, location) = variable {
+    return Err(raise_error(
+      location.clone(),
+      "Compile-time loop variables can only loop over values known at compile time.",
+    ));
+  }
+  Ok(())
+}
@@ -0,0 +1,123 @@
+use std::{collections::HashMap, mem::replace};
+
+use ecow::{eco_format, EcoString};
+
+use crate::error::{raise_error, Result};
+use crate::parser::ast::{self, ParameterKind};
+
+use super::{
+  expression::Expression,
+  file_tree::{ResourceLocation, ScoreboardLocation},
+  scope::FunctionDefinition,
+  utils::ToEcoString,
+  Compiler,
+};
+
+impl Compiler {
+  // Compile-time arguments aren't passed to the function, but choose which
+  // variant of it is called
+  pub(super) fn select_variant(
+    &mut self,
+    definition: &mut FunctionDefinition,
+    arguments: &mut Vec<Expression>,
+  ) -> Result<()> {
+    let signature = definition.signature();
+    let mut comptime_arguments = Vec::new();
+    let parameters = std::mem::take(&mut definition.arguments);
+    for (parameter, argument) in parameters.into_iter().zip(std::mem::take(arguments)) {
+      if parameter.kind != ParameterKind::CompileTime {
+        definition.arguments.push(parameter);
+        arguments.push(argument);
+        continue;
+      }
+      let Some(value) = argument
+        .kind
+        .compile_time_known()
+        .then(|| argument.kind.to_comptime_string(false))
+        .flatten()
+        .filter(|_| !argument.needs_macro)
+      else {
+        return Err(raise_error(
+          argument.location,
+          eco_format!(
+            "The compile-time parameter `&{}` must be given a value known at compile time. The function is declared as `{signature}`.",
+            parameter.name
+          ),
+        ));
+      };
+      comptime_arguments.push((parameter.name, value, argument));
+    }
+
+    definition.location = self.function_variant(&definition.location, comptime_arguments)?;
+    Ok(())
+  }
+
+  // Each variant is compiled the first time it is called, with its
+  // compile-time parameters set in the scope the function is defined in
+  fn function_variant(
+    &mut self,
+    location: &ResourceLocation,
+    arguments: Vec<(EcoString, EcoString, Expression)>,
+  ) -> Result<ResourceLocation> {
+    let generic = self
+      .generic_function_registry
+      .get_mut(location)
+      .expect("Only functions with compile-time parameters have variants");
+    let key: Vec<_> = arguments
+      .iter()
+      .map(|(_, value, _)| value.clone())
+      .collect();
+    if let Some(variant) = generic.variants.get(&key) {
+      return Ok(variant.clone());
+    }
+
+    // Variants are named with a number, which a user function name can't be
+    let name = generic.variants.len().to_eco_string();
+    let modules: Vec<_> = location.modules.iter().map(EcoString::as_str).collect();
+    let module = ResourceLocation::new_module(&location.namespace, &modules);
+    let variant = module.clone().with_name(&name);
+    // Added before compiling, so recursive calls use the same variant
+    generic.variants.insert(key, variant.clone());
+
+    let scope = generic.scope;
+    let function = ast::Function {
+      name,
+      parameters: generic
+        .function
+        .parameters
+        .iter()
+        .filter(|parameter| parameter.kind != ParameterKind::CompileTime)
+        .cloned()
+        .collect(),
+      is_test: false,
+      renamed_from: Vec::new(),
+      tags: Vec::new(),
+      advancement: None,
+      ..generic.function.clone()
+    };
+    if function
+      .parameters
+      .iter()
+      .any(|parameter| parameter.kind == ParameterKind::Scoreboard)
+    {
+      self.use_scoreboard(
+        ScoreboardLocation::new(variant.clone(), "").scoreboard_string(),
+        None,
+        &variant.namespace,
+      );
+    }
+
+    let bindings: HashMap<_, _> = arguments
+      .into_iter()
+      .map(|(name, _, value)| (name, value))
+      .collect();
+    let current_scope = replace(&mut self.current_scope, scope);
+    let comptime_scopes = replace(&mut self.comptime_scopes, vec![bindings]);
+    let result = self.compile_ast_function(function, &module);
+    self.current_scope = current_scope;
+    self.comptime_scopes = comptime_scopes;
+    result?;
+
+    Ok(variant)
+  }
+}
@@ -1,122 +1,188 @@
-use std::sync::OnceLock;
+use std::mem::take;
 
-use ecow::eco_format;
+use ecow::{eco_format, EcoString};
 
-use crate::error::Location;
+use crate::error::{Location, Result};
+use crate::parser::ast::File;
 
-use super::{file_tree::ResourceLocation, Compiler};
-
-static RESET_DIRECT_RETURN: OnceLock<ResourceLocation> = OnceLock::new();
-static DYNAMIC_INDEX: OnceLock<ResourceLocation> = OnceLock::new();
-static DYNAMIC_RANGE_INDEX: OnceLock<ResourceLocation> = OnceLock::new();
-static DYNAMIC_RANGE_INDEX_NO_END: OnceLock<ResourceLocation> = OnceLock::new();
-static DYNAMIC_MEMBER: OnceLock<ResourceLocation> = OnceLock::new();
+use super::{
+  file_tree::{FileTree, ResourceLocation},
+  CompileOptions, Compiler,
+};
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
-impl Compiler {
-  pub fn reset_direct_return(&mut self, namespace: &str) -> &ResourceLocation {
-    RESET_DIRECT_RETURN.get_or_init(|| {
-      let location = ResourceLocation::new_function(
-        "zoglin", &["internal", VERSION,
-        "reset_return"],
-      );
-      self
-        .add_function_item(
-          Location::blank(),
-          location.clone(),
-          Vec::from([
-            eco_format!("scoreboard players operation $temp_return zoglin.internal.{namespace}.vars = $should_return zoglin.internal.{namespace}.vars"),
-            eco_format!("scoreboard players reset $should_return zoglin.internal.{namespace}.vars"),
-            eco_format!("return run scoreboard players get $temp_return zoglin.internal.{namespace}.vars")
-          ]),
-        )
-        .expect("Function should not already be defined");
-      location
-    })
+struct Helper {
+  name: &'static str,
+  // `{storage}` is replaced with the helper's storage, `{internal}` with the
+  // module of the helpers, and `{namespace}` with the namespace of the
+  // function that first uses it
+  template: &'static str,
+  // Macro parameters read from the storage, besides `target`
+  parameters: &'static [&'static str],
+}
+
+const HELPERS: &[Helper] = &[
+  Helper {
+    name: "reset_return",
+    template: include_str!("internals/reset_return.mcfunction"),
+    parameters: &[],
+  },
+  Helper {
+    name: "dynamic_index",
+    template: include_str!("internals/dynamic_index.mcfunction"),
+    parameters: &["__index"],
+  },
+  Helper {
+    name: "dynamic_range_index",
+    template: include_str!("internals/dynamic_range_index.mcfunction"),
+    parameters: &["__start", "__end"],
+  },
+  Helper {
+    name: "dynamic_range_index_no_end",
+    template: include_str!("internals/dynamic_range_index_no_end.mcfunction"),
+    parameters: &["__start"],
+  },
+  Helper {
+    name: "dynamic_member",
+    template: include_str!("internals/dynamic_member.mcfunction"),
+    parameters: &["__member"],
+  },
+  Helper {
+    name: "power",
+    template: include_str!("internals/power.mcfunction"),
+    parameters: &[],
+  },
+  Helper {
+    name: "power_loop",
+    template: include_str!("internals/power_loop.mcfunction"),
+    parameters: &[],
+  },
+  Helper {
+    name: "shift_left",
+    template: include_str!("internals/shift_left.mcfunction"),
+    parameters: &[],
+  },
+  Helper {
+    name: "shift_left_loop",
+    template: include_str!("internals/shift_left_loop.mcfunction"),
+    parameters: &[],
+  },
+  Helper {
+    name: "shift_right",
+    template: include_str!("internals/shift_right.mcfunction"),
+    parameters: &[],
+  },
+  Helper {
+    name: "shift_right_loop",
+    template: include_str!("internals/shift_right_loop.mcfunction"),
+    parameters: &[],
+  },
+];
+
+impl Helper {
+  fn location(&self) -> ResourceLocation {
+    ResourceLocation::new_function("zoglin", &["internal", VERSION, self.name])
   }
 
-  pub fn dynamic_index(&mut self) -> &ResourceLocation {
-    DYNAMIC_INDEX.get_or_init(|| {
-      let location = ResourceLocation::new_function(
-        "zoglin", &["internal", VERSION,
-        "dynamic_index"],
-      );
+  fn render(&self, namespace: &str) -> Vec<EcoString> {
+    debug_assert!(
       self
-        .add_function_item(
-          Location::blank(),
-          location.clone(),
-          vec![
-            eco_format!(
-              "$data modify storage zoglin:internal/{VERSION}/dynamic_index return set from storage zoglin:internal/{VERSION}/dynamic_index target[$(__index)]"
-            ),
-          ],
-        )
-        .expect("Function should not already be defined");
-      location
-    })
+        .parameters
+        .iter()
+        .all(|parameter| self.template.contains(&format!("$({parameter})"))),
+      "Helper `{}` doesn't use all of its parameters",
+      self.name
+    );
+    let storage = eco_format!("zoglin:internal/{VERSION}/{}", self.name);
+    self
+      .template
+      .lines()
+      .filter(|line| !line.trim().is_empty())
+      .map(|line| {
+        line
+          .replace("{storage}", &storage)
+          .replace("{internal}", &format!("zoglin:internal/{VERSION}"))
+          .replace("{namespace}", namespace)
+          .into()
+      })
+      .collect()
   }
+}
 
-  pub fn dynamic_range_index(&mut self) -> &ResourceLocation {
-    DYNAMIC_RANGE_INDEX.get_or_init(|| {
-      let location = ResourceLocation::new_function(
-        "zoglin", &["internal", VERSION,
-        "dynamic_range_index"],
-      );
+impl Compiler {
+  fn internal_helper(&mut self, name: &str, namespace: &str) -> ResourceLocation {
+    let helper = HELPERS
+      .iter()
+      .find(|helper| helper.name == name)
+      .expect("Helper is registered");
+    let location = helper.location();
+    if self.internal_helpers.insert(helper.name) {
       self
         .add_function_item(
           Location::blank(),
           location.clone(),
-          vec![
-            eco_format!(
-              "$data modify storage zoglin:internal/{VERSION}/dynamic_range_index return set string storage zoglin:internal/{VERSION}/dynamic_range_index target $(__start) $(__end)"
-            ),
-          ],
+          helper.render(namespace),
         )
         .expect("Function should not already be defined");
-      location
-    })
+    }
+    location
   }
 
-  pub fn dynamic_range_index_no_end(&mut self) -> &ResourceLocation {
-    DYNAMIC_RANGE_INDEX_NO_END.get_or_init(|| {
-      let location = ResourceLocation::new_function(
-        "zoglin", &["internal", VERSION,
-        "dynamic_range_index_no_end"],
-      );
-      self
-        .add_function_item(
-          Location::blank(),
-          location.clone(),
-          vec![
-            eco_format!(
-              "$data modify storage zoglin:internal/{VERSION}/dynamic_range_index_no_end return set string storage zoglin:internal/{VERSION}/dynamic_range_index_no_end target $(__start)"
-            ),
-          ],
-        )
-        .expect("Function should not already be defined");
-      location
-    })
+  // Writes every internal helper, without the rest of the project
+  pub fn compile_internals(ast: &File, output: &str, options: &CompileOptions) -> Result<()> {
+    let mut compiler = Compiler::default();
+    let namespace = ast
+      .items
+      .first()
+      .map_or("zoglin", |namespace| namespace.name.as_str());
+    for helper in HELPERS {
+      compiler.internal_helper(helper.name, namespace);
+    }
+
+    FileTree {
+      namespaces: take(&mut compiler.namespaces).into_values().collect(),
+      tests: Vec::new(),
+    }
+    .generate(output, options)
   }
 
-  pub fn dynamic_member(&mut self) -> &ResourceLocation {
-    DYNAMIC_MEMBER.get_or_init(|| {
-      let location = ResourceLocation::new_function(
-        "zoglin", &["internal", VERSION,
-        "dynamic_member"],
-      );
-      self
-        .add_function_item(
-          Location::blank(),
-          location.clone(),
-          vec![
-            eco_format!(
-              "$data modify storage zoglin:internal/{VERSION}/dynamic_member return set from storage zoglin:internal/{VERSION}/dynamic_member target.\"$(__member)\""
-            ),
-          ],
-        )
-        .expect("Function should not already be defined");
-      location
-    })
+  pub fn reset_direct_return(&mut self, namespace: &str) -> ResourceLocation {
+    self.internal_helper("reset_return", namespace)
+  }
+
+  pub fn dynamic_index(&mut self) -> ResourceLocation {
+    self.internal_helper("dynamic_index", "")
+  }
+
+  pub fn dynamic_range_index(&mut self) -> ResourceLocation {
+    self.internal_helper("dynamic_range_index", "")
+  }
+
+  pub fn dynamic_range_index_no_end(&mut self) -> ResourceLocation {
+    self.internal_helper("dynamic_range_index_no_end", "")
+  }
+
+  pub fn dynamic_member(&mut self) -> ResourceLocation {
+    self.internal_helper("dynamic_member", "")
+  }
+
+  // Raises `$base` to the power of `$exponent` in `zoglin.internal.power`,
+  // leaving the result in `$result`
+  pub fn power(&mut self) -> ResourceLocation {
+    self.internal_helper("power_loop", "");
+    self.internal_helper("power", "")
+  }
+
+  // Shifts `$value` by `$amount` bits in `zoglin.internal.shift`, one bit at a
+  // time. Negative amounts leave it unchanged
+  pub fn shift_left(&mut self) -> ResourceLocation {
+    self.internal_helper("shift_left_loop", "");
+    self.internal_helper("shift_left", "")
+  }
+
+  pub fn shift_right(&mut self) -> ResourceLocation {
+    self.internal_helper("shift_right_loop", "");
+    self.internal_helper("shift_right", "")
   }
 }
@@ -0,0 +1,2 @@
+data remove storage {storage} return
+$data modify storage {storage} return set from storage {storage} target."$(__member)"
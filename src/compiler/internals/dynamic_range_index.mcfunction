@@ -0,0 +1 @@
+$data modify storage {storage} return set string storage {storage} target $(__start) $(__end)
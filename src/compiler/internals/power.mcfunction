@@ -0,0 +1,3 @@
+scoreboard players set $result zoglin.internal.power 1
+execute if score $exponent zoglin.internal.power matches ..-1 run return run scoreboard players set $result zoglin.internal.power 0
+execute if score $exponent zoglin.internal.power matches 1.. run function {internal}/power_loop
@@ -0,0 +1,3 @@
+scoreboard players operation $result zoglin.internal.power *= $base zoglin.internal.power
+scoreboard players remove $exponent zoglin.internal.power 1
+execute if score $exponent zoglin.internal.power matches 1.. run function {internal}/power_loop
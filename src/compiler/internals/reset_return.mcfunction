@@ -0,0 +1,3 @@
+scoreboard players operation $temp_return zoglin.internal.{namespace}.vars = $should_return zoglin.internal.{namespace}.vars
+scoreboard players reset $should_return zoglin.internal.{namespace}.vars
+return run scoreboard players get $temp_return zoglin.internal.{namespace}.vars
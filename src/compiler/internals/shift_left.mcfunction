@@ -0,0 +1,2 @@
+execute if score $amount zoglin.internal.shift matches 32.. run return run scoreboard players set $value zoglin.internal.shift 0
+execute if score $amount zoglin.internal.shift matches 1.. run function {internal}/shift_left_loop
@@ -0,0 +1,3 @@
+scoreboard players operation $value zoglin.internal.shift += $value zoglin.internal.shift
+scoreboard players remove $amount zoglin.internal.shift 1
+execute if score $amount zoglin.internal.shift matches 1.. run function {internal}/shift_left_loop
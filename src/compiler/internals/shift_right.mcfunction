@@ -0,0 +1,3 @@
+scoreboard players set $two zoglin.internal.shift 2
+execute if score $amount zoglin.internal.shift matches 32.. run scoreboard players set $amount zoglin.internal.shift 31
+execute if score $amount zoglin.internal.shift matches 1.. run function {internal}/shift_right_loop
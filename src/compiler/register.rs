@@ -1,30 +1,37 @@
+use std::collections::HashMap;
+
 use ecow::EcoString;
 
+use crate::error::{raise_error, raise_warning, Location, Result, Warnings};
 use crate::parser::ast::{
-  self, File, Function, Import, Item, Module, Namespace, ParameterKind, ReturnType,
+  self, ArrayType, ElseStatement, File, Function, Import, Item, Module, Namespace, ParameterKind,
+  ReturnType, Statement,
 };
 
 use super::{
+  expression::NbtType,
   file_tree::{ResourceLocation, ScoreboardLocation},
-  scope::{ComptimeFunction, FunctionDefinition, Imported, Scope},
-  Compiler, FunctionContext,
+  scope::{ComptimeFunction, FunctionDefinition, GenericFunction, Imported, Scope},
+  Compiler, FunctionContext, Profile,
 };
 
 impl Compiler {
-  pub fn register(&mut self, ast: &mut File) {
+  pub fn register(&mut self, ast: &mut File) -> Result<()> {
     self.scopes.push(Scope::new(0));
     for namespace in ast.items.iter_mut() {
-      self.register_namespace(namespace, 0);
+      self.register_namespace(namespace, 0)?;
     }
+    Ok(())
   }
 
-  fn register_namespace(&mut self, namespace: &mut Namespace, parent_scope: usize) {
+  fn register_namespace(&mut self, namespace: &mut Namespace, parent_scope: usize) -> Result<()> {
     let index = self.push_scope(namespace.name.clone(), parent_scope);
 
     for item in namespace.items.iter_mut() {
       let mut resource = ResourceLocation::new_module(&namespace.name, &[]);
-      self.register_item(item, &mut resource, index);
+      self.register_item(item, &mut resource, index, false)?;
     }
+    Ok(())
   }
 
   fn register_item(
@@ -32,21 +39,35 @@ impl Compiler {
     item: &mut Item,
     location: &mut ResourceLocation,
     parent_scope: usize,
-  ) {
+    test_only: bool,
+  ) -> Result<()> {
     match item {
-      Item::Module(module) => self.register_module(module, location, parent_scope),
+      Item::Module(module) => self.register_module(module, location, parent_scope, test_only)?,
 
       Item::Import(import) => self.register_import(import, location, parent_scope),
 
+      Item::Function(function)
+        if self.profile == Profile::Release && (test_only || function.is_test_only) =>
+      {
+        // Still added to the scope, so that calls resolve to it and are reported
+        self.add_function(
+          parent_scope,
+          function.name.clone(),
+          location.clone().with_name(&function.name),
+        );
+        self
+          .excluded_functions
+          .insert(location.clone().with_name(&function.name));
+      }
       Item::Function(function) => self.register_function(function, location, parent_scope),
 
-      Item::Resource(_) => {}
+      Item::Resource(_) | Item::BuiltinFunction(_, _, _) => {}
 
-      Item::ComptimeAssignment(_, _) => {
-        let Item::ComptimeAssignment(name, value) = item.take() else {
+      Item::ComptimeAssignment(..) => {
+        let Item::ComptimeAssignment(name, value, name_location) = item.take() else {
           unreachable!()
         };
-        self.register_comptime_assignment(name, value, location, parent_scope)
+        self.register_comptime_assignment(name, value, name_location, location, parent_scope)?
       }
       Item::ComptimeFunction(_) => {
         let Item::ComptimeFunction(ast::ComptimeFunction {
@@ -73,6 +94,7 @@ impl Compiler {
       }
       Item::None => {}
     }
+    Ok(())
   }
 
   fn register_module(
@@ -80,19 +102,35 @@ impl Compiler {
     module: &mut Module,
     location: &mut ResourceLocation,
     parent_scope: usize,
-  ) {
+    test_only: bool,
+  ) -> Result<()> {
     let index = self.push_scope(module.name.clone(), parent_scope);
 
     location.modules.push(module.name.clone());
+    if let Some((fragment, _)) = &module.wraps {
+      self
+        .module_wrappers
+        .insert(location.clone(), fragment.clone());
+    }
 
+    let test_only = test_only || module.is_test_only;
     for item in module.items.iter_mut() {
-      self.register_item(item, location, index);
+      self.register_item(item, location, index, test_only)?;
     }
 
     location.modules.pop();
+    Ok(())
   }
 
   fn register_import(&mut self, import: &Import, _location: &ResourceLocation, scope: usize) {
+    let mut scope = scope;
+    if import.is_global {
+      // The namespace's scope is the one directly inside the root scope
+      while self.scopes[scope].parent != 0 {
+        scope = self.scopes[scope].parent;
+      }
+    }
+
     let name = import.alias.clone().unwrap_or_else(|| {
       import
         .path
@@ -129,25 +167,56 @@ impl Compiler {
       .iter()
       .any(|param| matches!(param.kind, ParameterKind::Scoreboard))
     {
-      self.use_scoreboard_dummy(
+      self.use_scoreboard(
         ScoreboardLocation::new(function_location.clone(), "").scoreboard_string(),
+        None,
+        &function_location.namespace,
       );
     }
 
+    let return_shape = if function.return_type == ReturnType::Storage {
+      function_return_shape(function, &self.warnings)
+    } else {
+      NbtType::Unknown
+    };
     let definition = FunctionDefinition {
       location: function_location.clone(),
       arguments: function.parameters.clone(),
       return_type: function.return_type,
+      return_shape,
+      is_pure: function.is_pure,
+      must_use: function.must_use,
     };
 
     self.add_function(scope, function.name.clone(), function_location.clone());
 
+    if function
+      .parameters
+      .iter()
+      .any(|param| param.kind == ParameterKind::CompileTime)
+    {
+      self.generic_function_registry.insert(
+        function_location.clone(),
+        GenericFunction {
+          function: function.clone(),
+          scope,
+          variants: HashMap::new(),
+        },
+      );
+    }
+
     self.function_registry.insert(function_location, definition);
 
     if &function.name == "tick" && location.modules.is_empty() {
-      self.tick_functions.push(function_path);
+      self
+        .function_tags
+        .entry(ResourceLocation::new_function("minecraft", &["tick"]))
+        .or_default()
+        .push(function_path);
     } else if &function.name == "load" && location.modules.is_empty() {
-      self.load_functions.push(function_path);
+      self
+        .load_functions
+        .push((location.namespace.clone(), function_path));
     }
   }
 
@@ -155,16 +224,95 @@ impl Compiler {
     &mut self,
     name: EcoString,
     value: ast::Expression,
+    name_location: Location,
     location: &ResourceLocation,
     scope: usize,
-  ) {
+  ) -> Result<()> {
+    // Values are fixed once registered, so functions see the same value
+    // regardless of the order they are compiled in
+    if self.scopes[scope].comptime_values.contains_key(&name) {
+      return Err(raise_error(
+        name_location,
+        format!("Compile-time variable `&{name}` is already assigned in this scope."),
+      ));
+    }
     let mut context = FunctionContext::new(location.clone(), ReturnType::Direct);
-    // TODO: Add some sort of validation
-    let compiled_value = self
-      .compile_expression(value, &mut context, false)
-      .expect("TODO: return error");
+    let compiled_value = self.compile_expression(value, &mut context, false)?;
     self.scopes[scope]
       .comptime_values
       .insert(name, compiled_value);
+    Ok(())
+  }
+}
+
+// The type of every value the function returns, if they are all written as
+// literals of the same type. Returning different types warns, as callers
+// can't know what to expect in the return slot
+fn function_return_shape(function: &Function, warnings: &Warnings) -> NbtType {
+  let mut shapes = Vec::new();
+  collect_return_shapes(&function.items, &mut shapes);
+  let Some(&first) = shapes.first() else {
+    return NbtType::Unknown;
+  };
+  if shapes.iter().all(|&shape| shape == first) {
+    return first;
+  }
+  if shapes
+    .iter()
+    .all(|&shape| shape != NbtType::Byte && shape.is_numeric())
+  {
+    return NbtType::Numeric;
+  }
+  if !shapes.contains(&NbtType::Unknown) {
+    warnings.push(raise_warning(
+      function.location.clone(),
+      "This function returns values of different types, so callers can't rely on the type of its return value.",
+    ));
+  }
+  NbtType::Unknown
+}
+
+fn collect_return_shapes(block: &[Statement], shapes: &mut Vec<NbtType>) {
+  for statement in block {
+    match statement {
+      Statement::Return(Some(value)) => shapes.push(literal_shape(value)),
+      Statement::If(if_statement) => collect_if_return_shapes(if_statement, shapes),
+      Statement::WhileLoop(while_loop) => collect_return_shapes(&while_loop.block, shapes),
+      Statement::ForLoop(for_loop) => collect_return_shapes(&for_loop.block, shapes),
+      Statement::Execute(execute) => collect_return_shapes(&execute.block, shapes),
+      Statement::Command(_)
+      | Statement::Comment(_)
+      | Statement::Expression(_)
+      | Statement::Discard(_)
+      | Statement::Return(None)
+      | Statement::LocalComptime(..) => {}
+    }
+  }
+}
+
+fn collect_if_return_shapes(if_statement: &ast::IfStatement, shapes: &mut Vec<NbtType>) {
+  collect_return_shapes(&if_statement.block, shapes);
+  match &if_statement.child {
+    Some(ElseStatement::IfStatement(child)) => collect_if_return_shapes(child, shapes),
+    Some(ElseStatement::Block(block)) => collect_return_shapes(block, shapes),
+    None => {}
+  }
+}
+
+fn literal_shape(value: &ast::Expression) -> NbtType {
+  match value {
+    ast::Expression::Boolean(..) | ast::Expression::Byte(..) => NbtType::Byte,
+    ast::Expression::Short(..) => NbtType::Short,
+    ast::Expression::Integer(..) => NbtType::Int,
+    ast::Expression::Long(..) => NbtType::Long,
+    ast::Expression::Float(..) => NbtType::Float,
+    ast::Expression::Double(..) => NbtType::Double,
+    ast::Expression::String(..) => NbtType::String,
+    ast::Expression::Array(ArrayType::Any, _, _) => NbtType::List,
+    ast::Expression::Array(ArrayType::Byte, _, _) => NbtType::ByteArray,
+    ast::Expression::Array(ArrayType::Int, _, _) => NbtType::IntArray,
+    ast::Expression::Array(ArrayType::Long, _, _) => NbtType::LongArray,
+    ast::Expression::Compound(..) => NbtType::Compound,
+    _ => NbtType::Unknown,
   }
 }
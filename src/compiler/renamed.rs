@@ -0,0 +1,115 @@
+use ecow::{eco_format, EcoString};
+use serde_json::json;
+
+use crate::error::{raise_error, Location, Result};
+use crate::parser::{
+  ast::{self, ParameterKind, ReturnType},
+  name::{validate, NameKind},
+};
+
+use super::{
+  builtins::function_path,
+  file_tree::{ResourceLocation, ScoreboardLocation},
+  Compiler,
+};
+
+impl Compiler {
+  // Generates a function at `old_path` which forwards its parameters to the
+  // renamed function, and warns that the old path is deprecated
+  pub(super) fn compile_renamed_shim(
+    &mut self,
+    old_path: &str,
+    location: Location,
+    new_location: &ResourceLocation,
+    function: &ast::Function,
+  ) -> Result<()> {
+    let old_path = function_path(old_path, location.clone());
+    for module in old_path.modules.iter() {
+      validate(module, &location, NameKind::Module)?;
+    }
+    validate(&old_path.name, &location, NameKind::Function)?;
+    let old_location =
+      self.resolve_zoglin_resource(old_path, &new_location.clone().module(), false)?;
+    if self.function_registry.contains_key(&old_location) {
+      return Err(raise_error(
+        location,
+        format!("Cannot rename from `{old_location}`, as a function already exists there."),
+      ));
+    }
+
+    let namespace = new_location.namespace.clone();
+    let old_storage = self.function_storage(&old_location);
+    let new_storage = self.function_storage(new_location);
+    let mut commands = Vec::new();
+
+    for parameter in function.parameters.iter() {
+      match parameter.kind {
+        ParameterKind::Storage => commands.push(eco_format!(
+          "data modify storage {new_storage} {name} set from storage {old_storage} {name}",
+          name = parameter.name
+        )),
+        ParameterKind::Macro => commands.push(eco_format!(
+          "data modify storage {new_storage} __{name} set from storage {old_storage} __{name}",
+          name = parameter.name
+        )),
+        ParameterKind::Scoreboard => {
+          let name = eco_format!("${}", parameter.name);
+          let old_scoreboard = ScoreboardLocation::new(old_storage.clone(), &name);
+          let new_scoreboard = ScoreboardLocation::new(new_storage.clone(), &name);
+          self.use_scoreboard(old_scoreboard.scoreboard_string(), None, &namespace);
+          commands.push(eco_format!(
+            "scoreboard players operation {new_scoreboard} = {old_scoreboard}"
+          ));
+        }
+        ParameterKind::CompileTime => unreachable!("Renamed functions can't be generic"),
+      }
+    }
+
+    // The flag is reset by the load function, so the notice is shown once per load
+    let flag = ScoreboardLocation {
+      scoreboard: ResourceLocation::new_function("zoglin", &["internal", &namespace, "renamed"]),
+      name: eco_format!("$renamed_{}", self.next_counter(&namespace, "renamed")),
+    };
+    self.use_scoreboard(flag.scoreboard_string(), None, &namespace);
+    let notice = json!({
+      "text": format!("`{old_location}` is deprecated, use `{new_location}` instead."),
+      "color": "yellow",
+    });
+    commands.push(eco_format!(
+      "execute unless score {flag} matches 1 run tellraw {} {notice}",
+      self.message_target
+    ));
+    commands.push(eco_format!("scoreboard players set {flag} 1"));
+
+    let has_macro_parameters = function
+      .parameters
+      .iter()
+      .any(|parameter| parameter.kind == ParameterKind::Macro);
+    let call: EcoString = if has_macro_parameters {
+      eco_format!("function {new_location} with storage {new_storage}")
+    } else {
+      eco_format!("function {new_location}")
+    };
+
+    match function.return_type {
+      ReturnType::Direct => commands.push(eco_format!("return run {call}")),
+      ReturnType::Storage => {
+        commands.push(call);
+        commands.push(eco_format!(
+          "data modify storage {old_storage} return set from storage {new_storage} return"
+        ));
+      }
+      ReturnType::Scoreboard => {
+        let old_return = ScoreboardLocation::new(old_location.clone(), "$return");
+        let new_return = ScoreboardLocation::new(new_location.clone(), "$return");
+        self.use_scoreboard(old_return.scoreboard_string(), None, &namespace);
+        commands.push(call);
+        commands.push(eco_format!(
+          "scoreboard players operation {old_return} = {new_return}"
+        ));
+      }
+    }
+
+    self.add_function_item(location, old_location, commands)
+  }
+}
@@ -2,20 +2,30 @@ use std::collections::HashMap;
 
 use ecow::EcoString;
 
-use crate::parser::ast::{Parameter, ReturnType, Statement};
+use crate::parser::ast::{Function, Parameter, ReturnType, Statement};
 
-use super::{expression::Expression, file_tree::ResourceLocation};
+use super::{
+  expression::{Expression, NbtType},
+  file_tree::ResourceLocation,
+};
 
 #[derive(Clone)]
 pub struct FunctionDefinition {
   pub location: ResourceLocation,
   pub arguments: Vec<Parameter>,
   pub return_type: ReturnType,
+  // The type of the values the function returns, where it is known from the
+  // source, which the return slot is reset to an empty value of
+  pub return_shape: NbtType,
+  pub is_pure: bool,
+  pub must_use: bool,
 }
 
 pub struct CalledFunction {
   pub location: ResourceLocation,
   pub return_type: ReturnType,
+  pub return_shape: NbtType,
+  pub pure_key: Option<EcoString>,
 }
 
 #[derive(Clone)]
@@ -25,6 +35,16 @@ pub struct ComptimeFunction {
   pub body: Vec<Statement>,
 }
 
+// A function with compile-time parameters, which is compiled separately for
+// each set of compile-time arguments it is called with
+pub struct GenericFunction {
+  pub function: Function,
+  // The scope the function is defined in, to compile its body from
+  pub scope: usize,
+  // The location of each variant, keyed by its compile-time arguments
+  pub variants: HashMap<Vec<EcoString>, ResourceLocation>,
+}
+
 pub struct Scope {
   pub parent: usize,
   pub children: HashMap<EcoString, Vec<usize>>,
@@ -32,8 +52,14 @@ pub struct Scope {
   pub comptime_functions: HashMap<EcoString, ResourceLocation>,
   pub imported_items: HashMap<EcoString, Imported>,
   pub comptime_values: HashMap<EcoString, Expression>,
+  // Resources resolved from this scope, keyed by their first segment. Scopes
+  // don't change after registering, so entries never need invalidating.
+  pub resolved_resources: HashMap<ResolvedKey, Option<ResourceLocation>>,
 }
 
+// (first segment, comptime, could be a function)
+pub type ResolvedKey = (EcoString, bool, bool);
+
 #[derive(Debug)]
 pub enum Imported {
   Comptime(ResourceLocation),
@@ -49,6 +75,7 @@ impl Scope {
       comptime_functions: HashMap::new(),
       imported_items: HashMap::new(),
       comptime_values: HashMap::new(),
+      resolved_resources: HashMap::new(),
     }
   }
 
@@ -0,0 +1,88 @@
+use ecow::{eco_format, EcoString};
+
+use crate::error::{raise_error, Location, Result};
+use crate::parser::ast::{Parameter, ParameterKind, ReturnType};
+
+use super::{
+  call_stubs::literal_default,
+  file_tree::ResourceLocation,
+  scope::{ComptimeFunction, FunctionDefinition},
+};
+
+impl FunctionDefinition {
+  // The function as it would be declared, such as
+  // `fn example:spawn(count, $speed, %type = "zombie")`, for error messages
+  pub fn signature(&self) -> EcoString {
+    let prefix = match self.return_type {
+      ReturnType::Storage => "",
+      ReturnType::Scoreboard => "$",
+      ReturnType::Direct => "%",
+    };
+    signature(prefix, &self.location, &self.arguments)
+  }
+}
+
+impl ComptimeFunction {
+  pub fn signature(&self) -> EcoString {
+    signature("&", &self.location, &self.parameters)
+  }
+}
+
+fn signature(prefix: &str, location: &ResourceLocation, parameters: &[Parameter]) -> EcoString {
+  let parameters: Vec<_> = parameters
+    .iter()
+    .map(|parameter| {
+      let kind = match parameter.kind {
+        ParameterKind::Storage => "",
+        ParameterKind::Scoreboard => "$",
+        ParameterKind::Macro => "%",
+        ParameterKind::CompileTime => "&",
+      };
+      // Only literal defaults can be shown, as the AST doesn't keep the source
+      match &parameter.default {
+        Some(default) => {
+          let default = literal_default(default)
+            .and_then(|default| default.to_comptime_string(false))
+            .unwrap_or_else(|| "...".into());
+          eco_format!("{kind}{} = {default}", parameter.name)
+        }
+        None => eco_format!("{kind}{}", parameter.name),
+      }
+    })
+    .collect();
+  eco_format!("fn {prefix}{location}({})", parameters.join(", "))
+}
+
+// Calls must give an argument for each parameter without a default, and no
+// more arguments than there are parameters
+pub(super) fn check_argument_count(
+  parameters: &[Parameter],
+  given: usize,
+  signature: &str,
+  location: &Location,
+) -> Result<()> {
+  let required = parameters
+    .iter()
+    .filter(|parameter| parameter.default.is_none())
+    .count();
+  let expected = if given >= required && given <= parameters.len() {
+    return Ok(());
+  } else if required == parameters.len() {
+    count(required)
+  } else if given < required {
+    eco_format!("at least {}", count(required))
+  } else {
+    eco_format!("at most {}", count(parameters.len()))
+  };
+  Err(raise_error(
+    location.clone(),
+    format!("Expected {expected}, but got {given}. The function is declared as `{signature}`."),
+  ))
+}
+
+fn count(arguments: usize) -> EcoString {
+  match arguments {
+    1 => "1 argument".into(),
+    arguments => eco_format!("{arguments} arguments"),
+  }
+}
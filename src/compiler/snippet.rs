@@ -0,0 +1,60 @@
+use std::collections::{BTreeMap, HashSet};
+
+use ecow::{eco_format, EcoString};
+
+use crate::error::MultiResult;
+use crate::lexer::Lexer;
+use crate::parser::Parser;
+
+use super::{file_tree::Item, CompileOptions, Compiler};
+
+const SNIPPET_FUNCTION: &str = "repl:snippet";
+
+pub struct Snippet {
+  pub commands: Vec<EcoString>,
+  // Every other function in the output, such as the bodies of `if`
+  // statements, by resource location
+  pub helpers: BTreeMap<EcoString, Vec<EcoString>>,
+}
+
+impl Compiler {
+  // Compiles a list of statements as the body of an implicit function, so
+  // tools can show what a piece of code compiles to
+  pub fn compile_snippet(source: &str, options: &CompileOptions) -> MultiResult<Snippet> {
+    let tokens =
+      Lexer::from_source("<snippet>", source.to_string(), &options.warnings).tokenise()?;
+    let ast = Parser::new(tokens, options).parse_snippet()?;
+    let tree = Compiler::compile_to_tree(ast, options, &mut HashSet::new())?;
+
+    let mut helpers = BTreeMap::new();
+    for namespace in tree.namespaces {
+      collect_functions(
+        namespace.items,
+        &eco_format!("{}:", namespace.name),
+        &mut helpers,
+      );
+    }
+    let commands = helpers.remove(SNIPPET_FUNCTION).unwrap_or_default();
+    Ok(Snippet { commands, helpers })
+  }
+}
+
+fn collect_functions(
+  items: Vec<Item>,
+  prefix: &str,
+  functions: &mut BTreeMap<EcoString, Vec<EcoString>>,
+) {
+  for item in items {
+    match item {
+      Item::Module(module) => collect_functions(
+        module.items,
+        &eco_format!("{prefix}{}/", module.name),
+        functions,
+      ),
+      Item::Function(function) => {
+        functions.insert(eco_format!("{prefix}{}", function.name), function.commands);
+      }
+      Item::TextResource(_) | Item::FileResource(_) => {}
+    }
+  }
+}
@@ -0,0 +1,120 @@
+use super::{
+  file_tree::{ScoreboardLocation, StorageLocation},
+  Compiler,
+};
+
+// The temporary scoreboards and storages made while compiling a function.
+// Those made by a statement can't be referenced once it is compiled, so later
+// statements reuse them instead of making new ones
+pub struct TempPool {
+  namespace: String,
+  scoreboards: TempSlots<ScoreboardLocation>,
+  storages: TempSlots<StorageLocation>,
+}
+
+struct TempSlots<T> {
+  slots: Vec<T>,
+  used: usize,
+  // Slots below this are kept until the function is compiled, as something
+  // outlives the statement that made them
+  pinned: usize,
+}
+
+impl<T: Clone> TempSlots<T> {
+  fn new() -> TempSlots<T> {
+    TempSlots {
+      slots: Vec::new(),
+      used: 0,
+      pinned: 0,
+    }
+  }
+
+  fn reuse(&mut self) -> Option<T> {
+    let slot = self.slots.get(self.used)?.clone();
+    self.used += 1;
+    Some(slot)
+  }
+
+  fn add(&mut self, slot: T) {
+    self.slots.push(slot);
+    self.used = self.slots.len();
+  }
+
+  fn release(&mut self, mark: usize) {
+    self.used = mark.max(self.pinned);
+  }
+
+  fn pin(&mut self) {
+    self.pinned = self.used;
+  }
+}
+
+// The number of temporaries in use before a statement
+pub type TempMark = Option<(usize, usize)>;
+
+impl Compiler {
+  pub(super) fn push_temp_pool(&mut self, namespace: &str) {
+    self.temp_pools.push(TempPool {
+      namespace: namespace.into(),
+      scoreboards: TempSlots::new(),
+      storages: TempSlots::new(),
+    });
+  }
+
+  pub(super) fn pop_temp_pool(&mut self) {
+    self.temp_pools.pop();
+  }
+
+  pub(super) fn temp_mark(&self) -> TempMark {
+    self
+      .temp_pools
+      .last()
+      .map(|pool| (pool.scoreboards.used, pool.storages.used))
+  }
+
+  pub(super) fn release_temps(&mut self, mark: TempMark) {
+    if let (Some(pool), Some((scoreboards, storages))) = (self.temp_pools.last_mut(), mark) {
+      pool.scoreboards.release(scoreboards);
+      pool.storages.release(storages);
+    }
+  }
+
+  // Keeps the temporaries in use for the rest of the function, for values
+  // which are still referenced after the statement, such as cached calls
+  pub(super) fn pin_temps(&mut self) {
+    if let Some(pool) = self.temp_pools.last_mut() {
+      pool.scoreboards.pin();
+      pool.storages.pin();
+    }
+  }
+
+  pub(super) fn reuse_temp_scoreboard(&mut self, namespace: &str) -> Option<ScoreboardLocation> {
+    let pool = self.current_temp_pool(namespace)?;
+    pool.scoreboards.reuse()
+  }
+
+  pub(super) fn add_temp_scoreboard(&mut self, scoreboard: &ScoreboardLocation, namespace: &str) {
+    if let Some(pool) = self.current_temp_pool(namespace) {
+      pool.scoreboards.add(scoreboard.clone());
+    }
+  }
+
+  pub(super) fn reuse_temp_storage(&mut self, namespace: &str) -> Option<StorageLocation> {
+    let pool = self.current_temp_pool(namespace)?;
+    pool.storages.reuse()
+  }
+
+  pub(super) fn add_temp_storage(&mut self, storage: &StorageLocation, namespace: &str) {
+    if let Some(pool) = self.current_temp_pool(namespace) {
+      pool.storages.add(storage.clone());
+    }
+  }
+
+  // Only temporaries in the function's own namespace are reused
+  fn current_temp_pool(&mut self, namespace: &str) -> Option<&mut TempPool> {
+    self
+      .temp_pools
+      .last_mut()
+      .filter(|pool| pool.namespace == namespace)
+  }
+}
@@ -11,3 +11,20 @@ impl<T: Display + ?Sized> ToEcoString for T {
     eco_format!("{}", self)
   }
 }
+
+// Levenshtein distance, used to suggest similar names
+pub fn edit_distance(a: &str, b: &str) -> usize {
+  let b: Vec<char> = b.chars().collect();
+  let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+  for (i, a_char) in a.chars().enumerate() {
+    let mut current = vec![i + 1];
+    for (j, b_char) in b.iter().enumerate() {
+      let substitution = previous[j] + usize::from(a_char != *b_char);
+      current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+    }
+    previous = current;
+  }
+
+  previous[b.len()]
+}
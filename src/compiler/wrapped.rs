@@ -0,0 +1,76 @@
+use ecow::{eco_format, EcoString};
+
+use crate::error::{Location, Result};
+use crate::parser::ast::{Parameter, ParameterKind, ReturnType};
+
+use super::{file_tree::ResourceLocation, Compiler};
+
+impl Compiler {
+  // The innermost module with `wraps` that a function is in, and its fragment
+  pub(super) fn module_wrapper(
+    &self,
+    function: &ResourceLocation,
+  ) -> Option<(ResourceLocation, EcoString)> {
+    let module = function.clone().module();
+    (0..=module.modules.len()).rev().find_map(|length| {
+      let modules: Vec<_> = module.modules[..length]
+        .iter()
+        .map(EcoString::as_str)
+        .collect();
+      let location = ResourceLocation::new_module(&module.namespace, &modules);
+      let fragment = self.module_wrappers.get(&location)?;
+      Some((location, fragment.clone()))
+    })
+  }
+
+  // Where a function in a wrapped module keeps its code. Its own path runs
+  // this in the module's context
+  pub(super) fn wrapped_implementation(function: &ResourceLocation) -> ResourceLocation {
+    function.clone().with_name("impl")
+  }
+
+  // The function a call runs. Calls from inside a wrapped module already run
+  // in its context, so skip the wrapper
+  pub(super) fn call_target(
+    &self,
+    function: &ResourceLocation,
+    caller: &ResourceLocation,
+  ) -> ResourceLocation {
+    match self.module_wrapper(function) {
+      Some((module, _))
+        if caller.namespace == module.namespace && caller.modules.starts_with(&module.modules) =>
+      {
+        Compiler::wrapped_implementation(function)
+      }
+      _ => function.clone(),
+    }
+  }
+
+  // Adds the function at `location`, which runs its implementation with the
+  // module's `execute` subcommands
+  pub(super) fn compile_wrapper(
+    &mut self,
+    location: &ResourceLocation,
+    fragment: &str,
+    parameters: &[Parameter],
+    return_type: ReturnType,
+  ) -> Result<()> {
+    let implementation = Compiler::wrapped_implementation(location);
+    let has_macro_parameters = parameters
+      .iter()
+      .any(|parameter| parameter.kind == ParameterKind::Macro);
+    let call = if has_macro_parameters {
+      eco_format!(
+        "function {implementation} with storage {}",
+        self.function_storage(location)
+      )
+    } else {
+      eco_format!("function {implementation}")
+    };
+    let command = match return_type {
+      ReturnType::Direct => eco_format!("return run execute {fragment} run {call}"),
+      ReturnType::Storage | ReturnType::Scoreboard => eco_format!("execute {fragment} run {call}"),
+    };
+    self.add_function_item(Location::blank(), location.clone(), vec![command])
+  }
+}
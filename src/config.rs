@@ -5,6 +5,8 @@ use std::{cmp::Ordering, collections::HashMap, fmt::Display};
 
 use serde::{Deserialize, Serialize};
 
+use crate::parser::ast::ReturnType;
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Config {
   /// The zoglin version constraint
@@ -17,6 +19,31 @@ pub struct Config {
   pub meta: McMeta,
   /// The map of dependency projects to their version constraints
   pub dependencies: HashMap<String, VersionConstraint>,
+  /// How functions declared without a return sigil return their value
+  #[serde(default)]
+  pub default_return: DefaultReturn,
+  /// Whether every function must choose its return kind with a sigil
+  #[serde(default)]
+  pub require_explicit_return_type: bool,
+}
+
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DefaultReturn {
+  #[default]
+  Storage,
+  Direct,
+  Scoreboard,
+}
+
+impl DefaultReturn {
+  pub fn return_type(self) -> ReturnType {
+    match self {
+      DefaultReturn::Storage => ReturnType::Storage,
+      DefaultReturn::Direct => ReturnType::Direct,
+      DefaultReturn::Scoreboard => ReturnType::Scoreboard,
+    }
+  }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -61,12 +88,21 @@ pub struct McMeta {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct McPack {
   pub pack_format: u32,
-  pub description: String,
+  pub description: Description,
   #[serde(skip_serializing_if = "Option::is_none")]
   #[serde(default)]
   pub supported_formats: Option<SupportedFormats>,
 }
 
+/// Either a plain string, or a JSON text component, which is written to
+/// pack.mcmeta as it is
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum Description {
+  Plain(String),
+  Component(serde_json::Value),
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum SupportedFormats {
@@ -93,6 +129,13 @@ pub struct Version {
 }
 
 impl Version {
+  // The version of Zoglin being run
+  pub fn current() -> Version {
+    env!("CARGO_PKG_VERSION")
+      .parse()
+      .expect("The package version is valid")
+  }
+
   fn next(&self) -> Version {
     match self {
       Version {
@@ -1,4 +1,4 @@
-use std::iter::Peekable;
+use std::{iter::Peekable, str::FromStr};
 
 use serde::{
   de::{self, Visitor},
@@ -7,7 +7,24 @@ use serde::{
 
 use crate::config::ConstraintKind;
 
-use super::{Version, VersionConstraint};
+use super::{Description, Version, VersionConstraint};
+
+impl<'de> Deserialize<'de> for Description {
+  fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+  where
+    D: serde::Deserializer<'de>,
+  {
+    match serde_json::Value::deserialize(deserializer)? {
+      serde_json::Value::String(text) => Ok(Description::Plain(text)),
+      component @ (serde_json::Value::Array(_) | serde_json::Value::Object(_)) => {
+        Ok(Description::Component(component))
+      }
+      other => Err(de::Error::custom(format!(
+        "Expected a string or a text component for the description, got `{other}`"
+      ))),
+    }
+  }
+}
 
 impl Serialize for VersionConstraint {
   fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
@@ -272,6 +289,14 @@ fn parse_version<E: de::Error>(
   Ok(version)
 }
 
+impl FromStr for Version {
+  type Err = de::value::Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    parse_version(&mut s.chars().peekable())
+  }
+}
+
 impl<'de> Deserialize<'de> for Version {
   fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
   where
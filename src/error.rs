@@ -1,6 +1,11 @@
+use std::{
+  fs,
+  sync::{Arc, Mutex},
+};
+
 use ecow::EcoString;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Location {
   pub line: usize,
   pub column: usize,
@@ -9,7 +14,7 @@ pub struct Location {
 }
 
 impl Location {
-  pub fn blank() -> Location {
+  pub const fn blank() -> Location {
     Location {
       line: 0,
       column: 0,
@@ -22,20 +27,96 @@ impl Location {
 const RESET: &str = "\x1b[0m";
 const RED: &str = "\x1b[31m";
 const YELLOW: &str = "\x1b[33m";
+const CYAN: &str = "\x1b[36m";
+
+// Tabs are expanded so that the caret lines up however the terminal shows them
+const TAB_WIDTH: usize = 4;
+
+// Prints the line at `location`, with a caret under its column. Nothing is
+// printed if the file can't be read, such as when it has since been deleted
+fn print_source(location: &Location, colour: &str) {
+  let Ok(source) = fs::read_to_string(location.file.as_str()) else {
+    return;
+  };
+  let Some(line) = location
+    .line
+    .checked_sub(1)
+    .and_then(|index| source.lines().nth(index))
+  else {
+    return;
+  };
+
+  let mut text = String::new();
+  let mut padding = String::new();
+  // Columns count characters, so the line is never indexed by bytes
+  for (index, char) in line.chars().enumerate() {
+    let before_column = index + 1 < location.column;
+    if char == '\t' {
+      text.push_str(&" ".repeat(TAB_WIDTH));
+      if before_column {
+        padding.push_str(&" ".repeat(TAB_WIDTH));
+      }
+    } else {
+      text.push(char);
+      if before_column {
+        padding.push(' ');
+      }
+    }
+  }
+
+  let number = location.line.to_string();
+  let gutter = " ".repeat(number.len());
+  eprintln!("{gutter} |");
+  eprintln!("{number} | {text}");
+  eprintln!("{gutter} | {padding}{colour}^{RESET}");
+}
+
+// What an error is about, so that errors caused by the same problem can be
+// grouped even when their messages differ
+#[derive(Debug, Clone, PartialEq)]
+pub enum ErrorKind {
+  Other,
+  // A name that couldn't be resolved, such as `&config` or `@nope`
+  Missing(EcoString),
+}
 
 #[derive(Debug)]
 pub struct Error {
   location: Option<Location>,
   message: String,
+  kind: ErrorKind,
 }
 
 impl Error {
+  pub fn without_location(self) -> Error {
+    Error {
+      location: None,
+      ..self
+    }
+  }
+
+  pub fn with_kind(self, kind: ErrorKind) -> Error {
+    Error { kind, ..self }
+  }
+
+  // Errors of a known kind are the same problem wherever they occur, while
+  // other errors are only the same if they are at the same place
+  fn same_problem(&self, other: &Error) -> bool {
+    match (&self.kind, &other.kind) {
+      (ErrorKind::Other, ErrorKind::Other) => {
+        self.message == other.message && self.location == other.location
+      }
+      (kind, other_kind) => kind == other_kind,
+    }
+  }
+
   pub fn print(&self) {
     if let Some(ref location) = self.location {
       eprintln!(
         "{}:{}:{}: {}{}{}",
         location.file, location.line, location.column, RED, self.message, RESET
       );
+      print_source(location, RED);
     } else {
       eprintln!("Error: {}{}{}", RED, self.message, RESET);
     }
@@ -44,10 +125,67 @@ impl Error {
 
 pub type Result<T> = std::result::Result<T, Error>;
 
+// For stages that carry on after an error, so every error can be reported at once
+pub type MultiResult<T> = std::result::Result<T, Vec<Error>>;
+
+impl From<Error> for Vec<Error> {
+  fn from(error: Error) -> Self {
+    vec![error]
+  }
+}
+
+// How many other locations are listed for an error that occurs repeatedly
+const MAX_REPEATED_LOCATIONS: usize = 3;
+
+// Prints each distinct error once, along with where else it occurred. Errors
+// after the first `max_errors` distinct ones are only counted
+pub fn print_errors(errors: &[Error], max_errors: usize) {
+  let mut groups: Vec<Vec<&Error>> = Vec::new();
+  for error in errors {
+    match groups.iter_mut().find(|group| group[0].same_problem(error)) {
+      // The same problem reported twice at one place is only counted once
+      Some(group) if group.iter().any(|other| other.location == error.location) => {}
+      Some(group) => group.push(error),
+      None => groups.push(vec![error]),
+    }
+  }
+
+  for group in groups.iter().take(max_errors) {
+    group[0].print();
+    let repeats = &group[1..];
+    if repeats.is_empty() {
+      continue;
+    }
+    let locations: Vec<_> = repeats
+      .iter()
+      .filter_map(|error| error.location.as_ref())
+      .take(MAX_REPEATED_LOCATIONS)
+      .map(|location| format!("{}:{}:{}", location.file, location.line, location.column))
+      .collect();
+    let count = match repeats.len() {
+      1 => "1 more time".to_string(),
+      count => format!("{count} more times"),
+    };
+    if locations.is_empty() {
+      eprintln!("  ...and {count}");
+    } else {
+      eprintln!("  ...and {count}, including at {}", locations.join(", "));
+    }
+  }
+
+  let hidden: usize = groups.iter().skip(max_errors).map(Vec::len).sum();
+  if hidden == 1 {
+    eprintln!("...and 1 more error");
+  } else if hidden > 1 {
+    eprintln!("...and {hidden} more errors");
+  }
+}
+
 pub fn raise_error(location: Location, message: impl ToString) -> Error {
   Error {
     location: Some(location),
     message: message.to_string(),
+    kind: ErrorKind::Other,
   }
 }
 
@@ -55,17 +193,64 @@ pub fn raise_floating_error(message: impl ToString) -> Error {
   Error {
     location: None,
     message: message.to_string(),
+    kind: ErrorKind::Other,
   }
 }
 
-pub fn raise_warning(location: Location, message: impl ToString) {
-  eprintln!(
-    "{}:{}:{}: {}{}{}",
-    location.file,
-    location.line,
-    location.column,
-    YELLOW,
-    message.to_string(),
-    RESET
-  );
+#[derive(Debug)]
+pub struct Warning {
+  location: Location,
+  message: String,
+  colour: &'static str,
+}
+
+impl Warning {
+  pub fn print(&self) {
+    eprintln!(
+      "{}:{}:{}: {}{}{}",
+      self.location.file,
+      self.location.line,
+      self.location.column,
+      self.colour,
+      self.message,
+      RESET
+    );
+    print_source(&self.location, self.colour);
+  }
+}
+
+// Collects the warnings and notes of a compile, so they can be printed once
+// it is done, or read by a program embedding the compiler
+#[derive(Debug, Clone, Default)]
+pub struct Warnings(Arc<Mutex<Vec<Warning>>>);
+
+impl Warnings {
+  pub fn push(&self, warning: Warning) {
+    self
+      .0
+      .lock()
+      .expect("Warnings are never poisoned")
+      .push(warning);
+  }
+
+  // Removes and returns the warnings raised so far
+  pub fn take(&self) -> Vec<Warning> {
+    std::mem::take(&mut self.0.lock().expect("Warnings are never poisoned"))
+  }
+}
+
+pub fn raise_warning(location: Location, message: impl ToString) -> Warning {
+  Warning {
+    location,
+    message: message.to_string(),
+    colour: YELLOW,
+  }
+}
+
+pub fn raise_note(location: Location, message: impl ToString) -> Warning {
+  Warning {
+    location,
+    message: message.to_string(),
+    colour: CYAN,
+  }
 }
@@ -0,0 +1,145 @@
+use std::{fs, path::Path};
+
+use crate::{
+  error::{raise_floating_error, Location, Result},
+  parser::name::{check_reserved, validate, NameKind},
+  CONFIG_FILE,
+};
+
+pub const TEMPLATES: [&str; 3] = ["basic", "datapack", "library"];
+
+const BASIC_PROJECT: &[(&str, &str)] = &[(
+  "main.zog",
+  r#"namespace $name {
+  fn tick() {
+
+  }
+
+  fn load() {
+    tellraw @a "Loaded $name"
+  }
+}
+"#,
+)];
+
+const DATAPACK_PROJECT: &[(&str, &str)] = &[
+  (
+    "src/main.zog",
+    r#"namespace $name {
+  include "events"
+}
+"#,
+  ),
+  (
+    "src/events.zog",
+    r#"fn tick() {
+
+}
+
+fn load() {
+  tellraw @a "Loaded $name"
+}
+"#,
+  ),
+];
+
+const LIBRARY_PROJECT: &[(&str, &str)] = &[
+  (
+    "src/main.zog",
+    r#"namespace $name {
+  include "api"
+
+  # Shows how the API is used, and can be removed
+  module example {
+    fn greet_everyone() {
+      $name:api/greet("@a")
+    }
+  }
+}
+"#,
+  ),
+  (
+    "src/api.zog",
+    r#"# The functions other packs can call
+module api {
+  # Greets `%player`, which can be any selector
+  fn greet(%player) {
+    tellraw %player "Hello from $name!"
+  }
+}
+"#,
+  ),
+];
+
+const DEFAULT_CONFIG: &str = r#"{
+  "zoglin": ">= $version",
+  "entry": "$entry",
+  "package": {
+    "name": "$name",
+    "version": "0.1.0",
+    "summary": "",
+    "author": "",
+    "supports": "~> 1.21.0"
+  },
+  "meta": {
+    "pack": {
+      "pack_format": 48,
+      "description": ""
+    }
+  },
+  "dependencies": {}
+}
+"#;
+
+// Creates a project in the directory `name`, or the current directory if it
+// is empty. The directory's name is used as the project's namespace.
+pub fn init(name: &str, template: &str) -> Result<()> {
+  let directory = if name.is_empty() {
+    std::env::current_dir().map_err(raise_floating_error)?
+  } else {
+    Path::new(name).to_path_buf()
+  };
+  let namespace = directory
+    .file_name()
+    .and_then(|name| name.to_str())
+    .ok_or_else(|| raise_floating_error("The project directory must have a valid name."))?;
+  // Without a location, the error is printed on its own
+  validate(namespace, &Location::blank(), NameKind::Namespace)
+    .and_then(|_| check_reserved(namespace, &Location::blank(), NameKind::Namespace))
+    .map_err(|e| e.without_location())?;
+
+  if name.is_empty() {
+    let is_empty = directory
+      .read_dir()
+      .map_err(raise_floating_error)?
+      .next()
+      .is_none();
+    if !is_empty {
+      println!("No init'ing projects in non-empty directories, naughty naughty!");
+      return Ok(());
+    }
+  } else {
+    fs::create_dir(&directory).map_err(raise_floating_error)?;
+  }
+
+  let files = match template {
+    "datapack" => DATAPACK_PROJECT,
+    "library" => LIBRARY_PROJECT,
+    _ => BASIC_PROJECT,
+  };
+  for (path, contents) in files {
+    let path = directory.join(path);
+    if let Some(parent) = path.parent() {
+      fs::create_dir_all(parent).map_err(raise_floating_error)?;
+    }
+    fs::write(path, contents.replace("$name", namespace)).map_err(raise_floating_error)?;
+  }
+
+  let (entry, _) = files[0];
+  let config = DEFAULT_CONFIG
+    .replace("$name", namespace)
+    .replace("$entry", entry)
+    .replace("$version", env!("CARGO_PKG_VERSION"));
+  fs::write(directory.join(CONFIG_FILE), config).map_err(raise_floating_error)?;
+  fs::write(directory.join(".gitignore"), "/build/\n").map_err(raise_floating_error)
+}
@@ -1,11 +1,19 @@
 mod registries;
 pub mod token;
-use crate::error::{raise_error, raise_floating_error, raise_warning, Location, Result};
+use crate::error::{
+  raise_error, raise_floating_error, raise_note, raise_warning, Location, Result, Warnings,
+};
 
 use ecow::EcoString;
 use glob::glob;
 use registries::{COMMANDS, KEYWORD_REGISTRY, OPERATOR_REGISTRY};
-use std::{collections::HashSet, fs, path::Path, str};
+use std::{
+  collections::HashSet,
+  fs, io,
+  mem::take,
+  path::{Path, PathBuf},
+  str,
+};
 use token::{Token, TokenKind};
 
 pub struct Lexer {
@@ -13,34 +21,139 @@ pub struct Lexer {
   root: EcoString,
   src: String,
   pub dependent_files: HashSet<EcoString>,
+  // A byte offset into `src`, so the text of a token can be sliced from it
   position: usize,
   is_newline: bool,
+  // The kind of the last token, to tell names apart from data targets
+  previous_kind: Option<TokenKind>,
   next_brace_json: bool,
   line: usize,
   column: usize,
+  // Files are tracked by their `file_key`, while `file` keeps the path as it
+  // was written for error locations
   include_chain: Vec<EcoString>,
+  // Every file included so far, across all branches of the include tree
+  included_files: HashSet<EcoString>,
+  warnings: Warnings,
+}
+
+// The form of a path used to tell whether two paths are the same file, so a
+// file isn't tracked twice under different spellings
+pub fn file_key(path: &Path) -> EcoString {
+  key_with(path, Path::canonicalize, cfg!(windows))
+}
+
+// `file_key`, with the filesystem and platform given so tests can use any
+fn key_with(
+  path: &Path,
+  canonicalize: impl FnOnce(&Path) -> io::Result<PathBuf>,
+  windows: bool,
+) -> EcoString {
+  let path = canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+  let path = path.to_string_lossy();
+  if windows {
+    normalize_windows_path(&path)
+  } else {
+    path.into()
+  }
+}
+
+#[derive(Debug, PartialEq)]
+enum Inclusion {
+  // The file includes itself
+  Itself,
+  // The file is already being included further up the chain
+  Circular,
+  Repeated,
+  New,
+}
+
+// Whether the file with `key` should be included, recording it if so
+fn inclusion(
+  key: &EcoString,
+  include_chain: &[EcoString],
+  included_files: &mut HashSet<EcoString>,
+) -> Inclusion {
+  if let Some(index) = include_chain.iter().position(|file| file == key) {
+    if index == include_chain.len() - 1 {
+      Inclusion::Itself
+    } else {
+      Inclusion::Circular
+    }
+  } else if included_files.insert(key.clone()) {
+    Inclusion::New
+  } else {
+    Inclusion::Repeated
+  }
+}
+
+fn is_glob(pattern: &str) -> bool {
+  pattern.contains(['*', '?', '['])
+}
+
+// The deepest directory of an include pattern that doesn't contain any glob
+// characters
+fn pattern_directory(pattern: &Path) -> PathBuf {
+  let mut directory = PathBuf::from(".");
+  for component in pattern.parent().into_iter().flat_map(Path::components) {
+    if is_glob(&component.as_os_str().to_string_lossy()) {
+      break;
+    }
+    directory.push(component);
+  }
+  directory
+}
+
+fn normalize_windows_path(path: &str) -> EcoString {
+  // Canonical paths on Windows have a verbatim prefix, and glob can return
+  // either separator
+  let mut path = path
+    .strip_prefix(r"\\?\")
+    .unwrap_or(path)
+    .replace('\\', "/");
+  // Drive letters aren't case sensitive
+  if path.get(1..2) == Some(":") {
+    path[..1].make_ascii_lowercase();
+  }
+  path.into()
 }
 
 impl Lexer {
-  pub fn new(file: &str) -> Result<Lexer> {
+  pub fn new(file: &str, warnings: &Warnings) -> Result<Lexer> {
     let contents = fs::read_to_string(file).map_err(raise_floating_error)?;
+    Ok(Lexer::from_source(file, contents, warnings))
+  }
+
+  // Lexes source that isn't read from a file, with `file` as the name used in
+  // error locations
+  pub fn from_source(file: &str, src: String, warnings: &Warnings) -> Lexer {
+    let key = file_key(Path::new(file));
     let file: EcoString = file.into();
-    Ok(Lexer {
+    Lexer {
       file: file.clone(),
-      root: file.clone(),
-      src: contents,
+      root: file,
+      src,
       position: 0,
       is_newline: true,
+      previous_kind: None,
       next_brace_json: false,
       line: 1,
       column: 1,
       dependent_files: HashSet::new(),
-      include_chain: vec![file],
-    })
+      include_chain: vec![key.clone()],
+      included_files: HashSet::from([key]),
+      warnings: warnings.clone(),
+    }
   }
 
-  fn child(file: &str, root_path: &str, mut include_chain: Vec<EcoString>) -> Result<Lexer> {
-    include_chain.push(file.into());
+  fn child(
+    file: &str,
+    root_path: &str,
+    mut include_chain: Vec<EcoString>,
+    included_files: HashSet<EcoString>,
+    warnings: Warnings,
+  ) -> Result<Lexer> {
+    include_chain.push(file_key(Path::new(file)));
     let contents = fs::read_to_string(file).map_err(raise_floating_error)?;
     Ok(Lexer {
       file: file.into(),
@@ -48,17 +161,21 @@ impl Lexer {
       src: contents,
       position: 0,
       is_newline: true,
+      previous_kind: None,
       next_brace_json: false,
       line: 1,
       column: 1,
       dependent_files: HashSet::new(),
       include_chain,
+      included_files,
+      warnings,
     })
   }
 
   pub fn tokenise(&mut self) -> Result<Vec<Token>> {
     let mut tokens = Vec::new();
-    self.dependent_files.insert(self.file.clone());
+    let file = self.include_chain.last().expect("Chain includes this file");
+    self.dependent_files.insert(file.clone());
     loop {
       let next = self.next_token()?;
       
@@ -66,7 +183,20 @@ impl Lexer {
         TokenKind::IncludeKeyword => {tokens.extend(self.parse_include()?);},
         TokenKind::CommandBegin(backtick) => {
           tokens.push(next);
-          tokens.extend(self.parse_command(backtick)?);
+          tokens.extend(self.parse_command(if backtick { Some('`') } else { None })?);
+        }
+        TokenKind::DataTargetBegin => {
+          tokens.push(next);
+          self.consume();
+          tokens.extend(self.parse_command(Some(')'))?);
+        }
+        TokenKind::JsonTemplateBegin(include_braces) => {
+          tokens.push(next);
+          tokens.extend(self.parse_json_template(include_braces)?);
+        }
+        TokenKind::Ampersand if self.next_brace_json && self.current() == '{' => {
+          tokens.push(next);
+          tokens.extend(self.parse_resource_name()?);
         }
         _ => {
           tokens.push(next);
@@ -81,7 +211,11 @@ impl Lexer {
   }
 
   fn peek(&self, offset: usize) -> char {
-    self.src.chars().nth(self.position + offset).unwrap_or('\0')
+    self
+      .src
+      .get(self.position..)
+      .and_then(|rest| rest.chars().nth(offset))
+      .unwrap_or('\0')
   }
 
   fn current(&self) -> char {
@@ -105,10 +239,19 @@ impl Lexer {
       kind = TokenKind::EndOfFile;
       value = Some("\0".into());
     } else if self.current() == '{' && self.next_brace_json {
-      kind = TokenKind::Json;
       self.next_brace_json = false;
-      if !self.tokenise_json() {
-        value = Some(self.src[position + 1..self.position - 1].into());
+      let include_braces = self.tokenise_json();
+      if self.src[position..self.position].contains("&{") {
+        // Rewind, so that the template can be split into its parts
+        self.position = position;
+        self.line = line;
+        self.column = column;
+        kind = TokenKind::JsonTemplateBegin(include_braces);
+      } else {
+        kind = TokenKind::Json;
+        if !include_braces {
+          value = Some(self.src[position + 1..self.position - 1].into());
+        }
       }
     } else if self.current() == '`' {
       self.consume();
@@ -118,6 +261,15 @@ impl Lexer {
         self.consume();
       }
       kind = TokenKind::Comment;
+    } else if self.current() == '/' && self.peek(1) == '*' {
+      self.tokenise_block_comment(line, column)?;
+      // Block comments are trivia, so a command can still follow one at the start of a line
+      return Ok(Token {
+        kind: TokenKind::BlockComment,
+        value: None,
+        raw: self.src[position..self.position].into(),
+        location: self.location(line, column),
+      });
     } else if let Some(punctuation) = self.parse_punctuation() {
       kind = punctuation;
     } else if self.current().is_ascii_digit() {
@@ -144,6 +296,7 @@ impl Lexer {
     }
 
     self.is_newline = false;
+    self.previous_kind = Some(kind);
     let raw = self.src[position..self.position].into();
 
     Ok(Token {
@@ -181,12 +334,75 @@ impl Lexer {
 
       index += 1;
     }
+    // `x--1` subtracts a negative number, so `++` and `--` are only increments
+    // when no operand follows them
+    if let Some(kind @ (TokenKind::PlusPlus | TokenKind::MinusMinus)) = exact {
+      if self.operand_follows(2) {
+        index = 1;
+        exact = Some(if kind == TokenKind::PlusPlus {
+          TokenKind::Plus
+        } else {
+          TokenKind::Minus
+        });
+      }
+    }
     if exact.is_some() {
       self.consume_many(index);
     }
     exact
   }
 
+  // Whether the brackets after `entity` or `block` hold a target rather than
+  // function arguments. A target starts with a selector, a position or
+  // interpolation, and a name after `fn` or in a path is never a target
+  fn data_target_follows(&self, identifier: &str) -> bool {
+    if matches!(
+      self.previous_kind,
+      Some(
+        TokenKind::FunctionKeyword
+          | TokenKind::Ampersand
+          | TokenKind::ForwardSlash
+          | TokenKind::Colon
+      )
+    ) {
+      return false;
+    }
+
+    let mut offset = 1;
+    while matches!(self.peek(offset), ' ' | '\t') {
+      offset += 1;
+    }
+    match (identifier, self.peek(offset), self.peek(offset + 1)) {
+      (_, '&', '{') | (_, '%', _) => true,
+      ("entity", '@', _) => true,
+      ("block", '~' | '^', next) => next != '/',
+      ("block", first, _) => (first.is_ascii_digit() || first == '-') && self.is_position(offset),
+      _ => false,
+    }
+  }
+
+  // Whether the text from `offset` up to the closing bracket is three parts
+  // separated by spaces, like `1 64 -3`, rather than a list of arguments
+  fn is_position(&self, mut offset: usize) -> bool {
+    let mut parts = 1;
+    loop {
+      match self.peek(offset) {
+        ')' => return parts == 3,
+        ',' | '\n' | '\0' => return false,
+        ' ' | '\t' => {
+          while matches!(self.peek(offset + 1), ' ' | '\t') {
+            offset += 1;
+          }
+          if self.peek(offset + 1) != ')' {
+            parts += 1;
+          }
+        }
+        _ => {}
+      }
+      offset += 1;
+    }
+  }
+
   fn tokenise_identifier(
     &mut self,
     position: usize,
@@ -245,6 +461,15 @@ impl Lexer {
       ));
     }
 
+    // `entity(...)` and `block(...)` hold a raw selector or position, which is lexed
+    // like a command
+    if matches!(identifier_value, "entity" | "block")
+      && self.current() == '('
+      && self.data_target_follows(identifier_value)
+    {
+      return Ok((TokenKind::DataTargetBegin, identifier_value.into()));
+    }
+
     if let Some((_, keyword_kind)) = KEYWORD_REGISTRY
       .iter()
       .find(|(text, _)| *text == identifier_value)
@@ -253,6 +478,8 @@ impl Lexer {
     } else if self.is_newline
       && COMMANDS.contains(&identifier_value)
       && self.next_significant_char() != '('
+      && !self.assignment_follows()
+      && !self.execute_block_follows(identifier_value)
     {
       self.position = position;
       self.line = line;
@@ -263,6 +490,11 @@ impl Lexer {
     }
   }
 
+  // `execute "as @a" { ... }` is a block of statements, rather than a command
+  fn execute_block_follows(&self, identifier: &str) -> bool {
+    identifier == "execute" && self.next_significant_char() == '"'
+  }
+
   fn skip_whitespace(&mut self) {
     while self.current().is_whitespace() {
       self.consume();
@@ -277,6 +509,30 @@ impl Lexer {
     self.peek(offset)
   }
 
+  // Whether a value starts after `offset` on the same line
+  fn operand_follows(&self, mut offset: usize) -> bool {
+    while matches!(self.peek(offset), ' ' | '\t') {
+      offset += 1;
+    }
+    let next = self.peek(offset);
+    next.is_alphanumeric() || matches!(next, '_' | '$' | '&' | '"' | '(' | '@' | '~' | '^')
+  }
+
+  // No command's arguments start with an assignment or increment operator, so
+  // a command word followed by one, like `tp = 5`, is a variable
+  fn assignment_follows(&self) -> bool {
+    let mut offset = 0;
+    while matches!(self.peek(offset), ' ' | '\t') {
+      offset += 1;
+    }
+    match (self.peek(offset), self.peek(offset + 1)) {
+      ('=', next) => next != '=',
+      ('+', '+') | ('-', '-') => !self.operand_follows(offset + 2),
+      ('+' | '-' | '*' | '/' | '%', next) => next == '=',
+      _ => false,
+    }
+  }
+
   fn consume(&mut self) -> char {
     self.column += 1;
     let current = self.current();
@@ -285,7 +541,7 @@ impl Lexer {
       self.line += 1;
       self.column = 1;
     }
-    self.position += 1;
+    self.position += current.len_utf8();
     current
   }
 
@@ -336,6 +592,21 @@ impl Lexer {
     include_braces
   }
 
+  fn tokenise_block_comment(&mut self, line: usize, column: usize) -> Result<()> {
+    self.consume_many(2);
+    while !(self.current() == '*' && self.peek(1) == '/') {
+      if self.current() == '\0' {
+        return Err(raise_error(
+          self.location(line, column),
+          "Unterminated block comment",
+        ));
+      }
+      self.consume();
+    }
+    self.consume_many(2);
+    Ok(())
+  }
+
   fn tokenise_string(&mut self) -> EcoString {
     let char = self.current();
     let mut string = EcoString::new();
@@ -379,8 +650,9 @@ impl Lexer {
         self.consume();
         kind = TokenKind::Double
       }
-      // So that 1.. gets parsed a (1).. rather than (1.).
-      '.' if self.peek(1) != '.' => {
+      // So that 1.. gets parsed a (1).. rather than (1.), and the member in
+      // `list.0.name` isn't read as 0.
+      '.' if self.peek(1) != '.' && self.previous_kind != Some(TokenKind::Dot) => {
         str_value.push(self.consume());
         kind = TokenKind::Double;
 
@@ -406,7 +678,10 @@ impl Lexer {
   }
 
   fn parse_include(&mut self) -> Result<Vec<Token>> {
-    let token = self.next_token()?;
+    let mut token = self.next_token()?;
+    while token.kind == TokenKind::BlockComment {
+      token = self.next_token()?;
+    }
 
     if token.kind != TokenKind::String {
       return Err(raise_error(token.location, "Expected file name."));
@@ -428,27 +703,49 @@ impl Lexer {
         .join(path.as_str())
     };
     let mut tokens = Vec::new();
+    let pattern = relative_path.to_str().expect("Path should be valid");
+    let mut matched = false;
 
-    for entry in
-      glob(relative_path.to_str().expect("Path should be valid")).map_err(raise_floating_error)?
-    {
+    for entry in glob(pattern).map_err(raise_floating_error)? {
       match entry {
         Ok(path) => {
+          matched = true;
           let path_str = path.to_str().expect("Path should be valid");
-          if let Some(index) = self.include_chain.iter().position(|file| path_str == file) {
-            if index != (self.include_chain.len() - 1) {
-              raise_warning(
+          if is_glob(pattern) {
+            let directory = path.parent().expect("Path should be valid");
+            self.dependent_files.insert(file_key(directory));
+          }
+          let key = file_key(&path);
+          match inclusion(&key, &self.include_chain, &mut self.included_files) {
+            Inclusion::Itself => continue,
+            Inclusion::Circular => {
+              self.warnings.push(raise_warning(
                 token.location.clone(),
                 "Circular dependency detected, not including file.",
-              );
+              ));
+              continue;
             }
-            continue;
+            Inclusion::Repeated => {
+              self.warnings.push(raise_note(
+                token.location.clone(),
+                format!("`{path_str}` has already been included, not including it again."),
+              ));
+              continue;
+            }
+            Inclusion::New => {}
           }
-          self.dependent_files.insert(path_str.into());
+          self.dependent_files.insert(key);
 
-          let mut lexer = Lexer::child(path_str, &self.root, self.include_chain.clone())?;
+          let mut lexer = Lexer::child(
+            path_str,
+            &self.root,
+            self.include_chain.clone(),
+            take(&mut self.included_files),
+            self.warnings.clone(),
+          )?;
 
           tokens.extend(lexer.tokenise()?);
+          self.included_files = take(&mut lexer.included_files);
           self.dependent_files.extend(lexer.dependent_files);
           tokens.last_mut().expect("Tokens always includes EOF").kind = TokenKind::EndOfInclude;
         }
@@ -457,11 +754,22 @@ impl Lexer {
         }
       }
     }
+
+    // Files created later that the pattern matches aren't tracked yet, so the
+    // directory they would be created in is watched instead
+    if !matched || is_glob(pattern) {
+      self
+        .dependent_files
+        .insert(file_key(&pattern_directory(&relative_path)));
+    }
     Ok(tokens)
   }
 
-  fn parse_command(&mut self, backtick: bool) -> Result<Vec<Token>> {
+  // Lexes a command until `end`, or the end of the line if there is none
+  fn parse_command(&mut self, end: Option<char>) -> Result<Vec<Token>> {
     let mut tokens = Vec::new();
+    // The opening backtick was just consumed
+    let start = self.location(self.line, self.column - 1);
 
     let mut current_part = EcoString::new();
     let mut line = self.line;
@@ -474,7 +782,11 @@ impl Lexer {
     // Flag for when the last character was a whitespace character. Used to strip whitespace.
     let mut last_was_whitespace: bool = false;
 
-    while if backtick {self.current() != '`'} else {!self.current_is_delim()} {
+    while match end {
+      Some(')') => self.current() != ')' && !self.current_is_delim(),
+      Some(end) => self.current() != end && self.current() != '\0',
+      None => !self.current_is_delim(),
+    } {
       let current = self.current();
 
       match (current, self.peek(1)) {
@@ -498,19 +810,7 @@ impl Lexer {
           });
           current_part = EcoString::new();
 
-          self.consume();
-          self.consume();
-          let mut brace_level = 0;
-          while self.current() != '}' || brace_level > 0 {
-            let next = self.next_token()?;
-            if next.kind == TokenKind::LeftBrace {
-              brace_level += 1;
-            } else if next.kind == TokenKind::RightBrace {
-              brace_level -= 1;
-            }
-            tokens.push(next);
-          }
-          self.consume();
+          tokens.extend(self.tokenise_interpolation()?);
 
           line = self.line;
           column = self.column;
@@ -601,13 +901,174 @@ impl Lexer {
       location: self.location(self.line, self.column),
     });
 
-    if backtick {
+    if end == Some('`') && self.current() != '`' {
+      return Err(raise_error(
+        start,
+        "Unterminated backtick command. Use `\\`` for a backtick within the command.",
+      ));
+    }
+
+    if end == Some(')') && self.current() != ')' {
+      return Err(raise_error(
+        self.location(self.line, self.column),
+        "Expected `)` to close the target.",
+      ));
+    }
+
+    if end.is_some() {
       self.consume();
     }
 
     Ok(tokens)
   }
 
+  fn parse_json_template(&mut self, include_braces: bool) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+
+    let mut current_part = EcoString::new();
+    let mut line = self.line;
+    let mut column = self.column;
+    let mut string_char: Option<char> = None;
+    let mut brace_level = 0;
+
+    loop {
+      match (self.current(), self.peek(1)) {
+        ('\0', _) => {
+          return Err(raise_error(
+            self.location(line, column),
+            "Unterminated resource body.",
+          ))
+        }
+        ('\\', '&') => {
+          self.consume();
+          current_part.push(self.consume());
+        }
+        ('\\', _) if string_char.is_some() => {
+          current_part.push(self.consume());
+          current_part.push(self.consume());
+        }
+        ('&', '{') => {
+          tokens.push(Token {
+            kind: TokenKind::CommandString,
+            value: None,
+            raw: current_part,
+            location: self.location(line, column),
+          });
+          current_part = EcoString::new();
+
+          tokens.extend(self.tokenise_interpolation()?);
+
+          line = self.line;
+          column = self.column;
+        }
+        ('\'' | '"', _) => {
+          if let Some(value) = string_char {
+            if value == self.current() {
+              string_char = None;
+            }
+          } else {
+            string_char = Some(self.current());
+          }
+          current_part.push(self.consume());
+        }
+        ('{', _) if string_char.is_none() => {
+          brace_level += 1;
+          let brace = self.consume();
+          if brace_level > 1 || include_braces {
+            current_part.push(brace);
+          }
+        }
+        ('}', _) if string_char.is_none() => {
+          brace_level -= 1;
+          let brace = self.consume();
+          if brace_level > 0 || include_braces {
+            current_part.push(brace);
+          }
+          if brace_level == 0 {
+            break;
+          }
+        }
+        _ => current_part.push(self.consume()),
+      }
+    }
+
+    tokens.push(Token {
+      kind: TokenKind::CommandString,
+      value: None,
+      raw: current_part,
+      location: self.location(line, column),
+    });
+    tokens.push(Token {
+      kind: TokenKind::CommandEnd,
+      value: None,
+      raw: EcoString::new(),
+      location: self.location(self.line, self.column),
+    });
+
+    Ok(tokens)
+  }
+
+  // The braces around a templated resource name are part of the expression, not JSON
+  fn parse_resource_name(&mut self) -> Result<Vec<Token>> {
+    self.next_brace_json = false;
+    let mut tokens = Vec::new();
+    let mut brace_level = 0;
+    loop {
+      let next = self.next_token()?;
+      match next.kind {
+        TokenKind::LeftBrace => brace_level += 1,
+        TokenKind::RightBrace => brace_level -= 1,
+        TokenKind::EndOfFile => {
+          return Err(raise_error(
+            next.location,
+            "Expected `}` to close the name.",
+          ))
+        }
+        _ => {}
+      }
+      tokens.push(next);
+      if brace_level == 0 {
+        break;
+      }
+    }
+    self.next_brace_json = true;
+    Ok(tokens)
+  }
+
+  // Tokenises the expression inside `&{}`, consuming both braces
+  fn tokenise_interpolation(&mut self) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    self.consume_many(2);
+    let mut brace_level = 0;
+    loop {
+      self.skip_whitespace();
+      if self.current() == '}' && brace_level == 0 {
+        break;
+      }
+      let next = self.next_token()?;
+      match next.kind {
+        TokenKind::LeftBrace => brace_level += 1,
+        TokenKind::RightBrace => brace_level -= 1,
+        TokenKind::DataTargetBegin => {
+          tokens.push(next);
+          self.consume();
+          tokens.extend(self.parse_command(Some(')'))?);
+          continue;
+        }
+        TokenKind::EndOfFile => {
+          return Err(raise_error(
+            next.location,
+            "Expected `}` to close the interpolation.",
+          ))
+        }
+        _ => {}
+      }
+      tokens.push(next);
+    }
+    self.consume();
+    Ok(tokens)
+  }
+
   fn location(&self, line: usize, column: usize) -> Location {
     Location {
       file: self.file.clone(),
@@ -625,3 +1086,89 @@ fn valid_identifier_start(character: char) -> bool {
 fn valid_identifier_body(character: char) -> bool {
   character.is_ascii_alphanumeric() || character == '_'
 }
+
+#[cfg(test)]
+mod tests {
+  use std::{
+    collections::HashSet,
+    io,
+    path::{Path, PathBuf},
+  };
+
+  use ecow::EcoString;
+
+  use super::{inclusion, key_with, Inclusion};
+
+  // Resolves paths like Windows does for a project in `C:\Project`, ignoring
+  // case and either separator
+  fn canonicalize(path: &Path) -> io::Result<PathBuf> {
+    let path = path
+      .to_string_lossy()
+      .replace('/', "\\")
+      .replace("\\.\\", "\\")
+      .to_lowercase();
+    match path.as_str() {
+      r"c:\project\main.zog" => Ok(r"\\?\C:\Project\main.zog".into()),
+      r"c:\project\lib\util.zog" => Ok(r"\\?\C:\Project\lib\util.zog".into()),
+      _ => Err(io::ErrorKind::NotFound.into()),
+    }
+  }
+
+  fn key(path: &str) -> EcoString {
+    key_with(Path::new(path), canonicalize, true)
+  }
+
+  #[test]
+  fn spellings_of_a_file_share_a_key() {
+    for path in [
+      r"C:\Project\lib\util.zog",
+      "C:/Project/lib/util.zog",
+      r"c:\Project/lib\./util.zog",
+      r"C:\PROJECT\LIB\UTIL.ZOG",
+    ] {
+      assert_eq!(key(path), "c:/Project/lib/util.zog", "{path}");
+    }
+  }
+
+  #[test]
+  fn missing_files_are_still_normalized() {
+    assert_eq!(key(r"C:\Project\new/file.zog"), "c:/Project/new/file.zog");
+  }
+
+  #[test]
+  fn other_platforms_keep_backslashes() {
+    // A backslash is an ordinary character in a Unix file name
+    let key = key_with(Path::new(r"lib\odd.zog"), |path| Ok(path.into()), false);
+    assert_eq!(key, r"lib\odd.zog");
+  }
+
+  #[test]
+  fn a_file_is_included_once_whatever_its_spelling() {
+    let chain = [key(r"C:\Project\main.zog")];
+    let mut included = HashSet::from(chain.clone());
+    assert_eq!(
+      inclusion(&key(r"C:\Project\lib\util.zog"), &chain, &mut included),
+      Inclusion::New
+    );
+    assert_eq!(
+      inclusion(&key("c:/project/LIB/util.zog"), &chain, &mut included),
+      Inclusion::Repeated
+    );
+    assert_eq!(included.len(), 2);
+  }
+
+  #[test]
+  fn circular_includes_are_found_whatever_their_spelling() {
+    let chain = [key(r"C:\Project\main.zog"), key("C:/Project/lib/util.zog")];
+    let mut included = HashSet::from(chain.clone());
+    assert_eq!(
+      inclusion(&key(r"c:\project\MAIN.zog"), &chain, &mut included),
+      Inclusion::Circular
+    );
+    assert_eq!(
+      inclusion(&key(r"C:\Project\lib/./util.zog"), &chain, &mut included),
+      Inclusion::Itself
+    );
+    assert_eq!(included.len(), 2);
+  }
+}
@@ -69,6 +69,8 @@ pub const OPERATOR_REGISTRY: &[(&str, TokenKind)] = &[
   (",", TokenKind::Comma),
   ("+", TokenKind::Plus),
   ("-", TokenKind::Minus),
+  ("++", TokenKind::PlusPlus),
+  ("--", TokenKind::MinusMinus),
   ("*", TokenKind::Star),
   ("%", TokenKind::Percent),
   ("**", TokenKind::DoubleStar),
@@ -106,6 +108,7 @@ pub const KEYWORD_REGISTRY: &[(&str, TokenKind)] = &[
   ("if", TokenKind::IfKeyword),
   ("else", TokenKind::ElseKeyword),
   ("while", TokenKind::WhileKeyword),
+  ("for", TokenKind::ForKeyword),
   ("true", TokenKind::TrueKeyword),
   ("false", TokenKind::FalseKeyword),
   ("return", TokenKind::ReturnKeyword),
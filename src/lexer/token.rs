@@ -38,16 +38,20 @@ pub enum TokenKind {
   IfKeyword,
   ElseKeyword,
   WhileKeyword,
+  ForKeyword,
   TrueKeyword,
   FalseKeyword,
   ReturnKeyword,
 
   // Non-zoglin
   CommandBegin(bool),
+  DataTargetBegin,
   CommandString,
   CommandEnd,
   Json,
+  JsonTemplateBegin(bool),
   Comment,
+  BlockComment,
 
   // Symbols
   LeftBrace,
@@ -64,6 +68,8 @@ pub enum TokenKind {
   Comma,
   Plus,
   Minus,
+  PlusPlus,
+  MinusMinus,
   Star,
   Percent,
   DoubleStar,
@@ -1,83 +1,445 @@
-use clap::{self, Arg, Command};
+use clap::{self, parser::ValueSource, Arg, ArgAction, ArgMatches, Command};
 mod compiler;
 mod config;
 mod error;
+mod init;
 mod lexer;
 mod parser;
+mod simulator;
+mod watch;
 
+use config::Config;
 use ecow::EcoString;
-use error::Result;
+use error::{print_errors, raise_floating_error, Error, MultiResult, Result, Warnings};
 use std::{
-  collections::{HashMap, HashSet},
-  fs,
+  collections::HashSet,
+  fs, io,
   path::Path,
   process::exit,
-  thread,
   time::{Duration, SystemTime},
 };
 
-use lexer::Lexer;
+use lexer::{file_key, Lexer};
+use watch::{FileWatcher, PollWatcher};
 
-use crate::{compiler::Compiler, parser::Parser};
+use crate::{
+  compiler::{CompileOptions, Compiler, FileTree, LineEndings, Profile},
+  error::Location,
+  parser::{
+    ast::ReturnType,
+    name::{validate, NameKind},
+    Parser,
+  },
+};
 
 fn main() {
   let matches = Command::new("zog")
     .subcommand(Command::new("build").args([
       Arg::new("file").short('f').default_value("main.zog"),
       Arg::new("output").short('o').default_value("build"),
-      Arg::new("debug_mode").long("debug").default_value("none"),
+      debug_arg(),
+      emit_internals_only_arg(),
+      line_endings_arg(),
+      storage_prefix_arg(),
+      message_target_arg(),
+      no_style_warnings_arg(),
+      auto_fix_names_arg(),
+      inline_load_arg(),
+      loop_guard_arg(),
+      loop_guard_target_arg(),
+      emit_call_stubs_arg(),
+      no_version_check_arg(),
+      profile_arg(),
+      max_errors_arg(),
+    ]))
+    .subcommand(
+      Command::new("init").args([
+        Arg::new("name"),
+        Arg::new("template")
+          .long("template")
+          .value_parser(init::TEMPLATES)
+          .default_value("basic"),
+      ]),
+    )
+    .subcommand(Command::new("check").args([
+      Arg::new("file").short('f').default_value("main.zog"),
+      storage_prefix_arg(),
+      message_target_arg(),
+      no_style_warnings_arg(),
+      auto_fix_names_arg(),
+      inline_load_arg(),
+      loop_guard_arg(),
+      loop_guard_target_arg(),
+      profile_arg(),
+      max_errors_arg(),
+    ]))
+    .subcommand(Command::new("test").args([
+      Arg::new("file").short('f').default_value("main.zog"),
+      storage_prefix_arg(),
+      message_target_arg(),
+      no_style_warnings_arg(),
+      auto_fix_names_arg(),
+      inline_load_arg(),
+      loop_guard_arg(),
+      loop_guard_target_arg(),
+      max_errors_arg(),
     ]))
-    .subcommand(Command::new("init").arg(Arg::new("name")))
     .subcommand(Command::new("watch").args([
       Arg::new("file").short('f').default_value("main.zog"),
       Arg::new("output").short('o').default_value("build"),
+      debug_arg(),
+      line_endings_arg(),
+      storage_prefix_arg(),
+      message_target_arg(),
+      no_style_warnings_arg(),
+      auto_fix_names_arg(),
+      inline_load_arg(),
+      loop_guard_arg(),
+      loop_guard_target_arg(),
+      emit_call_stubs_arg(),
+      no_version_check_arg(),
+      profile_arg(),
+      max_errors_arg(),
+      poll_arg(),
+      poll_interval_arg(),
+    ]))
+    .subcommand(Command::new("snippet").args([
+      Arg::new("code").short('e'),
+      storage_prefix_arg(),
+      message_target_arg(),
+      no_style_warnings_arg(),
+      auto_fix_names_arg(),
+      inline_load_arg(),
+      loop_guard_arg(),
+      loop_guard_target_arg(),
+      max_errors_arg(),
     ]))
     .get_matches();
 
   if let Some(matches) = matches.subcommand_matches("build") {
-    let file: &String = matches
-      .get_one("file")
-      .expect("Argument has a default value");
+    let config = read_config().unwrap_or_else(|e| exit_with_error(e, matches));
+    let file = &input_file(matches, config.as_ref());
     let output: &String = matches
       .get_one("output")
       .expect("Argument has a default value");
     let debug_mode: &String = matches
       .get_one("debug_mode")
       .expect("Argument has a default value");
-    if let Err(e) = build(file, output, debug_mode).1 {
-      e.print();
+    let debug_mode = if matches.get_flag("emit_internals_only") {
+      "internals"
+    } else {
+      debug_mode
+    };
+    let options = project_options(matches, config);
+    if let Err(errors) = build(file, output, debug_mode, &options).1 {
+      print_errors(&errors, max_errors(matches));
       exit(1);
     }
   } else if let Some(matches) = matches.subcommand_matches("init") {
-    let name = matches.get_one("name");
-    if let Some(name) = name {
-      init(name);
-    } else {
-      init(&String::new());
+    let name = matches.get_one::<String>("name").map_or("", String::as_str);
+    let template: &String = matches
+      .get_one("template")
+      .expect("Argument has a default value");
+    if let Err(e) = init::init(name, template) {
+      print_errors(&[e], 1);
+      exit(1);
+    }
+  } else if let Some(matches) = matches.subcommand_matches("check") {
+    let config = read_config().unwrap_or_else(|e| exit_with_error(e, matches));
+    let file = &input_file(matches, config.as_ref());
+    if let Err(errors) = check(file, &project_options(matches, config)) {
+      print_errors(&errors, max_errors(matches));
+      exit(1);
+    }
+  } else if let Some(matches) = matches.subcommand_matches("test") {
+    let config = read_config().unwrap_or_else(|e| exit_with_error(e, matches));
+    let file = &input_file(matches, config.as_ref());
+    match test(file, &project_options(matches, config)) {
+      Ok(true) => {}
+      Ok(false) => exit(1),
+      Err(errors) => {
+        print_errors(&errors, max_errors(matches));
+        exit(1);
+      }
     }
   } else if let Some(matches) = matches.subcommand_matches("watch") {
-    let file: &String = matches
-      .get_one("file")
-      .expect("Argument has a default value");
+    let config = read_config().unwrap_or_else(|e| exit_with_error(e, matches));
+    let file = &input_file(matches, config.as_ref());
     let output: &String = matches
       .get_one("output")
       .expect("Argument has a default value");
-    watch(file, output);
+    let options = project_options(matches, config);
+    watch(file, output, &options, matches);
+  } else if let Some(matches) = matches.subcommand_matches("snippet") {
+    let code: Option<&String> = matches.get_one("code");
+    if let Err(errors) = snippet(code, &compile_options(matches)) {
+      print_errors(&errors, max_errors(matches));
+      exit(1);
+    }
+  }
+}
+
+fn line_endings_arg() -> Arg {
+  Arg::new("line_endings")
+    .long("line-endings")
+    .value_parser(["lf", "crlf"])
+    .default_value("lf")
+}
+
+// `--debug` on its own makes a debug build, while `--debug tokens`,
+// `--debug ast` or `--debug compiled` prints the result of that stage instead
+// of building
+fn debug_arg() -> Arg {
+  Arg::new("debug_mode")
+    .long("debug")
+    .num_args(0..=1)
+    .default_missing_value("build")
+    .default_value("none")
+}
+
+fn emit_internals_only_arg() -> Arg {
+  Arg::new("emit_internals_only")
+    .long("emit-internals-only")
+    .action(ArgAction::SetTrue)
+}
+
+fn no_style_warnings_arg() -> Arg {
+  Arg::new("no_style_warnings")
+    .long("no-style-warnings")
+    .action(ArgAction::SetTrue)
+}
+
+fn auto_fix_names_arg() -> Arg {
+  Arg::new("auto_fix_names")
+    .long("auto-fix-names")
+    .action(ArgAction::SetTrue)
+}
+
+fn inline_load_arg() -> Arg {
+  Arg::new("inline_load")
+    .long("inline-load")
+    .action(ArgAction::SetTrue)
+}
+
+fn emit_call_stubs_arg() -> Arg {
+  Arg::new("emit_call_stubs")
+    .long("emit-call-stubs")
+    .action(ArgAction::SetTrue)
+}
+
+fn no_version_check_arg() -> Arg {
+  Arg::new("no_version_check")
+    .long("no-version-check")
+    .action(ArgAction::SetTrue)
+}
+
+// `release` leaves out functions and modules marked `#[test_only]`
+fn profile_arg() -> Arg {
+  Arg::new("profile")
+    .long("profile")
+    .value_parser(["dev", "release"])
+    .default_value("dev")
+}
+
+fn loop_guard_arg() -> Arg {
+  Arg::new("loop_guard")
+    .long("loop-guard")
+    .value_parser(clap::value_parser!(u32).range(1..))
+}
+
+// Loops usually run from a tick function, where there is no player to tell,
+// so the message goes to players given a tag
+fn loop_guard_target_arg() -> Arg {
+  Arg::new("loop_guard_target")
+    .long("loop-guard-target")
+    .value_parser(parse_target)
+    .default_value("@a[tag=zoglin.ops]")
+}
+
+fn max_errors_arg() -> Arg {
+  Arg::new("max_errors")
+    .long("max-errors")
+    .value_parser(clap::value_parser!(u32).range(1..))
+    .default_value("20")
+}
+
+fn exit_with_error(error: Error, matches: &ArgMatches) -> ! {
+  print_errors(&[error], max_errors(matches));
+  exit(1)
+}
+
+fn max_errors(matches: &ArgMatches) -> usize {
+  let max_errors: &u32 = matches
+    .get_one("max_errors")
+    .expect("Argument has a default value");
+  *max_errors as usize
+}
+
+fn poll_arg() -> Arg {
+  Arg::new("poll").long("poll").action(ArgAction::SetTrue)
+}
+
+fn poll_interval_arg() -> Arg {
+  Arg::new("poll_interval")
+    .long("poll-interval")
+    .value_parser(clap::value_parser!(u64).range(1..))
+    .default_value("1000")
+}
+
+fn storage_prefix_arg() -> Arg {
+  Arg::new("storage_prefix")
+    .long("storage-prefix")
+    .value_parser(|prefix: &str| {
+      if validate(prefix, &Location::blank(), NameKind::ResourcePathComponent).is_ok() {
+        Ok(prefix.to_string())
+      } else {
+        Err(format!("`{prefix}` is not a valid storage prefix."))
+      }
+    })
+}
+
+// Debug output and deprecation notices go to whoever ran the function by
+// default, rather than every player on the server
+fn message_target_arg() -> Arg {
+  Arg::new("message_target")
+    .long("message-target")
+    .value_parser(parse_target)
+    .default_value("@s")
+}
+
+fn parse_target(target: &str) -> std::result::Result<String, String> {
+  if target.starts_with('@') || !target.contains(char::is_whitespace) {
+    Ok(target.to_string())
+  } else {
+    Err(format!(
+      "`{target}` is not a valid selector or player name."
+    ))
   }
 }
 
-fn build(file: &String, output: &String, debug_mode: &str) -> (HashSet<EcoString>, Result<()>) {
+pub const CONFIG_FILE: &str = "zoglin.json";
+
+// The config of the project in the current directory, if it has one
+fn read_config() -> Result<Option<Config>> {
+  let text = match fs::read_to_string(CONFIG_FILE) {
+    Ok(text) => text,
+    Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+    Err(e) => return Err(raise_floating_error(e)),
+  };
+  json5::from_str(&text)
+    .map(Some)
+    .map_err(|e| raise_floating_error(format!("Invalid `{CONFIG_FILE}`: {e}")))
+}
+
+// `-f` takes precedence over the config's entry file
+fn input_file(matches: &ArgMatches, config: Option<&Config>) -> String {
+  let file: &String = matches
+    .get_one("file")
+    .expect("Argument has a default value");
+  match config {
+    Some(config) if matches.value_source("file") == Some(ValueSource::DefaultValue) => {
+      config.entry.clone()
+    }
+    _ => file.clone(),
+  }
+}
+
+fn compile_options(matches: &ArgMatches) -> CompileOptions {
+  // `zog test` doesn't write any files, so has no line endings argument
+  let line_endings = matches
+    .try_get_one::<String>("line_endings")
+    .ok()
+    .flatten()
+    .map_or(LineEndings::Lf, |name| {
+      LineEndings::from_name(name).expect("Argument only accepts valid values")
+    });
+  let storage_prefix: Option<&String> = matches.get_one("storage_prefix");
+  CompileOptions {
+    line_endings,
+    storage_prefix: storage_prefix.map(|prefix| prefix.into()),
+    style_warnings: !matches.get_flag("no_style_warnings"),
+    auto_fix_names: matches.get_flag("auto_fix_names"),
+    inline_load: matches.get_flag("inline_load"),
+    loop_guard: matches.get_one("loop_guard").copied(),
+    loop_guard_target: matches
+      .get_one::<String>("loop_guard_target")
+      .expect("Argument has a default value")
+      .into(),
+    debug: matches
+      .try_get_one::<String>("debug_mode")
+      .is_ok_and(|mode| mode.is_some_and(|mode| mode == "build")),
+    message_target: matches
+      .get_one::<String>("message_target")
+      .expect("Argument has a default value")
+      .into(),
+    // Only `zog build` and `zog watch` write files
+    emit_call_stubs: matches
+      .try_get_one::<bool>("emit_call_stubs")
+      .is_ok_and(|flag| flag.is_some_and(|flag| *flag)),
+    meta: None,
+    version_check: !matches
+      .try_get_one::<bool>("no_version_check")
+      .is_ok_and(|flag| flag.is_some_and(|flag| *flag)),
+    // `zog test` and `zog snippet` always use the dev profile
+    profile: matches
+      .try_get_one::<String>("profile")
+      .ok()
+      .flatten()
+      .and_then(|name| Profile::from_name(name))
+      .unwrap_or_default(),
+    default_return: ReturnType::Storage,
+    require_explicit_return_type: false,
+    warnings: Warnings::default(),
+  }
+}
+
+// The options of a command run in a project, with the settings from its config
+fn project_options(matches: &ArgMatches, config: Option<Config>) -> CompileOptions {
+  let mut options = compile_options(matches);
+  if let Some(config) = config {
+    options.default_return = config.default_return.return_type();
+    options.require_explicit_return_type = config.require_explicit_return_type;
+    options.meta = Some(config.meta);
+  }
+  options
+}
+
+fn build(
+  file: &String,
+  output: &String,
+  debug_mode: &str,
+  options: &CompileOptions,
+) -> (HashSet<EcoString>, MultiResult<()>) {
   print!("Building {} into {}... ", file, output);
+  let result = build_file(file, output, debug_mode, options);
+  print_warnings(options);
+  result
+}
+
+// Warnings are printed once a command is done, after its own output
+fn print_warnings(options: &CompileOptions) {
+  for warning in options.warnings.take() {
+    warning.print();
+  }
+}
+
+fn build_file(
+  file: &str,
+  output: &str,
+  debug_mode: &str,
+  options: &CompileOptions,
+) -> (HashSet<EcoString>, MultiResult<()>) {
   let start = SystemTime::now();
-  let result = Lexer::new(file);
+  let result = Lexer::new(file, &options.warnings);
   let mut lexer = match result {
     Ok(lexer) => lexer,
-    Err(e) => return (HashSet::new(), Err(e)),
+    // Watched anyway, so the build is retried once the file can be read
+    Err(e) => return (HashSet::from([file_key(Path::new(file))]), Err(vec![e])),
   };
   let result = lexer.tokenise();
   let tokens = match result {
     Ok(tokens) => tokens,
-    Err(e) => return (lexer.dependent_files, Err(e)),
+    Err(e) => return (lexer.dependent_files, Err(vec![e])),
   };
 
   if debug_mode == "tokens" {
@@ -92,11 +454,11 @@ fn build(file: &String, output: &String, debug_mode: &str) -> (HashSet<EcoString
     return (lexer.dependent_files, Ok(()));
   }
 
-  let mut parser = Parser::new(tokens);
+  let mut parser = Parser::new(tokens, options);
   let result = parser.parse();
   let ast = match result {
     Ok(ast) => ast,
-    Err(e) => return (lexer.dependent_files, Err(e)),
+    Err(errors) => return (lexer.dependent_files, Err(errors)),
   };
 
   if debug_mode == "ast" {
@@ -111,8 +473,35 @@ fn build(file: &String, output: &String, debug_mode: &str) -> (HashSet<EcoString
     return (lexer.dependent_files, Ok(()));
   }
 
-  if let Err(e) = Compiler::compile(ast, output) {
-    return (lexer.dependent_files, Err(e));
+  // `compiled=<filter>` only shows functions whose path contains the filter
+  let compiled_filter = match debug_mode {
+    "compiled" => Some(""),
+    mode => mode.strip_prefix("compiled="),
+  };
+  if let Some(filter) = compiled_filter {
+    let tree = match Compiler::compile_to_tree(ast, options, &mut lexer.dependent_files) {
+      Ok(tree) => tree,
+      Err(errors) => return (lexer.dependent_files, Err(errors)),
+    };
+    println!(
+      "Compiled in {}ms",
+      SystemTime::now()
+        .duration_since(start)
+        .expect("Now is always later than previously")
+        .as_millis()
+    );
+    print!("{}", tree.dump(filter));
+    return (lexer.dependent_files, Ok(()));
+  }
+
+  if debug_mode == "internals" {
+    let result = Compiler::compile_internals(&ast, output, options);
+    println!("Wrote internal helpers");
+    return (lexer.dependent_files, result.map_err(|e| vec![e]));
+  }
+
+  if let Err(errors) = Compiler::compile(ast, output, options, &mut lexer.dependent_files) {
+    return (lexer.dependent_files, Err(errors));
   }
 
   println!(
@@ -125,83 +514,88 @@ fn build(file: &String, output: &String, debug_mode: &str) -> (HashSet<EcoString
   (lexer.dependent_files, Ok(()))
 }
 
-const DEFAULT_PROJECT: &str = r#"namespace $name {
-  fn tick() {
-
+// Compiles the project without writing anything, to report its errors and
+// warnings
+fn check(file: &str, options: &CompileOptions) -> MultiResult<()> {
+  print!("Checking {}... ", file);
+  let start = SystemTime::now();
+  let result = compile_project(file, options).map(|_| ());
+  if result.is_ok() {
+    println!(
+      "Checked in {}ms",
+      SystemTime::now()
+        .duration_since(start)
+        .expect("Now is always later than previously")
+        .as_millis()
+    );
   }
+  print_warnings(options);
+  result
+}
 
-  fn load() {
-    tellraw @a "Loaded $name"
-  }
+// Compiles the project without writing it, then runs its tests
+fn test(file: &str, options: &CompileOptions) -> MultiResult<bool> {
+  let tree = compile_project(file, options);
+  print_warnings(options);
+  Ok(simulator::run_tests(&tree?))
 }
-"#;
-
-fn init(name: &String) {
-  if name.is_empty() {
-    let dir = std::env::current_dir().expect("Current directory should be valid");
-    let current_dir = Path::new(&dir)
-      .file_name()
-      .expect("Current directory cannot end in ..")
-      .to_str()
-      .expect("Path should be valid");
-    let is_empty = dir
-      .read_dir()
-      .expect("Directory should be readable")
-      .next()
-      .is_none();
-    if !is_empty {
-      println!("No init'ing projects in non-empty directories, naughty naughty!");
-      return;
+
+fn compile_project(file: &str, options: &CompileOptions) -> MultiResult<FileTree> {
+  let mut lexer = Lexer::new(file, &options.warnings)?;
+  let tokens = lexer.tokenise()?;
+  let ast = Parser::new(tokens, options).parse()?;
+  Compiler::compile_to_tree(ast, options, &mut lexer.dependent_files)
+}
+
+// Prints the commands a list of statements compiles to. The statements are
+// read from standard input unless given with `-e`
+fn snippet(code: Option<&String>, options: &CompileOptions) -> MultiResult<()> {
+  let code = match code {
+    Some(code) => code.clone(),
+    None => io::read_to_string(io::stdin()).map_err(raise_floating_error)?,
+  };
+  let snippet = Compiler::compile_snippet(&code, options);
+  print_warnings(options);
+  let snippet = snippet?;
+  for command in snippet.commands {
+    println!("{command}");
+  }
+  for (name, commands) in snippet.helpers {
+    println!("\n# {name}");
+    for command in commands {
+      println!("{command}");
     }
-    let contents = DEFAULT_PROJECT.replace("$name", current_dir);
-    fs::write("main.zog", contents).expect("Directory should be writable");
-  } else {
-    fs::create_dir(name).expect("Directory should be writable");
-    let contents = DEFAULT_PROJECT.replace("$name", name);
-    fs::write(name.clone() + "/main.zog", contents).expect("Directory should be writable");
   }
+  Ok(())
 }
 
-fn watch(file: &String, output: &String) {
-  let (dep_files, result) = build(file, output, "none");
-  if let Err(e) = result {
-    e.print();
-  }
-  let mut files: HashMap<_, _> = get_modification_times(dep_files);
-  loop {
-    thread::sleep(Duration::from_secs(1));
-    let mut dependent_files = None;
-    for (name, last_modified) in files.iter() {
-      if !Path::new(name.as_str()).exists() {
-        continue;
-      }
-      let modified = fs::metadata(name.as_str())
-        .and_then(|metadata| metadata.modified())
-        .expect("Path must be valid and readable");
-      if &modified != last_modified {
-        let (dep_files, result) = build(file, output, "none");
-        if let Err(e) = result {
-          e.print();
-        }
-        dependent_files = Some(dep_files);
-        break;
+fn watch(file: &String, output: &String, options: &CompileOptions, matches: &ArgMatches) {
+  let interval: &u64 = matches
+    .get_one("poll_interval")
+    .expect("Argument has a default value");
+  let mut poll_watcher = PollWatcher::new(Duration::from_millis(*interval));
+  let watcher: &mut dyn FileWatcher = &mut poll_watcher;
+
+  #[cfg(feature = "native-watch")]
+  let mut native_watcher = None;
+  #[cfg(feature = "native-watch")]
+  let watcher = if matches.get_flag("poll") {
+    watcher
+  } else {
+    match watch::NativeWatcher::new() {
+      Ok(native) => native_watcher.insert(native),
+      Err(e) => {
+        println!("Could not watch files for changes ({e}), polling instead.");
+        watcher
       }
     }
+  };
 
-    if let Some(dep_files) = dependent_files {
-      files = get_modification_times(dep_files);
+  watch::watch(watcher, || {
+    let (dependent_files, result) = build(file, output, "none", options);
+    if let Err(errors) = result {
+      print_errors(&errors, max_errors(matches));
     }
-  }
-}
-
-fn get_modification_times(files: HashSet<EcoString>) -> HashMap<EcoString, SystemTime> {
-  files
-    .into_iter()
-    .map(|name| {
-      let time = fs::metadata(name.as_str())
-        .and_then(|meta| meta.modified())
-        .expect("Path must be valid and readable");
-      (name, time)
-    })
-    .collect()
+    dependent_files
+  })
 }
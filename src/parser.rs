@@ -1,25 +1,32 @@
 use ast::{
-  ArrayType, Command, CommandPart, ComptimeFunction, ElseStatement, KeyValue, Parameter,
-  ParameterKind, ReturnType, StaticExpr, WhileLoop,
+  ArrayType, BinaryOperation, Command, CommandPart, ComptimeFunction, DataKind, ElseStatement,
+  ExecuteBlock, ForLoop, KeyValue, Parameter, ParameterKind, ReturnType, StaticExpr, WhileLoop,
 };
 use ecow::{eco_format, EcoString};
-use name::{validate, validate_or_quote, NameKind};
+use name::{
+  check_reserved, suggest_resource_name, validate, validate_or_quote, NameKind, GENERATED_MODULES,
+};
+use std::{
+  collections::{HashMap, HashSet},
+  mem::take,
+};
 
 use self::ast::{
   Expression, File, Function, FunctionCall, IfStatement, Import, Item, Module, Namespace, Resource,
-  ResourceContent, Statement, ZoglinResource,
+  ResourceContent, Statement, TemplatePart, TextComponentKind, ZoglinResource,
 };
 use crate::{
-  error::{raise_error, raise_warning, Location, Result},
+  compiler::CompileOptions,
+  error::{raise_error, raise_warning, Error, Location, MultiResult, Result, Warnings},
   lexer::token::{Token, TokenKind},
 };
 
 pub mod ast;
 mod binary_operation;
-mod name;
+pub mod name;
 mod resource;
 
-fn json5_to_json(text: &str, location: Location) -> Result<EcoString> {
+pub fn json5_to_json(text: &str, location: Location) -> Result<EcoString> {
   let map: serde_json::Value = json5::from_str(text).map_err(|e| raise_error(location, e))?;
   Ok(
     serde_json::to_string_pretty(&map)
@@ -28,27 +35,137 @@ fn json5_to_json(text: &str, location: Location) -> Result<EcoString> {
   )
 }
 
+// Returns which quote the JSON string open at the end of `text` uses, if any
+fn json_string_state(text: &str, mut string_char: Option<char>) -> Option<char> {
+  let mut chars = text.chars();
+  while let Some(char) = chars.next() {
+    match (char, string_char) {
+      ('\\', Some(_)) => {
+        chars.next();
+      }
+      (char, Some(quote)) if char == quote => string_char = None,
+      ('"' | '\'', None) => string_char = Some(char),
+      _ => {}
+    }
+  }
+  string_char
+}
+
 pub struct Parser {
   tokens: Vec<Token>,
   position: usize,
+  style_warnings: bool,
+  leading_colon_warned: HashSet<EcoString>,
+  auto_fix_names: bool,
+  // Maps each renamed name to the name it was renamed from
+  renamed_names: HashMap<EcoString, EcoString>,
+  // Errors in items which were skipped so that parsing could continue
+  errors: Vec<Error>,
+  default_return: ReturnType,
+  require_explicit_return_type: bool,
+  warnings: Warnings,
 }
 
 impl Parser {
-  pub fn new(tokens: Vec<Token>) -> Parser {
+  pub fn new(tokens: Vec<Token>, options: &CompileOptions) -> Parser {
     Parser {
       tokens,
       position: 0,
+      style_warnings: options.style_warnings,
+      leading_colon_warned: HashSet::new(),
+      auto_fix_names: options.auto_fix_names,
+      renamed_names: HashMap::new(),
+      errors: Vec::new(),
+      default_return: options.default_return,
+      require_explicit_return_type: options.require_explicit_return_type,
+      warnings: options.warnings.clone(),
     }
   }
 
-  pub fn parse(&mut self) -> Result<File> {
+  pub fn parse(&mut self) -> MultiResult<File> {
     let mut items = Vec::new();
+    let debug = self.tokens.first().is_some_and(|token| {
+      token.kind == TokenKind::Comment && token.get_value().trim() == "#!debug"
+    });
+
+    while !self.eof() {
+      let start = self.position;
+      match self.parse_namespace() {
+        Ok(namespaces) => items.extend(namespaces),
+        Err(e) => {
+          self.errors.push(e);
+          break;
+        }
+      }
+      self.check_progress(start)?;
+    }
+
+    if !self.errors.is_empty() {
+      return Err(take(&mut self.errors));
+    }
+    Ok(File { items, debug })
+  }
 
+  // Parses a list of statements as the body of the function `repl:snippet`
+  pub fn parse_snippet(&mut self) -> Result<File> {
+    let mut statements = Vec::new();
     while !self.eof() {
-      items.extend(self.parse_namespace()?);
+      let start = self.position;
+      statements.push(self.parse_statement()?);
+      self.check_progress(start)?;
     }
 
-    Ok(File { items })
+    let function = Function {
+      location: Location::blank(),
+      return_type: ReturnType::Direct,
+      name: "snippet".into(),
+      parameters: Vec::new(),
+      items: statements,
+      is_pure: false,
+      is_test: false,
+      is_test_only: false,
+      must_use: false,
+      renamed_from: Vec::new(),
+      tags: Vec::new(),
+      advancement: None,
+    };
+    Ok(File {
+      items: vec![Namespace {
+        name: "repl".into(),
+        items: vec![Item::Function(function)],
+      }],
+      debug: false,
+    })
+  }
+
+  // Rebuilds the source text of a range of tokens, with any whitespace between
+  // them as a single space
+  fn source_text(&self, start: usize, end: usize) -> EcoString {
+    let mut text = EcoString::new();
+    let mut previous_end = None;
+    for token in self.tokens[start..end].iter() {
+      if matches!(
+        token.kind,
+        TokenKind::Comment | TokenKind::BlockComment | TokenKind::EndOfInclude
+      ) {
+        continue;
+      }
+      if previous_end.is_some_and(|end| end != (token.location.line, token.location.column)) {
+        text.push(' ');
+      }
+      text.push_str(&token.raw);
+      previous_end = Some((
+        token.location.line,
+        token.location.column + token.raw.chars().count(),
+      ));
+    }
+    text
+  }
+
+  fn parse_expression_with_source(&mut self) -> Result<(EcoString, Expression)> {
+    let start = self.position;
+    let expression = self.parse_expression()?;
+    Ok((self.source_text(start, self.position), expression))
   }
 
   fn eof(&mut self) -> bool {
@@ -62,6 +179,7 @@ impl Parser {
     }
 
     self.tokens[self.position + offset].kind == TokenKind::Comment
+      || self.tokens[self.position + offset].kind == TokenKind::BlockComment
       || self.tokens[self.position + offset].kind == TokenKind::EndOfInclude
   }
 
@@ -88,20 +206,40 @@ impl Parser {
     &self.tokens[self.position + offset]
   }
 
+  // The end of the file is never consumed, so reading past it gives another
+  // `EndOfFile` rather than going out of bounds
   fn consume(&mut self) -> &Token {
     while self.should_skip(0, &[]) {
       self.position += 1;
     }
-    self.position += 1;
-    &self.tokens[self.position - 1]
+    if self.tokens[self.position].kind != TokenKind::EndOfFile {
+      self.position += 1;
+      return &self.tokens[self.position - 1];
+    }
+    &self.tokens[self.position]
   }
 
   fn consume_including(&mut self, kinds: &[TokenKind]) -> &Token {
     while self.should_skip(0, kinds) {
       self.position += 1;
     }
-    self.position += 1;
-    &self.tokens[self.position - 1]
+    if self.tokens[self.position].kind != TokenKind::EndOfFile {
+      self.position += 1;
+      return &self.tokens[self.position - 1];
+    }
+    &self.tokens[self.position]
+  }
+
+  // Checked after each iteration of a loop over tokens, so that input which
+  // isn't consumed is an error rather than a hang
+  fn check_progress(&self, start: usize) -> Result<()> {
+    if self.position == start {
+      return Err(raise_error(
+        self.current().location.clone(),
+        "The parser made no progress. This is a bug, please report it.",
+      ));
+    }
+    Ok(())
   }
 
   fn expect(&mut self, kind: TokenKind) -> Result<&Token> {
@@ -115,6 +253,41 @@ impl Parser {
     Ok(next)
   }
 
+  // Validates a module or function name. With `--auto-fix-names`, invalid
+  // names are renamed to the suggested name instead of erroring.
+  fn fix_name(
+    &mut self,
+    name: EcoString,
+    location: &Location,
+    kind: NameKind,
+  ) -> Result<EcoString> {
+    let error = match validate(&name, location, kind) {
+      Ok(()) => return Ok(name),
+      Err(error) => error,
+    };
+    let Some(fixed) = suggest_resource_name(&name).filter(|_| self.auto_fix_names) else {
+      return Err(error);
+    };
+
+    match self.renamed_names.get(&fixed) {
+      Some(original) if *original == name => {}
+      Some(original) => {
+        return Err(raise_error(
+          location.clone(),
+          eco_format!("`{name}` and `{original}` would both be renamed to `{fixed}`."),
+        ))
+      }
+      None => {
+        self.warnings.push(raise_warning(
+          location.clone(),
+          eco_format!("`{name}` is not a valid name, renaming it to `{fixed}`."),
+        ));
+        self.renamed_names.insert(fixed.clone(), name);
+      }
+    }
+    Ok(fixed)
+  }
+
   fn parse_namespace(&mut self) -> Result<Vec<Namespace>> {
     let file = self
       .expect(TokenKind::NamespaceKeyword)?
@@ -123,6 +296,7 @@ impl Parser {
       .clone();
     let name = self.expect(TokenKind::Identifier)?;
     validate(name.get_value(), &name.location, NameKind::Namespace)?;
+    check_reserved(name.get_value(), &name.location, NameKind::Namespace)?;
     let name = name.get_value().clone();
 
     if self.current().kind == TokenKind::LeftBrace {
@@ -132,18 +306,22 @@ impl Parser {
 
     let mut items = Vec::new();
     while !self.is_namespace_end(&file) {
+      let start = self.position;
       if self.current().kind == TokenKind::NamespaceKeyword {
         namespaces.extend(self.parse_namespace()?);
-      } else {
-        items.push(self.parse_item()?);
+      } else if let Some(item) = self.parse_item_or_skip() {
+        items.push(item);
       }
+      self.check_progress(start)?;
     }
 
     if self.is(&[TokenKind::EndOfInclude]) {
       self.consume_including(&[TokenKind::EndOfInclude]);
     }
 
-    namespaces.push(Namespace { items, name });
+    // The namespaces after this one were parsed first, but this one was
+    // declared before them
+    namespaces.insert(0, Namespace { items, name });
 
     Ok(namespaces)
   }
@@ -167,23 +345,85 @@ impl Parser {
     self.expect(TokenKind::LeftBrace)?;
 
     let mut items = Vec::new();
-    while self.current().kind != TokenKind::RightBrace {
-      items.push(self.parse_item()?);
+    while !self.eof() && self.current().kind != TokenKind::RightBrace {
+      let start = self.position;
+      if let Some(item) = self.parse_item_or_skip() {
+        items.push(item);
+      }
+      self.check_progress(start)?;
     }
     self.expect(TokenKind::RightBrace)?;
 
     Ok(Namespace { name, items })
   }
 
+  // When an item has an error, it is recorded and the rest of the item is
+  // skipped, so the items after it are still parsed
+  fn parse_item_or_skip(&mut self) -> Option<Item> {
+    let start = self.position;
+    match self.parse_item() {
+      Ok(item) => Some(item),
+      Err(e) => {
+        self.errors.push(e);
+        self.skip_item(start);
+        None
+      }
+    }
+  }
+
+  // Skips to the next item keyword or closing brace outside of any braces the
+  // item opens
+  fn skip_item(&mut self, start: usize) {
+    self.position = start;
+    self.consume();
+    let mut depth = 0;
+    loop {
+      match self.current_including(&[TokenKind::EndOfInclude]).kind {
+        TokenKind::EndOfFile | TokenKind::EndOfInclude => return,
+        TokenKind::LeftBrace => depth += 1,
+        TokenKind::RightBrace if depth == 0 => return,
+        TokenKind::RightBrace => depth -= 1,
+        TokenKind::FunctionKeyword
+        | TokenKind::ModuleKeyword
+        | TokenKind::ImportKeyword
+        | TokenKind::ResourceKeyword
+        | TokenKind::AssetKeyword
+        | TokenKind::NamespaceKeyword
+          if depth == 0 =>
+        {
+          return
+        }
+        _ => {}
+      }
+      self.consume();
+    }
+  }
+
   fn parse_item(&mut self) -> Result<Item> {
     Ok(match self.current().kind {
       TokenKind::ModuleKeyword => Item::Module(self.parse_module()?),
       TokenKind::ImportKeyword => Item::Import(self.parse_import()?),
+      TokenKind::Identifier
+        if self.current().get_value() == "global"
+          && self.peek(1).kind == TokenKind::ImportKeyword =>
+      {
+        self.consume();
+        Item::Import(Import {
+          is_global: true,
+          ..self.parse_import()?
+        })
+      }
       TokenKind::ResourceKeyword | TokenKind::AssetKeyword => {
         Item::Resource(self.parse_resource()?)
       }
       TokenKind::FunctionKeyword => self.parse_function()?,
       TokenKind::Ampersand => self.parse_comptime_assignment()?,
+      TokenKind::BuiltinName => {
+        let token = self.consume().clone();
+        self.expect(TokenKind::LeftParen)?;
+        let arguments = self.parse_list(TokenKind::RightParen, Parser::parse_expression)?;
+        Item::BuiltinFunction(token.get_value().clone(), arguments, token.location)
+      }
       _ => {
         return Err(raise_error(
           self.current().location.clone(),
@@ -195,30 +435,85 @@ impl Parser {
 
   fn parse_comptime_assignment(&mut self) -> Result<Item> {
     self.consume();
+    let name_token = self.expect(TokenKind::Identifier)?;
+    let name = name_token.get_value().clone();
+    let location = name_token.location.clone();
+    self.expect(TokenKind::Equals)?;
+    let value = self.parse_expression()?;
+    Ok(Item::ComptimeAssignment(name, value, location))
+  }
+
+  fn parse_local_comptime(&mut self) -> Result<Statement> {
+    self.consume();
+    self.expect(TokenKind::Ampersand)?;
     let name = self.expect(TokenKind::Identifier)?.get_value().clone();
     self.expect(TokenKind::Equals)?;
     let value = self.parse_expression()?;
-    Ok(Item::ComptimeAssignment(name, value))
+    Ok(Statement::LocalComptime(name, value))
   }
 
   fn parse_module(&mut self) -> Result<Module> {
+    let is_test_only = self.has_attribute("test_only");
     self.expect(TokenKind::ModuleKeyword)?;
-    let name = self.expect(TokenKind::Identifier)?;
-    validate(name.get_value(), &name.location, NameKind::Module)?;
-    let name = name.get_value().clone();
+    let token = self.expect(TokenKind::Identifier)?.clone();
+    let name = self.fix_name(token.get_value().clone(), &token.location, NameKind::Module)?;
+    check_reserved(&name, &token.location, NameKind::Module)?;
+    if GENERATED_MODULES.contains(&name.as_str()) {
+      self.warnings.push(raise_warning(
+        token.location.clone(),
+        format!(
+          "The compiler also generates code in modules named `{name}`, which may be confusing."
+        ),
+      ));
+    }
+    let wraps = self.parse_module_wrapper()?;
     self.expect(TokenKind::LeftBrace)?;
 
     let mut items = Vec::new();
-    while self.current().kind != TokenKind::RightBrace {
-      items.push(self.parse_item()?);
+    while !self.eof() && self.current().kind != TokenKind::RightBrace {
+      let start = self.position;
+      if let Some(item) = self.parse_item_or_skip() {
+        items.push(item);
+      }
+      self.check_progress(start)?;
     }
     self.expect(TokenKind::RightBrace)?;
 
-    Ok(Module { name, items })
+    Ok(Module {
+      name,
+      items,
+      is_test_only,
+      wraps,
+    })
+  }
+
+  // The `wraps "as @e[tag=ui] at @s"` after a module's name
+  fn parse_module_wrapper(&mut self) -> Result<Option<(EcoString, Location)>> {
+    if !(self.current().kind == TokenKind::Identifier && self.current().get_value() == "wraps") {
+      return Ok(None);
+    }
+    self.consume();
+    self.parse_execute_subcommands().map(Some)
+  }
+
+  // A string of `execute` subcommands, such as `"as @a at @s"`
+  fn parse_execute_subcommands(&mut self) -> Result<(EcoString, Location)> {
+    let token = self.expect(TokenKind::String)?.clone();
+    let fragment: EcoString = token.get_value().trim().into();
+    if fragment.is_empty()
+      || fragment.starts_with("execute ")
+      || fragment.split(' ').any(|word| word == "run")
+    {
+      return Err(raise_error(
+        token.location,
+        "Expected the subcommands of an `execute` command, such as `as @a at @s`, without `execute` or `run`.",
+      ));
+    }
+    Ok((fragment, token.location))
   }
 
   fn parse_import(&mut self) -> Result<Import> {
-    self.expect(TokenKind::ImportKeyword)?;
+    let location = self.expect(TokenKind::ImportKeyword)?.location.clone();
     let path = self.parse_import_resource()?;
     let mut alias = None;
     if self.current().kind == TokenKind::AsKeyword {
@@ -228,7 +523,12 @@ impl Parser {
       // catch that here
       alias = Some(self.expect(TokenKind::Identifier)?.get_value().clone());
     }
-    Ok(Import { path, alias })
+    Ok(Import {
+      path,
+      alias,
+      location,
+      is_global: false,
+    })
   }
 
   fn parse_resource(&mut self) -> Result<Resource> {
@@ -240,12 +540,33 @@ impl Parser {
       let name = self.consume();
       validate(name.get_value(), &name.location, NameKind::Resource)?;
       let name = name.get_value().clone();
-      let token = self.expect(TokenKind::Json)?;
 
-      ResourceContent::Text(
-        name,
-        json5_to_json(token.get_value(), token.location.clone())?,
-      )
+      if let TokenKind::JsonTemplateBegin(_) = self.current().kind {
+        let location = self.consume().location.clone();
+        let name = Expression::String(name, location);
+        ResourceContent::Template(name, self.parse_json_template()?)
+      } else {
+        let token = self.expect(TokenKind::Json)?;
+        ResourceContent::Text(
+          name,
+          json5_to_json(token.get_value(), token.location.clone())?,
+        )
+      }
+    } else if self.current().kind == TokenKind::Ampersand {
+      self.consume();
+      self.expect(TokenKind::LeftBrace)?;
+      let name = self.parse_expression()?;
+      self.expect(TokenKind::RightBrace)?;
+
+      let body = if let TokenKind::JsonTemplateBegin(_) = self.current().kind {
+        self.consume();
+        self.parse_json_template()?
+      } else {
+        vec![TemplatePart::Literal(
+          self.expect(TokenKind::Json)?.get_value().clone(),
+        )]
+      };
+      ResourceContent::Template(name, body)
     } else {
       let token = self.expect(TokenKind::String)?;
       let (base_path, path) = if token.get_value().starts_with('/') {
@@ -264,11 +585,89 @@ impl Parser {
     })
   }
 
+  // The component replaces the whole string it is in, including the quotes
+  fn parse_text_component(
+    &mut self,
+    parts: &mut [TemplatePart],
+    quote: char,
+  ) -> Result<(TemplatePart, EcoString)> {
+    let keyword = self.consume().clone();
+    let kind = match keyword.get_value().as_str() {
+      "score" => TextComponentKind::Score,
+      _ => TextComponentKind::Nbt,
+    };
+    let error = || {
+      raise_error(
+        keyword.location.clone(),
+        format!(
+          "`{}` components must be the whole string, like `\"&{{{} ...}}\"`.",
+          keyword.get_value(),
+          keyword.get_value()
+        ),
+      )
+    };
+
+    match parts.last_mut() {
+      Some(TemplatePart::Literal(text)) if text.ends_with(quote) => {
+        text.pop();
+      }
+      _ => return Err(error()),
+    }
+    let expression = self.parse_expression()?;
+    if self.current().kind != TokenKind::CommandString {
+      return Err(error());
+    }
+    let text = self.consume().get_value().clone();
+    let Some(rest) = text.strip_prefix(quote) else {
+      return Err(error());
+    };
+
+    Ok((TemplatePart::TextComponent(kind, expression), rest.into()))
+  }
+
+  fn parse_json_template(&mut self) -> Result<Vec<TemplatePart>> {
+    let mut parts = Vec::new();
+    let mut string_char = None;
+
+    while self.current().kind != TokenKind::CommandEnd {
+      let start = self.position;
+      match self.current().kind {
+        TokenKind::CommandString => {
+          let text = self.consume().get_value().clone();
+          string_char = json_string_state(&text, string_char);
+          parts.push(TemplatePart::Literal(text));
+        }
+        TokenKind::Identifier
+          if string_char.is_some()
+            && matches!(self.current().get_value().as_str(), "score" | "nbt")
+            && self.peek(1).kind != TokenKind::CommandString =>
+        {
+          let quote = string_char.expect("Is inside a string");
+          let (component, rest) = self.parse_text_component(&mut parts, quote)?;
+          string_char = json_string_state(&rest, None);
+          parts.push(component);
+          parts.push(TemplatePart::Literal(rest));
+        }
+        _ => parts.push(TemplatePart::Expression(
+          self.parse_expression()?,
+          string_char,
+        )),
+      }
+      self.check_progress(start)?;
+    }
+
+    self.consume();
+
+    Ok(parts)
+  }
+
   fn parse_block(&mut self) -> Result<Vec<Statement>> {
     self.expect(TokenKind::LeftBrace)?;
     let mut items = Vec::new();
     while self.current().kind != TokenKind::RightBrace {
+      let start = self.position;
       items.push(self.parse_statement()?);
+      self.check_progress(start)?;
     }
     self.expect(TokenKind::RightBrace)?;
     Ok(items)
@@ -311,13 +710,17 @@ impl Parser {
         self.consume();
         ParameterKind::Macro
       }
-      TokenKind::Ampersand => todo!(),
+      TokenKind::Ampersand => {
+        self.consume();
+        ParameterKind::CompileTime
+      }
       _ => ParameterKind::Storage,
     };
     let token = self.expect(TokenKind::Identifier)?.clone();
     let name = token.get_value().clone();
     let location = token.location;
     validate(&name, &location, NameKind::Parameter(kind))?;
+    check_reserved(&name, &location, NameKind::Parameter(kind))?;
 
     let default = if self.current().kind == TokenKind::Equals {
       self.consume();
@@ -364,6 +767,12 @@ impl Parser {
   }
 
   fn parse_function(&mut self) -> Result<Item> {
+    let is_pure = self.has_attribute("pure");
+    let is_test = self.has_attribute("test");
+    let is_test_only = self.has_attribute("test_only");
+    let must_use = self.has_attribute("must_use");
+    let renamed_from = self.attribute_arguments("renamed_from");
+    let tags = self.attribute_arguments("tag");
     self.expect(TokenKind::FunctionKeyword)?;
 
     let return_type = match self.current().kind {
@@ -379,13 +788,22 @@ impl Parser {
         self.consume();
         return self.parse_comptime_function();
       }
-      _ => ReturnType::Storage,
+      TokenKind::Tilde => {
+        self.consume();
+        ReturnType::Storage
+      }
+      _ if self.require_explicit_return_type => {
+        return Err(raise_error(
+          self.current().location.clone(),
+          "This project requires functions to choose how they return: `fn ~name` returns in storage, `fn %name` directly and `fn $name` in a scoreboard.",
+        ));
+      }
+      _ => self.default_return,
     };
 
     let token = self.expect(TokenKind::Identifier)?.clone();
-    let name = token.get_value().clone();
-    let location = token.location;
-    validate(&name, &location, NameKind::Function)?;
+    let location = token.location.clone();
+    let name = self.fix_name(token.take_value(), &location, NameKind::Function)?;
 
     self.expect(TokenKind::LeftParen)?;
 
@@ -403,6 +821,19 @@ impl Parser {
       }
     }
 
+    let mut advancement = None;
+    if self.current().kind == TokenKind::Identifier && self.current().get_value() == "on" {
+      self.consume();
+      let token = self.expect(TokenKind::Identifier)?;
+      if token.get_value() != "advancement" {
+        return Err(raise_error(
+          token.location.clone(),
+          "Expected `advancement`.",
+        ));
+      }
+      advancement = Some(self.parse_expression()?);
+    }
+
     let items = self.parse_block()?;
 
     Ok(Item::Function(Function {
@@ -411,9 +842,53 @@ impl Parser {
       location,
       parameters,
       items,
+      is_pure,
+      is_test,
+      is_test_only,
+      must_use,
+      renamed_from,
+      tags,
+      advancement,
     }))
   }
 
+  // Attributes are comments such as `#[pure]`, directly before an item
+  fn has_attribute(&self, name: &str) -> bool {
+    let attribute = eco_format!("#[{name}]");
+    let mut offset = 0;
+    while self.should_skip(offset, &[]) {
+      let token = &self.tokens[self.position + offset];
+      if token.kind == TokenKind::Comment && token.get_value().trim() == attribute {
+        return true;
+      }
+      offset += 1;
+    }
+    false
+  }
+
+  // The string arguments of each attribute like `#[name("argument")]`
+  // before an item
+  fn attribute_arguments(&self, name: &str) -> Vec<(EcoString, Location)> {
+    let prefix = eco_format!("#[{name}(\"");
+    let mut arguments = Vec::new();
+    let mut offset = 0;
+    while self.should_skip(offset, &[]) {
+      let token = &self.tokens[self.position + offset];
+      if token.kind == TokenKind::Comment {
+        let argument = token
+          .get_value()
+          .trim()
+          .strip_prefix(prefix.as_str())
+          .and_then(|rest| rest.strip_suffix("\")]"));
+        if let Some(argument) = argument {
+          arguments.push((argument.into(), token.location.clone()));
+        }
+      }
+      offset += 1;
+    }
+    arguments
+  }
+
   // Expects `fn &` already to be consumed
   fn parse_comptime_function(&mut self) -> Result<Item> {
     let name = self.expect(TokenKind::Identifier)?.get_value().clone();
@@ -443,13 +918,32 @@ impl Parser {
       }
       TokenKind::IfKeyword => Statement::If(self.parse_if_statement()?),
       TokenKind::WhileKeyword => Statement::WhileLoop(self.parse_while_loop()?),
+      TokenKind::ForKeyword => Statement::ForLoop(Box::new(self.parse_for_loop()?)),
       TokenKind::ReturnKeyword => Statement::Return(self.parse_return()?),
+      TokenKind::Identifier
+        if self.current().get_value() == "execute" && self.peek(1).kind == TokenKind::String =>
+      {
+        Statement::Execute(self.parse_execute_block()?)
+      }
+      TokenKind::Identifier
+        if self.current().get_value() == "local" && self.peek(1).kind == TokenKind::Ampersand =>
+      {
+        self.parse_local_comptime()?
+      }
+      TokenKind::Identifier
+        if self.current().get_value() == "_" && self.peek(1).kind == TokenKind::Equals =>
+      {
+        self.consume();
+        self.consume();
+        Statement::Discard(self.parse_expression()?)
+      }
       _ => Statement::Expression(self.parse_expression()?),
     })
   }
 
   fn parse_command(&mut self) -> Result<Command> {
     let next = self.consume();
+    let location = next.location.clone();
     if next.kind != TokenKind::CommandBegin(true) && next.kind != TokenKind::CommandBegin(false) {
       return Err(raise_error(
         next.location.clone(),
@@ -457,20 +951,40 @@ impl Parser {
       ));
     }
     
+    let parts = self.parse_command_parts()?;
+    Ok(Command { parts, location })
+  }
+
+  fn parse_command_parts(&mut self) -> Result<Vec<CommandPart>> {
     let mut parts = Vec::new();
 
     while self.current().kind != TokenKind::CommandEnd {
+      let start = self.position;
       match self.current().kind {
         TokenKind::CommandString => {
           parts.push(CommandPart::Literal(self.consume().get_value().clone()))
         }
         _ => parts.push(CommandPart::Expression(self.parse_static_expr()?)),
       }
+      self.check_progress(start)?;
     }
 
     self.consume();
 
-    Ok(Command { parts })
+    Ok(parts)
+  }
+
+  fn parse_data_target(&mut self) -> Result<Expression> {
+    let token = self.expect(TokenKind::DataTargetBegin)?;
+    let location = token.location.clone();
+    let kind = if token.get_value() == "entity" {
+      DataKind::Entity
+    } else {
+      DataKind::Block
+    };
+
+    let parts = self.parse_command_parts()?;
+    Ok(Expression::DataTarget(kind, parts, location))
   }
 
   fn parse_return(&mut self) -> Result<Option<Expression>> {
@@ -510,8 +1024,10 @@ impl Parser {
             )
           })?;
 
-          raise_warning(current.location.clone(), format!("Value {} is too large for an int, automatically converting to a long. If this is intentional, suffix it with 'l'.", current.get_value()));
-          Expression::Long(value, current.location.clone())
+          let warning = raise_warning(current.location.clone(), format!("Value {} is too large for an int, automatically converting to a long. If this is intentional, suffix it with 'l'.", current.get_value()));
+          let location = current.location.clone();
+          self.warnings.push(warning);
+          Expression::Long(value, location)
         }
       },
       TokenKind::Long => {
@@ -592,8 +1108,10 @@ impl Parser {
 
     let mut expressions = Vec::new();
     while !self.eof() && self.current().kind != TokenKind::RightSquare {
+      let start = self.position;
       let expression = self.parse_expression()?;
       expressions.push(expression);
+      self.check_progress(start)?;
 
       if self.current().kind == TokenKind::Comma {
         self.consume();
@@ -610,8 +1128,17 @@ impl Parser {
     let location = self.expect(TokenKind::LeftBrace)?.location.clone();
     let mut key_values = Vec::new();
 
-    while !self.eof() && self.current().kind != TokenKind::LeftBrace {
-      let token = self.expect(TokenKind::Identifier)?.clone();
+    while !self.eof() && self.current().kind != TokenKind::RightBrace {
+      let token = self.consume().clone();
+      if !matches!(
+        token.kind,
+        TokenKind::Identifier | TokenKind::Integer | TokenKind::String
+      ) {
+        return Err(raise_error(
+          token.location,
+          format!("Expected a compound key, got {:?}", token.kind),
+        ));
+      }
       let key = token.get_value().clone();
       let location = token.location;
 
@@ -638,15 +1165,20 @@ impl Parser {
   }
 
   fn parse_identifier(&mut self) -> Result<Expression> {
-    let resource = self.parse_zoglin_resource(NameKind::Unknown)?;
+    let mut resource = self.parse_zoglin_resource(NameKind::Unknown)?;
     if self.current().kind == TokenKind::LeftParen {
-      validate(&resource.name, &resource.location, NameKind::Function)?;
+      resource.name = self.fix_name(resource.name, &resource.location, NameKind::Function)?;
       Ok(Expression::FunctionCall(
         self.parse_function_call(resource, false)?,
       ))
     } else {
       let mut resource = resource;
 
+      check_reserved(
+        &resource.name,
+        &resource.location,
+        NameKind::StorageVariable,
+      )?;
       resource.name =
         validate_or_quote(resource.name, &resource.location, NameKind::StorageVariable);
       Ok(Expression::Variable(resource))
@@ -654,7 +1186,17 @@ impl Parser {
   }
 
   fn parse_scoreboard_variable(&mut self) -> Result<Expression> {
-    self.expect(TokenKind::Dollar)?;
+    let location = self.expect(TokenKind::Dollar)?.location.clone();
+
+    // `$return`, or a `$` on its own, refers to the current function's return value
+    match self.current().kind {
+      TokenKind::ReturnKeyword => {
+        self.consume();
+        return Ok(Expression::ReturnSlot(location));
+      }
+      TokenKind::Identifier | TokenKind::Colon | TokenKind::Tilde | TokenKind::LeftSquare => {}
+      _ => return Ok(Expression::ReturnSlot(location)),
+    }
 
     let mut resource: ZoglinResource;
 
@@ -687,6 +1229,9 @@ impl Parser {
     let mut output = EcoString::new();
 
     while square_count > 0 || self.current().kind != TokenKind::RightSquare {
+      if self.eof() {
+        break;
+      }
       if self.current().kind == TokenKind::LeftSquare {
         square_count += 1;
       }
@@ -704,17 +1249,34 @@ impl Parser {
 
   fn parse_static_expr(&mut self) -> Result<StaticExpr> {
     match self.current().kind {
+      TokenKind::Identifier
+        if self.current().get_value() == "nbt"
+          && !matches!(
+            self.peek(1).kind,
+            TokenKind::Colon
+              | TokenKind::ForwardSlash
+              | TokenKind::LeftParen
+              | TokenKind::CommandString
+              | TokenKind::CommandEnd
+          ) =>
+      {
+        self.consume();
+        Ok(StaticExpr::Nbt(Box::new(self.parse_static_expr()?)))
+      }
       TokenKind::Percent => {
         self.consume();
         let name = self.expect(TokenKind::Identifier)?;
         validate(name.get_value(), &name.location, NameKind::MacroVariable)?;
         Ok(StaticExpr::MacroVariable(name.get_value().clone()))
       }
-      TokenKind::Ampersand => match self.parse_comptime_variable()? {
+      TokenKind::Ampersand => match self.parse_expression()? {
         Expression::FunctionCall(call) => Ok(StaticExpr::FunctionCall(call)),
-        Expression::ComptimeVariable(name, _) => Ok(StaticExpr::ComptimeVariable(name)),
-        _ => unreachable!(),
+        Expression::ComptimeVariable(name, location) => {
+          Ok(StaticExpr::ComptimeVariable(name, location))
+        }
+        expression => Ok(StaticExpr::Expression(expression)),
       },
+      TokenKind::BuiltinName => Ok(StaticExpr::Expression(self.parse_expression()?)),
       TokenKind::FunctionKeyword => {
         self.consume();
         let path = match self.current().kind {
@@ -723,7 +1285,7 @@ impl Parser {
         };
         Ok(StaticExpr::FunctionRef { path })
       }
-      _ => {
+      TokenKind::Identifier | TokenKind::Colon | TokenKind::Tilde => {
         let resource = self.parse_zoglin_resource(NameKind::Resource)?;
         if self.current().kind == TokenKind::LeftParen {
           return Ok(StaticExpr::FunctionCall(
@@ -732,6 +1294,7 @@ impl Parser {
         }
         Ok(StaticExpr::ResourceRef { resource })
       }
+      _ => Ok(StaticExpr::Expression(self.parse_expression()?)),
     }
   }
 
@@ -748,7 +1311,12 @@ impl Parser {
 
   fn parse_if_statement(&mut self) -> Result<IfStatement> {
     self.consume();
-    let condition = self.parse_expression()?;
+    let binding = self.parse_if_binding()?;
+    let condition = if binding.is_some() {
+      self.parse_expression()?
+    } else {
+      self.parse_condition()?
+    };
     let block = self.parse_block()?;
 
     let mut child = None;
@@ -767,19 +1335,119 @@ impl Parser {
 
     Ok(IfStatement {
       condition,
+      binding,
       block,
       child,
     })
   }
 
+  // The `let name =` of `if let name = value`
+  fn parse_if_binding(&mut self) -> Result<Option<(EcoString, Location)>> {
+    if !(self.current().kind == TokenKind::Identifier
+      && self.current().get_value() == "let"
+      && self.peek(1).kind == TokenKind::Identifier
+      && self.peek(2).kind == TokenKind::Equals)
+    {
+      return Ok(None);
+    }
+    self.consume();
+    let token = self.consume().clone();
+    validate(
+      token.get_value(),
+      &token.location,
+      NameKind::StorageVariable,
+    )?;
+    self.expect(TokenKind::Equals)?;
+    Ok(Some((token.get_value().clone(), token.location)))
+  }
+
+  fn parse_execute_block(&mut self) -> Result<ExecuteBlock> {
+    self.consume();
+    let (subcommands, location) = self.parse_execute_subcommands()?;
+    let block = self.parse_block()?;
+    Ok(ExecuteBlock {
+      subcommands,
+      location,
+      block,
+    })
+  }
+
   fn parse_while_loop(&mut self) -> Result<WhileLoop> {
     self.consume();
-    let condition = self.parse_expression()?;
+    let condition = self.parse_condition()?;
     let block = self.parse_block()?;
 
     Ok(WhileLoop { condition, block })
   }
 
+  fn parse_for_loop(&mut self) -> Result<ForLoop> {
+    self.consume();
+    let variable = match self.current().kind {
+      TokenKind::Identifier => self.parse_identifier()?,
+      TokenKind::Dollar => self.parse_scoreboard_variable()?,
+      TokenKind::Ampersand => self.parse_comptime_variable()?,
+      _ => {
+        return Err(raise_error(
+          self.current().location.clone(),
+          "Expected a loop variable.",
+        ))
+      }
+    };
+    if !matches!(
+      variable,
+      Expression::Variable(_)
+        | Expression::ScoreboardVariable(_)
+        | Expression::ComptimeVariable(..)
+    ) {
+      return Err(raise_error(
+        variable.location(),
+        "The loop variable must be a storage, scoreboard or compile-time variable.",
+      ));
+    }
+
+    let token = self.expect(TokenKind::Identifier)?;
+    if token.get_value() != "in" {
+      return Err(raise_error(token.location.clone(), "Expected `in`."));
+    }
+
+    let mut start = None;
+    let mut end = self.parse_expression()?;
+    if self.current().kind == TokenKind::DoubleDot {
+      self.consume();
+      start = Some(end);
+      end = self.parse_expression()?;
+    }
+    let block = self.parse_block()?;
+
+    Ok(ForLoop {
+      variable,
+      start,
+      end,
+      block,
+    })
+  }
+
+  fn parse_condition(&mut self) -> Result<Expression> {
+    let condition = self.parse_expression()?;
+    // Assigning in a condition is allowed, but must be wrapped in parentheses to show it is intended
+    if let Expression::BinaryOperation(BinaryOperation {
+      operator,
+      parenthesized: false,
+      location,
+      ..
+    }) = &condition
+    {
+      if !operator.is_assignment() {
+        return Ok(condition);
+      }
+      self.warnings.push(raise_warning(
+        location.clone(),
+        "Assignment used as a condition. Did you mean `==`?",
+      ));
+    }
+    Ok(condition)
+  }
+
   fn parse_list<T>(
     &mut self,
     delimiter: TokenKind,
@@ -787,7 +1455,9 @@ impl Parser {
   ) -> Result<Vec<T>> {
     let mut list = Vec::new();
     while self.current().kind != delimiter {
+      let start = self.position;
       list.push(parse_fn(self)?);
+      self.check_progress(start)?;
 
       if self.current().kind == TokenKind::Comma {
         self.consume();
@@ -7,6 +7,8 @@ use crate::error::Location;
 #[derive(Debug)]
 pub struct File {
   pub items: Vec<Namespace>,
+  // Set by `#!debug` at the start of the file
+  pub debug: bool,
 }
 
 #[derive(Debug)]
@@ -23,7 +25,8 @@ pub enum Item {
   Function(Function),
   ComptimeFunction(ComptimeFunction),
   Resource(Resource),
-  ComptimeAssignment(EcoString, Expression),
+  ComptimeAssignment(EcoString, Expression, Location),
+  BuiltinFunction(EcoString, Vec<Expression>, Location),
 }
 
 impl Item {
@@ -36,12 +39,21 @@ impl Item {
 pub struct Module {
   pub name: EcoString,
   pub items: Vec<Item>,
+  // From `#[test_only]`, which leaves the module out of release builds
+  pub is_test_only: bool,
+  // From `module name wraps "..."`, the `execute` subcommands which calls from
+  // outside the module run its functions with
+  pub wraps: Option<(EcoString, Location)>,
 }
 
 #[derive(Debug)]
 pub struct Import {
   pub path: ImportPath,
   pub alias: Option<EcoString>,
+  pub location: Location,
+  // From `global import`, which makes the import visible to the whole
+  // namespace, wherever it appears
+  pub is_global: bool,
 }
 
 #[derive(Debug)]
@@ -55,9 +67,25 @@ pub struct Resource {
 #[derive(Debug)]
 pub enum ResourceContent {
   Text(EcoString, EcoString),
+  Template(Expression, Vec<TemplatePart>),
   File(EcoString, EcoString),
 }
 
+#[derive(Debug)]
+pub enum TemplatePart {
+  Literal(EcoString),
+  // The quote character is set when the expression is inside a string
+  Expression(Expression, Option<char>),
+  // `"&{score ...}"` or `"&{nbt ...}"`, which replaces the string with a text component
+  TextComponent(TextComponentKind, Expression),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TextComponentKind {
+  Score,
+  Nbt,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ParameterKind {
   Storage,
@@ -74,13 +102,26 @@ pub struct Parameter {
   pub default: Option<Expression>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Function {
   pub location: Location,
   pub return_type: ReturnType,
   pub name: EcoString,
   pub parameters: Vec<Parameter>,
   pub items: Vec<Statement>,
+  pub is_pure: bool,
+  pub is_test: bool,
+  // From `#[test_only]`, which leaves the function out of release builds
+  pub is_test_only: bool,
+  // From `#[must_use]`, which warns when a call's result is ignored
+  pub must_use: bool,
+  // Old paths from `#[renamed_from("...")]`, which get a forwarding function
+  pub renamed_from: Vec<(EcoString, Location)>,
+  // Function tags from `#[tag("...")]`, which the function is added to
+  pub tags: Vec<(EcoString, Location)>,
+  // The advancement from `on advancement {...}`, which runs this function
+  // when granted
+  pub advancement: Option<Expression>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -102,14 +143,22 @@ pub enum Statement {
   Command(Command),
   Comment(EcoString),
   Expression(Expression),
+  // `_ = value`, which explicitly ignores the result
+  Discard(Expression),
   If(IfStatement),
   WhileLoop(WhileLoop),
+  ForLoop(Box<ForLoop>),
+  Execute(ExecuteBlock),
   Return(Option<Expression>),
+  // `local &name = value`, which shadows a compile-time variable for the
+  // rest of the block
+  LocalComptime(EcoString, Expression),
 }
 
 #[derive(Debug, Clone)]
 pub struct Command {
   pub parts: Vec<CommandPart>,
+  pub location: Location,
 }
 
 #[derive(Debug, Clone)]
@@ -121,10 +170,13 @@ pub enum CommandPart {
 #[derive(Debug, Clone)]
 pub enum StaticExpr {
   MacroVariable(EcoString),
-  ComptimeVariable(EcoString),
+  ComptimeVariable(EcoString, Location),
   FunctionCall(FunctionCall),
   ResourceRef { resource: ZoglinResource },
   FunctionRef { path: Option<ZoglinResource> },
+  Expression(Expression),
+  // `&{nbt ...}`, which writes the value as SNBT
+  Nbt(Box<StaticExpr>),
 }
 
 #[derive(Debug, Clone)]
@@ -142,8 +194,12 @@ pub enum Expression {
   Compound(Vec<KeyValue>, Location),
   BuiltinVariable(EcoString, Location),
   BuiltinFunction(EcoString, Vec<Expression>, Location),
+  // `@dump`, with the source text of each argument
+  Dump(Vec<(EcoString, Expression)>, Location),
   Variable(ZoglinResource),
   ScoreboardVariable(ZoglinResource),
+  ReturnSlot(Location),
+  DataTarget(DataKind, Vec<CommandPart>, Location),
   MacroVariable(EcoString, Location),
   ComptimeVariable(EcoString, Location),
   BinaryOperation(BinaryOperation),
@@ -173,7 +229,10 @@ impl Expression {
       | Expression::Variable(ZoglinResource { location, .. })
       | Expression::BuiltinVariable(_, location)
       | Expression::BuiltinFunction(_, _, location)
+      | Expression::Dump(_, location)
       | Expression::ScoreboardVariable(ZoglinResource { location, .. })
+      | Expression::ReturnSlot(location)
+      | Expression::DataTarget(_, _, location)
       | Expression::MacroVariable(_, location)
       | Expression::ComptimeVariable(_, location)
       | Expression::BinaryOperation(BinaryOperation { location, .. })
@@ -183,6 +242,57 @@ impl Expression {
       Expression::Member(member) => member.left.location(),
     }
   }
+
+  // Whether evaluating the expression could change the value of another one,
+  // such as by calling a function or assigning to a variable
+  pub fn has_side_effects(&self) -> bool {
+    let is_literal = |expression: &Expression| matches!(expression, Expression::Integer(..));
+    match self {
+      Expression::FunctionCall(_) | Expression::BuiltinFunction(..) => true,
+      Expression::Boolean(..)
+      | Expression::Byte(..)
+      | Expression::Short(..)
+      | Expression::Integer(..)
+      | Expression::Long(..)
+      | Expression::Float(..)
+      | Expression::Double(..)
+      | Expression::String(..)
+      | Expression::BuiltinVariable(..)
+      | Expression::Dump(..)
+      | Expression::Variable(_)
+      | Expression::ScoreboardVariable(_)
+      | Expression::ReturnSlot(_)
+      | Expression::DataTarget(..)
+      | Expression::MacroVariable(..)
+      | Expression::ComptimeVariable(..) => false,
+      Expression::Array(_, values, _) => values.iter().any(Expression::has_side_effects),
+      Expression::Compound(key_values, _) => key_values
+        .iter()
+        .any(|key_value| key_value.value.has_side_effects()),
+      Expression::BinaryOperation(operation) => {
+        operation.operator.is_assignment()
+          || operation.left.has_side_effects()
+          || operation.right.has_side_effects()
+      }
+      Expression::UnaryOperation(operation) => operation.operand.has_side_effects(),
+      // Indexes that aren't literals may use a helper function, which overwrites its previous result
+      Expression::Index(index) => !is_literal(&index.index) || index.left.has_side_effects(),
+      Expression::RangeIndex(index) => {
+        !index.start.as_deref().is_none_or(is_literal)
+          || !index.end.as_deref().is_none_or(is_literal)
+          || index.left.has_side_effects()
+      }
+      Expression::Member(member) => {
+        matches!(*member.member, MemberKind::Dynamic(_)) || member.left.has_side_effects()
+      }
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum DataKind {
+  Entity,
+  Block,
 }
 
 #[derive(Debug, Clone)]
@@ -253,6 +363,7 @@ pub struct BinaryOperation {
   pub left: Box<Expression>,
   pub right: Box<Expression>,
   pub operator: Operator,
+  pub parenthesized: bool,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -281,6 +392,30 @@ pub enum Operator {
   ModAssign,
 }
 
+impl Operator {
+  pub fn is_assignment(self) -> bool {
+    matches!(
+      self,
+      Operator::Assign
+        | Operator::AddAssign
+        | Operator::SubAssign
+        | Operator::MulAssign
+        | Operator::DivAssign
+        | Operator::ModAssign
+    )
+  }
+
+  pub fn is_comparison(self) -> bool {
+    matches!(
+      self,
+      Operator::LessThan
+        | Operator::GreaterThan
+        | Operator::LessThanEquals
+        | Operator::GreaterThanEquals
+    )
+  }
+}
+
 #[derive(Debug, Clone)]
 pub struct UnaryExpression {
   pub location: Location,
@@ -297,6 +432,9 @@ pub enum UnaryOperator {
 #[derive(Debug, Clone)]
 pub struct IfStatement {
   pub condition: Expression,
+  // The variable from `if let name = value`, which is set to the value if it
+  // exists. The condition is then the value to check
+  pub binding: Option<(EcoString, Location)>,
   pub block: Vec<Statement>,
   pub child: Option<ElseStatement>,
 }
@@ -307,6 +445,25 @@ pub struct WhileLoop {
   pub block: Vec<Statement>,
 }
 
+// `execute "as @a at @s" { ... }`, which runs the block with the subcommands
+#[derive(Debug, Clone)]
+pub struct ExecuteBlock {
+  pub subcommands: EcoString,
+  pub location: Location,
+  pub block: Vec<Statement>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ForLoop {
+  // A storage, scoreboard or compile-time variable
+  pub variable: Expression,
+  // Only set for `start..end` ranges. Otherwise, `end` is either a number of
+  // iterations or an array to loop over
+  pub start: Option<Expression>,
+  pub end: Expression,
+  pub block: Vec<Statement>,
+}
+
 #[derive(Debug, Clone)]
 pub enum ElseStatement {
   IfStatement(Box<IfStatement>),
@@ -1,6 +1,6 @@
 use crate::error::Result;
 use crate::lexer::token::Token;
-use crate::parser::name::{validate, NameKind};
+use crate::parser::name::{check_reserved, validate, NameKind};
 use crate::{error::raise_error, lexer::token::TokenKind};
 
 use super::ast::{
@@ -60,7 +60,9 @@ impl Parser {
   fn match_precedence(kind: TokenKind) -> (Precedence, Precedence) {
     use Precedence::*;
     match kind {
-      TokenKind::LeftSquare | TokenKind::Dot => (Postfix, Postfix),
+      TokenKind::LeftSquare | TokenKind::Dot | TokenKind::PlusPlus | TokenKind::MinusMinus => {
+        (Postfix, Postfix)
+      }
       TokenKind::DoubleStar => (Exponentiation, Multiplication),
       TokenKind::ForwardSlash | TokenKind::Star | TokenKind::Percent => {
         (Multiplication, Multiplication)
@@ -94,6 +96,7 @@ impl Parser {
       LeftSquare => Parser::parse_array,
       LeftBrace => Parser::parse_compound,
       Dollar => Parser::parse_scoreboard_variable,
+      DataTargetBegin => Parser::parse_data_target,
       Percent => |parser: &mut Parser| {
         parser.consume();
         let name = parser.expect(TokenKind::Identifier)?.clone();
@@ -111,6 +114,11 @@ impl Parser {
         let location = token.location;
         if parser.current().kind == TokenKind::LeftParen {
           parser.consume();
+          if name == "dump" {
+            let args =
+              parser.parse_list(TokenKind::RightParen, Parser::parse_expression_with_source)?;
+            return Ok(Expression::Dump(args, location));
+          }
           let args = parser.parse_list(TokenKind::RightParen, Parser::parse_expression)?;
           Ok(Expression::BuiltinFunction(name, args, location))
         } else {
@@ -148,6 +156,7 @@ impl Parser {
       | TokenKind::PercentEquals => Parser::parse_binary_operation,
       TokenKind::LeftSquare => Parser::parse_index_expr,
       TokenKind::Dot => Parser::parse_member_expr,
+      TokenKind::PlusPlus | TokenKind::MinusMinus => Parser::parse_increment,
       _ => return None,
     };
     Some(function)
@@ -155,8 +164,11 @@ impl Parser {
 
   pub fn parse_bracketed_expression(&mut self) -> Result<Expression> {
     self.expect(TokenKind::LeftParen)?;
-    let expression = self.parse_expression()?;
+    let mut expression = self.parse_expression()?;
     self.expect(TokenKind::RightParen)?;
+    if let Expression::BinaryOperation(operation) = &mut expression {
+      operation.parenthesized = true;
+    }
     Ok(expression)
   }
 
@@ -193,11 +205,79 @@ impl Parser {
     let operator = Parser::match_operator(kind);
     let precedence = Parser::match_precedence(kind);
     let right = self.parse_sub_expression(precedence.1)?;
+
+    if operator.is_assignment() {
+      if let Some(target) = invalid_assignment_target(&left) {
+        return Err(raise_error(location, format!("Cannot assign to {target}.")));
+      }
+      if let Expression::BinaryOperation(BinaryOperation {
+        operator,
+        parenthesized: false,
+        location,
+        ..
+      }) = &right
+      {
+        if operator.is_assignment() {
+          return Err(raise_error(
+            location.clone(),
+            "Chained assignment is not supported. Assign each variable separately.",
+          ));
+        }
+      }
+    }
+
+    // `a < b < c` would compare the boolean `a < b` with `c`
+    if operator.is_comparison() {
+      if let Expression::BinaryOperation(BinaryOperation {
+        operator: left_operator,
+        parenthesized: false,
+        ..
+      }) = &left
+      {
+        if left_operator.is_comparison() {
+          return Err(raise_error(
+            location,
+            "Chained comparisons are not supported. Compare each pair separately, like `a < b && b < c`.",
+          ));
+        }
+      }
+    }
+
     Ok(Expression::BinaryOperation(BinaryOperation {
       operator,
       location,
       left: Box::new(left),
       right: Box::new(right),
+      parenthesized: false,
+    }))
+  }
+
+  // `x++` and `x--` are the same as `x += 1` and `x -= 1`
+  fn parse_increment(&mut self, left: Expression) -> Result<Expression> {
+    let Token { location, kind, .. } = self.consume().clone();
+    if let Some(target) = invalid_assignment_target(&left) {
+      let operator = if kind == TokenKind::PlusPlus {
+        "++"
+      } else {
+        "--"
+      };
+      return Err(raise_error(
+        location,
+        format!("Cannot use `{operator}` on {target}."),
+      ));
+    }
+
+    let operator = if kind == TokenKind::PlusPlus {
+      Operator::AddAssign
+    } else {
+      Operator::SubAssign
+    };
+    Ok(Expression::BinaryOperation(BinaryOperation {
+      operator,
+      right: Box::new(Expression::Integer(1, location.clone())),
+      location,
+      left: Box::new(left),
+      parenthesized: false,
     }))
   }
 
@@ -251,7 +331,12 @@ impl Parser {
     self.consume();
     let member = match self.current().kind {
       TokenKind::Identifier => {
-        let token = self.consume();
+        let token = self.consume().clone();
+        check_reserved(
+          token.get_value(),
+          &token.location,
+          NameKind::NBTPathComponent,
+        )?;
         let member = validate_or_quote(
           token.get_value().clone(),
           &token.location,
@@ -259,6 +344,23 @@ impl Parser {
         );
         MemberKind::Literal(member)
       }
+      TokenKind::Integer => {
+        let token = self.consume().clone();
+        MemberKind::Literal(token.get_value().clone())
+      }
+      TokenKind::String => {
+        let token = self.consume().clone();
+        check_reserved(
+          token.get_value(),
+          &token.location,
+          NameKind::NBTPathComponent,
+        )?;
+        MemberKind::Literal(validate_or_quote(
+          token.get_value().clone(),
+          &token.location,
+          NameKind::NBTPathComponent,
+        ))
+      }
       TokenKind::LeftSquare => {
         self.consume();
         let expr = self.parse_expression()?;
@@ -268,7 +370,7 @@ impl Parser {
       _ => {
         return Err(raise_error(
           self.current().location.clone(),
-          "Expected an identifier or square-bracket after member access operator.",
+          "Expected a name, number or square-bracket after member access operator.",
         ))
       }
     };
@@ -318,3 +420,32 @@ impl Parser {
 }
 
 type InfixFn = fn(&mut Parser, Expression) -> Result<Expression>;
+
+// Describes the left side of an assignment, if it can never be assigned to
+fn invalid_assignment_target(expression: &Expression) -> Option<&'static str> {
+  Some(match expression {
+    Expression::Variable(_)
+    | Expression::ScoreboardVariable(_)
+    | Expression::ComptimeVariable(..)
+    | Expression::ReturnSlot(_)
+    | Expression::DataTarget(..)
+    | Expression::Member(_)
+    | Expression::Index(_) => return None,
+    Expression::FunctionCall(_) => "a function call result",
+    Expression::BuiltinFunction(..) | Expression::Dump(..) => "a builtin function result",
+    Expression::BuiltinVariable(..) => "a builtin variable",
+    Expression::Boolean(..) => "a boolean",
+    Expression::Byte(..)
+    | Expression::Short(..)
+    | Expression::Integer(..)
+    | Expression::Long(..)
+    | Expression::Float(..)
+    | Expression::Double(..) => "a number",
+    Expression::String(..) => "a string",
+    Expression::Array(..) => "an array",
+    Expression::Compound(..) => "a compound",
+    Expression::MacroVariable(..) => "a macro variable",
+    Expression::BinaryOperation(_) | Expression::UnaryOperation(_) => "the result of an operation",
+    Expression::RangeIndex(_) => "a range index",
+  })
+}
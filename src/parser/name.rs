@@ -37,16 +37,11 @@ pub fn validate(name: &str, location: &Location, kind: NameKind) -> Result<(), E
     NameKind::Unknown => Ok(()),
     NameKind::MacroVariable => verify(name, location, macro_variable, "macro variable"),
     NameKind::NBTPathComponent => verify(name, location, nbt_path_component, "compound member"),
-    NameKind::Namespace => verify(name, location, resource_location_component, "namespace"),
-    NameKind::Module => verify(name, location, resource_location_component, "module"),
-    NameKind::Function => verify(name, location, resource_location_component, "function"),
-    NameKind::Resource => verify(name, location, resource_location_component, "resource"),
-    NameKind::ResourcePathComponent => verify(
-      name,
-      location,
-      resource_location_component,
-      "resource path component",
-    ),
+    NameKind::Namespace => verify_resource(name, location, "namespace"),
+    NameKind::Module => verify_resource(name, location, "module"),
+    NameKind::Function => verify_resource(name, location, "function"),
+    NameKind::Resource => verify_resource(name, location, "resource"),
+    NameKind::ResourcePathComponent => verify_resource(name, location, "resource path component"),
     NameKind::Parameter(ParameterKind::Storage) => {
       verify(name, location, nbt_path_component, "storage parameter")
     }
@@ -67,6 +62,36 @@ pub fn validate(name: &str, location: &Location, kind: NameKind) -> Result<(), E
   }
 }
 
+// The namespace that the compiler generates its own functions and storage in
+const RESERVED_NAMESPACE: &str = "zoglin";
+// Module names used for generated code. They don't collide with user code,
+// but could be confused with it.
+pub const GENERATED_MODULES: &[&str] = &["generated", "internal"];
+
+// Checks a name against the names the compiler reserves for itself. This
+// applies to names that are declared or attached to a location, not ones
+// that just refer to an existing location.
+pub fn check_reserved(name: &str, location: &Location, kind: NameKind) -> Result<(), Error> {
+  match kind {
+    NameKind::Namespace if name == RESERVED_NAMESPACE => Err(raise_error(
+      location.clone(),
+      "The `zoglin` namespace is reserved for code generated by the compiler.",
+    )),
+    NameKind::NBTPathComponent
+    | NameKind::Parameter(_)
+    | NameKind::StorageVariable
+    | NameKind::ScoreboardVariable
+      if name.starts_with("__") =>
+    {
+      Err(raise_error(
+        location.clone(),
+        format!("`{name}` is reserved, since names beginning with `__` are used internally."),
+      ))
+    }
+    _ => Ok(()),
+  }
+}
+
 pub fn validate_zoglin_resource(resource: &ZoglinResource, kind: NameKind) -> Result<(), Error> {
   let location = &resource.location;
   match resource.namespace.as_deref() {
@@ -78,7 +103,8 @@ pub fn validate_zoglin_resource(resource: &ZoglinResource, kind: NameKind) -> Re
   for module in resource.modules.iter() {
     validate(module, location, NameKind::Module)?;
   }
-  validate(&resource.name, location, kind)
+  validate(&resource.name, location, kind)?;
+  check_reserved(&resource.name, location, kind)
 }
 
 fn verify(
@@ -97,6 +123,52 @@ fn verify(
   }
 }
 
+fn verify_resource(name: &str, location: &Location, description: &str) -> Result<(), Error> {
+  if name.chars().all(resource_location_component) {
+    return Ok(());
+  }
+
+  let message = match suggest_resource_name(name) {
+    Some(suggestion) => {
+      eco_format!("`{name}` is not a valid {description} name. Did you mean `{suggestion}`?")
+    }
+    None => eco_format!("`{name}` is not a valid {description} name."),
+  };
+  Err(raise_error(location.clone(), message))
+}
+
+// Converts a name to snake_case, replacing any other invalid characters with
+// underscores. Returns None if nothing usable is left.
+pub fn suggest_resource_name(name: &str) -> Option<EcoString> {
+  let chars: Vec<char> = name.chars().collect();
+  let mut suggestion = EcoString::new();
+
+  for (i, &c) in chars.iter().enumerate() {
+    if c.is_ascii_uppercase() {
+      // Start a new word at `aB`, `1B`, and at the last capital of `ABc`
+      let starts_word = i > 0
+        && (chars[i - 1].is_ascii_lowercase()
+          || chars[i - 1].is_ascii_digit()
+          || (chars[i - 1].is_ascii_uppercase()
+            && chars.get(i + 1).is_some_and(char::is_ascii_lowercase)));
+      if starts_word && !suggestion.ends_with('_') {
+        suggestion.push('_');
+      }
+      suggestion.push(c.to_ascii_lowercase());
+    } else if resource_location_component(c) && c != '-' {
+      suggestion.push(c);
+    } else if !suggestion.ends_with('_') {
+      suggestion.push('_');
+    }
+  }
+
+  if suggestion.chars().all(|c| c == '_') {
+    None
+  } else {
+    Some(suggestion)
+  }
+}
+
 fn verify_all(
   name: &str,
   location: &Location,
@@ -124,7 +196,7 @@ fn nbt_path_component(c: char) -> bool {
 }
 
 fn resource_location_component(c: char) -> bool {
-  c.is_numeric() || c.is_ascii_lowercase() || matches!(c, '_' | '.' | '-')
+  c.is_ascii_digit() || c.is_ascii_lowercase() || matches!(c, '_' | '.' | '-')
 }
 
 fn scoreboard_player(name: &str) -> bool {
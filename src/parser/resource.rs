@@ -1,6 +1,8 @@
+use std::mem::take;
+
 use ecow::EcoString;
 
-use crate::error::Result;
+use crate::error::{raise_warning, Result};
 use crate::lexer::token::TokenKind;
 
 use super::{
@@ -21,7 +23,14 @@ impl Parser {
     let mut done = false;
 
     if self.current().kind == TokenKind::Colon {
-      self.consume();
+      let location = self.consume().location.clone();
+      if self.style_warnings && self.leading_colon_warned.insert(location.file.clone()) {
+        self.warnings.push(raise_warning(
+          location,
+          "A leading `:` refers to the root of the current namespace. \
+Use `~/` for a path relative to the current module, or a bare name to look it up in scope.",
+        ));
+      }
       allow_colon = false;
       resource.namespace = Some(EcoString::new());
     } else if self.current().kind == TokenKind::Tilde {
@@ -61,6 +70,13 @@ impl Parser {
       }
     }
 
+    for module in resource.modules.iter_mut() {
+      *module = self.fix_name(take(module), &resource.location, NameKind::Module)?;
+    }
+    if kind == NameKind::Function {
+      resource.name = self.fix_name(take(&mut resource.name), &resource.location, kind)?;
+    }
+
     validate_zoglin_resource(&resource, kind)?;
     Ok(resource)
   }
@@ -88,20 +104,20 @@ impl Parser {
 
       let identifier = self.expect(TokenKind::Identifier)?.clone();
       if self.current().kind == TokenKind::ForwardSlash {
-        validate(
-          identifier.get_value(),
+        let module = self.fix_name(
+          identifier.get_value().clone(),
           &identifier.location,
           NameKind::Module,
         )?;
-        path.push(identifier.take_value());
+        path.push(module);
         self.consume();
       } else {
-        validate(
-          identifier.get_value(),
+        let name = self.fix_name(
+          identifier.get_value().clone(),
           &identifier.location,
           NameKind::ResourcePathComponent,
         )?;
-        path.push(identifier.get_value().clone());
+        path.push(name);
         break;
       }
     }
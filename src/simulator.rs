@@ -0,0 +1,802 @@
+use std::{collections::HashMap, ops::RangeInclusive};
+
+use ecow::{eco_format, EcoString};
+
+use crate::{
+  compiler::{FileTree, Function, Item, ResourceLocation, TEST_STORAGE},
+  error::{raise_error, raise_note, Location},
+};
+
+use nbt::{parse_path, parse_snbt, Nbt, PathNode};
+
+mod nbt;
+
+// The same limits as Minecraft's defaults, so runaway recursion ends the test
+const MAX_COMMANDS: usize = 65536;
+const MAX_DEPTH: usize = 512;
+
+// Runs every `#[test]` function, returning whether they all passed
+pub fn run_tests(tree: &FileTree) -> bool {
+  let count = tree.tests.len();
+  println!("Running {count} test{}", if count == 1 { "" } else { "s" });
+
+  let mut failed = 0;
+  for test in tree.tests.iter() {
+    let mut simulator = Simulator::new(tree);
+    let failures = simulator.run_test(test);
+    let location = simulator
+      .find_function(&test.to_string())
+      .map(|function| function.location.clone())
+      .unwrap_or_else(Location::blank);
+
+    if failures.is_empty() {
+      println!("test {test} ... ok");
+    } else {
+      failed += 1;
+      println!("test {test} ... FAILED");
+    }
+    for command in simulator.skipped {
+      raise_note(
+        location.clone(),
+        format!("Skipped unsupported command `{command}`"),
+      )
+      .print();
+    }
+    for failure in failures {
+      raise_error(location.clone(), failure).print();
+    }
+  }
+
+  println!("{} passed, {failed} failed", count - failed);
+  failed == 0
+}
+
+// Interprets the subset of commands that Zoglin generates, using only
+// scoreboards and storage. Anything involving the world is skipped.
+struct Simulator<'t> {
+  tree: &'t FileTree,
+  // Keyed by objective, then score holder
+  scores: HashMap<(EcoString, EcoString), i32>,
+  storages: HashMap<EcoString, Nbt>,
+  commands_run: usize,
+  depth: usize,
+  skipped: Vec<EcoString>,
+}
+
+#[derive(Clone, Copy)]
+struct CommandResult {
+  success: bool,
+  value: i32,
+}
+
+impl CommandResult {
+  const FAILURE: CommandResult = CommandResult {
+    success: false,
+    value: 0,
+  };
+
+  fn success(value: i32) -> CommandResult {
+    CommandResult {
+      success: true,
+      value,
+    }
+  }
+}
+
+enum Flow {
+  Next(CommandResult),
+  Return(CommandResult),
+}
+
+enum Interrupt {
+  // The command isn't one the simulator understands, so it is skipped
+  Unsupported,
+  // The test can't continue, such as after a missing macro argument
+  Error(EcoString),
+}
+
+type Run<T> = std::result::Result<T, Interrupt>;
+
+enum Store {
+  Score {
+    success: bool,
+    holder: EcoString,
+    objective: EcoString,
+  },
+  Storage {
+    success: bool,
+    storage: EcoString,
+    path: Vec<PathNode>,
+    kind: EcoString,
+    scale: f64,
+  },
+}
+
+impl<'t> Simulator<'t> {
+  fn new(tree: &'t FileTree) -> Simulator<'t> {
+    Simulator {
+      tree,
+      scores: HashMap::new(),
+      storages: HashMap::new(),
+      commands_run: 0,
+      depth: 0,
+      skipped: Vec::new(),
+    }
+  }
+
+  // Runs the load functions, then the test, returning the reason for each failure
+  fn run_test(&mut self, test: &ResourceLocation) -> Vec<EcoString> {
+    let result = if self.function_tag("minecraft:load").is_some() {
+      self.run_function("#minecraft:load", None)
+    } else {
+      Ok(CommandResult::FAILURE)
+    };
+    if let Err(Interrupt::Error(message)) =
+      result.and_then(|_| self.run_function(&test.to_string(), None))
+    {
+      return vec![message];
+    }
+
+    match self
+      .storages
+      .get(TEST_STORAGE)
+      .and_then(|storage| storage.get(&[PathNode::Key("failures".into())]))
+    {
+      Some(Nbt::List(failures)) => failures.iter().map(Nbt::macro_string).collect(),
+      _ => Vec::new(),
+    }
+  }
+
+  fn find_function(&self, id: &str) -> Option<&'t Function> {
+    let (namespace, path) = id.split_once(':').unwrap_or(("minecraft", id));
+    let modules: Vec<EcoString> = path.split('/').map(EcoString::from).collect();
+    self
+      .tree
+      .namespaces
+      .iter()
+      .find(|candidate| candidate.name == namespace)?
+      .find_function(&modules)
+  }
+
+  fn function_tag(&self, id: &str) -> Option<Vec<EcoString>> {
+    let (namespace, path) = id.split_once(':').unwrap_or(("minecraft", id));
+    let path: Vec<&str> = path.split('/').collect();
+    let (name, modules) = path.split_last()?;
+
+    let mut items = &self
+      .tree
+      .namespaces
+      .iter()
+      .find(|candidate| candidate.name == namespace)?
+      .items;
+    for module in modules {
+      items = items.iter().find_map(|item| match item {
+        Item::Module(candidate) if candidate.name == *module => Some(&candidate.items),
+        _ => None,
+      })?;
+    }
+    let text = items.iter().find_map(|item| match item {
+      Item::TextResource(resource)
+        if resource.kind == "tags/function" && resource.name == *name =>
+      {
+        Some(&resource.text)
+      }
+      _ => None,
+    })?;
+
+    let json: serde_json::Value = serde_json::from_str(text).ok()?;
+    json["values"]
+      .as_array()?
+      .iter()
+      .map(|value| {
+        value
+          .as_str()
+          .or_else(|| value["id"].as_str())
+          .map(EcoString::from)
+      })
+      .collect()
+  }
+
+  fn run_function(&mut self, id: &str, arguments: Option<Nbt>) -> Run<CommandResult> {
+    if let Some(tag) = id.strip_prefix('#') {
+      let functions = self
+        .function_tag(tag)
+        .ok_or_else(|| Interrupt::Error(eco_format!("Function tag `#{tag}` does not exist.")))?;
+      let mut result = CommandResult::FAILURE;
+      for function in functions {
+        result = self.run_function(&function, arguments.clone())?;
+      }
+      return Ok(result);
+    }
+
+    let function = self
+      .find_function(id)
+      .ok_or_else(|| Interrupt::Error(eco_format!("Function `{id}` does not exist.")))?;
+    if self.depth >= MAX_DEPTH {
+      return Err(Interrupt::Error(eco_format!(
+        "Functions were nested more than {MAX_DEPTH} deep."
+      )));
+    }
+
+    self.depth += 1;
+    let result = self.run_commands(&function.commands, arguments.as_ref());
+    self.depth -= 1;
+    result
+  }
+
+  fn run_commands(
+    &mut self,
+    commands: &[EcoString],
+    arguments: Option<&Nbt>,
+  ) -> Run<CommandResult> {
+    for line in commands.iter().flat_map(|command| command.lines()) {
+      let line = line.trim();
+      if line.is_empty() || line.starts_with('#') {
+        continue;
+      }
+
+      self.commands_run += 1;
+      if self.commands_run > MAX_COMMANDS {
+        return Err(Interrupt::Error(eco_format!(
+          "Ran more than {MAX_COMMANDS} commands."
+        )));
+      }
+
+      let command = match line.strip_prefix('$') {
+        Some(line) => substitute_macros(line, arguments)?,
+        None => line.into(),
+      };
+      match self.run_command(&command) {
+        Ok(Flow::Next(_)) => {}
+        Ok(Flow::Return(result)) => return Ok(result),
+        Err(Interrupt::Unsupported) => self.skip(command),
+        Err(error) => return Err(error),
+      }
+    }
+    Ok(CommandResult::FAILURE)
+  }
+
+  fn skip(&mut self, command: EcoString) {
+    if !self.skipped.contains(&command) {
+      self.skipped.push(command);
+    }
+  }
+
+  // Runs the command after `execute ... run` or `return run`. An unsupported
+  // command is skipped and fails, so the `execute` or `return` still happens
+  fn run_nested(&mut self, command: &str) -> Run<Flow> {
+    match self.run_command(command) {
+      Err(Interrupt::Unsupported) => {
+        self.skip(command.into());
+        Ok(Flow::Next(CommandResult::FAILURE))
+      }
+      result => result,
+    }
+  }
+
+  fn run_command(&mut self, command: &str) -> Run<Flow> {
+    let mut reader = Reader { text: command };
+    match reader.arg()? {
+      "scoreboard" => self.scoreboard(&mut reader).map(Flow::Next),
+      "data" => self.data(&mut reader).map(Flow::Next),
+      "execute" => self.execute(&mut reader),
+      "function" => self.function(&mut reader).map(Flow::Next),
+      "return" => self.return_command(&mut reader),
+      // There are no players, so messages don't reach anyone
+      "tellraw" => Ok(Flow::Next(CommandResult::success(0))),
+      _ => Err(Interrupt::Unsupported),
+    }
+  }
+
+  fn score(&self, holder: &str, objective: &str) -> Option<i32> {
+    self.scores.get(&(objective.into(), holder.into())).copied()
+  }
+
+  fn set_score(&mut self, holder: &str, objective: &str, value: i32) {
+    self.scores.insert((objective.into(), holder.into()), value);
+  }
+
+  fn storage(&self, storage: &str, path: Option<&[PathNode]>) -> Option<&Nbt> {
+    let root = self.storages.get(storage)?;
+    match path {
+      Some(path) => root.get(path),
+      None => Some(root),
+    }
+  }
+
+  fn storage_mut(&mut self, storage: &str) -> &mut Nbt {
+    self
+      .storages
+      .entry(storage.into())
+      .or_insert_with(Nbt::compound)
+  }
+
+  fn scoreboard(&mut self, reader: &mut Reader) -> Run<CommandResult> {
+    let result = match (reader.arg()?, reader.arg()?) {
+      // Objectives are created as soon as they are used
+      ("objectives", "add") => {
+        reader.rest();
+        CommandResult::success(0)
+      }
+      ("objectives", "remove") => {
+        let objective = reader.arg()?;
+        self
+          .scores
+          .retain(|(candidate, _), _| candidate != objective);
+        CommandResult::success(0)
+      }
+      ("players", "set") => {
+        let (holder, objective) = (holder(reader)?, reader.arg()?);
+        let value = number(reader.arg()?)?;
+        self.set_score(holder, objective, value);
+        CommandResult::success(value)
+      }
+      ("players", action @ ("add" | "remove")) => {
+        let (holder, objective) = (holder(reader)?, reader.arg()?);
+        let amount = number(reader.arg()?)?;
+        let current = self.score(holder, objective).unwrap_or(0);
+        let value = if action == "add" {
+          current.wrapping_add(amount)
+        } else {
+          current.wrapping_sub(amount)
+        };
+        self.set_score(holder, objective, value);
+        CommandResult::success(value)
+      }
+      ("players", "get") => {
+        let (holder, objective) = (holder(reader)?, reader.arg()?);
+        match self.score(holder, objective) {
+          Some(value) => CommandResult::success(value),
+          None => CommandResult::FAILURE,
+        }
+      }
+      ("players", "reset") => match reader.arg()? {
+        // Every holder, which is only supported for a single objective
+        "*" => {
+          let objective = reader.arg()?;
+          self
+            .scores
+            .retain(|(candidate, _), _| candidate != objective);
+          CommandResult::success(0)
+        }
+        holder if holder.starts_with('@') => return Err(Interrupt::Unsupported),
+        holder => {
+          match reader.next() {
+            Some(objective) => {
+              self.scores.remove(&(objective.into(), holder.into()));
+            }
+            None => self.scores.retain(|(_, candidate), _| candidate != holder),
+          }
+          CommandResult::success(0)
+        }
+      },
+      ("players", "operation") => {
+        let (target, target_objective) = (holder(reader)?, reader.arg()?);
+        let operation = reader.arg()?;
+        let (source, source_objective) = (holder(reader)?, reader.arg()?);
+        let a = self.score(target, target_objective).unwrap_or(0);
+        let b = self.score(source, source_objective).unwrap_or(0);
+        let value = match operation {
+          "=" => b,
+          "+=" => a.wrapping_add(b),
+          "-=" => a.wrapping_sub(b),
+          "*=" => a.wrapping_mul(b),
+          "/=" if b != 0 => a.div_euclid(b),
+          "%=" if b != 0 => a.rem_euclid(b),
+          "/=" | "%=" => a,
+          "<" => a.min(b),
+          ">" => a.max(b),
+          "><" => {
+            self.set_score(source, source_objective, a);
+            b
+          }
+          _ => return Err(Interrupt::Unsupported),
+        };
+        self.set_score(target, target_objective, value);
+        CommandResult::success(value)
+      }
+      _ => return Err(Interrupt::Unsupported),
+    };
+    reader.end()?;
+    Ok(result)
+  }
+
+  fn data(&mut self, reader: &mut Reader) -> Run<CommandResult> {
+    let action = reader.arg()?;
+    if reader.arg()? != "storage" {
+      return Err(Interrupt::Unsupported);
+    }
+    let storage = reader.arg()?;
+
+    let result = match action {
+      "get" => {
+        let path = reader.next().map(path).transpose()?;
+        let scale = reader.next().map(scale).transpose()?;
+        match self.storage(storage, path.as_deref()) {
+          Some(value) => CommandResult::success(value.get_as_int(scale)),
+          None => CommandResult::FAILURE,
+        }
+      }
+      "remove" => {
+        let path = path(reader.arg()?)?;
+        if self.storage_mut(storage).remove(&path) {
+          CommandResult::success(1)
+        } else {
+          CommandResult::FAILURE
+        }
+      }
+      "merge" => {
+        let value = parse_snbt(reader.rest()).ok_or(Interrupt::Unsupported)?;
+        match self.storage_mut(storage).merge(value) {
+          Some(true) => CommandResult::success(1),
+          _ => CommandResult::FAILURE,
+        }
+      }
+      "modify" => self.data_modify(storage, reader)?,
+      _ => return Err(Interrupt::Unsupported),
+    };
+    reader.end()?;
+    Ok(result)
+  }
+
+  fn data_modify(&mut self, storage: &str, reader: &mut Reader) -> Run<CommandResult> {
+    let path = path(reader.arg()?)?;
+    let operation = reader.arg()?;
+    let index = match operation {
+      "insert" => Some(number(reader.arg()?)?),
+      _ => None,
+    };
+    // A missing source makes the command fail without changing anything
+    let Some(value) = self.data_source(reader)? else {
+      return Ok(CommandResult::FAILURE);
+    };
+
+    let root = self.storage_mut(storage);
+    let changed = match operation {
+      "set" => root.set(&path, value),
+      "merge" => root
+        .get_mut(&path, true)
+        .and_then(|target| target.merge(value)),
+      "append" | "prepend" | "insert" => root.list_mut(&path).and_then(|list| {
+        let index = match (operation, index) {
+          ("append", _) => list.len() as i32,
+          (_, Some(index)) if index < 0 => list.len() as i32 + index + 1,
+          (_, index) => index.unwrap_or(0),
+        };
+        let index = usize::try_from(index)
+          .ok()
+          .filter(|index| *index <= list.len())?;
+        list.insert(index, value);
+        Some(true)
+      }),
+      _ => return Err(Interrupt::Unsupported),
+    };
+
+    Ok(match changed {
+      Some(true) => CommandResult::success(1),
+      _ => CommandResult::FAILURE,
+    })
+  }
+
+  fn data_source(&mut self, reader: &mut Reader) -> Run<Option<Nbt>> {
+    let kind = reader.arg()?;
+    if kind == "value" {
+      return parse_snbt(reader.rest())
+        .map(Some)
+        .ok_or(Interrupt::Unsupported);
+    }
+    if !matches!(kind, "from" | "string") || reader.arg()? != "storage" {
+      return Err(Interrupt::Unsupported);
+    }
+
+    let storage = reader.arg()?;
+    let path = reader.next().map(path).transpose()?;
+    let value = self.storage(storage, path.as_deref()).cloned();
+    if kind == "from" {
+      return Ok(value);
+    }
+
+    let start = reader.next().map(number).transpose()?.unwrap_or(0);
+    let end = reader.next().map(number).transpose()?;
+    Ok(value.and_then(|value| {
+      let text: Vec<char> = value.macro_string().chars().collect();
+      let resolve = |index: i32| {
+        let index = if index < 0 {
+          text.len() as i32 + index
+        } else {
+          index
+        };
+        usize::try_from(index)
+          .ok()
+          .filter(|index| *index <= text.len())
+      };
+      let (start, end) = (resolve(start)?, resolve(end.unwrap_or(text.len() as i32))?);
+      (start <= end).then(|| Nbt::String(text[start..end].iter().copied().collect()))
+    }))
+  }
+
+  fn execute(&mut self, reader: &mut Reader) -> Run<Flow> {
+    let mut stores = Vec::new();
+    loop {
+      match reader.arg()? {
+        "run" => {
+          let flow = self.run_nested(reader.rest())?;
+          let (Flow::Next(result) | Flow::Return(result)) = flow;
+          self.store(&stores, result);
+          return Ok(flow);
+        }
+        "store" => stores.push(store(reader)?),
+        check @ ("if" | "unless") => {
+          let passed = self.condition(reader)? == (check == "if");
+          // A condition at the end of the command is its result
+          if reader.end().is_ok() {
+            let result = if passed {
+              CommandResult::success(1)
+            } else {
+              CommandResult::FAILURE
+            };
+            self.store(&stores, result);
+            return Ok(Flow::Next(result));
+          }
+          if !passed {
+            return Ok(Flow::Next(CommandResult::FAILURE));
+          }
+        }
+        _ => return Err(Interrupt::Unsupported),
+      }
+    }
+  }
+
+  fn condition(&mut self, reader: &mut Reader) -> Run<bool> {
+    match reader.arg()? {
+      "score" => {
+        let score = self.score(holder(reader)?, reader.arg()?);
+        let comparison = reader.arg()?;
+        if comparison == "matches" {
+          let range = range(reader.arg()?)?;
+          return Ok(score.is_some_and(|score| range.contains(&score)));
+        }
+
+        let other = self.score(holder(reader)?, reader.arg()?);
+        let (Some(a), Some(b)) = (score, other) else {
+          return Ok(false);
+        };
+        match comparison {
+          "<" => Ok(a < b),
+          "<=" => Ok(a <= b),
+          "=" => Ok(a == b),
+          ">=" => Ok(a >= b),
+          ">" => Ok(a > b),
+          _ => Err(Interrupt::Unsupported),
+        }
+      }
+      "data" => {
+        if reader.arg()? != "storage" {
+          return Err(Interrupt::Unsupported);
+        }
+        let storage = reader.arg()?;
+        let path = path(reader.arg()?)?;
+        Ok(self.storage(storage, Some(&path)).is_some())
+      }
+      "function" => {
+        let result = self.run_function(reader.arg()?, None)?;
+        Ok(result.success && result.value != 0)
+      }
+      _ => Err(Interrupt::Unsupported),
+    }
+  }
+
+  fn store(&mut self, stores: &[Store], result: CommandResult) {
+    for store in stores {
+      match store {
+        Store::Score {
+          success,
+          holder,
+          objective,
+        } => {
+          let value = if *success {
+            result.success as i32
+          } else {
+            result.value
+          };
+          self.set_score(holder, objective, value);
+        }
+        Store::Storage {
+          success,
+          storage,
+          path,
+          kind,
+          scale,
+        } => {
+          let value = if *success {
+            result.success as i32
+          } else {
+            result.value
+          };
+          if let Some(value) = Nbt::from_number(kind, value as f64 * scale) {
+            self.storage_mut(storage).set(path, value);
+          }
+        }
+      }
+    }
+  }
+
+  fn function(&mut self, reader: &mut Reader) -> Run<CommandResult> {
+    let id = reader.arg()?;
+    let arguments = match reader.next() {
+      None => None,
+      Some("with") => {
+        if reader.arg()? != "storage" {
+          return Err(Interrupt::Unsupported);
+        }
+        let storage = reader.arg()?;
+        let path = reader.next().map(path).transpose()?;
+        match self.storage(storage, path.as_deref()) {
+          Some(arguments @ Nbt::Compound(_)) => Some(arguments.clone()),
+          _ => return Ok(CommandResult::FAILURE),
+        }
+      }
+      Some(arguments) => Some(parse_snbt(arguments).ok_or(Interrupt::Unsupported)?),
+    };
+    reader.end()?;
+    self.run_function(id, arguments)
+  }
+
+  fn return_command(&mut self, reader: &mut Reader) -> Run<Flow> {
+    match reader.arg()? {
+      "fail" => Ok(Flow::Return(CommandResult::FAILURE)),
+      "run" => {
+        let (Flow::Next(result) | Flow::Return(result)) = self.run_nested(reader.rest())?;
+        Ok(Flow::Return(result))
+      }
+      value => {
+        let value = number(value)?;
+        reader.end()?;
+        Ok(Flow::Return(CommandResult::success(value)))
+      }
+    }
+  }
+}
+
+fn substitute_macros(line: &str, arguments: Option<&Nbt>) -> Run<EcoString> {
+  let Some(Nbt::Compound(arguments)) = arguments else {
+    return Err(Interrupt::Error(eco_format!(
+      "`${line}` is a macro command, but the function was called without arguments."
+    )));
+  };
+
+  let mut result = EcoString::new();
+  let mut rest = line;
+  while let Some(start) = rest.find("$(") {
+    result.push_str(&rest[..start]);
+    let Some(length) = rest[start..].find(')') else {
+      break;
+    };
+    let name = &rest[start + 2..start + length];
+    let value = arguments
+      .get(name)
+      .ok_or_else(|| Interrupt::Error(eco_format!("Missing macro argument `{name}`.")))?;
+    result.push_str(&value.macro_string());
+    rest = &rest[start + length + 1..];
+  }
+  result.push_str(rest);
+  Ok(result)
+}
+
+fn store(reader: &mut Reader) -> Run<Store> {
+  let success = match reader.arg()? {
+    "result" => false,
+    "success" => true,
+    _ => return Err(Interrupt::Unsupported),
+  };
+  match reader.arg()? {
+    "score" => Ok(Store::Score {
+      success,
+      holder: holder(reader)?.into(),
+      objective: reader.arg()?.into(),
+    }),
+    "storage" => Ok(Store::Storage {
+      success,
+      storage: reader.arg()?.into(),
+      path: path(reader.arg()?)?,
+      kind: reader.arg()?.into(),
+      scale: scale(reader.arg()?)?,
+    }),
+    _ => Err(Interrupt::Unsupported),
+  }
+}
+
+// Selectors need entities, which the simulator doesn't have
+fn holder<'a>(reader: &mut Reader<'a>) -> Run<&'a str> {
+  match reader.arg()? {
+    holder if holder.starts_with('@') || holder == "*" => Err(Interrupt::Unsupported),
+    holder => Ok(holder),
+  }
+}
+
+fn number(text: &str) -> Run<i32> {
+  text.parse().map_err(|_| Interrupt::Unsupported)
+}
+
+fn scale(text: &str) -> Run<f64> {
+  text.parse().map_err(|_| Interrupt::Unsupported)
+}
+
+fn path(text: &str) -> Run<Vec<PathNode>> {
+  parse_path(text).ok_or(Interrupt::Unsupported)
+}
+
+fn range(text: &str) -> Run<RangeInclusive<i32>> {
+  let bound = |text: &str, default: i32| {
+    if text.is_empty() {
+      Ok(default)
+    } else {
+      number(text)
+    }
+  };
+  match text.split_once("..") {
+    Some((start, end)) => Ok(bound(start, i32::MIN)?..=bound(end, i32::MAX)?),
+    None => {
+      let value = number(text)?;
+      Ok(value..=value)
+    }
+  }
+}
+
+// Splits a command into arguments, keeping quoted text and brackets together
+struct Reader<'a> {
+  text: &'a str,
+}
+
+impl<'a> Reader<'a> {
+  fn next(&mut self) -> Option<&'a str> {
+    let text = self.text.trim_start();
+    if text.is_empty() {
+      return None;
+    }
+
+    let mut depth = 0;
+    let mut quote = None;
+    let mut escaped = false;
+    let mut end = text.len();
+    for (index, c) in text.char_indices() {
+      match quote {
+        Some(_) if escaped => escaped = false,
+        Some(_) if c == '\\' => escaped = true,
+        Some(open) if c == open => quote = None,
+        Some(_) => {}
+        None => match c {
+          '"' | '\'' => quote = Some(c),
+          '[' | '{' | '(' => depth += 1,
+          ']' | '}' | ')' => depth -= 1,
+          c if c.is_whitespace() && depth <= 0 => {
+            end = index;
+            break;
+          }
+          _ => {}
+        },
+      }
+    }
+
+    self.text = &text[end..];
+    Some(&text[..end])
+  }
+
+  fn arg(&mut self) -> Run<&'a str> {
+    self.next().ok_or(Interrupt::Unsupported)
+  }
+
+  fn rest(&mut self) -> &'a str {
+    let rest = self.text.trim();
+    self.text = "";
+    rest
+  }
+
+  fn end(&self) -> Run<()> {
+    if self.text.trim().is_empty() {
+      Ok(())
+    } else {
+      Err(Interrupt::Unsupported)
+    }
+  }
+}
@@ -0,0 +1,465 @@
+use std::{collections::BTreeMap, fmt::Display};
+
+use ecow::EcoString;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Nbt {
+  Byte(i8),
+  Short(i16),
+  Int(i32),
+  Long(i64),
+  Float(f32),
+  Double(f64),
+  String(EcoString),
+  List(Vec<Nbt>),
+  Array(ArrayKind, Vec<Nbt>),
+  Compound(BTreeMap<EcoString, Nbt>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArrayKind {
+  Byte,
+  Int,
+  Long,
+}
+
+#[derive(Debug, Clone)]
+pub enum PathNode {
+  Key(EcoString),
+  Index(i32),
+}
+
+impl Nbt {
+  pub fn compound() -> Nbt {
+    Nbt::Compound(BTreeMap::new())
+  }
+
+  // Converts the result of `execute store ... storage` to the requested type
+  pub fn from_number(kind: &str, value: f64) -> Option<Nbt> {
+    Some(match kind {
+      "byte" => Nbt::Byte(value as i32 as i8),
+      "short" => Nbt::Short(value as i32 as i16),
+      "int" => Nbt::Int(value as i32),
+      "long" => Nbt::Long(value as i64),
+      "float" => Nbt::Float(value as f32),
+      "double" => Nbt::Double(value),
+      _ => return None,
+    })
+  }
+
+  fn as_number(&self) -> Option<f64> {
+    Some(match self {
+      Nbt::Byte(n) => *n as f64,
+      Nbt::Short(n) => *n as f64,
+      Nbt::Int(n) => *n as f64,
+      Nbt::Long(n) => *n as f64,
+      Nbt::Float(n) => *n as f64,
+      Nbt::Double(n) => *n,
+      _ => return None,
+    })
+  }
+
+  // The result of `data get`, which is the size of anything that isn't a number
+  pub fn get_as_int(&self, scale: Option<f64>) -> i32 {
+    if let (Some(scale), Some(number)) = (scale, self.as_number()) {
+      return (number * scale).floor() as i32;
+    }
+    match self {
+      Nbt::Byte(n) => *n as i32,
+      Nbt::Short(n) => *n as i32,
+      Nbt::Int(n) => *n,
+      Nbt::Long(n) => *n as i32,
+      Nbt::Float(n) => n.floor() as i32,
+      Nbt::Double(n) => n.floor() as i32,
+      Nbt::String(text) => text.chars().count() as i32,
+      Nbt::List(values) | Nbt::Array(_, values) => values.len() as i32,
+      Nbt::Compound(values) => values.len() as i32,
+    }
+  }
+
+  // Strings are inserted into macros without quotes
+  pub fn macro_string(&self) -> EcoString {
+    match self {
+      Nbt::String(text) => text.clone(),
+      _ => self.to_string().into(),
+    }
+  }
+
+  pub fn get(&self, path: &[PathNode]) -> Option<&Nbt> {
+    path.iter().try_fold(self, |value, node| value.child(node))
+  }
+
+  // Compounds are created along the way if `create` is set, as `data modify` does
+  pub fn get_mut(&mut self, path: &[PathNode], create: bool) -> Option<&mut Nbt> {
+    path
+      .iter()
+      .try_fold(self, |value, node| value.child_mut(node, create))
+  }
+
+  fn child(&self, node: &PathNode) -> Option<&Nbt> {
+    match (self, node) {
+      (Nbt::Compound(values), PathNode::Key(key)) => values.get(key),
+      (Nbt::List(values) | Nbt::Array(_, values), PathNode::Index(index)) => {
+        values.get(resolve_index(*index, values.len())?)
+      }
+      _ => None,
+    }
+  }
+
+  fn child_mut(&mut self, node: &PathNode, create: bool) -> Option<&mut Nbt> {
+    match (self, node) {
+      (Nbt::Compound(values), PathNode::Key(key)) => {
+        if create {
+          Some(values.entry(key.clone()).or_insert_with(Nbt::compound))
+        } else {
+          values.get_mut(key)
+        }
+      }
+      (Nbt::List(values) | Nbt::Array(_, values), PathNode::Index(index)) => {
+        let index = resolve_index(*index, values.len())?;
+        values.get_mut(index)
+      }
+      _ => None,
+    }
+  }
+
+  // Returns whether the value changed, since `data modify` fails otherwise
+  pub fn set(&mut self, path: &[PathNode], value: Nbt) -> Option<bool> {
+    let (last, parent) = path.split_last()?;
+    match (self.get_mut(parent, true)?, last) {
+      (Nbt::Compound(values), PathNode::Key(key)) => {
+        let previous = values.insert(key.clone(), value.clone());
+        Some(previous.as_ref() != Some(&value))
+      }
+      (parent, last) => {
+        let slot = parent.child_mut(last, false)?;
+        let changed = *slot != value;
+        *slot = value;
+        Some(changed)
+      }
+    }
+  }
+
+  pub fn remove(&mut self, path: &[PathNode]) -> bool {
+    let Some((last, parent)) = path.split_last() else {
+      return false;
+    };
+    match (self.get_mut(parent, false), last) {
+      (Some(Nbt::Compound(values)), PathNode::Key(key)) => values.remove(key).is_some(),
+      (Some(Nbt::List(values) | Nbt::Array(_, values)), PathNode::Index(index)) => {
+        match resolve_index(*index, values.len()) {
+          Some(index) => {
+            values.remove(index);
+            true
+          }
+          None => false,
+        }
+      }
+      _ => false,
+    }
+  }
+
+  // The list at the path, which is created if it doesn't exist
+  pub fn list_mut(&mut self, path: &[PathNode]) -> Option<&mut Vec<Nbt>> {
+    let (last, parent) = path.split_last()?;
+    let list = match (self.get_mut(parent, true)?, last) {
+      (Nbt::Compound(values), PathNode::Key(key)) => values
+        .entry(key.clone())
+        .or_insert_with(|| Nbt::List(Vec::new())),
+      (parent, last) => parent.child_mut(last, false)?,
+    };
+    match list {
+      Nbt::List(values) | Nbt::Array(_, values) => Some(values),
+      _ => None,
+    }
+  }
+
+  pub fn merge(&mut self, other: Nbt) -> Option<bool> {
+    let (Nbt::Compound(values), Nbt::Compound(other)) = (self, other) else {
+      return None;
+    };
+    let mut changed = false;
+    for (key, value) in other {
+      if let (Some(existing @ Nbt::Compound(_)), Nbt::Compound(_)) = (values.get_mut(&key), &value)
+      {
+        changed |= existing.merge(value)?;
+        continue;
+      }
+      changed |= values.get(&key) != Some(&value);
+      values.insert(key, value);
+    }
+    Some(changed)
+  }
+}
+
+fn resolve_index(index: i32, length: usize) -> Option<usize> {
+  let index = if index < 0 {
+    length as i64 + index as i64
+  } else {
+    index as i64
+  };
+  (0..length as i64)
+    .contains(&index)
+    .then_some(index as usize)
+}
+
+impl Display for Nbt {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Nbt::Byte(n) => write!(f, "{n}b"),
+      Nbt::Short(n) => write!(f, "{n}s"),
+      Nbt::Int(n) => write!(f, "{n}"),
+      Nbt::Long(n) => write!(f, "{n}L"),
+      Nbt::Float(n) => write!(f, "{n:?}f"),
+      Nbt::Double(n) => write!(f, "{n:?}d"),
+      Nbt::String(text) => write!(f, "{}", quote(text)),
+      Nbt::List(values) => write!(f, "[{}]", join(values)),
+      Nbt::Array(kind, values) => {
+        let prefix = match kind {
+          ArrayKind::Byte => 'B',
+          ArrayKind::Int => 'I',
+          ArrayKind::Long => 'L',
+        };
+        write!(f, "[{prefix}; {}]", join(values))
+      }
+      Nbt::Compound(values) => {
+        let entries: Vec<String> = values
+          .iter()
+          .map(|(key, value)| {
+            if key.chars().all(is_unquoted_char) {
+              format!("{key}: {value}")
+            } else {
+              format!("{}: {value}", quote(key))
+            }
+          })
+          .collect();
+        write!(f, "{{{}}}", entries.join(", "))
+      }
+    }
+  }
+}
+
+fn join(values: &[Nbt]) -> String {
+  values
+    .iter()
+    .map(|value| value.to_string())
+    .collect::<Vec<_>>()
+    .join(", ")
+}
+
+fn quote(text: &str) -> String {
+  format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn is_unquoted_char(c: char) -> bool {
+  c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | '+')
+}
+
+pub fn parse_snbt(text: &str) -> Option<Nbt> {
+  let mut reader = SnbtReader::new(text);
+  let value = reader.value()?;
+  reader.skip_whitespace();
+  reader.at_end().then_some(value)
+}
+
+pub fn parse_path(text: &str) -> Option<Vec<PathNode>> {
+  let mut reader = SnbtReader::new(text);
+  let mut nodes = Vec::new();
+  while !reader.at_end() {
+    match reader.peek()? {
+      '[' => {
+        reader.position += 1;
+        let index = reader.unquoted()?.parse().ok()?;
+        reader.expect(']')?;
+        nodes.push(PathNode::Index(index));
+      }
+      '.' if !nodes.is_empty() => reader.position += 1,
+      '"' | '\'' => nodes.push(PathNode::Key(reader.quoted()?)),
+      _ => {
+        // Compound filters such as `a{b: 1}` aren't supported
+        let start = reader.position;
+        while reader.peek().is_some_and(|c| {
+          !matches!(c, '.' | '[' | ']' | '{' | '}' | '"' | '\'') && !c.is_whitespace()
+        }) {
+          reader.position += 1;
+        }
+        if reader.position == start {
+          return None;
+        }
+        nodes.push(PathNode::Key(
+          reader.chars[start..reader.position]
+            .iter()
+            .copied()
+            .collect(),
+        ));
+      }
+    }
+  }
+  (!nodes.is_empty()).then_some(nodes)
+}
+
+struct SnbtReader {
+  chars: Vec<char>,
+  position: usize,
+}
+
+impl SnbtReader {
+  fn new(text: &str) -> SnbtReader {
+    SnbtReader {
+      chars: text.chars().collect(),
+      position: 0,
+    }
+  }
+
+  fn peek(&self) -> Option<char> {
+    self.chars.get(self.position).copied()
+  }
+
+  fn at_end(&self) -> bool {
+    self.position >= self.chars.len()
+  }
+
+  fn skip_whitespace(&mut self) {
+    while self.peek().is_some_and(char::is_whitespace) {
+      self.position += 1;
+    }
+  }
+
+  fn expect(&mut self, expected: char) -> Option<()> {
+    self.skip_whitespace();
+    (self.peek()? == expected).then(|| self.position += 1)
+  }
+
+  fn value(&mut self) -> Option<Nbt> {
+    self.skip_whitespace();
+    match self.peek()? {
+      '{' => self.compound(),
+      '[' => self.list(),
+      '"' | '\'' => self.quoted().map(Nbt::String),
+      _ => Some(parse_literal(&self.unquoted()?)),
+    }
+  }
+
+  fn compound(&mut self) -> Option<Nbt> {
+    self.position += 1;
+    let mut values = BTreeMap::new();
+    self.skip_whitespace();
+    if self.peek()? == '}' {
+      self.position += 1;
+      return Some(Nbt::Compound(values));
+    }
+
+    loop {
+      self.skip_whitespace();
+      let key = match self.peek()? {
+        '"' | '\'' => self.quoted()?,
+        _ => self.unquoted()?,
+      };
+      self.expect(':')?;
+      values.insert(key, self.value()?);
+      self.skip_whitespace();
+      match self.peek()? {
+        ',' => self.position += 1,
+        '}' => {
+          self.position += 1;
+          return Some(Nbt::Compound(values));
+        }
+        _ => return None,
+      }
+    }
+  }
+
+  fn list(&mut self) -> Option<Nbt> {
+    self.position += 1;
+    let kind = match (self.peek(), self.chars.get(self.position + 1)) {
+      (Some('B'), Some(';')) => Some(ArrayKind::Byte),
+      (Some('I'), Some(';')) => Some(ArrayKind::Int),
+      (Some('L'), Some(';')) => Some(ArrayKind::Long),
+      _ => None,
+    };
+    if kind.is_some() {
+      self.position += 2;
+    }
+
+    let mut values = Vec::new();
+    self.skip_whitespace();
+    if self.peek()? == ']' {
+      self.position += 1;
+    } else {
+      loop {
+        values.push(self.value()?);
+        self.skip_whitespace();
+        match self.peek()? {
+          ',' => self.position += 1,
+          ']' => {
+            self.position += 1;
+            break;
+          }
+          _ => return None,
+        }
+      }
+    }
+
+    Some(match kind {
+      Some(kind) => Nbt::Array(kind, values),
+      None => Nbt::List(values),
+    })
+  }
+
+  fn quoted(&mut self) -> Option<EcoString> {
+    let quote = self.peek()?;
+    self.position += 1;
+    let mut text = EcoString::new();
+    loop {
+      match self.peek()? {
+        '\\' => {
+          self.position += 1;
+          text.push(self.peek()?);
+        }
+        c if c == quote => {
+          self.position += 1;
+          return Some(text);
+        }
+        c => text.push(c),
+      }
+      self.position += 1;
+    }
+  }
+
+  fn unquoted(&mut self) -> Option<EcoString> {
+    let start = self.position;
+    while self.peek().is_some_and(is_unquoted_char) {
+      self.position += 1;
+    }
+    (self.position > start).then(|| self.chars[start..self.position].iter().copied().collect())
+  }
+}
+
+fn parse_literal(text: &str) -> Nbt {
+  match text {
+    "true" => return Nbt::Byte(1),
+    "false" => return Nbt::Byte(0),
+    _ => {}
+  }
+
+  let (number, suffix) = text.split_at(text.len() - 1);
+  let typed = match suffix {
+    "b" | "B" => number.parse().ok().map(Nbt::Byte),
+    "s" | "S" => number.parse().ok().map(Nbt::Short),
+    "l" | "L" => number.parse().ok().map(Nbt::Long),
+    "f" | "F" => number.parse().ok().map(Nbt::Float),
+    "d" | "D" => number.parse().ok().map(Nbt::Double),
+    _ => None,
+  };
+  if let Some(value) = typed {
+    return value;
+  }
+
+  if let Ok(value) = text.parse() {
+    Nbt::Int(value)
+  } else if let (true, Ok(value)) = (text.contains(['.', 'e', 'E']), text.parse()) {
+    Nbt::Double(value)
+  } else {
+    Nbt::String(text.into())
+  }
+}
@@ -0,0 +1,293 @@
+use std::{
+  collections::{HashMap, HashSet},
+  fs, thread,
+  time::{Duration, Instant, SystemTime},
+};
+
+use ecow::EcoString;
+
+// How long files must stop changing before a rebuild starts, so saving
+// several files at once only rebuilds once
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+pub trait FileWatcher {
+  // Replaces the watched files with the ones the latest build depends on
+  fn set_files(&mut self, files: HashSet<EcoString>);
+  // Waits up to `timeout` for a watched file to change
+  fn changed_within(&mut self, timeout: Duration) -> bool;
+}
+
+// Rebuilds whenever a file the build depends on changes, forever
+pub fn watch(watcher: &mut dyn FileWatcher, mut build: impl FnMut() -> HashSet<EcoString>) -> ! {
+  watcher.set_files(build());
+  loop {
+    wait_for_changes(watcher);
+    watcher.set_files(build());
+  }
+}
+
+// Waits for a watched file to change, then for the files to stop changing
+fn wait_for_changes(watcher: &mut dyn FileWatcher) {
+  while !watcher.changed_within(Duration::MAX) {}
+  while watcher.changed_within(DEBOUNCE) {}
+}
+
+// The time source of the polling backend, so tests don't have to wait
+pub trait Clock {
+  fn now(&self) -> Instant;
+  fn sleep(&mut self, duration: Duration);
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+  fn now(&self) -> Instant {
+    Instant::now()
+  }
+
+  fn sleep(&mut self, duration: Duration) {
+    thread::sleep(duration);
+  }
+}
+
+pub struct PollWatcher<F, C> {
+  interval: Duration,
+  times: HashMap<EcoString, Option<SystemTime>>,
+  // Reads a file's modification time, or `None` if it doesn't exist
+  modified: F,
+  clock: C,
+}
+
+impl PollWatcher<fn(&str) -> Option<SystemTime>, SystemClock> {
+  pub fn new(interval: Duration) -> Self {
+    PollWatcher::with_clock(interval, SystemClock, |path| {
+      fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+    })
+  }
+}
+
+impl<F: FnMut(&str) -> Option<SystemTime>, C: Clock> PollWatcher<F, C> {
+  pub fn with_clock(interval: Duration, clock: C, modified: F) -> Self {
+    PollWatcher {
+      interval,
+      times: HashMap::new(),
+      modified,
+      clock,
+    }
+  }
+
+  // Updates the stored modification times, returning whether any changed
+  fn poll(&mut self) -> bool {
+    let mut changed = false;
+    for (path, time) in self.times.iter_mut() {
+      let current = (self.modified)(path);
+      if current != *time {
+        *time = current;
+        changed = true;
+      }
+    }
+    changed
+  }
+}
+
+impl<F: FnMut(&str) -> Option<SystemTime>, C: Clock> FileWatcher for PollWatcher<F, C> {
+  fn set_files(&mut self, files: HashSet<EcoString>) {
+    self.times = files
+      .into_iter()
+      .map(|path| {
+        let time = (self.modified)(&path);
+        (path, time)
+      })
+      .collect();
+  }
+
+  fn changed_within(&mut self, timeout: Duration) -> bool {
+    let deadline = self.clock.now().checked_add(timeout);
+    loop {
+      self.clock.sleep(self.interval);
+      if self.poll() {
+        return true;
+      }
+      if deadline.is_some_and(|deadline| self.clock.now() >= deadline) {
+        return false;
+      }
+    }
+  }
+}
+
+#[cfg(feature = "native-watch")]
+pub use native::NativeWatcher;
+
+#[cfg(feature = "native-watch")]
+mod native {
+  use std::{
+    collections::HashSet,
+    path::Path,
+    sync::mpsc::{channel, Receiver},
+    time::{Duration, Instant},
+  };
+
+  use ecow::EcoString;
+  use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+  use super::FileWatcher;
+
+  pub struct NativeWatcher {
+    watcher: RecommendedWatcher,
+    events: Receiver<notify::Result<Event>>,
+    files: HashSet<EcoString>,
+  }
+
+  impl NativeWatcher {
+    pub fn new() -> notify::Result<NativeWatcher> {
+      let (sender, events) = channel();
+      let watcher = notify::recommended_watcher(sender)?;
+      Ok(NativeWatcher {
+        watcher,
+        events,
+        files: HashSet::new(),
+      })
+    }
+  }
+
+  impl FileWatcher for NativeWatcher {
+    // Files are registered again after every build, since editors often
+    // replace a file when saving it
+    fn set_files(&mut self, files: HashSet<EcoString>) {
+      // Like polling, changes made while building are ignored, since the
+      // build replaces the output directory, which may be in a watched one
+      while self.events.try_recv().is_ok() {}
+      for path in self.files.drain() {
+        let _ = self.watcher.unwatch(Path::new(path.as_str()));
+      }
+      for path in files.iter() {
+        let _ = self
+          .watcher
+          .watch(Path::new(path.as_str()), RecursiveMode::NonRecursive);
+      }
+      self.files = files;
+    }
+
+    fn changed_within(&mut self, timeout: Duration) -> bool {
+      let deadline = Instant::now().checked_add(timeout);
+      loop {
+        let remaining = deadline.map_or(Duration::MAX, |deadline| {
+          deadline.saturating_duration_since(Instant::now())
+        });
+        match self.events.recv_timeout(remaining) {
+          // Opening or closing a file doesn't change it
+          Ok(Ok(event)) if !matches!(event.kind, EventKind::Access(_)) => return true,
+          Ok(_) => {}
+          Err(_) => return false,
+        }
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::{
+    cell::Cell,
+    rc::Rc,
+    time::{Duration, Instant, SystemTime},
+  };
+
+  use super::{wait_for_changes, Clock, FileWatcher, PollWatcher};
+
+  // Moves forward only when the watcher sleeps
+  struct FakeClock {
+    start: Instant,
+    elapsed: Rc<Cell<Duration>>,
+  }
+
+  impl Clock for FakeClock {
+    fn now(&self) -> Instant {
+      self.start + self.elapsed.get()
+    }
+
+    fn sleep(&mut self, duration: Duration) {
+      self.elapsed.set(self.elapsed.get() + duration);
+    }
+  }
+
+  // Watches `a.zog` and `b.zog`, polling every 50ms. Each file is written at
+  // the listed times in milliseconds, and `a.zog` is deleted at `deleted`
+  struct Test {
+    watcher: Box<dyn FileWatcher>,
+    elapsed: Rc<Cell<Duration>>,
+  }
+
+  impl Test {
+    fn new(writes: &'static [(&'static str, u64)], deleted: Option<u64>) -> Test {
+      let elapsed = Rc::new(Cell::new(Duration::ZERO));
+      let clock = FakeClock {
+        start: Instant::now(),
+        elapsed: elapsed.clone(),
+      };
+      let now = elapsed.clone();
+      let mut watcher = PollWatcher::with_clock(Duration::from_millis(50), clock, move |path| {
+        let now = now.get().as_millis() as u64;
+        if path == "a.zog" && deleted.is_some_and(|deleted| deleted <= now) {
+          return None;
+        }
+        let last_write = writes
+          .iter()
+          .filter(|(file, time)| *file == path && *time <= now)
+          .map(|(_, time)| *time)
+          .max()
+          .unwrap_or(0);
+        Some(SystemTime::UNIX_EPOCH + Duration::from_millis(last_write))
+      });
+      watcher.set_files(["a.zog".into(), "b.zog".into()].into());
+      Test {
+        watcher: Box::new(watcher),
+        elapsed,
+      }
+    }
+
+    // Waits for the next rebuild, returning when it starts in milliseconds
+    fn next_rebuild(&mut self) -> u128 {
+      wait_for_changes(self.watcher.as_mut());
+      self.elapsed.get().as_millis()
+    }
+  }
+
+  #[test]
+  fn rebuilds_once_changes_settle() {
+    let mut test = Test::new(&[("a.zog", 1000)], None);
+    assert_eq!(test.next_rebuild(), 1200);
+  }
+
+  #[test]
+  fn a_burst_of_changes_rebuilds_once() {
+    let mut test = Test::new(
+      &[
+        ("a.zog", 1000),
+        ("a.zog", 1100),
+        ("b.zog", 1150),
+        ("a.zog", 1250),
+        ("b.zog", 3000),
+      ],
+      None,
+    );
+    assert_eq!(test.next_rebuild(), 1450);
+    assert_eq!(test.next_rebuild(), 3200);
+  }
+
+  #[test]
+  fn changes_further_apart_than_the_debounce_rebuild_separately() {
+    let mut test = Test::new(&[("a.zog", 1000), ("b.zog", 1250)], None);
+    assert_eq!(test.next_rebuild(), 1200);
+    assert_eq!(test.next_rebuild(), 1450);
+  }
+
+  #[test]
+  fn deleting_a_file_is_a_change() {
+    let mut test = Test::new(&[("b.zog", 2000)], Some(500));
+    assert_eq!(test.next_rebuild(), 700);
+    assert_eq!(test.next_rebuild(), 2200);
+  }
+}
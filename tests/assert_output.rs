@@ -0,0 +1,81 @@
+mod common;
+
+use common::Project;
+
+const FUNCTIONS: &str = "namespace example
+
+module api {
+  fn fast_path() {
+    $y = 1
+    say hi
+  }
+
+  fn slow_path(x) {
+    $y = x
+    say a
+    say b
+  }
+";
+
+fn project(name: &str, assertion: &str) -> Project {
+  let project = Project::new(name);
+  project.file("main.zog", &format!("{FUNCTIONS}\n  {assertion}\n}}\n"));
+  project
+}
+
+#[test]
+fn max_commands_passes_at_the_limit() {
+  project(
+    "assert_output_max_pass",
+    r#"@assert_output("~/fast_path", max_commands = 2)"#,
+  )
+  .build(&[])
+  .assert_success();
+}
+
+#[test]
+fn max_commands_fails_over_the_limit() {
+  project(
+    "assert_output_max_fail",
+    r#"@assert_output("~/slow_path", max_commands = 2)"#,
+  )
+  .build(&[])
+  .assert_failure()
+  .assert_stderr_contains(
+    "Function `example:api/slow_path` compiled to 3 commands, but at most 2 are allowed:",
+  )
+  .assert_stderr_contains("say b");
+}
+
+#[test]
+fn forbid_passes_without_the_text() {
+  project(
+    "assert_output_forbid_pass",
+    r#"@assert_output("~/fast_path", forbid = ["data get", "function "])"#,
+  )
+  .build(&[])
+  .assert_success();
+}
+
+#[test]
+fn forbid_fails_with_the_text() {
+  project(
+    "assert_output_forbid_fail",
+    r#"@assert_output("~/slow_path", forbid = "data get")"#,
+  )
+  .build(&[])
+  .assert_failure()
+  .assert_stderr_contains(
+    "Function `example:api/slow_path` contains `data get`, which is forbidden:",
+  )
+  .assert_stderr_contains("run data get storage example:api/slow_path x")
+  .assert_stderr_lacks("say a");
+
+  project(
+    "assert_output_forbid_array_fail",
+    r#"@assert_output("~/slow_path", max_commands = 5, forbid = ["tellraw", "say b"])"#,
+  )
+  .build(&[])
+  .assert_failure()
+  .assert_stderr_contains("contains `say b`, which is forbidden:");
+}
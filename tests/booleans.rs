@@ -0,0 +1,89 @@
+mod common;
+
+use common::Project;
+
+const SOURCE: &str = r#"namespace example
+
+fn compare(a, b) {
+  flag = a > b
+  if flag != true {
+    @test_fail("runtime true")
+  }
+  if flag == false {
+    @test_fail("runtime false")
+  }
+  if flag != 1b {
+    @test_fail("runtime 1b")
+  }
+  if flag == 0 {
+    @test_fail("runtime 0")
+  }
+  other = a < b
+  if other != false {
+    @test_fail("runtime other false")
+  }
+  if other != 0b {
+    @test_fail("runtime other 0b")
+  }
+}
+
+#[test]
+fn booleans() {
+  compare(2, 1)
+  flag = 2 > 1
+  if flag != true {
+    @test_fail("folded true")
+  }
+  if flag != 1b {
+    @test_fail("folded 1b")
+  }
+  if flag == 0 {
+    @test_fail("folded 0")
+  }
+}
+"#;
+
+#[test]
+fn stored_conditions_compare_as_booleans() {
+  let project = Project::new("booleans_compare");
+  project.file("main.zog", SOURCE);
+  project.test().assert_success();
+}
+
+#[test]
+fn conditions_are_stored_as_bytes() {
+  let project = Project::new("booleans_stored");
+  project.file("main.zog", SOURCE);
+  project.build(&[]).assert_success();
+
+  let compare = project.function("example:compare");
+  assert!(
+    compare
+      .contains("execute store success storage example:compare flag byte 1 run execute if score"),
+    "{compare}"
+  );
+}
+
+#[test]
+fn wrong_boolean_comparisons_fail() {
+  let project = Project::new("booleans_wrong");
+  project.file(
+    "main.zog",
+    r#"namespace example
+
+fn check(a, b) {
+  flag = a > b
+  if flag == true {
+    @test_fail("reversed")
+  }
+}
+
+#[test]
+fn reversed() {
+  check(1, 2)
+  check(2, 1)
+}
+"#,
+  );
+  project.test().assert_failure();
+}
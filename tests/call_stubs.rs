@@ -0,0 +1,109 @@
+mod common;
+
+use common::Project;
+
+const SOURCE: &str = r#"namespace example
+
+fn storage_only(name, count = 3) {
+  tellraw @a "hello"
+}
+
+fn with_macro(%target, %amount = 5) {
+  give %target diamond %amount
+}
+
+fn scores($points, $bonus = 2) {
+  $total = $points + $bonus
+}
+
+fn comptime(&value) {
+  say &{value}
+}
+
+fn mixed(%who, $score, label = "hi") {
+  tellraw %who "hi"
+}
+
+fn none() {
+  say hi
+}
+
+module inner {
+  fn nested(value) {
+    return value
+  }
+}
+"#;
+
+#[test]
+fn stubs_cover_each_parameter_kind() {
+  let project = Project::new("call_stubs_kinds");
+  project.file("main.zog", SOURCE);
+  project.build(&["--emit-call-stubs"]).assert_success();
+
+  assert_eq!(
+    project.read("build/data/example/CALLING.md"),
+    r#"# Calling functions in `example`
+
+## `example:inner/nested`
+
+```mcfunction
+data modify storage example:inner/nested value set value <value>
+function example:inner/nested
+```
+
+## `example:mixed`
+
+```mcfunction
+data modify storage example:mixed __who set value <who>
+scoreboard players set $score example.mixed <score>
+data modify storage example:mixed label set value "hi"
+function example:mixed with storage example:mixed
+```
+
+## `example:storage_only`
+
+```mcfunction
+data modify storage example:storage_only name set value <name>
+data modify storage example:storage_only count set value 3
+function example:storage_only
+```
+
+## `example:with_macro`
+
+```mcfunction
+data modify storage example:with_macro __target set value <target>
+data modify storage example:with_macro __amount set value 5
+function example:with_macro with storage example:with_macro
+```
+"#
+  );
+}
+
+#[test]
+fn stubs_are_only_written_when_asked_for() {
+  let project = Project::new("call_stubs_off");
+  project.file("main.zog", SOURCE);
+  project.build(&[]).assert_success();
+  assert!(!project.exists("build/data/example/CALLING.md"));
+}
+
+#[test]
+fn stubs_use_the_storage_prefix() {
+  let project = Project::new("call_stubs_prefix");
+  project.file("main.zog", SOURCE);
+  project
+    .build(&["--emit-call-stubs", "--storage-prefix", "data"])
+    .assert_success();
+
+  let stubs = project.read("build/data/example/CALLING.md");
+  assert!(
+    stubs.contains("data modify storage example:data/storage_only name set value <name>\n"),
+    "{stubs}"
+  );
+  // Scoreboards aren't prefixed
+  assert!(
+    stubs.contains("scoreboard players set $score example.mixed <score>\n"),
+    "{stubs}"
+  );
+}
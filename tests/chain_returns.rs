@@ -0,0 +1,80 @@
+mod common;
+
+use common::Project;
+
+// Returns from each branch of an if-else chain, including from an if
+// statement nested in a branch. `trace` records which code ran after the
+// return should have happened
+fn source(sigil: &str) -> String {
+  format!(
+    "namespace example
+
+fn {sigil}pick(x) {{
+  example:state/trace = 0
+  if x == 1 {{
+    return 10
+  }} else if x == 2 {{
+    if x > 0 {{
+      return 20
+    }}
+    example:state/trace = 2
+  }} else if x == 3 {{
+    example:state/trace = 3
+  }} else {{
+    return 40
+  }}
+  example:state/trace += 10
+  return 99
+}}
+
+#[test]
+fn chain() {{
+  if pick(1) != 10 {{
+    @test_fail(\"if\")
+  }}
+  if example:state/trace != 0 {{
+    @test_fail(\"code after the if branch ran\")
+  }}
+  if pick(2) != 20 {{
+    @test_fail(\"else if\")
+  }}
+  if example:state/trace != 0 {{
+    @test_fail(\"code after the else if branch ran\")
+  }}
+  if pick(3) != 99 {{
+    @test_fail(\"fallthrough\")
+  }}
+  if example:state/trace != 13 {{
+    @test_fail(\"code after the chain was skipped\")
+  }}
+  if pick(4) != 40 {{
+    @test_fail(\"else\")
+  }}
+  if example:state/trace != 0 {{
+    @test_fail(\"code after the else branch ran\")
+  }}
+}}
+"
+  )
+}
+
+#[test]
+fn storage_returns() {
+  let project = Project::new("chain_returns_storage");
+  project.file("main.zog", &source("~"));
+  project.test().assert_success();
+}
+
+#[test]
+fn direct_returns() {
+  let project = Project::new("chain_returns_direct");
+  project.file("main.zog", &source("%"));
+  project.test().assert_success();
+}
+
+#[test]
+fn scoreboard_returns() {
+  let project = Project::new("chain_returns_scoreboard");
+  project.file("main.zog", &source("$"));
+  project.test().assert_success();
+}
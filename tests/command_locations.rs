@@ -0,0 +1,56 @@
+mod common;
+
+use common::Project;
+
+#[test]
+fn missing_comptime_variables_point_at_the_name() {
+  let project = Project::new("command_locations_missing");
+  project.file(
+    "main.zog",
+    "namespace example
+
+fn a() {
+  say &{&a}
+}
+
+fn b() {
+  say x
+  say before   &{&b} after
+}
+",
+  );
+  project
+    .build(&[])
+    .assert_failure()
+    .assert_stderr_contains("main.zog:4:10: \x1b[31mThe compile-time variable a is not in scope.")
+    .assert_stderr_contains("main.zog:9:19: \x1b[31mThe compile-time variable b is not in scope.")
+    .assert_stderr_contains("9 |   say before   &{&b} after");
+}
+
+#[test]
+fn errors_in_included_files_use_their_path() {
+  let project = Project::new("command_locations_include");
+  project.file(
+    "main.zog",
+    "namespace example
+
+include \"./src/api\"
+",
+  );
+  project.file(
+    "src/api.zog",
+    "namespace example
+
+fn api(x) {
+  &list = [1, 2]
+  tellraw @a \"value: &{&list[x]}\"
+}
+",
+  );
+  project
+    .build(&[])
+    .assert_failure()
+    .assert_stderr_contains("src/api.zog:5:25: \x1b[31mThis value cannot be statically resolved.")
+    .assert_stderr_contains("5 |   tellraw @a \"value: &{&list[x]}\"")
+    .assert_stderr_lacks("main.zog:");
+}
@@ -0,0 +1,138 @@
+// Runs `zog` on small projects written to a temporary directory. Each test
+// file uses some of these helpers
+#![allow(dead_code)]
+
+use std::{
+  fs,
+  path::{Path, PathBuf},
+  process::Command,
+};
+
+pub struct Project {
+  dir: PathBuf,
+}
+
+pub struct Output {
+  pub success: bool,
+  pub stdout: String,
+  pub stderr: String,
+}
+
+impl Output {
+  // Fails the test with everything `zog` printed
+  pub fn assert_success(&self) -> &Output {
+    assert!(
+      self.success,
+      "zog failed\n--- stdout\n{}\n--- stderr\n{}",
+      self.stdout, self.stderr
+    );
+    self
+  }
+
+  pub fn assert_failure(&self) -> &Output {
+    assert!(
+      !self.success,
+      "zog succeeded\n--- stdout\n{}\n--- stderr\n{}",
+      self.stdout, self.stderr
+    );
+    self
+  }
+
+  pub fn assert_stderr_contains(&self, text: &str) -> &Output {
+    assert!(
+      self.stderr.contains(text),
+      "expected stderr to contain {text:?}\n--- stderr\n{}",
+      self.stderr
+    );
+    self
+  }
+
+  pub fn assert_stderr_lacks(&self, text: &str) -> &Output {
+    assert!(
+      !self.stderr.contains(text),
+      "expected stderr not to contain {text:?}\n--- stderr\n{}",
+      self.stderr
+    );
+    self
+  }
+}
+
+impl Project {
+  // Each test uses its own directory, so tests can run in parallel
+  pub fn new(name: &str) -> Project {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join(name);
+    if dir.exists() {
+      fs::remove_dir_all(&dir).expect("Test directory can be removed");
+    }
+    fs::create_dir_all(&dir).expect("Test directory can be created");
+    Project { dir }
+  }
+
+  pub fn file(&self, path: &str, contents: &str) -> &Project {
+    let path = self.dir.join(path);
+    if let Some(parent) = path.parent() {
+      fs::create_dir_all(parent).expect("Parent directory can be created");
+    }
+    fs::write(path, contents).expect("File can be written");
+    self
+  }
+
+  pub fn zog(&self, args: &[&str]) -> Output {
+    let output = Command::new(env!("CARGO_BIN_EXE_zoglin"))
+      .args(args)
+      .current_dir(&self.dir)
+      .output()
+      .expect("zog can be run");
+    Output {
+      success: output.status.success(),
+      stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+      stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+    }
+  }
+
+  // Builds `main.zog` into `build`
+  pub fn build(&self, args: &[&str]) -> Output {
+    self.zog(&[&["build"], args].concat())
+  }
+
+  // Runs the `#[test]` functions in `main.zog`
+  pub fn test(&self) -> Output {
+    self.zog(&["test"])
+  }
+
+  pub fn read(&self, path: &str) -> String {
+    fs::read_to_string(self.dir.join(path)).unwrap_or_else(|e| panic!("Can't read {path}: {e}"))
+  }
+
+  pub fn exists(&self, path: &str) -> bool {
+    self.dir.join(path).exists()
+  }
+
+  // The commands of a built function, such as `example:foo/bar`
+  pub fn function(&self, function: &str) -> String {
+    let (namespace, path) = function.split_once(':').expect("Function has a namespace");
+    self.read(&format!(
+      "build/data/{namespace}/function/{path}.mcfunction"
+    ))
+  }
+
+  // The files under a directory, relative to it, sorted
+  pub fn files(&self, path: &str) -> Vec<String> {
+    let root = self.dir.join(path);
+    let mut files = Vec::new();
+    let mut directories = vec![root.clone()];
+    while let Some(directory) = directories.pop() {
+      for entry in fs::read_dir(&directory).expect("Directory can be read") {
+        let path = entry.expect("Entry can be read").path();
+        if path.is_dir() {
+          directories.push(path);
+        } else {
+          let relative = path.strip_prefix(&root).expect("Path is inside the root");
+          files.push(relative.to_string_lossy().replace('\\', "/"));
+        }
+      }
+    }
+    files.sort();
+    files
+  }
+}
@@ -0,0 +1,55 @@
+mod common;
+
+use common::Project;
+
+#[test]
+fn numeric_and_quoted_keys() {
+  let project = Project::new("compound_keys_literals");
+  project.file(
+    "main.zog",
+    r#"namespace example
+
+fn main() {
+  c = {0: "zero", "some key": 1, 1: {name: "a"}, 'say "hi"': 2}
+}
+
+fn members(d) {
+  a = d.0
+  b = d."some key"
+  n = d.1.name
+  d.0 = 5
+}
+"#,
+  );
+  project.build(&[]).assert_success();
+
+  let main = project.function("example:main");
+  assert!(
+    main.contains(r#"set value {"say \"hi\"": 2, "some key": 1, 0: "zero", 1: {name: "a"}}"#),
+    "{main}"
+  );
+
+  let members = project.function("example:members");
+  assert!(members.contains("example:members a set from storage example:members d.0"));
+  assert!(members.contains(r#"example:members b set from storage example:members d."some key""#));
+  assert!(members.contains("example:members n set from storage example:members d.1.name"));
+  assert!(members.contains("storage example:members d.0 set value 5"));
+}
+
+#[test]
+fn string_index_is_an_error() {
+  let project = Project::new("compound_keys_string_index");
+  project.file(
+    "main.zog",
+    r#"namespace example
+
+fn main(c) {
+  d = c["0"]
+}
+"#,
+  );
+  project
+    .build(&[])
+    .assert_failure()
+    .assert_stderr_contains("Strings can't be used as indexes");
+}
@@ -0,0 +1,119 @@
+mod common;
+
+use common::Project;
+
+#[test]
+fn folds_members_and_indexes_of_constants() {
+  let project = Project::new("constant_folding_members");
+  project.file(
+    "main.zog",
+    r#"namespace example
+
+fn main() {
+  config = {max: 10, names: ["a", "b"]}
+  a = config.max + 5
+  b = config.names[1]
+}
+"#,
+  );
+  project.build(&[]).assert_success();
+
+  let main = project.function("example:main");
+  assert!(main.contains("data modify storage example:main a set value 15"));
+  assert!(main.contains(r#"data modify storage example:main b set value "b""#));
+}
+
+#[test]
+fn commands_and_calls_stop_folding() {
+  let project = Project::new("constant_folding_invalidation");
+  project.file(
+    "main.zog",
+    r#"namespace example
+
+fn command() {
+  config = {max: 10}
+  data modify storage example:command config.max set value 3
+  c = config.max + 5
+}
+
+fn call() {
+  config = {max: 10}
+  command()
+  c = config.max + 5
+}
+"#,
+  );
+  project.build(&[]).assert_success();
+
+  for function in ["command", "call"] {
+    let text = project.function(&format!("example:{function}"));
+    assert!(
+      text.contains(&format!(
+        "run data get storage example:{function} config.max"
+      )),
+      "{text}"
+    );
+  }
+}
+
+#[test]
+fn folded_arithmetic_wraps_like_scoreboards() {
+  let project = Project::new("constant_folding_overflow");
+  project.file(
+    "main.zog",
+    r#"namespace example
+
+fn main() {
+  x = 2000000000
+  y = x + x
+  z = x * 3
+  w = (-2000000000) - x
+}
+"#,
+  );
+  project.build(&[]).assert_success();
+
+  let main = project.function("example:main");
+  assert!(main.contains("example:main y set value -294967296"));
+  assert!(main.contains("example:main z set value 1705032704"));
+  assert!(main.contains("example:main w set value 294967296"));
+}
+
+#[test]
+fn constant_scoreboards_wrap() {
+  let project = Project::new("constant_folding_scoreboards");
+  project.file(
+    "main.zog",
+    r#"namespace example
+
+fn main() {
+  $s = 2147483647
+  $s += 1
+  $t = -2147483647
+  $t -= 2
+  $u = 65536
+  $u *= 65536
+}
+
+fn compare() {
+  $max = 2147483647
+  if $b > $max {
+    say above
+  }
+  $min = -2147483647 - 1
+  if $b < $min {
+    say below
+  }
+}
+"#,
+  );
+  project.build(&[]).assert_success();
+
+  let main = project.function("example:main");
+  assert!(main.contains("scoreboard players set $s example.main -2147483648"));
+  assert!(main.contains("scoreboard players set $t example.main 2147483647"));
+  assert!(main.contains("scoreboard players set $u example.main 0"));
+
+  let compare = project.function("example:compare");
+  assert!(!compare.contains("say"), "{compare}");
+}
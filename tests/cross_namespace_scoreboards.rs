@@ -0,0 +1,70 @@
+mod common;
+
+use common::Project;
+
+// `alpha` and `beta` both use an objective owned by `zeta`, which never uses
+// it itself
+const SOURCE: &str = "namespace alpha
+
+fn show() {
+  $zeta:points[@s] = 5
+  $count[@s] = 1
+}
+
+namespace zeta
+
+fn other() {
+  say hi
+}
+
+namespace beta
+
+fn more() {
+  $zeta:points[@s] += 2
+  $zeta:stats/nested[@s] = $zeta:points[@s]
+  x = $zeta:points[@a]
+}
+";
+
+#[test]
+fn qualified_scoreboards_use_the_other_namespace_objective() {
+  let project = Project::new("cross_namespace_read_write");
+  project.file("main.zog", SOURCE);
+  project.build(&[]).assert_success();
+
+  assert_eq!(
+    project.function("alpha:show"),
+    "scoreboard players set @s zeta.points 5
+scoreboard players set @s alpha.show.count 1
+"
+  );
+  assert_eq!(
+    project.function("beta:more"),
+    "scoreboard players add @s zeta.points 2
+scoreboard players operation @s zeta.stats.nested = @s zeta.points
+execute store result storage beta:more x int 1 run scoreboard players get @a zeta.points
+"
+  );
+}
+
+#[test]
+fn objectives_are_added_once_by_their_namespace() {
+  let project = Project::new("cross_namespace_single_add");
+  project.file("main.zog", SOURCE);
+  project.build(&[]).assert_success();
+
+  assert_eq!(
+    project.function("zoglin:generated/zeta/load"),
+    "scoreboard objectives add zeta.points dummy
+scoreboard objectives add zeta.stats.nested dummy
+"
+  );
+  assert_eq!(
+    project.function("zoglin:generated/alpha/load"),
+    "scoreboard objectives add alpha.show.count dummy\n"
+  );
+  assert!(!project.exists("build/data/zoglin/function/generated/beta/load.mcfunction"));
+  let load = project.read("build/data/minecraft/tags/function/load.json");
+  assert!(load.contains("zoglin:generated/zeta/load"));
+  assert!(!load.contains("zoglin:generated/beta/load"));
+}
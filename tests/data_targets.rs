@@ -0,0 +1,72 @@
+mod common;
+
+use common::Project;
+
+#[test]
+fn entity_and_block_targets() {
+  let project = Project::new("data_targets_forms");
+  project.file(
+    "main.zog",
+    r#"namespace example
+
+fn main() {
+  v = entity(@s[tag=a]).Health
+  entity(@s).data.count += 1
+  block(~ ~1 ~).Items[0].count = 3
+  block(1 64 -3).Items[0].count = v
+  if entity(@s).OnGround {
+    say hi
+  }
+  u = block(^ ^ ^1).Items
+}
+"#,
+  );
+  project.build(&[]).assert_success();
+
+  let main = project.function("example:main");
+  assert!(main.contains("data modify storage example:main v set from entity @s[tag=a] Health"));
+  assert!(main.contains("run data get entity @s data.count"));
+  assert!(main.contains("data modify entity @s data.count set from"));
+  assert!(main.contains("data modify block ~ ~1 ~ Items[0].count set value 3"));
+  assert!(main.contains("data modify block 1 64 -3 Items[0].count set from storage example:main v"));
+  assert!(main.contains("run data get entity @s OnGround"));
+  assert!(main.contains("data modify storage example:main u set from block ^ ^ ^1 Items"));
+}
+
+#[test]
+fn functions_named_entity_and_block() {
+  let project = Project::new("data_targets_functions");
+  project.file(
+    "main.zog",
+    r#"namespace example
+
+fn block(x) {
+  return x
+}
+
+fn entity(a, b) {
+  return a
+}
+
+module utils {
+  fn block(x) {}
+}
+
+fn main() {
+  y = block(1)
+  z = entity(y, 2)
+  utils/block(-1)
+}
+"#,
+  );
+  project.build(&[]).assert_success();
+
+  assert!(project.exists("build/data/example/function/block.mcfunction"));
+  assert!(project.exists("build/data/example/function/entity.mcfunction"));
+  let main = project.function("example:main");
+  assert!(main.contains("data modify storage example:block x set value 1"));
+  assert!(main.contains("function example:block"));
+  assert!(main.contains("data modify storage example:entity a set from storage example:main y"));
+  assert!(main.contains("data modify storage example:utils/block x set value -1"));
+  assert!(main.contains("function example:utils/block"));
+}
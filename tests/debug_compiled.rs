@@ -0,0 +1,96 @@
+mod common;
+
+use common::Project;
+
+const SOURCE: &str = "namespace example
+
+fn tick() {
+  if $x > 1 {
+    say big
+  } else {
+    say small
+  }
+}
+
+module util {
+  fn count() {
+    while $n < 3 {
+      $n += 1
+    }
+  }
+}
+
+namespace other
+
+fn hello() {
+  say hi
+}
+";
+
+// The dump, without the first line, which includes the compile time
+fn dump(name: &str, mode: &str) -> String {
+  let project = Project::new(name);
+  project.file("main.zog", SOURCE);
+  let output = project.build(&["--debug", mode]);
+  output.assert_success();
+  assert!(
+    !project.exists("build"),
+    "The dump shouldn't write any files"
+  );
+  let (first, rest) = output.stdout.split_once('\n').expect("Dump has lines");
+  assert!(first.contains("Compiled in"), "{first}");
+  rest.to_string()
+}
+
+#[test]
+fn dump_snapshot() {
+  assert_eq!(
+    dump("debug_compiled", "compiled"),
+    "namespace example
+  fn tick
+    function zoglin:generated/example/tick/if_0
+  module util
+    fn count
+      function zoglin:generated/example/util/count/while_0
+namespace other
+  fn hello
+    say hi
+namespace zoglin
+  module generated
+    module example
+      fn load (generated)
+        scoreboard objectives add example.util.count dummy
+      module tick
+        fn if_0 (generated)
+          execute if score $x example.tick matches 2.. run return run say big
+          say small
+      module util
+        module count
+          fn while_0 (generated)
+            execute unless score $n example.util.count matches ..2 run return 0
+            scoreboard players add $n example.util.count 1
+            function zoglin:generated/example/util/count/while_0
+"
+  );
+}
+
+#[test]
+fn filtered_dump_snapshot() {
+  assert_eq!(
+    dump("debug_compiled_filter", "compiled=util"),
+    "namespace example
+  module util
+    fn count
+      function zoglin:generated/example/util/count/while_0
+namespace zoglin
+  module generated
+    module example
+      module util
+        module count
+          fn while_0 (generated)
+            execute unless score $n example.util.count matches ..2 run return 0
+            scoreboard players add $n example.util.count 1
+            function zoglin:generated/example/util/count/while_0
+"
+  );
+}
@@ -0,0 +1,120 @@
+mod common;
+
+use common::Project;
+
+const SOURCE: &str = "namespace example
+
+fn double(x) {
+  return x * 2
+}
+
+fn ~stored(x) {
+  return x
+}
+
+fn caller() {
+  y = double(3)
+  z = stored(4)
+}
+";
+
+// A config that sets `setting` on top of the required fields
+fn configured(name: &str, setting: &str, source: &str) -> Project {
+  let project = Project::new(name);
+  project.file(
+    "zoglin.json",
+    &format!(
+      r#"{{
+  "zoglin": ">= 0.1.0",
+  "entry": "main.zog",
+  "package": {{
+    "name": "example",
+    "version": "0.1.0",
+    "summary": "",
+    "author": "",
+    "supports": "~> 1.21.0"
+  }},
+  "meta": {{ "pack": {{ "pack_format": 48, "description": "" }} }},
+  "dependencies": {{}},
+  {setting}
+}}"#
+    ),
+  );
+  project.file("main.zog", source);
+  project
+}
+
+#[test]
+fn storage_is_the_default() {
+  let project = configured(
+    "default_return_storage",
+    r#""default_return": "storage""#,
+    SOURCE,
+  );
+  project.build(&[]).assert_success();
+
+  assert!(project
+    .function("example:double")
+    .contains("execute store result storage example:double return int 1 run"));
+  let caller = project.function("example:caller");
+  assert!(caller.contains("function example:double\n"));
+  assert!(caller.contains("storage example:caller y set from storage example:double return"));
+}
+
+#[test]
+fn direct_returns_with_return_run() {
+  let project = configured(
+    "default_return_direct",
+    r#""default_return": "direct""#,
+    SOURCE,
+  );
+  project.build(&[]).assert_success();
+
+  assert!(project
+    .function("example:double")
+    .contains("return run scoreboard players get"));
+  let caller = project.function("example:caller");
+  assert!(caller.contains("run function example:double\n"));
+  // A sigil still chooses storage
+  assert!(caller.contains("storage example:caller z set from storage example:stored return"));
+}
+
+#[test]
+fn scoreboard_returns_in_the_function_objective() {
+  let project = configured(
+    "default_return_scoreboard",
+    r#""default_return": "scoreboard""#,
+    SOURCE,
+  );
+  project.build(&[]).assert_success();
+
+  assert!(project
+    .function("example:double")
+    .contains("scoreboard players operation $return example.double ="));
+  let caller = project.function("example:caller");
+  assert!(caller.contains("scoreboard players set $return example.double 0"));
+  assert!(caller.contains("run scoreboard players get $return example.double"));
+  assert!(caller.contains("storage example:caller z set from storage example:stored return"));
+}
+
+#[test]
+fn explicit_return_type_is_required() {
+  let project = configured(
+    "default_return_explicit",
+    r#""require_explicit_return_type": true"#,
+    SOURCE,
+  );
+  project
+    .build(&[])
+    .assert_failure()
+    .assert_stderr_contains("main.zog:3:4")
+    .assert_stderr_contains("This project requires functions to choose how they return")
+    .assert_stderr_lacks("main.zog:7:");
+
+  let project = configured(
+    "default_return_explicit_sigils",
+    r#""require_explicit_return_type": true"#,
+    "namespace example\n\nfn %double(x) {\n  return x * 2\n}\n\nfn $less(x) {\n  return x - 1\n}\n",
+  );
+  project.build(&[]).assert_success();
+}
@@ -0,0 +1,48 @@
+mod common;
+
+use common::Project;
+
+fn build(name: &str, body: &str) -> (Project, common::Output) {
+  let project = Project::new(name);
+  project.file(
+    "main.zog",
+    &format!("namespace example\n\nfn main() {{\n{body}\n}}\n"),
+  );
+  let output = project.build(&[]);
+  (project, output)
+}
+
+#[test]
+fn constant_division_by_zero_is_an_error() {
+  for (index, body) in ["  x = 5 / 0", "  x = 5 % 0"].into_iter().enumerate() {
+    let (_, output) = build(&format!("division_zero_{index}"), body);
+    output
+      .assert_failure()
+      .assert_stderr_contains("Cannot divide by zero.");
+  }
+}
+
+#[test]
+fn scoreboard_division_by_zero_warns() {
+  let (_, output) = build("division_zero_scoreboard", "  $x /= 0");
+  output
+    .assert_success()
+    .assert_stderr_contains("Dividing a scoreboard by zero leaves it unchanged.");
+}
+
+#[test]
+fn dividing_the_smallest_int_by_minus_one_wraps() {
+  let (project, output) = build(
+    "division_overflow",
+    "  a = (-2147483647 - 1) / -1
+  b = (-2147483647 - 1) % -1
+  $s = -2147483647 - 1
+  $s /= -1",
+  );
+  output.assert_success();
+
+  let main = project.function("example:main");
+  assert!(main.contains("example:main a set value -2147483648"));
+  assert!(main.contains("example:main b set value 0"));
+  assert!(main.ends_with("scoreboard players set $s example.main -2147483648\n"));
+}
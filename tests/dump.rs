@@ -0,0 +1,45 @@
+mod common;
+
+use common::Project;
+
+const SOURCE: &str = r#"namespace example
+
+fn update(name) {
+  @dump($health, name)
+}
+"#;
+
+#[test]
+fn dump_prints_scoreboards_and_storage() {
+  let project = Project::new("dump_debug");
+  project.file("main.zog", SOURCE);
+  project.build(&["--debug"]).assert_success();
+
+  let update = project.function("example:update");
+  assert_eq!(
+    update.trim(),
+    r#"tellraw @s ["",{"color":"gray","text":"$health (scoreboard $health example.update) = "},{"score":{"name":"$health","objective":"example.update"}}," ",{"color":"gray","text":"name (storage example:update name) = "},{"nbt":"name","storage":"example:update"}]"#
+  );
+}
+
+#[test]
+fn dump_is_empty_in_release_builds() {
+  let project = Project::new("dump_release");
+  project.file("main.zog", SOURCE);
+  project.build(&[]).assert_success();
+
+  assert_eq!(project.function("example:update").trim(), "");
+}
+
+#[test]
+fn dump_message_target() {
+  let project = Project::new("dump_message_target");
+  project.file("main.zog", SOURCE);
+  project
+    .build(&["--debug", "--message-target", "@a[tag=debug]"])
+    .assert_success();
+
+  assert!(project
+    .function("example:update")
+    .starts_with("tellraw @a[tag=debug] "));
+}
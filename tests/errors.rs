@@ -0,0 +1,65 @@
+mod common;
+
+use common::Project;
+
+#[test]
+fn repeated_missing_symbol_is_grouped() {
+  let project = Project::new("errors_grouped");
+  let mut source = String::from("namespace example\n");
+  for index in 0..100 {
+    source.push_str(&format!("\nfn f{index}() {{\n  x = &config\n}}\n"));
+  }
+  project.file("main.zog", &source);
+
+  let output = project.build(&[]);
+  output
+    .assert_failure()
+    .assert_stderr_contains("main.zog:4:8: ")
+    .assert_stderr_contains(
+      "...and 99 more times, including at main.zog:8:8, main.zog:12:8, main.zog:16:8\n",
+    );
+  assert_eq!(output.stderr.matches("is not in scope").count(), 1);
+}
+
+#[test]
+fn distinct_errors_are_limited() {
+  let project = Project::new("errors_limited");
+  let mut source = String::from("namespace example\n");
+  for index in 0..100 {
+    source.push_str(&format!("\nfn f{index}() {{\n  x = &config{index}\n}}\n"));
+  }
+  project.file("main.zog", &source);
+
+  let output = project.build(&["--max-errors", "20"]);
+  output
+    .assert_failure()
+    .assert_stderr_contains("...and 80 more errors");
+  assert_eq!(output.stderr.matches("is not in scope").count(), 20);
+}
+
+#[test]
+fn same_message_at_different_places_is_not_grouped() {
+  let project = Project::new("errors_same_message");
+  project.file(
+    "main.zog",
+    r#"namespace example
+
+fn alarm() {}
+
+fn a() {
+  @schedule(alarm, "inf")
+}
+
+fn b() {
+  @schedule(alarm, "inf")
+}
+"#,
+  );
+
+  let output = project.build(&[]);
+  output
+    .assert_failure()
+    .assert_stderr_contains("main.zog:6:20: ")
+    .assert_stderr_contains("main.zog:10:20: ")
+    .assert_stderr_lacks("more time");
+}
@@ -0,0 +1,64 @@
+mod common;
+
+use common::Project;
+
+// `next` returns how many times it has been called, followed by its
+// argument, so the result of `pair` shows which argument was evaluated first
+fn source(sigil: &str) -> String {
+  format!(
+    "namespace example
+
+fn {sigil}next(x) {{
+  example:state/count += 1
+  return example:state/count * 10 + x
+}}
+
+fn {sigil}indirect() {{
+  return next(5)
+}}
+
+fn pair(a, b) {{
+  return a * 1000 + b
+}}
+
+#[test]
+fn same_function_twice() {{
+  example:state/count = 0
+  result = pair(next(1), next(2))
+  if result != 11022 {{
+    @test_fail(\"wrong order\")
+  }}
+}}
+
+#[test]
+fn through_another_function() {{
+  example:state/count = 0
+  result = pair(next(1), indirect())
+  if result != 11025 {{
+    @test_fail(\"wrong order\")
+  }}
+}}
+"
+  )
+}
+
+#[test]
+fn storage_results_are_evaluated_left_to_right() {
+  let project = Project::new("evaluation_order_storage");
+  project.file("main.zog", &source("~"));
+  project.test().assert_success();
+}
+
+#[test]
+fn scoreboard_results_are_evaluated_left_to_right() {
+  let project = Project::new("evaluation_order_scoreboard");
+  project.file("main.zog", &source("$"));
+  project.test().assert_success();
+}
+
+#[test]
+fn direct_results_are_evaluated_left_to_right() {
+  let project = Project::new("evaluation_order_direct");
+  project.file("main.zog", &source("%"));
+  project.test().assert_success();
+}
@@ -0,0 +1,52 @@
+mod common;
+
+use common::Project;
+
+const SHOULD_RETURN: &str = "execute if score $should_return zoglin.internal.example.vars matches -2147483648..2147483647 run return 0";
+
+#[test]
+fn return_in_execute_block_skips_remaining_entities() {
+  let project = Project::new("execute_return");
+  project.file(
+    "main.zog",
+    r#"namespace example
+
+fn first_low() {
+  execute "as @a" {
+    if entity(@s).Health < 5 {
+      return 1
+    }
+  }
+  return 0
+}
+"#,
+  );
+  project.build(&[]).assert_success();
+
+  let block = project.function("zoglin:generated/example/first_low/execute_1");
+  assert!(block.starts_with(SHOULD_RETURN), "{block}");
+  let function = project.function("example:first_low");
+  assert!(function
+    .starts_with("execute as @a run function zoglin:generated/example/first_low/execute_1\n"));
+}
+
+#[test]
+fn execute_block_without_return_has_no_check() {
+  let project = Project::new("execute_no_return");
+  project.file(
+    "main.zog",
+    r#"namespace example
+
+fn greet() {
+  execute "as @a" {
+    say hi
+    say bye
+  }
+}
+"#,
+  );
+  project.build(&[]).assert_success();
+
+  let block = project.function("zoglin:generated/example/greet/execute_0");
+  assert_eq!(block, "say hi\nsay bye\n");
+}
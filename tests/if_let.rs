@@ -0,0 +1,77 @@
+mod common;
+
+use common::Project;
+
+#[test]
+fn present_absent_and_else() {
+  let project = Project::new("if_let_cases");
+  project.file(
+    "main.zog",
+    r#"namespace example
+
+fn home_x(player) {
+  if let home = player.home {
+    return home.x
+  } else {
+    return -1
+  }
+}
+
+fn has_home(player) {
+  found = false
+  if let home = player.home {
+    found = true
+  }
+  return found
+}
+
+#[test]
+fn bindings() {
+  if home_x({home: {x: 5}}) != 5 {
+    @test_fail("present")
+  }
+  if home_x({}) != -1 {
+    @test_fail("else")
+  }
+  if has_home({}) {
+    @test_fail("absent")
+  }
+  if !has_home({home: {x: 1}}) {
+    @test_fail("present without else")
+  }
+}
+"#,
+  );
+  project.test().assert_success();
+
+  project.build(&[]).assert_success();
+  assert!(project
+    .function("example:has_home")
+    .contains("execute if data storage example:has_home player.home run function"));
+}
+
+#[test]
+fn compile_time_values_are_an_error() {
+  let project = Project::new("if_let_constant");
+  project.file(
+    "main.zog",
+    "namespace example\n\nfn f() {\n  if let x = 5 {\n    say hi\n  }\n}\n",
+  );
+  project
+    .build(&[])
+    .assert_failure()
+    .assert_stderr_contains("This value is known at compile time, so always exists.");
+}
+
+#[test]
+fn scoreboards_are_an_error() {
+  let project = Project::new("if_let_scoreboard");
+  project.file(
+    "main.zog",
+    "namespace example\n\nfn f() {\n  if let x = $score {\n    say hi\n  }\n}\n",
+  );
+  project
+    .build(&[])
+    .assert_failure()
+    .assert_stderr_contains("A scoreboard always has a value, so `if let` can't check it.");
+}
@@ -0,0 +1,122 @@
+mod common;
+
+use common::Project;
+
+const LIB: &str = r#"namespace lib {
+  module api {
+    fn bar() {
+      say bar
+    }
+  }
+
+  module util {
+    fn baz() {
+      say baz
+    }
+  }
+}
+"#;
+
+fn build(name: &str, source: &str) -> Project {
+  let project = Project::new(name);
+  project.file("main.zog", &format!("{LIB}\n{source}"));
+  project.build(&[]).assert_success();
+  project
+}
+
+#[test]
+fn imports_are_visible_to_child_modules() {
+  let project = build(
+    "imports_parent_to_child",
+    r#"namespace example {
+  import lib:util
+
+  module child {
+    module grandchild {
+      fn f() {
+        util/baz()
+      }
+    }
+  }
+}
+"#,
+  );
+  assert_eq!(
+    project.function("example:child/grandchild/f"),
+    "function lib:util/baz\n"
+  );
+}
+
+#[test]
+fn imports_are_not_visible_to_sibling_modules() {
+  let project = build(
+    "imports_siblings",
+    r#"namespace example {
+  module first {
+    import lib:api
+
+    fn g() {
+      api/bar()
+    }
+  }
+
+  module second {
+    fn h() {
+      api/bar()
+    }
+  }
+}
+"#,
+  );
+  assert_eq!(
+    project.function("example:first/g"),
+    "function lib:api/bar\n"
+  );
+  assert_eq!(
+    project.function("example:second/h"),
+    "function example:second/api/bar\n"
+  );
+}
+
+#[test]
+fn imports_apply_to_items_before_them() {
+  let project = build(
+    "imports_order",
+    r#"namespace example {
+  fn early() {
+    util/baz()
+  }
+
+  import lib:util
+}
+"#,
+  );
+  assert_eq!(project.function("example:early"), "function lib:util/baz\n");
+}
+
+#[test]
+fn global_imports_apply_to_the_whole_namespace_only() {
+  let project = build(
+    "imports_global",
+    r#"namespace other {
+  module shared {
+    global import lib:api
+  }
+
+  module user {
+    fn k() {
+      api/bar()
+    }
+  }
+}
+
+namespace example {
+  fn h() {
+    api/bar()
+  }
+}
+"#,
+  );
+  assert_eq!(project.function("other:user/k"), "function lib:api/bar\n");
+  assert_eq!(project.function("example:h"), "function example:api/bar\n");
+}
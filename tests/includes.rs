@@ -0,0 +1,95 @@
+mod common;
+
+use common::Project;
+
+// `main` includes `b` and `c`, which both include `d`
+fn diamond(name: &str) -> Project {
+  let project = Project::new(name);
+  project.file(
+    "main.zog",
+    r#"namespace example
+
+include "./lib/b"
+include "./lib/c"
+
+fn main() {
+  b()
+  c()
+  shared/d()
+}
+"#,
+  );
+  project.file(
+    "lib/b.zog",
+    r#"namespace example
+
+include "./d"
+
+fn b() {
+  shared/d()
+}
+"#,
+  );
+  project.file(
+    "lib/c.zog",
+    r#"namespace example
+
+include "./d"
+
+fn c() {
+  shared/d()
+}
+"#,
+  );
+  project.file(
+    "lib/d.zog",
+    r#"namespace example
+
+module shared {
+  fn d() {
+    say shared
+  }
+}
+
+fn tick() {
+  $ticks += 1
+}
+"#,
+  );
+  project
+}
+
+#[test]
+fn diamond_includes_compile_the_shared_file_once() {
+  let project = diamond("includes_diamond");
+  let output = project.build(&[]);
+  output
+    .assert_success()
+    .assert_stderr_contains("lib/c.zog:3:9")
+    .assert_stderr_contains("has already been included, not including it again.")
+    .assert_stderr_lacks("lib/b.zog:3:9")
+    .assert_stderr_lacks("already defined");
+  assert_eq!(
+    output.stderr.matches("has already been included").count(),
+    1
+  );
+
+  assert_eq!(
+    project.files("build/data/example/function"),
+    [
+      "b.mcfunction",
+      "c.mcfunction",
+      "main.mcfunction",
+      "shared/d.mcfunction",
+      "tick.mcfunction",
+    ]
+  );
+  assert_eq!(project.function("example:shared/d"), "say shared\n");
+  assert_eq!(
+    project
+      .read("build/data/minecraft/tags/function/tick.json")
+      .matches("example:tick")
+      .count(),
+    1
+  );
+}
@@ -0,0 +1,69 @@
+mod common;
+
+use common::Project;
+
+#[test]
+fn only_chains_and_loops_get_functions() {
+  let project = Project::new("inlining_files");
+  project.file(
+    "main.zog",
+    "namespace example
+
+fn constant() {
+  if false {
+    say a
+  } else if true {
+    say b
+  } else {
+    say c
+  }
+}
+
+fn single() {
+  if $x > 1 {
+    say big
+  }
+}
+
+fn chain() {
+  if $x > 1 {
+    say big
+  } else if $x < -1 {
+    say small
+  } else {
+    say zero
+  }
+}
+
+fn repeat() {
+  while $x < 10 {
+    $x += 1
+  }
+}
+",
+  );
+  project.build(&[]).assert_success();
+
+  assert_eq!(
+    project.files("build/data"),
+    [
+      "example/function/chain.mcfunction",
+      "example/function/constant.mcfunction",
+      "example/function/repeat.mcfunction",
+      "example/function/single.mcfunction",
+      "minecraft/tags/function/load.json",
+      "zoglin/function/generated/example/chain/if_0.mcfunction",
+      "zoglin/function/generated/example/load.mcfunction",
+      "zoglin/function/generated/example/repeat/while_0.mcfunction",
+    ]
+  );
+  assert_eq!(project.function("example:constant"), "say b\n");
+  assert_eq!(
+    project.function("example:single"),
+    "execute if score $x example.single matches 2.. run say big\n"
+  );
+  assert_eq!(
+    project.function("example:repeat"),
+    "function zoglin:generated/example/repeat/while_0\n"
+  );
+}
@@ -0,0 +1,204 @@
+mod common;
+
+use common::Project;
+
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+// Helpers live under a versioned path, written as `<version>` below
+fn golden(text: &str) -> String {
+  text.replace("<version>", VERSION)
+}
+
+fn helper(project: &Project, name: &str) -> String {
+  project.function(&format!("zoglin:internal/{VERSION}/{name}"))
+}
+
+const SOURCE: &str = "namespace example
+
+fn %pick(list, i) {
+  if i > 2 {
+    return list[i]
+  }
+  return 0
+}
+
+fn fields(object, key, text, start, end) {
+  a = object[key]
+  b = text[start..end]
+  c = text[start..]
+  d = object.[key]
+}
+";
+
+#[test]
+fn only_used_helpers_are_written() {
+  let project = Project::new("internals_used");
+  project.file("main.zog", SOURCE);
+  project.build(&[]).assert_success();
+
+  assert_eq!(
+    project.files("build/data"),
+    [
+      "example/function/fields.mcfunction",
+      "example/function/pick.mcfunction",
+      "minecraft/tags/function/load.json",
+      "zoglin/function/generated/example/load.mcfunction",
+      "zoglin/function/generated/example/pick/if_0.mcfunction",
+      &golden("zoglin/function/internal/<version>/dynamic_index.mcfunction"),
+      &golden("zoglin/function/internal/<version>/dynamic_member.mcfunction"),
+      &golden("zoglin/function/internal/<version>/dynamic_range_index.mcfunction"),
+      &golden("zoglin/function/internal/<version>/dynamic_range_index_no_end.mcfunction"),
+      &golden("zoglin/function/internal/<version>/reset_return.mcfunction"),
+    ]
+  );
+  assert_eq!(
+    project.function("example:fields"),
+    golden(
+      "data modify storage zoglin:internal/<version>/dynamic_index target set from storage example:fields object
+data modify storage zoglin:internal/<version>/dynamic_index __index set from storage example:fields key
+function zoglin:internal/<version>/dynamic_index with storage zoglin:internal/<version>/dynamic_index
+data modify storage example:fields a set from storage zoglin:internal/<version>/dynamic_index return
+data modify storage zoglin:internal/<version>/dynamic_range_index target set from storage example:fields text
+data modify storage zoglin:internal/<version>/dynamic_range_index __start set from storage example:fields start
+data modify storage zoglin:internal/<version>/dynamic_range_index __end set from storage example:fields end
+function zoglin:internal/<version>/dynamic_range_index with storage zoglin:internal/<version>/dynamic_range_index
+data modify storage example:fields b set from storage zoglin:internal/<version>/dynamic_range_index return
+data modify storage zoglin:internal/<version>/dynamic_range_index_no_end target set from storage example:fields text
+data modify storage zoglin:internal/<version>/dynamic_range_index_no_end __start set from storage example:fields start
+function zoglin:internal/<version>/dynamic_range_index_no_end with storage zoglin:internal/<version>/dynamic_range_index_no_end
+data modify storage example:fields c set from storage zoglin:internal/<version>/dynamic_range_index_no_end return
+data modify storage zoglin:internal/<version>/dynamic_member target set from storage example:fields object
+data modify storage zoglin:internal/<version>/dynamic_member __member set from storage example:fields key
+function zoglin:internal/<version>/dynamic_member with storage zoglin:internal/<version>/dynamic_member
+data modify storage example:fields d set from storage zoglin:internal/<version>/dynamic_member return
+"
+    )
+  );
+  assert_eq!(
+    project.function("example:pick"),
+    golden(
+      "execute store result score $var_0 zoglin.internal.example.vars run data get storage example:pick i
+execute if score $var_0 zoglin.internal.example.vars matches 3.. run function zoglin:generated/example/pick/if_0
+execute if score $should_return zoglin.internal.example.vars matches -2147483648..2147483647 run return run function zoglin:internal/<version>/reset_return
+return 0
+"
+    )
+  );
+}
+
+#[test]
+fn helper_bodies() {
+  let project = Project::new("internals_only");
+  project.file("main.zog", "namespace example\n\nfn main() {\n  x = 1\n}\n");
+  project.build(&["--emit-internals-only"]).assert_success();
+
+  assert_eq!(
+    project.files(&golden("build/data/zoglin/function/internal/<version>")),
+    [
+      "dynamic_index.mcfunction",
+      "dynamic_member.mcfunction",
+      "dynamic_range_index.mcfunction",
+      "dynamic_range_index_no_end.mcfunction",
+      "power.mcfunction",
+      "power_loop.mcfunction",
+      "reset_return.mcfunction",
+      "shift_left.mcfunction",
+      "shift_left_loop.mcfunction",
+      "shift_right.mcfunction",
+      "shift_right_loop.mcfunction",
+    ]
+  );
+  assert!(!project.exists("build/data/example"));
+
+  assert_eq!(
+    helper(&project, "dynamic_index"),
+    golden(
+      "data remove storage zoglin:internal/<version>/dynamic_index return
+$data modify storage zoglin:internal/<version>/dynamic_index return set from storage zoglin:internal/<version>/dynamic_index target[$(__index)]
+"
+    )
+  );
+  assert_eq!(
+    helper(&project, "dynamic_member"),
+    golden(
+      "data remove storage zoglin:internal/<version>/dynamic_member return
+$data modify storage zoglin:internal/<version>/dynamic_member return set from storage zoglin:internal/<version>/dynamic_member target.\"$(__member)\"
+"
+    )
+  );
+  assert_eq!(
+    helper(&project, "dynamic_range_index"),
+    golden(
+      "$data modify storage zoglin:internal/<version>/dynamic_range_index return set string storage zoglin:internal/<version>/dynamic_range_index target $(__start) $(__end)
+"
+    )
+  );
+  assert_eq!(
+    helper(&project, "dynamic_range_index_no_end"),
+    golden(
+      "$data modify storage zoglin:internal/<version>/dynamic_range_index_no_end return set string storage zoglin:internal/<version>/dynamic_range_index_no_end target $(__start)
+"
+    )
+  );
+  // The only helper that depends on the namespace using it
+  assert_eq!(
+    helper(&project, "reset_return"),
+    "scoreboard players operation $temp_return zoglin.internal.example.vars = $should_return zoglin.internal.example.vars
+scoreboard players reset $should_return zoglin.internal.example.vars
+return run scoreboard players get $temp_return zoglin.internal.example.vars
+"
+  );
+  assert_eq!(
+    helper(&project, "power"),
+    golden(
+      "scoreboard players set $result zoglin.internal.power 1
+execute if score $exponent zoglin.internal.power matches ..-1 run return run scoreboard players set $result zoglin.internal.power 0
+execute if score $exponent zoglin.internal.power matches 1.. run function zoglin:internal/<version>/power_loop
+"
+    )
+  );
+  assert_eq!(
+    helper(&project, "power_loop"),
+    golden(
+      "scoreboard players operation $result zoglin.internal.power *= $base zoglin.internal.power
+scoreboard players remove $exponent zoglin.internal.power 1
+execute if score $exponent zoglin.internal.power matches 1.. run function zoglin:internal/<version>/power_loop
+"
+    )
+  );
+  assert_eq!(
+    helper(&project, "shift_left"),
+    golden(
+      "execute if score $amount zoglin.internal.shift matches 32.. run return run scoreboard players set $value zoglin.internal.shift 0
+execute if score $amount zoglin.internal.shift matches 1.. run function zoglin:internal/<version>/shift_left_loop
+"
+    )
+  );
+  assert_eq!(
+    helper(&project, "shift_left_loop"),
+    golden(
+      "scoreboard players operation $value zoglin.internal.shift += $value zoglin.internal.shift
+scoreboard players remove $amount zoglin.internal.shift 1
+execute if score $amount zoglin.internal.shift matches 1.. run function zoglin:internal/<version>/shift_left_loop
+"
+    )
+  );
+  assert_eq!(
+    helper(&project, "shift_right"),
+    golden(
+      "scoreboard players set $two zoglin.internal.shift 2
+execute if score $amount zoglin.internal.shift matches 32.. run scoreboard players set $amount zoglin.internal.shift 31
+execute if score $amount zoglin.internal.shift matches 1.. run function zoglin:internal/<version>/shift_right_loop
+"
+    )
+  );
+  assert_eq!(
+    helper(&project, "shift_right_loop"),
+    golden(
+      "scoreboard players operation $value zoglin.internal.shift /= $two zoglin.internal.shift
+scoreboard players remove $amount zoglin.internal.shift 1
+execute if score $amount zoglin.internal.shift matches 1.. run function zoglin:internal/<version>/shift_right_loop
+"
+    )
+  );
+}
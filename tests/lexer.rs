@@ -0,0 +1,97 @@
+mod common;
+
+use common::Project;
+
+#[test]
+fn text_after_non_ascii_characters_is_read_correctly() {
+  let project = Project::new("lexer_non_ascii");
+  project.file(
+    "main.zog",
+    "namespace example\n\nfn greet() {\n  say héllo wörld\n  name = \"ñandú\"\n  tellraw @a \"done\"\n}\n",
+  );
+  project.build(&[]).assert_success();
+
+  assert_eq!(
+    project.function("example:greet"),
+    "say héllo wörld\ndata modify storage example:greet name set value \"ñandú\"\ntellraw @a \"done\"\n"
+  );
+}
+
+#[test]
+fn double_minus_before_an_operand_is_subtraction() {
+  let project = Project::new("lexer_double_minus");
+  project.file(
+    "main.zog",
+    r#"namespace example
+
+fn sub(x) {
+  return x--1
+}
+
+fn negate_twice(x) {
+  return --x
+}
+
+#[test]
+fn operators() {
+  $i = 1
+  $i++
+  $i--
+  $i++
+  if $i != 2 {
+    @test_fail("increment")
+  }
+  if sub(3) != 4 {
+    @test_fail("x--1")
+  }
+  if negate_twice(3) != 3 {
+    @test_fail("--x")
+  }
+}
+"#,
+  );
+  project.test().assert_success();
+}
+
+#[test]
+fn block_comments_are_skipped() {
+  let project = Project::new("lexer_block_comments");
+  project.file(
+    "main.zog",
+    r#"namespace example
+
+/* A comment
+   over several lines */
+fn add(a, b) { /* inline */
+  x = a /* in an expression */ + b
+  y = [1, /* between items */ 2]
+  /*
+  say not a command
+  */
+  name = "a /* not a comment */"
+  return x
+}
+"#,
+  );
+  project.build(&[]).assert_success();
+
+  let add = project.function("example:add");
+  assert!(add.contains("scoreboard players operation $var_0 zoglin.internal.example.vars += $var_1 zoglin.internal.example.vars"));
+  assert!(add.contains("storage example:add y set value [1, 2]"));
+  assert!(add.contains(r#"storage example:add name set value "a /* not a comment */""#));
+  assert!(!add.contains("say"), "{add}");
+}
+
+#[test]
+fn unterminated_block_comment_points_at_its_start() {
+  let project = Project::new("lexer_unterminated_block_comment");
+  project.file(
+    "main.zog",
+    "namespace example\n\nfn f() {\n  x = 1\n  /* never closed\n  y = 2\n}\n",
+  );
+  project
+    .build(&[])
+    .assert_failure()
+    .assert_stderr_contains("main.zog:5:3: ")
+    .assert_stderr_contains("Unterminated block comment");
+}
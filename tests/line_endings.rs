@@ -0,0 +1,96 @@
+mod common;
+
+use common::Project;
+
+const SOURCE: &str = r#"namespace example
+
+res predicates is_sneaking {
+  "condition": "minecraft:entity_properties",
+  "entity": "this"
+}
+
+fn load() {
+  say one
+  say two
+}
+"#;
+
+// One of each kind of text file the compiler writes
+const FILES: &[&str] = &[
+  "build/.zoglin-manifest.json",
+  "build/data/example/function/load.mcfunction",
+  "build/data/example/predicates/is_sneaking.json",
+  "build/data/minecraft/tags/function/load.json",
+  "build/pack.mcmeta",
+];
+
+fn built(name: &str, source: &str, args: &[&str]) -> Project {
+  let project = Project::new(name);
+  project.file("main.zog", source);
+  project.build(args).assert_success();
+  project
+}
+
+fn assert_lf(project: &Project) {
+  for file in FILES {
+    let text = project.read(file);
+    assert!(!text.contains('\r'), "{file} contains a carriage return");
+    assert!(
+      text.ends_with('\n') && !text.ends_with("\n\n"),
+      "{file} doesn't end with exactly one newline: {text:?}"
+    );
+  }
+}
+
+#[test]
+fn files_use_lf_by_default() {
+  let project = built("line_endings_lf", SOURCE, &[]);
+  assert_lf(&project);
+  assert_eq!(
+    project.read("build/data/example/function/load.mcfunction"),
+    "say one\nsay two\n"
+  );
+}
+
+#[test]
+fn crlf_sources_still_give_lf() {
+  let project = built(
+    "line_endings_crlf_source",
+    &SOURCE.replace('\n', "\r\n"),
+    &[],
+  );
+  assert_lf(&project);
+  assert!(project
+    .read("build/data/example/predicates/is_sneaking.json")
+    .ends_with("\"entity\": \"this\"\n}\n"));
+}
+
+#[test]
+fn crlf_can_be_chosen() {
+  let project = built("line_endings_crlf", SOURCE, &["--line-endings", "crlf"]);
+  for file in FILES {
+    let text = project.read(file);
+    assert!(
+      !text.replace("\r\n", "").contains(['\r', '\n']),
+      "{file} has a line ending other than CRLF: {text:?}"
+    );
+    assert!(
+      text.ends_with("\r\n") && !text.ends_with("\r\n\r\n"),
+      "{file} doesn't end with exactly one newline: {text:?}"
+    );
+  }
+  assert_eq!(
+    project.read("build/data/example/function/load.mcfunction"),
+    "say one\r\nsay two\r\n"
+  );
+}
+
+#[test]
+fn unknown_line_endings_are_rejected() {
+  let project = Project::new("line_endings_unknown");
+  project.file("main.zog", SOURCE);
+  project
+    .build(&["--line-endings", "cr"])
+    .assert_failure()
+    .assert_stderr_contains("invalid value 'cr'");
+}
@@ -0,0 +1,69 @@
+mod common;
+
+use common::Project;
+
+fn build(name: &str, body: &str) -> Project {
+  let project = Project::new(name);
+  project.file("main.zog", &format!("namespace example\n\n{body}"));
+  project.build(&[]).assert_success();
+  project
+}
+
+#[test]
+fn empty_project_has_no_load_function_or_tag() {
+  let project = build("load_empty", "fn f() {\n  say hi\n}\n");
+  assert_eq!(
+    project.files("build"),
+    [
+      ".zoglin-manifest.json",
+      "data/example/function/f.mcfunction",
+      "pack.mcmeta",
+    ]
+  );
+}
+
+#[test]
+fn user_load_function_is_the_only_tag_entry() {
+  let project = build("load_user_only", "fn load() {\n  say loaded\n}\n");
+  assert_eq!(
+    project.files("build/data"),
+    [
+      "example/function/load.mcfunction",
+      "minecraft/tags/function/load.json",
+    ]
+  );
+  assert_eq!(
+    project.read("build/data/minecraft/tags/function/load.json"),
+    "{\n  \"values\": [\n    \"example:load\"\n  ]\n}\n"
+  );
+}
+
+#[test]
+fn scoreboards_alone_get_a_generated_load_function() {
+  let project = build("load_scoreboards_only", "fn f() {\n  $count += 1\n}\n");
+  assert_eq!(
+    project.files("build/data"),
+    [
+      "example/function/f.mcfunction",
+      "minecraft/tags/function/load.json",
+      "zoglin/function/generated/example/load.mcfunction",
+    ]
+  );
+  assert_eq!(
+    project.read("build/data/minecraft/tags/function/load.json"),
+    "{\n  \"values\": [\n    \"zoglin:generated/example/load\"\n  ]\n}\n"
+  );
+  assert_eq!(
+    project.function("zoglin:generated/example/load"),
+    "scoreboard objectives add example.f dummy\n"
+  );
+}
+
+#[test]
+fn generated_load_runs_before_user_load() {
+  let project = build("load_both", "fn load() {\n  $count = 0\n}\n");
+  assert_eq!(
+    project.read("build/data/minecraft/tags/function/load.json"),
+    "{\n  \"values\": [\n    \"zoglin:generated/example/load\",\n    \"example:load\"\n  ]\n}\n"
+  );
+}
@@ -0,0 +1,135 @@
+mod common;
+
+use common::Project;
+
+fn load_tag(name: &str, source: &str) -> String {
+  let project = Project::new(name);
+  project.file("main.zog", source);
+  project.build(&[]).assert_success();
+  project.read("build/data/minecraft/tags/function/load.json")
+}
+
+// The load tag listing `functions` in order
+fn tag(functions: &[&str]) -> String {
+  let values: Vec<_> = functions
+    .iter()
+    .map(|function| format!("    \"{function}\""))
+    .collect();
+  format!("{{\n  \"values\": [\n{}\n  ]\n}}\n", values.join(",\n"))
+}
+
+// A namespace with a user load and a generated one
+fn namespace(name: &str, body: &str) -> String {
+  format!("namespace {name} {{\n{body}\n  fn load() {{\n    $value[@s] = 1\n  }}\n}}\n\n")
+}
+
+#[test]
+fn namespaces_load_in_declaration_order() {
+  let source = "namespace alpha
+
+fn load() {
+  $a[@s] = 1
+}
+
+namespace zeta
+
+fn load() {
+  $z[@s] = 1
+}
+
+namespace beta
+
+fn load() {
+  $b[@s] = 1
+}
+";
+  assert_eq!(
+    load_tag("load_order_lines", source),
+    tag(&[
+      "zoglin:generated/alpha/load",
+      "zoglin:generated/zeta/load",
+      "zoglin:generated/beta/load",
+      "alpha:load",
+      "zeta:load",
+      "beta:load",
+    ])
+  );
+
+  let source = namespace("alpha", "") + &namespace("zeta", "") + &namespace("beta", "");
+  assert_eq!(
+    load_tag("load_order_blocks", &source),
+    tag(&[
+      "zoglin:generated/alpha/load",
+      "zoglin:generated/zeta/load",
+      "zoglin:generated/beta/load",
+      "alpha:load",
+      "zeta:load",
+      "beta:load",
+    ])
+  );
+}
+
+#[test]
+fn included_namespaces_load_after_the_including_one() {
+  let project = Project::new("load_order_include");
+  project.file(
+    "main.zog",
+    "namespace main
+
+include \"./other\"
+
+fn load() {
+  say main
+}
+",
+  );
+  project.file(
+    "other.zog",
+    "namespace other
+
+fn load() {
+  say other
+}
+",
+  );
+  project.build(&[]).assert_success();
+  assert_eq!(
+    project.read("build/data/minecraft/tags/function/load.json"),
+    tag(&["main:load", "other:load"])
+  );
+}
+
+#[test]
+fn lower_priorities_load_first() {
+  let source = namespace("features", "")
+    + &namespace("core", "  &load_priority = -1\n")
+    + &namespace("extras", "  &load_priority = 1\n")
+    + &namespace("ui", "");
+  assert_eq!(
+    load_tag("load_order_priority", &source),
+    tag(&[
+      "zoglin:generated/core/load",
+      "zoglin:generated/features/load",
+      "zoglin:generated/ui/load",
+      "zoglin:generated/extras/load",
+      "core:load",
+      "features:load",
+      "ui:load",
+      "extras:load",
+    ])
+  );
+}
+
+#[test]
+fn priorities_must_be_integers() {
+  let project = Project::new("load_order_invalid");
+  project.file(
+    "main.zog",
+    &namespace("core", "  &load_priority = \"first\"\n"),
+  );
+  project
+    .build(&[])
+    .assert_failure()
+    .assert_stderr_contains("main.zog:2:20")
+    .assert_stderr_contains("`load_priority` must be an integer.");
+}
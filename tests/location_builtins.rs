@@ -0,0 +1,74 @@
+mod common;
+
+use common::Project;
+
+const MAIN: &str = r#"namespace example
+
+include "./src/api"
+
+fn &where() {
+  tellraw @a "at &{@file()}:&{@line()}"
+}
+
+fn report() {
+  here = @function_path()
+  tellraw @a "&{@file()}:&{@line()}"
+  &where()
+  if $x > 1 {
+    say a
+    tellraw @a "&{@function_path()}:&{@line()}"
+  }
+  while $x < 3 {
+    tellraw @a "&{@function_path()}"
+    $x += 1
+  }
+}
+"#;
+
+const API: &str = r#"namespace example
+
+module api {
+  fn info() {
+    tellraw @a "&{@file()}:&{@line()} &{@function_path()}"
+  }
+}
+"#;
+
+fn project(name: &str) -> Project {
+  let project = Project::new(name);
+  project.file("main.zog", MAIN);
+  project.file("src/api.zog", API);
+  project.build(&[]).assert_success();
+  project
+}
+
+#[test]
+fn included_files_use_their_own_path() {
+  let project = project("location_included");
+  assert_eq!(
+    project.function("example:api/info"),
+    "tellraw @a \"src/api.zog:5 example:api/info\"\n"
+  );
+  let report = project.function("example:report");
+  assert!(report.contains("storage example:report here set value \"example:report\""));
+  assert!(report.contains("tellraw @a \"main.zog:11\""));
+}
+
+#[test]
+fn generated_bodies_use_the_source_function() {
+  let project = project("location_generated");
+  assert!(project
+    .function("zoglin:generated/example/report/if_0")
+    .contains("tellraw @a \"example:report:15\""));
+  assert!(project
+    .function("zoglin:generated/example/report/while_1")
+    .contains("tellraw @a \"example:report\""));
+}
+
+#[test]
+fn compile_time_functions_use_where_they_are_written() {
+  let project = project("location_comptime");
+  assert!(project
+    .function("example:report")
+    .contains("tellraw @a \"at main.zog:6\""));
+}
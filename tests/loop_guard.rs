@@ -0,0 +1,103 @@
+mod common;
+
+use common::Project;
+
+const SOURCE: &str = "namespace example
+
+fn spin() {
+  say before
+  while true {
+    example:state/n += 1
+  }
+}
+";
+
+#[test]
+fn guard_commands() {
+  let project = Project::new("loop_guard_commands");
+  project.file("main.zog", SOURCE);
+  project.build(&["--loop-guard", "100"]).assert_success();
+
+  // The count is reset before the loop is called
+  assert_eq!(
+    project.function("example:spin"),
+    "say before
+scoreboard players set $var_0 zoglin.internal.example.vars 0
+function zoglin:generated/example/spin/while_0
+"
+  );
+  let body = project.function("zoglin:generated/example/spin/while_0");
+  let lines: Vec<&str> = body.lines().collect();
+  assert_eq!(
+    lines[0],
+    r#"execute if score $var_0 zoglin.internal.example.vars matches 100.. run return run tellraw @a[tag=zoglin.ops] {"color":"red","text":"Loop in example:spin stopped after 100 iterations."}"#
+  );
+  assert_eq!(
+    lines[1],
+    "scoreboard players add $var_0 zoglin.internal.example.vars 1"
+  );
+  assert_eq!(
+    lines.last(),
+    Some(&"function zoglin:generated/example/spin/while_0")
+  );
+}
+
+#[test]
+fn target_can_be_changed() {
+  let project = Project::new("loop_guard_target");
+  project.file("main.zog", SOURCE);
+  project
+    .build(&[
+      "--loop-guard",
+      "100",
+      "--loop-guard-target",
+      "@a[tag=admin]",
+    ])
+    .assert_success();
+  assert!(project
+    .function("zoglin:generated/example/spin/while_0")
+    .contains("run return run tellraw @a[tag=admin] "));
+}
+
+#[test]
+fn without_a_guard_the_loop_is_unchanged() {
+  let project = Project::new("loop_guard_off");
+  project.file("main.zog", SOURCE);
+  project.build(&[]).assert_success();
+  assert_eq!(
+    project.function("example:spin"),
+    "say before\nfunction zoglin:generated/example/spin/while_0\n"
+  );
+  assert!(!project
+    .function("zoglin:generated/example/spin/while_0")
+    .contains("tellraw"));
+}
+
+// Each call gets a fresh budget, so both stop after the same number of
+// iterations
+#[test]
+fn guard_stops_the_loop_each_time() {
+  let project = Project::new("loop_guard_runs");
+  project.file(
+    "main.zog",
+    &format!(
+      "{SOURCE}
+#[test]
+fn stops() {{
+  example:state/n = 0
+  spin()
+  if example:state/n != 50 {{
+    @test_fail(\"first run\")
+  }}
+  spin()
+  if example:state/n != 100 {{
+    @test_fail(\"second run\")
+  }}
+}}
+"
+    ),
+  );
+  project
+    .zog(&["test", "--loop-guard", "50"])
+    .assert_success();
+}
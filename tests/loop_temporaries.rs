@@ -0,0 +1,76 @@
+mod common;
+
+use common::Project;
+
+const SOURCE: &str = r#"namespace example
+
+fn grid(width, height) {
+  total = 0
+  i = 0
+  while i < height {
+    j = 0
+    while j < width + i {
+      k = 0
+      while k < i * 2 - j {
+        total += 1
+        k += 1
+      }
+      total += i * j + 1
+      j += 1
+    }
+    i += 1
+  }
+  return total
+}
+
+#[test]
+fn counts() {
+  # 2 + 9 + 26, one term for each value of `i`
+  if grid(2, 3) != 37 {
+    @test_fail("wrong total")
+  }
+  if grid(0, 0) != 0 {
+    @test_fail("wrong total without iterations")
+  }
+}
+"#;
+
+#[test]
+fn nested_loops_give_the_right_result() {
+  let project = Project::new("loop_temporaries_result");
+  project.file("main.zog", SOURCE);
+  project.test().assert_success();
+}
+
+// The temporaries a loop's condition is checked with are never written by
+// the loops inside it
+#[test]
+fn inner_loops_leave_outer_conditions_alone() {
+  let project = Project::new("loop_temporaries_reserved");
+  project.file("main.zog", SOURCE);
+  project.build(&[]).assert_success();
+
+  let loops: Vec<String> = (0..3)
+    .map(|index| project.function(&format!("zoglin:generated/example/grid/while_{index}")))
+    .collect();
+  for (index, outer) in loops.iter().enumerate() {
+    let condition = outer
+      .lines()
+      .find(|line| line.ends_with("run return 0"))
+      .expect("Loop checks its condition");
+    let temporaries: Vec<&str> = condition
+      .split(' ')
+      .filter(|word| word.starts_with("$var_"))
+      .collect();
+    assert_eq!(temporaries.len(), 2, "{condition}");
+
+    for inner in &loops[index + 1..] {
+      for temporary in &temporaries {
+        assert!(
+          !inner.contains(&format!("{temporary} ")),
+          "`{temporary}` of while_{index} is reused inside it:\n{inner}"
+        );
+      }
+    }
+  }
+}
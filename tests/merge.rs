@@ -0,0 +1,86 @@
+mod common;
+
+use common::Project;
+
+#[test]
+fn stored_compounds_merge_into_known_compounds() {
+  let project = Project::new("merge_stored");
+  project.file(
+    "main.zog",
+    r#"namespace example
+
+fn merge(extra) {
+  settings = {a: 1, b: 1}
+  settings += extra
+  return settings
+}
+
+fn merge_into(settings) {
+  settings += {b: 3}
+  return settings
+}
+
+#[test]
+fn merges() {
+  if merge({b: 2, c: 3}) != {a: 1, b: 2, c: 3} {
+    @test_fail("stored compound not merged")
+  }
+  if merge_into({a: 1, b: 2}) != {a: 1, b: 3} {
+    @test_fail("compound not merged into storage")
+  }
+}
+"#,
+  );
+  project.test().assert_success();
+  project.build(&[]).assert_success();
+
+  let merge = project.function("example:merge");
+  assert!(
+    merge.contains(
+      "data modify storage example:merge settings merge from storage example:merge extra"
+    ),
+    "{merge}"
+  );
+}
+
+#[test]
+fn unknown_storage_is_still_added() {
+  let project = Project::new("merge_numbers");
+  project.file(
+    "main.zog",
+    r#"namespace example
+
+fn add(a, b) {
+  a += b
+  return a
+}
+
+#[test]
+fn adds() {
+  if add(2, 3) != 5 {
+    @test_fail("not added")
+  }
+}
+"#,
+  );
+  project.test().assert_success();
+}
+
+#[test]
+fn only_compounds_merge_into_compounds() {
+  let project = Project::new("merge_number_into_compound");
+  project.file(
+    "main.zog",
+    r#"namespace example
+
+fn f() {
+  settings = {a: 1}
+  settings += 5
+}
+"#,
+  );
+  project
+    .build(&[])
+    .assert_failure()
+    .assert_stderr_contains("Only compounds can be merged into a compound.");
+}
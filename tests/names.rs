@@ -0,0 +1,120 @@
+mod common;
+
+use common::Project;
+
+fn build(name: &str, source: &str, args: &[&str]) -> (Project, common::Output) {
+  let project = Project::new(name);
+  project.file("main.zog", &format!("namespace example\n\n{source}"));
+  let output = project.build(args);
+  (project, output)
+}
+
+// Identifiers can't contain most characters, but project names can
+fn init(name: &str, pack: &str) -> common::Output {
+  Project::new(name).zog(&["init", pack])
+}
+
+#[test]
+fn uppercase_becomes_snake_case() {
+  let (_, output) = build("names_uppercase", "fn MyFunc() {\n  say a\n}\n", &[]);
+  output
+    .assert_failure()
+    .assert_stderr_contains("main.zog:3:4")
+    .assert_stderr_contains("`MyFunc` is not a valid function name. Did you mean `my_func`?");
+}
+
+#[test]
+fn acronyms_and_digits_start_words() {
+  let (_, output) = build(
+    "names_acronyms",
+    "fn HTTPServer() {\n  say a\n}\n\nfn Tower2Top() {\n  say b\n}\n",
+    &[],
+  );
+  output
+    .assert_failure()
+    .assert_stderr_contains(
+      "`HTTPServer` is not a valid function name. Did you mean `http_server`?",
+    )
+    .assert_stderr_contains("`Tower2Top` is not a valid function name. Did you mean `tower2_top`?");
+}
+
+#[test]
+fn modules_are_checked() {
+  let (_, output) = build(
+    "names_modules",
+    "module Tools {\n  fn go() {\n    say a\n  }\n}\n",
+    &[],
+  );
+  output
+    .assert_failure()
+    .assert_stderr_contains("main.zog:3:8")
+    .assert_stderr_contains("`Tools` is not a valid module name. Did you mean `tools`?");
+}
+
+#[test]
+fn spaces_become_underscores() {
+  init("names_spaces", "My Pack")
+    .assert_failure()
+    .assert_stderr_contains("`My Pack` is not a valid namespace name. Did you mean `my_pack`?");
+}
+
+#[test]
+fn dashes_are_kept_unless_the_name_is_invalid() {
+  init("names_dashes", "my-pack").assert_success();
+  init("names_dashes_invalid", "My-Pack")
+    .assert_failure()
+    .assert_stderr_contains("`My-Pack` is not a valid namespace name. Did you mean `my_pack`?");
+}
+
+#[test]
+fn other_characters_become_underscores() {
+  init("names_punctuation", "pack!!v2")
+    .assert_failure()
+    .assert_stderr_contains("`pack!!v2` is not a valid namespace name. Did you mean `pack_v2`?");
+  init("names_unicode", "café")
+    .assert_failure()
+    .assert_stderr_contains("`café` is not a valid namespace name. Did you mean `caf_`?");
+}
+
+#[test]
+fn names_with_nothing_usable_get_no_suggestion() {
+  init("names_unusable", "!!!")
+    .assert_failure()
+    .assert_stderr_contains("`!!!` is not a valid namespace name.")
+    .assert_stderr_lacks("Did you mean");
+}
+
+#[test]
+fn auto_fix_renames_and_warns() {
+  let (project, output) = build(
+    "names_auto_fix",
+    "fn MyFunc() {\n  say a\n}\n\nmodule Tools {\n  fn go() {\n    MyFunc()\n  }\n}\n",
+    &["--auto-fix-names"],
+  );
+  output
+    .assert_success()
+    .assert_stderr_contains("main.zog:3:4")
+    .assert_stderr_contains("`MyFunc` is not a valid name, renaming it to `my_func`.")
+    .assert_stderr_contains("`Tools` is not a valid name, renaming it to `tools`.");
+  assert_eq!(
+    project.files("build/data/example/function"),
+    ["my_func.mcfunction", "tools/go.mcfunction"]
+  );
+  assert_eq!(
+    project.function("example:tools/go"),
+    "function example:my_func\n"
+  );
+}
+
+#[test]
+fn auto_fix_rejects_collisions() {
+  let (_, output) = build(
+    "names_auto_fix_collision",
+    "fn MyFunc() {\n  say a\n}\n\nfn My_Func() {\n  say b\n}\n",
+    &["--auto-fix-names"],
+  );
+  output
+    .assert_failure()
+    .assert_stderr_contains("main.zog:7:4")
+    .assert_stderr_contains("`My_Func` and `MyFunc` would both be renamed to `my_func`.");
+}
@@ -0,0 +1,136 @@
+mod common;
+
+use common::Project;
+
+const SOURCE: &str = r#"namespace example
+
+fn load() {
+  tellraw @a "loaded"
+}
+"#;
+
+#[test]
+fn output_without_manifest_clears_generated_namespaces() {
+  let project = Project::new("output_no_manifest");
+  project.file("main.zog", SOURCE);
+  project.file("build/README.md", "Hand written");
+  project.file("build/data/example/function/old.mcfunction", "say old");
+  project.file(
+    "build/data/zoglin/function/generated/old.mcfunction",
+    "say old",
+  );
+  project.file("build/data/minecraft/loot_table/kept.json", "{}");
+
+  project
+    .build(&[])
+    .assert_success()
+    .assert_stderr_contains("The output has no readable manifest");
+
+  assert!(!project.exists("build/data/example/function/old.mcfunction"));
+  assert!(!project.exists("build/data/zoglin/function/generated/old.mcfunction"));
+  assert!(project.exists("build/README.md"));
+  assert!(project.exists("build/data/minecraft/loot_table/kept.json"));
+  assert!(project.exists("build/data/example/function/load.mcfunction"));
+}
+
+#[test]
+fn new_output_builds_without_notes() {
+  let project = Project::new("output_new");
+  project.file("main.zog", SOURCE);
+  project
+    .build(&[])
+    .assert_success()
+    .assert_stderr_lacks("manifest");
+  assert!(project.exists("build/.zoglin-manifest.json"));
+}
+
+// Builds once, then replaces the manifest with `manifest` and adds a stale
+// function it lists, along with a hand-written file
+fn rebuilt_project(name: &str, manifest: &str) -> Project {
+  let project = Project::new(name);
+  project.file("main.zog", SOURCE);
+  project.build(&[]).assert_success();
+  project.file("build/.zoglin-manifest.json", manifest);
+  project.file("build/data/example/function/stale.mcfunction", "say old");
+  project.file("build/data/example/function/hand.mcfunction", "say kept");
+  project
+}
+
+const STALE_FILES: &str = r#"[
+    "data/example/function/load.mcfunction",
+    "data/example/function/stale.mcfunction",
+    "pack.mcmeta"
+  ]"#;
+
+#[test]
+fn same_version_manifest_only_removes_stale_files() {
+  let version = env!("CARGO_PKG_VERSION");
+  let project = rebuilt_project(
+    "output_same_version",
+    &format!(r#"{{"zoglin": "{version}", "files": {STALE_FILES}}}"#),
+  );
+  project
+    .build(&[])
+    .assert_success()
+    .assert_stderr_lacks("rebuilt")
+    .assert_stderr_lacks("manifest");
+
+  assert!(!project.exists("build/data/example/function/stale.mcfunction"));
+  assert!(project.exists("build/data/example/function/hand.mcfunction"));
+  assert!(project.exists("build/data/example/function/load.mcfunction"));
+}
+
+#[test]
+fn other_version_manifest_rebuilds_everything() {
+  let project = rebuilt_project(
+    "output_other_version",
+    &format!(r#"{{"zoglin": "0.0.1", "files": {STALE_FILES}}}"#),
+  );
+  project
+    .build(&[])
+    .assert_success()
+    .assert_stderr_contains("built by Zoglin 0.0.1, so all of its files are being rebuilt");
+
+  assert!(!project.exists("build/data/example/function/stale.mcfunction"));
+  assert!(project.exists("build/data/example/function/hand.mcfunction"));
+  assert!(project.exists("build/data/example/function/load.mcfunction"));
+}
+
+#[test]
+fn old_manifest_format_is_another_version() {
+  let project = rebuilt_project("output_old_format", STALE_FILES);
+  project
+    .build(&[])
+    .assert_success()
+    .assert_stderr_contains("built by an older version of Zoglin");
+
+  assert!(!project.exists("build/data/example/function/stale.mcfunction"));
+  assert!(project.exists("build/data/example/function/hand.mcfunction"));
+  let manifest = project.read("build/.zoglin-manifest.json");
+  assert!(manifest.contains(r#""zoglin""#), "{manifest}");
+}
+
+#[test]
+fn unreadable_manifest_clears_generated_namespaces() {
+  let project = rebuilt_project("output_unreadable", "not json");
+  project
+    .build(&[])
+    .assert_success()
+    .assert_stderr_contains("The output has no readable manifest");
+
+  assert!(!project.exists("build/data/example/function/stale.mcfunction"));
+  assert!(!project.exists("build/data/example/function/hand.mcfunction"));
+  assert!(project.exists("build/data/example/function/load.mcfunction"));
+}
+
+#[test]
+fn version_check_can_be_skipped() {
+  let project = rebuilt_project("output_no_version_check", STALE_FILES);
+  project
+    .build(&["--no-version-check"])
+    .assert_success()
+    .assert_stderr_lacks("rebuilt");
+
+  assert!(!project.exists("build/data/example/function/stale.mcfunction"));
+  assert!(project.exists("build/data/example/function/hand.mcfunction"));
+}
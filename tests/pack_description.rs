@@ -0,0 +1,99 @@
+mod common;
+
+use common::Project;
+
+fn configured(name: &str, description: &str) -> Project {
+  let project = Project::new(name);
+  project.file(
+    "zoglin.json",
+    &format!(
+      r#"{{
+  "zoglin": ">= 0.1.0",
+  "entry": "main.zog",
+  "package": {{
+    "name": "example",
+    "version": "0.1.0",
+    "summary": "",
+    "author": "",
+    "supports": "~> 1.21.0"
+  }},
+  "meta": {{ "pack": {{ "pack_format": 48, "description": {description} }} }},
+  "dependencies": {{}}
+}}"#
+    ),
+  );
+  project.file("main.zog", "namespace example\n\nfn f() {\n  say hi\n}\n");
+  project
+}
+
+fn built_mcmeta(name: &str, description: &str) -> String {
+  let project = configured(name, description);
+  project.build(&[]).assert_success();
+  project.read("build/pack.mcmeta")
+}
+
+#[test]
+fn plain_string() {
+  assert_eq!(
+    built_mcmeta("pack_description_plain", r#""A pack""#),
+    r#"{
+  "pack": {
+    "pack_format": 48,
+    "description": "A pack"
+  }
+}
+"#
+  );
+}
+
+#[test]
+fn array_of_components() {
+  assert_eq!(
+    built_mcmeta(
+      "pack_description_array",
+      r#"[{ "text": "A ", "color": "gold" }, "pack"]"#
+    ),
+    r#"{
+  "pack": {
+    "pack_format": 48,
+    "description": [
+      {
+        "color": "gold",
+        "text": "A "
+      },
+      "pack"
+    ]
+  }
+}
+"#
+  );
+}
+
+#[test]
+fn object_component() {
+  assert_eq!(
+    built_mcmeta(
+      "pack_description_object",
+      r#"{ "translate": "pack.name", "bold": true }"#
+    ),
+    r#"{
+  "pack": {
+    "pack_format": 48,
+    "description": {
+      "bold": true,
+      "translate": "pack.name"
+    }
+  }
+}
+"#
+  );
+}
+
+#[test]
+fn other_values_are_rejected() {
+  let project = configured("pack_description_number", "5");
+  project
+    .build(&[])
+    .assert_failure()
+    .assert_stderr_contains("Expected a string or a text component for the description, got `5`");
+}
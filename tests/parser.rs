@@ -0,0 +1,57 @@
+mod common;
+
+use std::{sync::mpsc, thread, time::Duration};
+
+use common::{Output, Project};
+
+// Malformed input used to make some parser loops spin forever, so each build
+// is given a time limit
+fn build_in_time(name: &str, source: &str) -> Output {
+  let project = Project::new(name);
+  project.file("main.zog", source);
+  let (sender, receiver) = mpsc::channel();
+  thread::spawn(move || sender.send(project.build(&[])));
+  receiver
+    .recv_timeout(Duration::from_secs(20))
+    .unwrap_or_else(|_| panic!("Building {name} didn't finish"))
+}
+
+#[test]
+fn unclosed_compound_is_an_error() {
+  build_in_time(
+    "parser_unclosed_compound",
+    "namespace example\n\nfn f() {\n  x = {a: 1, b: 2\n}\n",
+  )
+  .assert_failure()
+  .assert_stderr_contains("Expected");
+}
+
+#[test]
+fn stray_token_in_array_is_an_error() {
+  build_in_time(
+    "parser_stray_array_token",
+    "namespace example\n\nfn f() {\n  x = [1, 2 } 3]\n}\n",
+  )
+  .assert_failure()
+  .assert_stderr_contains("Expected");
+}
+
+#[test]
+fn garbage_in_scoreboard_brackets_is_an_error() {
+  build_in_time(
+    "parser_scoreboard_garbage",
+    "namespace example\n\nfn f() {\n  $x[@a ) = 1\n}\n",
+  )
+  .assert_failure()
+  .assert_stderr_contains("Expected");
+}
+
+#[test]
+fn unclosed_compound_at_end_of_file_is_an_error() {
+  build_in_time(
+    "parser_unclosed_compound_eof",
+    "namespace example\n\nfn f() {\n  x = {a: ",
+  )
+  .assert_failure()
+  .assert_stderr_contains("Expected");
+}
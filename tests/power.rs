@@ -0,0 +1,135 @@
+mod common;
+
+use common::Project;
+
+fn build(name: &str, expression: &str) -> (Project, common::Output) {
+  let project = Project::new(name);
+  project.file(
+    "main.zog",
+    &format!("namespace example\n\nfn f() {{\n  y = {expression}\n}}\n"),
+  );
+  let output = project.build(&[]);
+  (project, output)
+}
+
+#[test]
+fn constants_are_folded() {
+  for (name, expression, result) in [
+    ("power_constant", "3 ** 4", "81"),
+    ("power_constant_zero", "0 ** 0", "1"),
+    ("power_constant_minimum", "(-2) ** 31", "-2147483648"),
+  ] {
+    let (project, output) = build(name, expression);
+    output.assert_success();
+    assert_eq!(
+      project.function("example:f"),
+      format!("data modify storage example:f y set value {result}\n")
+    );
+  }
+}
+
+#[test]
+fn constant_overflow_is_an_error() {
+  let (_, output) = build("power_overflow", "2 ** 31");
+  output
+    .assert_failure()
+    .assert_stderr_contains("main.zog:4:9")
+    .assert_stderr_contains("The result of this power is too large for an integer.");
+}
+
+#[test]
+fn constant_negative_exponents_are_an_error() {
+  let (_, output) = build("power_negative", "2 ** -1");
+  output
+    .assert_failure()
+    .assert_stderr_contains("main.zog:4:12")
+    .assert_stderr_contains("Cannot raise to a negative power, as the result isn't an integer.");
+}
+
+const RUNTIME: &str = r#"namespace example
+
+fn check(a, b, expected) {
+  if a ** b != expected {
+    @test_fail("wrong power")
+  }
+}
+
+#[test]
+fn powers() {
+  check(3, 4, 81)
+  check(7, 1, 7)
+  check(2, 0, 1)
+  check(0, 0, 1)
+  check(-2, 3, -8)
+}
+
+#[test]
+fn negative_exponents_give_zero() {
+  check(5, -1, 0)
+  check(1, -3, 0)
+}
+
+#[test]
+fn overflow_wraps() {
+  check(3, 20, -808182895)
+}
+"#;
+
+#[test]
+fn runtime_powers_use_the_helper() {
+  let project = Project::new("power_runtime");
+  project.file("main.zog", RUNTIME);
+  project.build(&[]).assert_success();
+  let check = project.function("example:check");
+  assert!(check.contains("run data get storage example:check a\n"));
+  assert!(check.contains(&format!(
+    "function zoglin:internal/{}/power\n",
+    env!("CARGO_PKG_VERSION")
+  )));
+  assert!(project
+    .function("zoglin:generated/example/load")
+    .contains("scoreboard objectives add zoglin.internal.power dummy\n"));
+
+  let output = project.test();
+  output.assert_success();
+  assert!(output.stdout.contains("3 passed, 0 failed"));
+}
+
+// A score used to be treated as never equal to storage, so these comparisons
+// were dropped
+#[test]
+fn scores_compare_with_storage() {
+  let project = Project::new("power_compare_storage");
+  project.file(
+    "main.zog",
+    r#"namespace example
+
+fn same(a, b, expected) {
+  return a * b == expected
+}
+
+fn different(a, b, expected) {
+  return a * b != expected
+}
+
+#[test]
+fn compares() {
+  if !same(3, 4, 12) {
+    @test_fail("3 * 4 == 12")
+  }
+  if same(3, 4, 13) {
+    @test_fail("3 * 4 != 13")
+  }
+  if different(3, 4, 12) {
+    @test_fail("3 * 4 == 12")
+  }
+  if !different(3, 4, 13) {
+    @test_fail("3 * 4 != 13")
+  }
+}
+"#,
+  );
+  let output = project.test();
+  output.assert_success();
+  assert!(output.stdout.contains("test example:compares ... ok"));
+}
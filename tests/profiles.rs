@@ -0,0 +1,90 @@
+mod common;
+
+use common::Project;
+
+const SOURCE: &str = r#"namespace example
+
+#[test_only]
+module harness {
+  #[tag("minecraft:tick")]
+  fn check() {
+    $example:harness/checks += 1
+  }
+}
+
+#[test_only]
+#[tag("example:setup")]
+fn setup() {
+  $example:stats/setup = 1
+}
+
+fn run() {
+  say run
+}
+"#;
+
+#[test]
+fn dev_profile_keeps_test_only_items() {
+  let project = Project::new("profiles_dev");
+  project.file("main.zog", SOURCE);
+  project.build(&[]).assert_success();
+
+  assert_eq!(
+    project.files("build/data"),
+    [
+      "example/function/harness/check.mcfunction",
+      "example/function/run.mcfunction",
+      "example/function/setup.mcfunction",
+      "example/tags/function/setup.json",
+      "minecraft/tags/function/load.json",
+      "minecraft/tags/function/tick.json",
+      "zoglin/function/generated/example/load.mcfunction",
+    ]
+  );
+  assert_eq!(
+    project.function("zoglin:generated/example/load"),
+    "scoreboard objectives add example.harness dummy\nscoreboard objectives add example.stats dummy\n"
+  );
+}
+
+#[test]
+fn release_profile_leaves_out_test_only_items() {
+  let project = Project::new("profiles_release");
+  project.file("main.zog", SOURCE);
+  project.build(&["--profile", "release"]).assert_success();
+
+  // Without scoreboards, there is nothing to load either
+  assert_eq!(
+    project.files("build/data"),
+    ["example/function/run.mcfunction"]
+  );
+}
+
+#[test]
+fn release_profile_rejects_calls_to_test_only_functions() {
+  let project = Project::new("profiles_call");
+  project.file("main.zog", &SOURCE.replace("  say run\n", "  setup()\n"));
+  project.build(&[]).assert_success();
+  project
+    .build(&["--profile", "release"])
+    .assert_failure()
+    .assert_stderr_contains(
+      "Function `example:setup` is test-only and excluded in release builds.",
+    );
+}
+
+#[test]
+fn release_profile_rejects_references_to_test_only_functions() {
+  let project = Project::new("profiles_reference");
+  project.file(
+    "main.zog",
+    &SOURCE.replace("  say run\n", "  &checker = @fn(harness/check)\n"),
+  );
+  project.build(&[]).assert_success();
+  project
+    .build(&["--profile", "release"])
+    .assert_failure()
+    .assert_stderr_contains(
+      "Function `example:harness/check` is test-only and excluded in release builds.",
+    );
+}
@@ -0,0 +1,74 @@
+mod common;
+
+use common::Project;
+
+// `greet` reads `%name` from its storage, so raw calls need `with storage`
+fn project(name: &str, caller: &str) -> Project {
+  let project = Project::new(name);
+  project.file(
+    "main.zog",
+    &format!(
+      r#"namespace example
+
+fn greet(%name) {{
+  tellraw @a "hi"
+}}
+
+fn plain(x) {{
+  y = x
+}}
+
+fn caller() {{
+{caller}
+}}
+"#
+    ),
+  );
+  project
+}
+
+#[test]
+fn calls_without_with_are_warned_about() {
+  let project = project(
+    "raw_calls_missing_with",
+    "  function example:greet\n  execute as @a run function example:greet",
+  );
+  project
+    .build(&[])
+    .assert_success()
+    .assert_stderr_contains("main.zog:12:3")
+    .assert_stderr_contains("main.zog:13:3")
+    .assert_stderr_contains(
+      "`example:greet` has macro parameters, so must be called `with storage example:greet`.",
+    );
+}
+
+#[test]
+fn calls_with_the_wrong_storage_are_warned_about() {
+  let project = project(
+    "raw_calls_wrong_storage",
+    "  function example:greet with storage example:other",
+  );
+  project
+    .build(&[])
+    .assert_success()
+    .assert_stderr_contains("main.zog:12:3")
+    .assert_stderr_contains(
+      "The macro parameters of `example:greet` are read from `storage example:greet`, not `storage example:other`.",
+    )
+    .assert_stderr_lacks("must be called");
+}
+
+#[test]
+fn correct_calls_are_not_warned_about() {
+  let project = project(
+    "raw_calls_correct",
+    "  function example:greet with storage example:greet
+  function example:greet with entity @s data
+  function example:plain",
+  );
+  project
+    .build(&[])
+    .assert_success()
+    .assert_stderr_lacks("macro parameters");
+}
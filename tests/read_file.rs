@@ -0,0 +1,148 @@
+mod common;
+
+use common::Project;
+
+// Reads a CSV file, splits it into rows and fields, and emits a command for
+// each row
+#[test]
+fn csv_pipeline() {
+  let project = Project::new("read_file_csv");
+  project.file("data/shop.csv", "apple,3\nbread,5\ncake,12\n");
+  project.file(
+    "main.zog",
+    "namespace example
+
+fn stock() {
+  &rows = @split(@read_file(\"data/shop.csv\"))
+  for &row in &rows {
+    &fields = @split(&row, \",\")
+    data modify storage example:shop prices.&{&fields[0]} set value &{&fields[1]}
+  }
+}
+
+#[test]
+fn prices() {
+  stock()
+  if example:shop/prices.apple != 3 {
+    @test_fail(\"apple\")
+  }
+  if example:shop/prices.cake != 12 {
+    @test_fail(\"cake\")
+  }
+}
+",
+  );
+  project.build(&[]).assert_success();
+  assert_eq!(
+    project.function("example:stock"),
+    "data modify storage example:shop prices.apple set value 3
+data modify storage example:shop prices.bread set value 5
+data modify storage example:shop prices.cake set value 12
+"
+  );
+  project.test().assert_success();
+}
+
+#[test]
+fn json_from_a_file() {
+  let project = Project::new("read_file_json");
+  project.file(
+    "data/items.json",
+    r#"{"sword": {"damage": 7}, "tags": ["a", "b"]}"#,
+  );
+  project.file(
+    "main.zog",
+    "namespace example
+
+fn load() {
+  items = @parse_json(@read_file(\"/data/items.json\"))
+}
+",
+  );
+  project.build(&[]).assert_success();
+  let load = project.function("example:load");
+  assert!(
+    load.contains("storage example:load items set value {"),
+    "{load}"
+  );
+  assert!(load.contains("sword: {damage: 7}"), "{load}");
+  assert!(load.contains(r#"tags: ["a", "b"]"#), "{load}");
+}
+
+#[test]
+fn arguments_must_be_known_at_compile_time() {
+  let project = Project::new("read_file_runtime");
+  project.file(
+    "main.zog",
+    "namespace example
+
+fn a(path) {
+  text = @read_file(path)
+}
+
+fn b(text) {
+  value = @parse_json(text)
+}
+
+fn c(text) {
+  &parts = @split(text, \",\")
+}
+",
+  );
+  let output = project.build(&[]);
+  output
+    .assert_failure()
+    .assert_stderr_contains("main.zog:4:")
+    .assert_stderr_contains("main.zog:8:")
+    .assert_stderr_contains("main.zog:12:");
+  assert_eq!(
+    output
+      .stderr
+      .matches("Invalid argument. Expected compile-time string.")
+      .count(),
+    3,
+    "{}",
+    output.stderr
+  );
+}
+
+#[test]
+fn files_outside_the_project_are_rejected() {
+  let project = Project::new("read_file_sandbox/project");
+  project.file("../secret.txt", "hidden");
+  project.file(
+    "main.zog",
+    "namespace example
+
+fn a() {
+  text = @read_file(\"../secret.txt\")
+}
+
+fn b() {
+  text = @read_file(\"/../secret.txt\")
+}
+",
+  );
+  let output = project.build(&[]);
+  output.assert_failure().assert_stderr_lacks("hidden");
+  assert_eq!(
+    output.stderr.matches("Cannot read `").count(),
+    2,
+    "{}",
+    output.stderr
+  );
+  output.assert_stderr_contains("as it is outside of the project directory.");
+}
+
+#[test]
+fn missing_files_are_an_error() {
+  let project = Project::new("read_file_missing");
+  project.file(
+    "main.zog",
+    "namespace example\n\nfn a() {\n  text = @read_file(\"nothing.txt\")\n}\n",
+  );
+  project
+    .build(&[])
+    .assert_failure()
+    .assert_stderr_contains("Could not read `nothing.txt`");
+}
@@ -0,0 +1,78 @@
+mod common;
+
+use common::Project;
+
+#[test]
+fn shim_forwards_each_parameter_kind() {
+  let project = Project::new("renamed_shim");
+  project.file(
+    "main.zog",
+    r#"namespace example
+
+module api {
+  #[renamed_from("getlevel")]
+  fn get_level($player, data, %name) {
+    return 1
+  }
+}
+"#,
+  );
+  project.build(&[]).assert_success();
+
+  let shim = project.function("example:api/getlevel");
+  let expected = [
+    "scoreboard players operation $player example.api.get_level = $player example.api.getlevel",
+    "data modify storage example:api/get_level data set from storage example:api/getlevel data",
+    "data modify storage example:api/get_level __name set from storage example:api/getlevel __name",
+    "execute unless score $renamed_0 zoglin.internal.example.renamed matches 1 run tellraw @s ",
+    "scoreboard players set $renamed_0 zoglin.internal.example.renamed 1",
+    "function example:api/get_level with storage example:api/get_level",
+    "data modify storage example:api/getlevel return set from storage example:api/get_level return",
+  ];
+  for (line, expected) in shim.lines().zip(expected) {
+    assert!(
+      line.starts_with(expected),
+      "{line:?} should start with {expected:?}"
+    );
+  }
+  assert_eq!(shim.lines().count(), expected.len());
+}
+
+#[test]
+fn notice_uses_message_target() {
+  let project = Project::new("renamed_message_target");
+  project.file(
+    "main.zog",
+    r#"namespace example
+
+#[renamed_from("old")]
+fn new() {}
+"#,
+  );
+  project
+    .build(&["--message-target", "@a[tag=admin]"])
+    .assert_success();
+
+  assert!(project
+    .function("example:old")
+    .contains("run tellraw @a[tag=admin] "));
+}
+
+#[test]
+fn collision_is_an_error() {
+  let project = Project::new("renamed_collision");
+  project.file(
+    "main.zog",
+    r#"namespace example
+
+fn old() {}
+
+#[renamed_from("old")]
+fn new() {}
+"#,
+  );
+  project
+    .build(&[])
+    .assert_failure()
+    .assert_stderr_contains("a function already exists there");
+}
@@ -0,0 +1,120 @@
+mod common;
+
+use common::Project;
+
+#[test]
+fn compound_return_slot_is_reset_to_an_empty_compound() {
+  let project = Project::new("return_reset_compound");
+  project.file(
+    "main.zog",
+    r#"namespace example
+
+fn settings(give) {
+  if give {
+    return {level: 3}
+  }
+  return
+}
+
+#[test]
+fn bare_return_leaves_an_empty_compound() {
+  full = settings(true)
+  if full.level != 3 {
+    @test_fail("returned compound")
+  }
+  result = settings(false)
+  if result != {} {
+    @test_fail("reset to something other than {}")
+  }
+  if result.level == 3 {
+    @test_fail("previous value kept")
+  }
+}
+"#,
+  );
+  project.test().assert_success();
+  project.build(&[]).assert_success();
+
+  let test = project.function("example:bare_return_leaves_an_empty_compound");
+  assert!(
+    test.contains("data modify storage example:settings return set value {}\n"),
+    "{test}"
+  );
+  assert!(!test.contains("return set value false"), "{test}");
+}
+
+#[test]
+fn other_shapes_reset_to_matching_defaults() {
+  let project = Project::new("return_reset_shapes");
+  project.file(
+    "main.zog",
+    r#"namespace example
+
+fn list() {
+  return [1, 2]
+}
+
+fn number() {
+  return 4
+}
+
+fn flag() {
+  return true
+}
+
+fn caller() {
+  a = list()
+  b = number()
+  c = flag()
+}
+"#,
+  );
+  project.build(&[]).assert_success();
+
+  let caller = project.function("example:caller");
+  assert!(
+    caller.contains("storage example:list return set value []\n"),
+    "{caller}"
+  );
+  assert!(
+    caller.contains("storage example:number return set value 0\n"),
+    "{caller}"
+  );
+  assert!(
+    caller.contains("storage example:flag return set value false\n"),
+    "{caller}"
+  );
+}
+
+#[test]
+fn mixed_return_types_warn_and_reset_to_false() {
+  let project = Project::new("return_reset_mixed");
+  project.file(
+    "main.zog",
+    r#"namespace example
+
+fn mixed(a) {
+  if a {
+    return {x: 1}
+  }
+  return 5
+}
+
+fn caller() {
+  value = mixed(true)
+}
+"#,
+  );
+  project
+    .build(&[])
+    .assert_success()
+    .assert_stderr_contains(
+      "This function returns values of different types, so callers can't rely on the type of its return value.",
+    );
+
+  let caller = project.function("example:caller");
+  assert!(
+    caller.contains("storage example:mixed return set value false\n"),
+    "{caller}"
+  );
+}
@@ -0,0 +1,95 @@
+mod common;
+
+use common::Project;
+
+// Checks the runtime conversions of `x` against the constant ones, which are
+// passed in
+const AGREE: &str = r#"namespace example
+
+fn agree(x, floor, ceil, round, assigned) {
+  if @floor(x) != floor {
+    @test_fail("@floor differs")
+  }
+  if @ceil(x) != ceil {
+    @test_fail("@ceil differs")
+  }
+  if @round(x) != round {
+    @test_fail("@round differs")
+  }
+  $score = x
+  if $score != assigned {
+    @test_fail("assigning differs")
+  }
+}
+"#;
+
+// A test comparing both conversions of each value
+fn test(name: &str, values: &[&str]) -> String {
+  let checks: Vec<_> = values
+    .iter()
+    .map(|value| {
+      format!(
+        "  $assigned = {value}\n  agree({value}, @floor({value}), @ceil({value}), @round({value}), $assigned)\n"
+      )
+    })
+    .collect();
+  format!("\n#[test]\nfn {name}() {{\n{}}}\n", checks.concat())
+}
+
+#[test]
+fn constant_and_runtime_conversions_agree() {
+  let project = Project::new("rounding_agree");
+  let source = [
+    AGREE.to_string(),
+    test("positive", &["0.4", "1.5", "2.5", "7.99", "3.0"]),
+    test(
+      "negative",
+      &["-0.4", "-0.5", "-1.5", "-2.5", "-7.99", "-3.0"],
+    ),
+    test("floats", &["2.5f", "-2.5f", "0.75f", "-0.75f"]),
+  ]
+  .concat();
+  project.file("main.zog", &source);
+  project.build(&[]).assert_success();
+
+  // The arguments are folded, so only `agree` converts at runtime
+  let positive = project.function("example:positive");
+  assert!(positive.contains(
+    "data merge storage example:agree {x: 2.5d, floor: 2, ceil: 3, round: 3, assigned: 2}\n"
+  ));
+  assert!(!positive.contains("data get"));
+  let negative = project.function("example:negative");
+  assert!(negative.contains(
+    "data merge storage example:agree {x: -2.5d, floor: -3, ceil: -2, round: -2, assigned: -3}\n"
+  ));
+
+  let output = project.test();
+  output.assert_success();
+  assert!(output.stdout.contains("3 passed, 0 failed"));
+}
+
+#[test]
+fn integers_are_unchanged() {
+  let project = Project::new("rounding_integers");
+  project.file(
+    "main.zog",
+    "namespace example
+
+fn f(x) {
+  $a = @floor(7)
+  $b = @round($a)
+  $c = @ceil(x)
+}
+",
+  );
+  project.build(&[]).assert_success();
+  assert_eq!(
+    project.function("example:f"),
+    "scoreboard players set $a example.f 7
+scoreboard players set $b example.f 7
+execute store result score $var_0 zoglin.internal.example.vars run data get storage example:f x -1
+scoreboard players operation $var_0 zoglin.internal.example.vars *= $-1 zoglin.internal.example.constants
+scoreboard players operation $c example.f = $var_0 zoglin.internal.example.vars
+"
+  );
+}
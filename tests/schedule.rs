@@ -0,0 +1,54 @@
+mod common;
+
+use common::Project;
+
+fn schedule(name: &str, delay: &str) -> (Project, common::Output) {
+  let project = Project::new(name);
+  project.file(
+    "main.zog",
+    &format!(
+      "namespace example\n\nfn alarm() {{}}\n\nfn start() {{\n  @schedule(alarm, {delay})\n}}\n"
+    ),
+  );
+  let output = project.build(&[]);
+  (project, output)
+}
+
+#[test]
+fn valid_delays() {
+  for (index, (delay, expected)) in [
+    ("10", "10t"),
+    ("\"10\"", "10"),
+    ("\"5t\"", "5t"),
+    ("\"1.5s\"", "1.5s"),
+    ("\"2d\"", "2d"),
+  ]
+  .into_iter()
+  .enumerate()
+  {
+    let (project, output) = schedule(&format!("schedule_valid_{index}"), delay);
+    output.assert_success();
+    assert_eq!(
+      project.function("example:start").trim(),
+      format!("schedule function example:alarm {expected}")
+    );
+  }
+}
+
+#[test]
+fn invalid_delays() {
+  for (index, delay) in [
+    "inf", "NaN", "1e3", "-5", ".5s", "5.", "+5", "0", "0.01t", "s", "10m",
+  ]
+  .into_iter()
+  .enumerate()
+  {
+    let (_, output) = schedule(
+      &format!("schedule_invalid_{index}"),
+      &format!("\"{delay}\""),
+    );
+    output
+      .assert_failure()
+      .assert_stderr_contains("Invalid delay");
+  }
+}
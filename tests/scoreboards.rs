@@ -0,0 +1,115 @@
+mod common;
+
+use common::Project;
+
+fn load(project: &Project) -> String {
+  project.function("zoglin:generated/example/load")
+}
+
+#[test]
+fn criteria_declared_after_dummy_use_wins() {
+  let project = Project::new("scoreboards_dummy_then_criteria");
+  project.file(
+    "main.zog",
+    r#"namespace example
+
+fn f() {
+  $example:stats/deaths[@s] = 0
+  @scoreboard(example:stats/deaths, "deathCount")
+}
+"#,
+  );
+  project.build(&[]).assert_success();
+  assert_eq!(
+    load(&project),
+    "scoreboard objectives add example.stats.deaths deathCount\n"
+  );
+}
+
+#[test]
+fn dummy_use_after_criteria_keeps_criteria() {
+  let project = Project::new("scoreboards_criteria_then_dummy");
+  project.file(
+    "main.zog",
+    r#"namespace example
+
+module a {
+  fn f() {
+    @scoreboard(example:stats/kills, "playerKillCount")
+  }
+}
+
+module b {
+  fn g() {
+    $example:stats/kills[@s] += 1
+    @scoreboard(example:stats/kills)
+  }
+}
+"#,
+  );
+  project.build(&[]).assert_success();
+  assert_eq!(
+    load(&project),
+    "scoreboard objectives add example.stats.kills playerKillCount\n"
+  );
+}
+
+#[test]
+fn conflicting_criteria_are_an_error() {
+  let project = Project::new("scoreboards_conflict");
+  project.file(
+    "main.zog",
+    r#"namespace example
+
+fn f() {
+  @scoreboard(example:stats/kills, "playerKillCount")
+  @scoreboard(example:stats/kills, "totalKillCount")
+}
+"#,
+  );
+  project
+    .build(&[])
+    .assert_failure()
+    .assert_stderr_contains(
+      "The objective `example.stats.kills` is declared with criteria `totalKillCount`, but was declared with `playerKillCount` at main.zog:4:",
+    );
+}
+
+#[test]
+fn each_objective_is_added_once() {
+  let project = Project::new("scoreboards_once");
+  project.file(
+    "main.zog",
+    r#"namespace example
+
+module a {
+  fn f() {
+    $count += 1
+    $example:stats/deaths[@s] = 0
+    @scoreboard(example:stats/deaths, "deathCount")
+  }
+}
+
+module b {
+  fn g() {
+    $example:a/count += 1
+    $example:stats/deaths[@s] += 1
+    @scoreboard(example:stats/deaths, "deathCount")
+  }
+}
+"#,
+  );
+  project.build(&[]).assert_success();
+
+  let load = load(&project);
+  let mut adds: Vec<_> = load
+    .lines()
+    .filter(|line| line.starts_with("scoreboard objectives add"))
+    .collect();
+  let count = adds.len();
+  adds.sort();
+  adds.dedup();
+  assert_eq!(adds.len(), count, "{load}");
+  assert!(load.contains("scoreboard objectives add example.a dummy\n"));
+  assert!(load.contains("scoreboard objectives add example.stats.deaths deathCount\n"));
+}
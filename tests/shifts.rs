@@ -0,0 +1,160 @@
+mod common;
+
+use common::Project;
+
+fn build(name: &str, body: &str) -> (Project, common::Output) {
+  let project = Project::new(name);
+  project.file(
+    "main.zog",
+    &format!("namespace example\n\nfn f(x) {{\n{body}\n}}\n"),
+  );
+  let output = project.build(&[]);
+  (project, output)
+}
+
+#[test]
+fn constants_are_folded() {
+  let (project, output) = build(
+    "shifts_constant",
+    "  a = 5 << 2
+  b = -5 >> 1
+  c = 3 << 30
+  d = 1 << 32
+  e = -1 << 40
+  f = -8 >> 31
+  g = -8 >> 40
+  h = 8 >> 31",
+  );
+  output.assert_success();
+  assert_eq!(
+    project.function("example:f"),
+    "data modify storage example:f a set value 20
+data modify storage example:f b set value -3
+data modify storage example:f c set value -1073741824
+data modify storage example:f d set value 0
+data modify storage example:f e set value 0
+data modify storage example:f f set value -1
+data modify storage example:f g set value -1
+data modify storage example:f h set value 0
+"
+  );
+}
+
+#[test]
+fn constant_negative_amounts_are_an_error() {
+  let (_, output) = build("shifts_negative", "  y = x << -1");
+  output
+    .assert_failure()
+    .assert_stderr_contains("main.zog:4:12")
+    .assert_stderr_contains("Cannot shift by a negative amount.");
+}
+
+#[test]
+fn constant_amounts_multiply_or_divide() {
+  let (project, output) = build(
+    "shifts_constant_amount",
+    "  $a = x << 3
+  $b = x >> 2
+  $c = x >> 31
+  $d = x << 32
+  $e = x >> 0",
+  );
+  output.assert_success();
+  // 2^31 doesn't fit in a score, so `>> 31` divides twice
+  assert_eq!(
+    project.function("example:f"),
+    "execute store result score $var_0 zoglin.internal.example.vars run data get storage example:f x
+scoreboard players operation $var_0 zoglin.internal.example.vars *= $8 zoglin.internal.example.constants
+scoreboard players operation $a example.f = $var_0 zoglin.internal.example.vars
+execute store result score $var_0 zoglin.internal.example.vars run data get storage example:f x
+scoreboard players operation $var_0 zoglin.internal.example.vars /= $4 zoglin.internal.example.constants
+scoreboard players operation $b example.f = $var_0 zoglin.internal.example.vars
+execute store result score $var_0 zoglin.internal.example.vars run data get storage example:f x
+scoreboard players operation $var_0 zoglin.internal.example.vars /= $1073741824 zoglin.internal.example.constants
+scoreboard players operation $var_0 zoglin.internal.example.vars /= $2 zoglin.internal.example.constants
+scoreboard players operation $c example.f = $var_0 zoglin.internal.example.vars
+execute store result score $var_0 zoglin.internal.example.vars run data get storage example:f x
+scoreboard players set $var_0 zoglin.internal.example.vars 0
+scoreboard players operation $d example.f = $var_0 zoglin.internal.example.vars
+execute store result score $var_0 zoglin.internal.example.vars run data get storage example:f x
+scoreboard players operation $e example.f = $var_0 zoglin.internal.example.vars
+"
+  );
+}
+
+const RUNTIME: &str = r#"namespace example
+
+fn left(x, n, expected) {
+  if x << n != expected {
+    @test_fail("wrong left shift")
+  }
+}
+
+fn right(x, n, expected) {
+  if x >> n != expected {
+    @test_fail("wrong right shift")
+  }
+}
+
+fn by_constants(x, left_2, left_32, right_1, right_31, right_40) {
+  if x << 2 != left_2 {
+    @test_fail("x << 2")
+  }
+  if x << 32 != left_32 {
+    @test_fail("x << 32")
+  }
+  if x >> 1 != right_1 {
+    @test_fail("x >> 1")
+  }
+  if x >> 31 != right_31 {
+    @test_fail("x >> 31")
+  }
+  if x >> 40 != right_40 {
+    @test_fail("x >> 40")
+  }
+}
+
+#[test]
+fn dynamic_amounts() {
+  left(5, 2, 20)
+  left(3, 30, -1073741824)
+  left(7, 0, 7)
+  left(1, 32, 0)
+  left(-1, 40, 0)
+  right(-20, 2, -5)
+  right(-5, 1, -3)
+  right(2147483647, 30, 1)
+  right(8, 31, 0)
+  right(-8, 31, -1)
+  right(-8, 40, -1)
+}
+
+#[test]
+fn negative_dynamic_amounts_change_nothing() {
+  left(100, -1, 100)
+  right(100, -1, 100)
+}
+
+#[test]
+fn constant_amounts() {
+  by_constants(5, 20, 0, 2, 0, 0)
+  by_constants(-5, -20, 0, -3, -1, -1)
+  by_constants(1073741824, 0, 0, 536870912, 0, 0)
+}
+"#;
+
+#[test]
+fn runtime_shifts_match_the_constant_rules() {
+  let project = Project::new("shifts_runtime");
+  project.file("main.zog", RUNTIME);
+  project.build(&[]).assert_success();
+  assert!(project.function("example:left").contains(&format!(
+    "function zoglin:internal/{}/shift_left\n",
+    env!("CARGO_PKG_VERSION")
+  )));
+  assert!(!project.function("example:by_constants").contains("shift"));
+
+  let output = project.test();
+  output.assert_success();
+  assert!(output.stdout.contains("3 passed, 0 failed"));
+}
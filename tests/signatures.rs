@@ -0,0 +1,56 @@
+mod common;
+
+use common::{Output, Project};
+
+const SOURCE: &str = r#"namespace example
+
+fn spawn(count, $speed, %type = "zombie") {
+  say spawning
+}
+
+fn place(&block) {
+  setblock ~ ~ ~ &{block}
+}
+"#;
+
+const SPAWN: &str =
+  r#"The function is declared as `fn example:spawn(count, $speed, %type = "zombie")`."#;
+
+fn build_with(name: &str, caller: &str) -> Output {
+  let project = Project::new(name);
+  project.file(
+    "main.zog",
+    &format!("{SOURCE}\nfn caller(value) {{\n  {caller}\n}}\n"),
+  );
+  project.build(&[])
+}
+
+#[test]
+fn too_few_arguments_show_the_signature() {
+  build_with("signatures_too_few", "spawn(1)")
+    .assert_failure()
+    .assert_stderr_contains(&format!(
+      "Expected at least 2 arguments, but got 1. {SPAWN}"
+    ));
+}
+
+#[test]
+fn too_many_arguments_show_the_signature() {
+  build_with("signatures_too_many", r#"spawn(1, 2, "skeleton", 4)"#)
+    .assert_failure()
+    .assert_stderr_contains(&format!("Expected at most 3 arguments, but got 4. {SPAWN}"));
+}
+
+#[test]
+fn unknown_compile_time_argument_shows_the_signature() {
+  build_with("signatures_kind", "place(value)")
+    .assert_failure()
+    .assert_stderr_contains(
+      "The compile-time parameter `&block` must be given a value known at compile time. The function is declared as `fn example:place(&block)`.",
+    );
+}
+
+#[test]
+fn matching_arguments_compile() {
+  build_with("signatures_match", r#"spawn(value, 2)"#).assert_success();
+}
@@ -0,0 +1,126 @@
+mod common;
+
+use common::Project;
+
+#[test]
+fn unsupported_commands_are_noted_and_skipped() {
+  let project = Project::new("simulator_unsupported");
+  project.file(
+    "main.zog",
+    "namespace example
+
+#[test]
+fn greets() {
+  say hello
+  $x = 1
+  if $x != 1 {
+    @test_fail(\"after skipped command\")
+  }
+}
+",
+  );
+  let output = project.test();
+  output
+    .assert_success()
+    .assert_stderr_contains("Skipped unsupported command `say hello`");
+  assert!(output.stdout.contains("test example:greets ... ok"));
+}
+
+#[test]
+fn test_fail_reports_its_message() {
+  let project = Project::new("simulator_fail");
+  project.file(
+    "main.zog",
+    "namespace example
+
+module nested {
+  #[test]
+  fn failing() {
+    @test_fail(\"expected failure\")
+  }
+}
+",
+  );
+  let output = project.test();
+  output
+    .assert_failure()
+    .assert_stderr_contains("main.zog:5:6")
+    .assert_stderr_contains("expected failure");
+  assert!(output
+    .stdout
+    .contains("test example:nested/failing ... FAILED"));
+}
+
+#[test]
+fn passes_and_failures_are_counted() {
+  let project = Project::new("simulator_counts");
+  project.file(
+    "main.zog",
+    "namespace example
+
+#[test]
+fn first() {}
+
+#[test]
+fn second() {
+  @test_fail(\"second\")
+}
+
+module inner {
+  #[test]
+  fn third() {}
+}
+
+fn not_a_test() {
+  @test_fail(\"not run\")
+}
+",
+  );
+  let output = project.test();
+  output.assert_failure().assert_stderr_lacks("not run");
+  assert!(
+    output.stdout.starts_with("Running 3 tests\n"),
+    "{}",
+    output.stdout
+  );
+  assert!(output.stdout.contains("test example:first ... ok"));
+  assert!(output.stdout.contains("test example:second ... FAILED"));
+  assert!(output.stdout.contains("test example:inner/third ... ok"));
+  assert!(output.stdout.contains("2 passed, 1 failed"));
+}
+
+// A branch whose only command is unsupported still returns, rather than
+// falling through to the next branch
+#[test]
+fn returns_run_through_unsupported_commands() {
+  let project = Project::new("simulator_return_unsupported");
+  project.file(
+    "main.zog",
+    "namespace example
+
+fn pick(x) {
+  y = 0
+  if x == 1 {
+    say one
+  } else {
+    y = 2
+  }
+  return y
+}
+
+#[test]
+fn branches() {
+  if pick(1) != 0 {
+    @test_fail(\"fell through\")
+  }
+  if pick(3) != 2 {
+    @test_fail(\"else\")
+  }
+}
+",
+  );
+  project
+    .test()
+    .assert_success()
+    .assert_stderr_contains("Skipped unsupported command `say one`");
+}
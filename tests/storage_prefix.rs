@@ -0,0 +1,95 @@
+mod common;
+
+use common::Project;
+
+const SOURCE: &str = r#"namespace example
+
+module foo {
+  fn bar(x) {
+    baz = x
+    ~/shared = 1
+  }
+}
+
+fn other() {
+  example:foo/bar/baz = 3
+  foo/bar(2)
+  some:custom/path/var = 5
+}
+"#;
+
+#[test]
+fn qualified_and_unqualified_variables_share_prefixed_storage() {
+  let project = Project::new("storage_prefix_forms");
+  project.file("main.zog", SOURCE);
+  project
+    .build(&["--storage-prefix", "data"])
+    .assert_success();
+
+  let bar = project.function("example:foo/bar");
+  assert!(bar.contains("storage example:data/foo/bar baz set from storage example:data/foo/bar x"));
+  assert!(bar.contains("storage example:data/foo shared set value 1"));
+
+  let other = project.function("example:other");
+  assert!(other.contains("storage example:data/foo/bar baz set value 3"));
+  assert!(other.contains("storage example:data/foo/bar x set value 2"));
+}
+
+#[test]
+fn prefix_leaves_other_namespaces_and_internals() {
+  let project = Project::new("storage_prefix_other");
+  project.file("main.zog", SOURCE);
+  project
+    .build(&["--storage-prefix", "data"])
+    .assert_success();
+
+  let other = project.function("example:other");
+  assert!(other.contains("storage some:custom/path var set value 5"));
+  for file in project.files("build/data") {
+    let text = project.read(&format!("build/data/{file}"));
+    for (index, _) in text.match_indices("storage example:") {
+      assert!(
+        text[index..].starts_with("storage example:data/"),
+        "Unprefixed variable storage in {file}:\n{text}"
+      );
+    }
+  }
+}
+
+#[test]
+fn scoreboard_parameters_are_not_prefixed() {
+  let project = Project::new("storage_prefix_scoreboards");
+  project.file(
+    "main.zog",
+    "namespace ex
+
+fn g($a, $b) {
+  if $a > 0 {
+    g($b, $a)
+  }
+}
+
+fn h() {
+  g(3, 4)
+}
+",
+  );
+  project
+    .build(&["--storage-prefix", "data"])
+    .assert_success();
+
+  let h = project.function("ex:h");
+  assert!(h.contains("scoreboard players set $a ex.g 3"));
+  assert!(h.contains("scoreboard players set $b ex.g 4"));
+  // The swapped argument is still copied before `$a` is overwritten
+  let body = project.function("zoglin:generated/ex/g/if_0");
+  assert!(
+    body.starts_with("scoreboard players operation $var_0 zoglin.internal.ex.vars = $a ex.g\n"),
+    "{body}"
+  );
+  assert!(body.contains("scoreboard players operation $a ex.g = $b ex.g"));
+  assert!(body.contains("scoreboard players operation $b ex.g = $var_0 zoglin.internal.ex.vars"));
+  assert!(!project.files("build/data").iter().any(|file| project
+    .read(&format!("build/data/{file}"))
+    .contains("ex.data.")));
+}
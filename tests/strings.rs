@@ -0,0 +1,80 @@
+mod common;
+
+use common::Project;
+
+#[test]
+fn joined_text_can_contain_quotes_and_backslashes() {
+  let project = Project::new("strings_escaped");
+  project.file(
+    "main.zog",
+    r#"namespace example
+
+fn join(a, b) {
+  return a + "\"q\\" + b
+}
+
+#[test]
+fn joins() {
+  if join("x", "y") != "x\"q\\y" {
+    @test_fail("wrong join")
+  }
+}
+"#,
+  );
+  project.test().assert_success();
+}
+
+#[test]
+fn chain_is_joined_once_without_copies() {
+  let project = Project::new("strings_chain");
+  project.file(
+    "main.zog",
+    r#"namespace example
+
+fn f(a, b) {
+  x = a + "-" + b + 1 + "!"
+}
+"#,
+  );
+  project.build(&[]).assert_success();
+
+  let f = project.function("example:f");
+  assert_eq!(f.matches("function zoglin:generated").count(), 1, "{f}");
+  assert!(f.ends_with(
+    "data modify storage example:f x set string storage zoglin:generated/example/f/join_0 return 0\n"
+  ), "{f}");
+  assert_eq!(
+    project.function("zoglin:generated/example/f/join_0"),
+    "$data modify storage zoglin:generated/example/f/join_0 return set value \"$(__0)-$(__1)1!\"\n"
+  );
+}
+
+#[test]
+fn joins_inside_compounds_and_arguments() {
+  let project = Project::new("strings_nested");
+  project.file(
+    "main.zog",
+    r#"namespace example
+
+fn greet(text) {
+  return text + "!"
+}
+
+fn check(name) {
+  tag = {label: "<" + name + ">"}
+  if tag.label != "<Steve>" {
+    @test_fail("wrong compound")
+  }
+  if greet("hi " + name) != "hi Steve!" {
+    @test_fail("wrong argument")
+  }
+}
+
+#[test]
+fn nested() {
+  check("Steve")
+}
+"#,
+  );
+  project.test().assert_success();
+}
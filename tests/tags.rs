@@ -0,0 +1,73 @@
+mod common;
+
+use common::Project;
+
+#[test]
+fn tag_resources_merge_with_generated_values() {
+  let project = Project::new("tags_merge");
+  project.file(
+    "main.zog",
+    r#"namespace example {
+  fn load() {}
+}
+
+namespace minecraft {
+  res tags/function load {
+    values: ["example:api/setup", "example:load"]
+  }
+}
+"#,
+  );
+  project.build(&[]).assert_success();
+
+  let tag: serde_json::Value =
+    serde_json::from_str(&project.read("build/data/minecraft/tags/function/load.json")).unwrap();
+  assert!(tag.get("replace").is_none());
+  // The generated entry comes first, and the repeated one is removed
+  assert_eq!(
+    tag["values"],
+    serde_json::json!(["example:load", "example:api/setup"])
+  );
+}
+
+#[test]
+fn replace_is_kept_without_values() {
+  let project = Project::new("tags_replace_empty");
+  project.file(
+    "main.zog",
+    r#"namespace example {
+  res tags/function every_second {
+    replace: true,
+    values: []
+  }
+
+  #[tag("example:every_second")]
+  fn update() {}
+}
+"#,
+  );
+  project.build(&[]).assert_success();
+
+  let tag: serde_json::Value =
+    serde_json::from_str(&project.read("build/data/example/tags/function/every_second.json"))
+      .unwrap();
+  assert_eq!(tag["replace"], true);
+  assert_eq!(tag["values"], serde_json::json!(["example:update"]));
+}
+
+#[test]
+fn other_duplicate_resources_are_errors() {
+  let project = Project::new("tags_duplicate_other");
+  project.file(
+    "main.zog",
+    r#"namespace example {
+  res loot_table chest {}
+  res loot_table chest {}
+}
+"#,
+  );
+  project
+    .build(&[])
+    .assert_failure()
+    .assert_stderr_contains("already defined");
+}
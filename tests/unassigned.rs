@@ -0,0 +1,90 @@
+mod common;
+
+use common::Project;
+
+fn build(name: &str, source: &str) -> common::Output {
+  let project = Project::new(name);
+  project.file("main.zog", source);
+  project.build(&[])
+}
+
+#[test]
+fn typos_suggest_an_assigned_name() {
+  build(
+    "unassigned_typo",
+    "namespace example
+
+fn typo() {
+  totals = 1
+  y = total + 1
+}
+",
+  )
+  .assert_success()
+  .assert_stderr_contains(
+    "main.zog:5:7: \x1b[33mVariable `total` is never assigned in this function. Did you mean `totals`?",
+  );
+}
+
+#[test]
+fn each_name_is_warned_about_once() {
+  let output = build(
+    "unassigned_once",
+    "namespace example
+
+fn never() {
+  y = unknown_thing
+  z = unknown_thing
+}
+",
+  );
+  output
+    .assert_success()
+    .assert_stderr_contains("main.zog:4:7")
+    .assert_stderr_contains("Variable `unknown_thing` is never assigned in this function.")
+    .assert_stderr_lacks("main.zog:5:7")
+    .assert_stderr_lacks("Did you mean");
+}
+
+#[test]
+fn parameters_paths_and_qualified_variables_are_allowed() {
+  build(
+    "unassigned_allowed",
+    "namespace example
+
+fn params(amount) {
+  y = amount
+  z = y.inner
+  w = y[0]
+}
+
+fn qualified() {
+  y = example:other/value
+  z = ~/state/count
+}
+",
+  )
+  .assert_success()
+  .assert_stderr_lacks("is never assigned");
+}
+
+#[test]
+fn the_comment_silences_the_function() {
+  build(
+    "unassigned_comment",
+    "namespace example
+
+fn allowed() {
+  # zoglin: allow-unassigned
+  y = missing
+}
+
+fn warned() {
+  y = missing
+}
+",
+  )
+  .assert_success()
+  .assert_stderr_lacks("main.zog:5:7")
+  .assert_stderr_contains("main.zog:9:7: \x1b[33mVariable `missing` is never assigned");
+}
@@ -0,0 +1,93 @@
+mod common;
+
+use common::Project;
+
+const SOURCE: &str = r#"namespace example
+
+module ui wraps "as @e[tag=ui,limit=1] at @s" {
+  fn show(x) {
+    say shown
+    hide()
+  }
+
+  fn hide() {
+    say hidden
+  }
+
+  #[tag("minecraft:tick")]
+  fn tick() {
+    say ticking
+  }
+
+  module inner wraps "positioned 0 0 0" {
+    fn place() {
+      say placed
+      hide()
+    }
+  }
+}
+
+fn open() {
+  ui/show(1)
+  ui/inner/place()
+}
+
+fn tick() {
+  say root
+}
+"#;
+
+#[test]
+fn external_calls_run_the_wrapper() {
+  let project = Project::new("wraps_external");
+  project.file("main.zog", SOURCE);
+  project.build(&[]).assert_success();
+
+  assert_eq!(
+    project.function("example:ui/show"),
+    "execute as @e[tag=ui,limit=1] at @s run function example:ui/show/impl\n"
+  );
+  let open = project.function("example:open");
+  assert!(open.contains("function example:ui/show\n"));
+  assert!(open.contains("function example:ui/inner/place\n"));
+}
+
+#[test]
+fn internal_calls_go_to_the_impl() {
+  let project = Project::new("wraps_internal");
+  project.file("main.zog", SOURCE);
+  project.build(&[]).assert_success();
+
+  assert_eq!(
+    project.function("example:ui/show/impl"),
+    "say shown\nfunction example:ui/hide/impl\n"
+  );
+  // A nested module only uses its own context, and calls into its parent directly
+  assert_eq!(
+    project.function("example:ui/inner/place"),
+    "execute positioned 0 0 0 run function example:ui/inner/place/impl\n"
+  );
+  assert_eq!(
+    project.function("example:ui/inner/place/impl"),
+    "say placed\nfunction example:ui/hide/impl\n"
+  );
+}
+
+#[test]
+fn tags_use_the_wrapper() {
+  let project = Project::new("wraps_tags");
+  project.file("main.zog", SOURCE);
+  project.build(&[]).assert_success();
+
+  let tag: serde_json::Value =
+    serde_json::from_str(&project.read("build/data/minecraft/tags/function/tick.json")).unwrap();
+  assert_eq!(
+    tag["values"],
+    serde_json::json!(["example:tick", "example:ui/tick"])
+  );
+  assert_eq!(
+    project.function("example:ui/tick"),
+    "execute as @e[tag=ui,limit=1] at @s run function example:ui/tick/impl\n"
+  );
+  assert_eq!(project.function("example:tick"), "say root\n");
+}